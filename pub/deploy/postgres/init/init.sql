@@ -11,7 +11,7 @@ CREATE TABLE "roles" (
     "created_at" TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
 );
 
-INSERT INTO "roles" ("id","name") VALUES (1,'admin'), (2,'user'), (3,'guest');
+INSERT INTO "roles" ("id","name") VALUES (1,'admin'), (2,'user'), (3,'guest'), (4,'reseller');
 
 -- 用户
 DROP TABLE IF EXISTS "users" CASCADE;
@@ -44,6 +44,14 @@ CREATE TABLE "apps" (
     "app_update_info" TEXT,
     "app_valid_key" VARCHAR(255) NOT NULL DEFAULT '', -- 应用验证key
     "trial_days" INTEGER NOT NULL DEFAULT 0, -- 试用期时长
+    "sign_public_key" VARCHAR NOT NULL DEFAULT '', -- 许可证签名公钥(Ed25519, base64)
+    "sign_private_key" VARCHAR NOT NULL DEFAULT '', -- 许可证签名私钥(Ed25519, base64)
+    "offline_grace_days" INTEGER NOT NULL DEFAULT 7, -- 客户端离线宽限天数
+    "strict_code_format" BOOLEAN NOT NULL DEFAULT false, -- 注册码必须带校验段，校验时先检查格式
+    "allow_legacy_validate" BOOLEAN NOT NULL DEFAULT true, -- 是否允许明文 app_key 的旧版校验接口
+    "max_transfers" INTEGER NOT NULL DEFAULT 3, -- 每个注册码允许用户自助解绑的次数，0 表示不允许
+    "transfer_cooldown_hours" INTEGER NOT NULL DEFAULT 72, -- 同一注册码两次自助解绑的最小间隔（小时）
+    "base_version" JSONB, -- 首次同步正式版前的版本字段，没有可用的正式版时恢复
     "sort_order" INTEGER NOT NULL DEFAULT 0,
     "status" SMALLINT NOT NULL DEFAULT 0,
     "created_at" TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
@@ -63,10 +71,181 @@ CREATE TABLE "app_devices" (
     "device_info" JSONB,
     "bind_time" TIMESTAMPTZ,
     "expire_time" TIMESTAMPTZ,
+    "bind_ip" VARCHAR, -- 首次校验时的客户端 IP
+    "trial_refused_reason" VARCHAR, -- 试用被拒绝的原因
+    "expired" BOOLEAN NOT NULL DEFAULT FALSE, -- 由定时任务按 expire_time 维护，仅用于列表筛选和统计
     CONSTRAINT "fk_app_device_app_id" FOREIGN KEY ("app_id") REFERENCES "apps" ("id") ON DELETE CASCADE ON UPDATE CASCADE
 );
 CREATE INDEX idx_app_devices_app_id ON "app_devices" ("app_id");
 CREATE INDEX idx_app_devices_device_id ON "app_devices" ("device_id");
+CREATE INDEX idx_app_devices_bind_time ON "app_devices" ("app_id", "bind_time");
+
+-- 应用试用策略，没有记录时不做限制
+DROP TABLE IF EXISTS "trial_policies" CASCADE;
+CREATE TABLE "trial_policies" (
+    "id" SERIAL PRIMARY KEY,
+    "app_id" INTEGER NOT NULL UNIQUE,
+    "window_hours" INTEGER NOT NULL DEFAULT 24, -- IP 限制的统计窗口（小时）
+    "max_trials_per_ip" INTEGER NOT NULL DEFAULT 0, -- 窗口内同一 IP 最多试用设备数，0 不限制
+    "max_trials_per_subnet" INTEGER NOT NULL DEFAULT 0, -- 窗口内同一网段最多试用设备数，0 不限制
+    "subnet_prefix" SMALLINT NOT NULL DEFAULT 24, -- IPv4 网段前缀长度，IPv6 固定 /64
+    "fingerprint_threshold" SMALLINT NOT NULL DEFAULT 0, -- 与已有设备 device_info 的相似度（百分比）达到该值时拒绝，0 关闭
+    "created_at" TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "updated_at" TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT "fk_trial_policies_app_id" FOREIGN KEY ("app_id") REFERENCES "apps" ("id") ON DELETE CASCADE ON UPDATE CASCADE,
+    CONSTRAINT "chk_subnet_prefix_range" CHECK ("subnet_prefix" BETWEEN 8 AND 32),
+    CONSTRAINT "chk_fingerprint_threshold_range" CHECK ("fingerprint_threshold" BETWEEN 0 AND 100)
+);
+
+-- 设备黑名单，按 app_devices.device_id 封禁
+DROP TABLE IF EXISTS "device_bans" CASCADE;
+CREATE TABLE "device_bans" (
+    "id" SERIAL PRIMARY KEY,
+    "app_id" INTEGER NOT NULL,
+    "device_id" VARCHAR NOT NULL,
+    "reason" VARCHAR NOT NULL DEFAULT '', -- 封禁原因
+    "created_at" TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "lifted_at" TIMESTAMPTZ, -- 解除封禁时间，非空表示已解封，保留记录供吊销列表增量同步
+    CONSTRAINT "fk_device_bans_app_id" FOREIGN KEY ("app_id") REFERENCES "apps" ("id") ON DELETE CASCADE ON UPDATE CASCADE,
+    CONSTRAINT "uq_device_bans" UNIQUE ("app_id", "device_id")
+);
+CREATE INDEX idx_device_bans_created_at ON "device_bans" ("created_at");
+
+-- 应用功能定义，商品和注册码的 entitlements 只能引用这里定义的 key
+DROP TABLE IF EXISTS "app_features" CASCADE;
+CREATE TABLE "app_features" (
+    "id" SERIAL PRIMARY KEY,
+    "app_id" INTEGER NOT NULL,
+    "feature_key" VARCHAR(64) NOT NULL, -- 功能标识
+    "name" VARCHAR NOT NULL,
+    "feature_type" SMALLINT NOT NULL DEFAULT 0, -- 0: 功能开关 1: 数值限额
+    "default_value" BIGINT NOT NULL DEFAULT 0, -- 未授权时的默认值，开关 0/1，限额为数值
+    "description" TEXT,
+    "created_at" TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "updated_at" TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT "fk_app_features_app_id" FOREIGN KEY ("app_id") REFERENCES "apps" ("id") ON DELETE CASCADE ON UPDATE CASCADE,
+    CONSTRAINT "uq_app_features" UNIQUE ("app_id", "feature_key"),
+    CONSTRAINT "chk_feature_type_range" CHECK ("feature_type" IN (0, 1))
+);
+
+-- 应用版本发布记录，每个版本和更新通道一行
+DROP TABLE IF EXISTS "app_releases" CASCADE;
+CREATE TABLE "app_releases" (
+    "id" SERIAL PRIMARY KEY,
+    "app_id" INTEGER NOT NULL,
+    "channel" VARCHAR(16) NOT NULL DEFAULT 'stable', -- stable: 正式版 beta: 测试版
+    "vername" VARCHAR(64) NOT NULL,
+    "vercode" INTEGER NOT NULL,
+    "downloads" JSONB NOT NULL DEFAULT '{}', -- 各平台下载地址 {"windows": "...", "macos": "..."}
+    "release_notes" TEXT,
+    "min_vercode" INTEGER NOT NULL DEFAULT 0, -- 最低支持的版本号，低于该版本的客户端必须更新
+    "force_update" BOOLEAN NOT NULL DEFAULT false, -- 是否强制更新到该版本
+    "rollout_percent" SMALLINT NOT NULL DEFAULT 100, -- 灰度比例 0-100，按设备 ID 哈希分桶
+    "status" SMALLINT NOT NULL DEFAULT 0, -- 0: 草稿 1: 已发布 2: 已暂停 3: 已回滚
+    "published_at" TIMESTAMPTZ,
+    "created_at" TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "updated_at" TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT "fk_app_releases_app_id" FOREIGN KEY ("app_id") REFERENCES "apps" ("id") ON DELETE CASCADE ON UPDATE CASCADE,
+    CONSTRAINT "uq_app_releases" UNIQUE ("app_id", "channel", "vercode"),
+    CONSTRAINT "chk_app_releases_channel" CHECK ("channel" IN ('stable', 'beta')),
+    CONSTRAINT "chk_app_releases_rollout_percent" CHECK ("rollout_percent" BETWEEN 0 AND 100)
+);
+CREATE INDEX idx_app_releases_app_id_vercode ON "app_releases" ("app_id", "vercode");
+
+-- 版本发布操作审计，版本删除后保留记录
+DROP TABLE IF EXISTS "app_release_events" CASCADE;
+CREATE TABLE "app_release_events" (
+    "id" SERIAL PRIMARY KEY,
+    "app_id" INTEGER NOT NULL,
+    "release_id" INTEGER,
+    "channel" VARCHAR(16) NOT NULL,
+    "vercode" INTEGER NOT NULL,
+    "action" VARCHAR(16) NOT NULL, -- create/update/publish/pause/resume/rollback/delete/manifest
+    "changes" JSONB, -- 变更的字段 {"field": [旧值, 新值]}
+    "operator_id" INTEGER, -- 操作人 users.id
+    "created_at" TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT "fk_app_release_events_app_id" FOREIGN KEY ("app_id") REFERENCES "apps" ("id") ON DELETE CASCADE ON UPDATE CASCADE,
+    CONSTRAINT "fk_app_release_events_release_id" FOREIGN KEY ("release_id") REFERENCES "app_releases" ("id") ON DELETE SET NULL ON UPDATE CASCADE,
+    CONSTRAINT "fk_app_release_events_operator_id" FOREIGN KEY ("operator_id") REFERENCES "users" ("id") ON DELETE SET NULL ON UPDATE CASCADE
+);
+CREATE INDEX idx_app_release_events_app_id ON "app_release_events" ("app_id", "created_at");
+CREATE INDEX idx_app_release_events_release_id ON "app_release_events" ("release_id");
+
+-- 版本文件清单，客户端下载时用应用私钥签名
+DROP TABLE IF EXISTS "app_release_manifests" CASCADE;
+CREATE TABLE "app_release_manifests" (
+    "id" SERIAL PRIMARY KEY,
+    "release_id" INTEGER NOT NULL UNIQUE,
+    "base_path" VARCHAR NOT NULL, -- 资源目录，清单中的文件路径相对该目录
+    "files" JSONB NOT NULL DEFAULT '[]', -- [{"path", "size", "sha256", "object_key", "url"}]
+    "total_size" BIGINT NOT NULL DEFAULT 0,
+    "created_by" INTEGER, -- 生成人 users.id
+    "created_at" TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "updated_at" TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT "fk_app_release_manifests_release_id" FOREIGN KEY ("release_id") REFERENCES "app_releases" ("id") ON DELETE CASCADE ON UPDATE CASCADE,
+    CONSTRAINT "fk_app_release_manifests_created_by" FOREIGN KEY ("created_by") REFERENCES "users" ("id") ON DELETE SET NULL ON UPDATE CASCADE
+);
+
+-- 应用远程配置，同一 key 可以有多条定向规则，客户端取命中规则中 priority 最高的一条
+DROP TABLE IF EXISTS "app_configs" CASCADE;
+CREATE TABLE "app_configs" (
+    "id" SERIAL PRIMARY KEY,
+    "app_id" INTEGER NOT NULL,
+    "config_key" VARCHAR(64) NOT NULL,
+    "value_type" SMALLINT NOT NULL DEFAULT 0, -- 0: 字符串 1: 整数 2: 小数 3: 布尔 4: JSON
+    "value" JSONB NOT NULL,
+    "channel" VARCHAR(16) NOT NULL DEFAULT 'all', -- all: 全部通道 stable: 正式版 beta: 测试版
+    "min_vercode" INTEGER NOT NULL DEFAULT 0, -- 客户端版本号下限（含），0 为不限
+    "max_vercode" INTEGER NOT NULL DEFAULT 0, -- 客户端版本号上限（含），0 为不限
+    "audience" SMALLINT NOT NULL DEFAULT 0, -- 0: 全部设备 1: 已授权设备 2: 试用设备
+    "priority" INTEGER NOT NULL DEFAULT 0, -- 同一 key 命中多条规则时取较大者
+    "enabled" BOOLEAN NOT NULL DEFAULT true,
+    "description" TEXT,
+    "created_at" TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "updated_at" TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT "fk_app_configs_app_id" FOREIGN KEY ("app_id") REFERENCES "apps" ("id") ON DELETE CASCADE ON UPDATE CASCADE,
+    CONSTRAINT "chk_app_configs_value_type" CHECK ("value_type" BETWEEN 0 AND 4),
+    CONSTRAINT "chk_app_configs_channel" CHECK ("channel" IN ('all', 'stable', 'beta')),
+    CONSTRAINT "chk_app_configs_audience" CHECK ("audience" IN (0, 1, 2))
+);
+CREATE INDEX idx_app_configs_app_id_key ON "app_configs" ("app_id", "config_key");
+
+-- 应用公告，客户端按应用 key 拉取当前生效的公告
+DROP TABLE IF EXISTS "app_announcements" CASCADE;
+CREATE TABLE "app_announcements" (
+    "id" SERIAL PRIMARY KEY,
+    "app_id" INTEGER NOT NULL,
+    "kind" SMALLINT NOT NULL DEFAULT 0, -- 0: 通知 1: 维护 2: 促销
+    "title" VARCHAR(128) NOT NULL,
+    "content" TEXT NOT NULL,
+    "link_url" VARCHAR,
+    "start_at" TIMESTAMPTZ, -- 生效时间，为空时立即生效
+    "end_at" TIMESTAMPTZ, -- 结束时间，为空时一直有效
+    "min_vercode" INTEGER NOT NULL DEFAULT 0, -- 客户端版本号下限（含），0 为不限
+    "max_vercode" INTEGER NOT NULL DEFAULT 0, -- 客户端版本号上限（含），0 为不限
+    "audience" SMALLINT NOT NULL DEFAULT 0, -- 0: 全部设备 1: 已授权设备 2: 试用设备
+    "priority" INTEGER NOT NULL DEFAULT 0, -- 越大越靠前
+    "dismissible" BOOLEAN NOT NULL DEFAULT true, -- 设备是否可以关闭
+    "enabled" BOOLEAN NOT NULL DEFAULT true,
+    "created_at" TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "updated_at" TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT "fk_app_announcements_app_id" FOREIGN KEY ("app_id") REFERENCES "apps" ("id") ON DELETE CASCADE ON UPDATE CASCADE,
+    CONSTRAINT "chk_app_announcements_kind" CHECK ("kind" IN (0, 1, 2)),
+    CONSTRAINT "chk_app_announcements_audience" CHECK ("audience" IN (0, 1, 2)),
+    CONSTRAINT "chk_app_announcements_window" CHECK ("start_at" IS NULL OR "end_at" IS NULL OR "start_at" < "end_at")
+);
+CREATE INDEX idx_app_announcements_app_id ON "app_announcements" ("app_id", "enabled");
+
+-- 设备关闭的公告，关闭后不再返回给该设备
+DROP TABLE IF EXISTS "app_announcement_dismissals" CASCADE;
+CREATE TABLE "app_announcement_dismissals" (
+    "id" SERIAL PRIMARY KEY,
+    "announcement_id" INTEGER NOT NULL,
+    "device_id" VARCHAR NOT NULL, -- 客户端设备 ID
+    "dismissed_at" TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT "fk_app_announcement_dismissals_announcement_id" FOREIGN KEY ("announcement_id") REFERENCES "app_announcements" ("id") ON DELETE CASCADE ON UPDATE CASCADE,
+    CONSTRAINT "uq_app_announcement_dismissals" UNIQUE ("announcement_id", "device_id")
+);
 
 -- 商品表
 DROP TABLE IF EXISTS "products" CASCADE;
@@ -80,6 +259,7 @@ CREATE TABLE "products" (
     "image_url" VARCHAR,
     "tags" TEXT[],
     "status" SMALLINT NOT NULL DEFAULT 0,
+    "entitlements" JSONB, -- 购买后授予的功能开关与限额 {"features": [], "limits": {}}
     "created_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
     "updated_at" TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
     "remark" TEXT,
@@ -118,6 +298,7 @@ CREATE TABLE "orders" (
     "original_price" BIGINT NOT NULL DEFAULT 0, -- 原价
     "final_price" BIGINT NOT NULL DEFAULT 0, -- 实付
     "remark" TEXT, -- 订单备注
+    "contact_email" VARCHAR(255), -- 买家联系邮箱，用于自助门户登录
     "created_at" TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
     "updated_at" TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
     "created_by" INTEGER NOT NULL, -- 创建者
@@ -133,6 +314,7 @@ CREATE INDEX idx_orders_order_id ON "orders" ("order_id");
 CREATE INDEX idx_orders_created_by ON "orders" ("created_by");
 CREATE INDEX idx_orders_updated_by ON "orders" ("updated_by");
 CREATE INDEX idx_orders_pay_method_id ON "orders" ("pay_method_id");
+CREATE INDEX idx_orders_contact_email ON "orders" (LOWER("contact_email"));
 COMMENT ON COLUMN "orders"."status" IS '0: 待支付 1: 已支付 2: 已取消 3: 已退款';
 
 -- 资源表
@@ -145,6 +327,9 @@ CREATE TABLE "resources" (
     "file_type" VARCHAR NOT NULL, -- 文件类型 folder,image,
     "res_type" SMALLINT NOT NULL DEFAULT 0, -- 0: 本地 1: OSS
     "object_key" VARCHAR NOT NULL,
+    "size" BIGINT, -- 以下三列为 OSS HeadObject 读取的文件信息，etag 变化时重新计算
+    "sha256" VARCHAR(64),
+    "etag" VARCHAR(128),
     "tags" TEXT[],
     "status" SMALLINT NOT NULL DEFAULT 1,
     "remark" TEXT,
@@ -237,6 +422,43 @@ CREATE TABLE "order_coupons" (
 CREATE INDEX idx_order_coupons_order_id ON "order_coupons" ("order_id");
 CREATE INDEX idx_order_coupons_coupon_id ON "order_coupons" ("coupon_id");
 
+-- 代理商，可在已购额度内为分配的应用生成注册码，可用应用通过 casbin 策略授予
+DROP TABLE IF EXISTS "resellers" CASCADE;
+CREATE TABLE "resellers" (
+    "id" SERIAL PRIMARY KEY,
+    "user_id" INTEGER NOT NULL UNIQUE, -- 代理商登录账号
+    "name" VARCHAR(255) NOT NULL,
+    "contact" VARCHAR(255),
+    "quota_balance" INTEGER NOT NULL DEFAULT 0, -- 剩余可生成的注册码数量
+    "status" SMALLINT NOT NULL DEFAULT 0, -- 0: 正常 1: 停用
+    "created_at" TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "updated_at" TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT "fk_resellers_user_id" FOREIGN KEY ("user_id") REFERENCES "users" ("id") ON DELETE CASCADE ON UPDATE CASCADE,
+    CONSTRAINT "chk_resellers_quota_balance" CHECK ("quota_balance" >= 0)
+);
+
+-- 注册码批次，记录一批注册码的发放渠道（代理商、活动等）
+DROP TABLE IF EXISTS "reg_code_batches" CASCADE;
+CREATE TABLE "reg_code_batches" (
+    "id" SERIAL PRIMARY KEY,
+    "app_id" INTEGER NOT NULL,
+    "name" VARCHAR(255) NOT NULL,
+    "channel" VARCHAR(255), -- 发放渠道，代理商或活动名称
+    "notes" TEXT,
+    "created_by" INTEGER, -- 创建人 users.id
+    "reseller_id" INTEGER, -- 代理商生成的批次
+    "voided_at" TIMESTAMPTZ, -- 作废时间，作废时批次内注册码全部吊销
+    "void_reason" VARCHAR,
+    "created_at" TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "updated_at" TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT "fk_reg_code_batches_app_id" FOREIGN KEY ("app_id") REFERENCES "apps" ("id") ON DELETE CASCADE ON UPDATE CASCADE,
+    CONSTRAINT "fk_reg_code_batches_created_by" FOREIGN KEY ("created_by") REFERENCES "users" ("id") ON DELETE SET NULL ON UPDATE CASCADE,
+    CONSTRAINT "fk_reg_code_batches_reseller_id" FOREIGN KEY ("reseller_id") REFERENCES "resellers" ("id") ON DELETE SET NULL ON UPDATE CASCADE
+);
+CREATE INDEX idx_reg_code_batches_app_id ON "reg_code_batches" ("app_id");
+CREATE INDEX idx_reg_code_batches_reseller_id ON "reg_code_batches" ("reseller_id");
+CREATE INDEX idx_reg_code_batches_channel ON "reg_code_batches" ("channel");
+
 -- 注册码
 DROP TABLE IF EXISTS "reg_codes" CASCADE;
 CREATE TABLE "reg_codes" (
@@ -245,21 +467,150 @@ CREATE TABLE "reg_codes" (
     "app_id" INTEGER NOT NULL, -- 应用ID
     "valid_days" INTEGER NOT NULL DEFAULT 0, -- 有效天数 1: 1天 2: 3天 3: 7天 4: 30天
     "max_devices" INTEGER NOT NULL DEFAULT 1, -- 最大绑定设备数
-    "status" SMALLINT NOT NULL DEFAULT 0, -- 状态 0: 未使用 1: 已使用 2: 已过期
+    "status" SMALLINT NOT NULL DEFAULT 0, -- 状态 0: 未使用 1: 已使用 2: 已过期 3: 已吊销
     "binding_time" TIMESTAMPTZ, -- 绑定时间
-    "code_type" SMALLINT NOT NULL DEFAULT 0, -- 0: 时间类型 1：次数类型
+    "code_type" SMALLINT NOT NULL DEFAULT 0, -- 0: 时间类型 1：次数类型 2：浮动授权（max_devices 为并发席位数）
     "expire_time" TIMESTAMPTZ, -- 过期时间（时间类型）
     "total_count" INTEGER, -- 总次数（次数类型）
     "use_count" INTEGER NOT NULL DEFAULT 0, -- 已使用次数
-    "device_id" INTEGER, -- 绑定设备ID
+    "revoked_reason" VARCHAR, -- 吊销原因
+    "revoked_at" TIMESTAMPTZ, -- 吊销时间
+    "restored_at" TIMESTAMPTZ, -- 从吊销状态恢复的时间，再次吊销时清空
+    "entitlements" JSONB, -- 注册码额外授予的功能开关与限额，与关联商品合并
+    "batch_id" INTEGER, -- 所属批次
     "created_at" TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
     "updated_at" TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
     "deleted_at" TIMESTAMPTZ,
     CONSTRAINT "fk_reg_code_app_id" FOREIGN KEY ("app_id") REFERENCES "apps" ("id") ON DELETE CASCADE ON UPDATE CASCADE,
-    CONSTRAINT "fk_reg_code_device_id" FOREIGN KEY ("device_id") REFERENCES "app_devices" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+    CONSTRAINT "fk_reg_code_batch_id" FOREIGN KEY ("batch_id") REFERENCES "reg_code_batches" ("id") ON DELETE SET NULL ON UPDATE CASCADE
 );
 CREATE INDEX idx_reg_codes_app_id ON "reg_codes" ("app_id");
+CREATE INDEX idx_reg_codes_batch_id ON "reg_codes" ("batch_id");
 CREATE INDEX idx_reg_codes_status ON "reg_codes" ("status");
+CREATE INDEX idx_reg_codes_revoked_at ON "reg_codes" ("revoked_at");
+
+-- 代理商额度流水，change 为正表示充值，为负表示生成注册码扣减
+DROP TABLE IF EXISTS "reseller_quota_ledger" CASCADE;
+CREATE TABLE "reseller_quota_ledger" (
+    "id" BIGSERIAL PRIMARY KEY,
+    "reseller_id" INTEGER NOT NULL,
+    "change" INTEGER NOT NULL,
+    "balance_after" INTEGER NOT NULL,
+    "kind" VARCHAR(32) NOT NULL, -- purchase: 购买 adjust: 调整 generate: 生成注册码
+    "batch_id" INTEGER, -- 生成注册码时对应的批次
+    "remark" VARCHAR,
+    "created_by" INTEGER, -- 操作人 users.id
+    "created_at" TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT "fk_reseller_quota_ledger_reseller_id" FOREIGN KEY ("reseller_id") REFERENCES "resellers" ("id") ON DELETE CASCADE ON UPDATE CASCADE,
+    CONSTRAINT "fk_reseller_quota_ledger_batch_id" FOREIGN KEY ("batch_id") REFERENCES "reg_code_batches" ("id") ON DELETE SET NULL ON UPDATE CASCADE,
+    CONSTRAINT "fk_reseller_quota_ledger_created_by" FOREIGN KEY ("created_by") REFERENCES "users" ("id") ON DELETE SET NULL ON UPDATE CASCADE
+);
+CREATE INDEX idx_reseller_quota_ledger_reseller_id ON "reseller_quota_ledger" ("reseller_id", "created_at");
+
+-- 注册码绑定的设备，数量受 reg_codes.max_devices 限制
+DROP TABLE IF EXISTS "reg_code_devices" CASCADE;
+CREATE TABLE "reg_code_devices" (
+    "id" SERIAL PRIMARY KEY,
+    "reg_code_id" INTEGER NOT NULL,
+    "device_id" INTEGER NOT NULL, -- app_devices.id
+    "bind_time" TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT "fk_reg_code_devices_reg_code_id" FOREIGN KEY ("reg_code_id") REFERENCES "reg_codes" ("id") ON DELETE CASCADE ON UPDATE CASCADE,
+    CONSTRAINT "fk_reg_code_devices_device_id" FOREIGN KEY ("device_id") REFERENCES "app_devices" ("id") ON DELETE CASCADE ON UPDATE CASCADE,
+    CONSTRAINT "uq_reg_code_devices" UNIQUE ("reg_code_id", "device_id")
+);
+CREATE INDEX idx_reg_code_devices_reg_code_id ON "reg_code_devices" ("reg_code_id");
+CREATE INDEX idx_reg_code_devices_device_id ON "reg_code_devices" ("device_id");
+
+-- 设备续期记录，同一设备兑换多个时长注册码时有效期叠加
+DROP TABLE IF EXISTS "device_renewals" CASCADE;
+CREATE TABLE "device_renewals" (
+    "id" SERIAL PRIMARY KEY,
+    "app_id" INTEGER NOT NULL,
+    "device_id" INTEGER NOT NULL, -- app_devices.id
+    "reg_code_id" INTEGER NOT NULL,
+    "order_id" INTEGER, -- 通过订单购买时的订单
+    "added_days" INTEGER NOT NULL, -- 本次叠加的天数
+    "previous_expire" TIMESTAMPTZ, -- 叠加前设备的过期时间
+    "new_expire" TIMESTAMPTZ NOT NULL, -- 叠加后设备的过期时间
+    "created_at" TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT "fk_device_renewals_app_id" FOREIGN KEY ("app_id") REFERENCES "apps" ("id") ON DELETE CASCADE ON UPDATE CASCADE,
+    CONSTRAINT "fk_device_renewals_device_id" FOREIGN KEY ("device_id") REFERENCES "app_devices" ("id") ON DELETE CASCADE ON UPDATE CASCADE,
+    CONSTRAINT "fk_device_renewals_reg_code_id" FOREIGN KEY ("reg_code_id") REFERENCES "reg_codes" ("id") ON DELETE CASCADE ON UPDATE CASCADE,
+    CONSTRAINT "fk_device_renewals_order_id" FOREIGN KEY ("order_id") REFERENCES "orders" ("id") ON DELETE SET NULL ON UPDATE CASCADE,
+    CONSTRAINT "uq_device_renewals" UNIQUE ("reg_code_id", "device_id")
+);
+CREATE INDEX idx_device_renewals_device_id ON "device_renewals" ("device_id");
+
+-- 注册码设备解绑和转移记录
+DROP TABLE IF EXISTS "reg_code_transfers" CASCADE;
+CREATE TABLE "reg_code_transfers" (
+    "id" SERIAL PRIMARY KEY,
+    "app_id" INTEGER NOT NULL,
+    "reg_code_id" INTEGER NOT NULL,
+    "from_device" VARCHAR(255) NOT NULL, -- 解绑的设备，app_devices.device_id
+    "to_device" VARCHAR(255), -- 管理员转移时的目标设备
+    "source" SMALLINT NOT NULL, -- 0: 客户端自助 1: 买家门户 2: 管理员解绑 3: 管理员转移
+    "ip" VARCHAR(64),
+    "operator_id" INTEGER, -- 管理员操作时的 users.id
+    "created_at" TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT "fk_reg_code_transfers_app_id" FOREIGN KEY ("app_id") REFERENCES "apps" ("id") ON DELETE CASCADE ON UPDATE CASCADE,
+    CONSTRAINT "fk_reg_code_transfers_reg_code_id" FOREIGN KEY ("reg_code_id") REFERENCES "reg_codes" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+CREATE INDEX idx_reg_code_transfers_reg_code_id ON "reg_code_transfers" ("reg_code_id", "created_at");
+
+-- 计次注册码的消耗记录
+DROP TABLE IF EXISTS "code_usages" CASCADE;
+CREATE TABLE "code_usages" (
+    "id" BIGSERIAL PRIMARY KEY,
+    "app_id" INTEGER NOT NULL,
+    "reg_code_id" INTEGER NOT NULL,
+    "device_id" INTEGER NOT NULL, -- app_devices.id
+    "amount" INTEGER NOT NULL, -- 本次消耗的次数
+    "remaining" INTEGER NOT NULL, -- 消耗后剩余次数
+    "idempotency_key" VARCHAR(64), -- 客户端幂等键，校验接口的消耗为空
+    "created_at" TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT "fk_code_usages_app_id" FOREIGN KEY ("app_id") REFERENCES "apps" ("id") ON DELETE CASCADE ON UPDATE CASCADE,
+    CONSTRAINT "fk_code_usages_reg_code_id" FOREIGN KEY ("reg_code_id") REFERENCES "reg_codes" ("id") ON DELETE CASCADE ON UPDATE CASCADE,
+    CONSTRAINT "fk_code_usages_device_id" FOREIGN KEY ("device_id") REFERENCES "app_devices" ("id") ON DELETE CASCADE ON UPDATE CASCADE,
+    CONSTRAINT "uq_code_usages_idempotency_key" UNIQUE ("reg_code_id", "idempotency_key"),
+    CONSTRAINT "chk_code_usages_amount" CHECK ("amount" > 0)
+);
+CREATE INDEX idx_code_usages_reg_code_id ON "code_usages" ("reg_code_id", "created_at");
+
+-- 定时任务执行记录
+DROP TABLE IF EXISTS "job_runs" CASCADE;
+CREATE TABLE "job_runs" (
+    "id" BIGSERIAL PRIMARY KEY,
+    "job_name" VARCHAR(64) NOT NULL,
+    "instance" VARCHAR(128) NOT NULL, -- 执行任务的实例
+    "trigger" VARCHAR(16) NOT NULL, -- schedule: 定时触发 manual: 管理员手动触发
+    "success" BOOLEAN NOT NULL,
+    "affected" BIGINT NOT NULL DEFAULT 0, -- 处理的记录数
+    "error" TEXT,
+    "started_at" TIMESTAMPTZ NOT NULL,
+    "finished_at" TIMESTAMPTZ NOT NULL
+);
+CREATE INDEX idx_job_runs_job_name ON "job_runs" ("job_name", "started_at");
+
+-- 注册码校验日志，由后台任务批量写入
+DROP TABLE IF EXISTS "validation_events" CASCADE;
+CREATE TABLE "validation_events" (
+    "id" BIGSERIAL PRIMARY KEY,
+    "app_id" INTEGER, -- 应用不存在时为空
+    "device_id" VARCHAR NOT NULL DEFAULT '',
+    "code" VARCHAR, -- 请求中注册码的 SHA-256 哈希，不保存明文，试用校验为空
+    "api_version" SMALLINT NOT NULL DEFAULT 1, -- 1: /api/reg/validate 2: /api/reg/v2/validate 3: 离线激活
+    "ip" VARCHAR NOT NULL DEFAULT '',
+    "user_agent" VARCHAR,
+    "success" BOOLEAN NOT NULL,
+    "error_code" INTEGER, -- 失败时的错误码
+    "reason" VARCHAR, -- 失败原因
+    "created_at" TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT "fk_validation_events_app_id" FOREIGN KEY ("app_id") REFERENCES "apps" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+CREATE INDEX idx_validation_events_app_id_created_at ON "validation_events" ("app_id", "created_at");
+CREATE INDEX idx_validation_events_device_id ON "validation_events" ("device_id");
+CREATE INDEX idx_validation_events_code ON "validation_events" ("code");
 
 -- 订单对应的注册码
 DROP TABLE IF EXISTS "order_reg_codes" CASCADE;
@@ -310,4 +661,8 @@ INSERT INTO "casbin_rule" ("ptype", "v0", "v1", "v2", "v3", "v4", "v5") VALUES (
 
 INSERT INTO "casbin_rule" ("ptype", "v0", "v1", "v2", "v3", "v4", "v5") VALUES ('p', 'guest', '/*', 'read', '', '', '');
 
+-- 代理商只能访问 /api/reseller 下的接口，可用应用按用户授予 ('p', '<user_id>', '/apps/<app_id>', 'resell')
+INSERT INTO "casbin_rule" ("ptype", "v0", "v1", "v2", "v3", "v4", "v5") VALUES ('p', 'reseller', '/api/reseller/*', 'read', '', '', '');
+INSERT INTO "casbin_rule" ("ptype", "v0", "v1", "v2", "v3", "v4", "v5") VALUES ('p', 'reseller', '/api/reseller/*', 'create', '', '', '');
+
 Insert into "casbin_rule" ("ptype", "v0", "v1", "v2", "v3", "v4", "v5") VALUES ('g', '1', 'admin', '', '', '', '');
\ No newline at end of file
@@ -0,0 +1,34 @@
+//! `SeaORM` Entity, handwritten for app_announcement_dismissals table
+
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, ToSchema)]
+#[sea_orm(table_name = "app_announcement_dismissals")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub announcement_id: i32,
+    pub device_id: String,
+    pub dismissed_at: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::app_announcements::Entity",
+        from = "Column::AnnouncementId",
+        to = "super::app_announcements::Column::Id"
+    )]
+    AppAnnouncements,
+}
+
+impl Related<super::app_announcements::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::AppAnnouncements.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
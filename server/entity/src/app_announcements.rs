@@ -0,0 +1,54 @@
+//! `SeaORM` Entity, handwritten for app_announcements table
+
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, ToSchema)]
+#[sea_orm(table_name = "app_announcements")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub app_id: i32,
+    pub kind: i16,
+    pub title: String,
+    pub content: String,
+    pub link_url: Option<String>,
+    pub start_at: Option<DateTime<Utc>>,
+    pub end_at: Option<DateTime<Utc>>,
+    pub min_vercode: i32,
+    pub max_vercode: i32,
+    pub audience: i16,
+    pub priority: i32,
+    pub dismissible: bool,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::apps::Entity",
+        from = "Column::AppId",
+        to = "super::apps::Column::Id"
+    )]
+    Apps,
+    #[sea_orm(has_many = "super::app_announcement_dismissals::Entity")]
+    AppAnnouncementDismissals,
+}
+
+impl Related<super::apps::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Apps.def()
+    }
+}
+
+impl Related<super::app_announcement_dismissals::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::AppAnnouncementDismissals.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
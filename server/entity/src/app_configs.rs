@@ -0,0 +1,44 @@
+//! `SeaORM` Entity, handwritten for app_configs table
+
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, ToSchema)]
+#[sea_orm(table_name = "app_configs")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub app_id: i32,
+    pub config_key: String,
+    pub value_type: i16,
+    pub value: Json,
+    pub channel: String,
+    pub min_vercode: i32,
+    pub max_vercode: i32,
+    pub audience: i16,
+    pub priority: i32,
+    pub enabled: bool,
+    pub description: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::apps::Entity",
+        from = "Column::AppId",
+        to = "super::apps::Column::Id"
+    )]
+    Apps,
+}
+
+impl Related<super::apps::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Apps.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
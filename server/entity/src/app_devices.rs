@@ -13,6 +13,9 @@ pub struct Model {
     pub device_info: Option<Json>,
     pub bind_time: Option<DateTime<Utc>>,    
     pub expire_time: Option<DateTime<Utc>>,
+    pub bind_ip: Option<String>,
+    pub trial_refused_reason: Option<String>,
+    pub expired: bool,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
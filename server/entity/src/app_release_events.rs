@@ -0,0 +1,39 @@
+//! `SeaORM` Entity, handwritten for app_release_events table
+
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, ToSchema)]
+#[sea_orm(table_name = "app_release_events")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub app_id: i32,
+    pub release_id: Option<i32>,
+    pub channel: String,
+    pub vercode: i32,
+    pub action: String,
+    pub changes: Option<Json>,
+    pub operator_id: Option<i32>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::app_releases::Entity",
+        from = "Column::ReleaseId",
+        to = "super::app_releases::Column::Id"
+    )]
+    AppReleases,
+}
+
+impl Related<super::app_releases::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::AppReleases.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
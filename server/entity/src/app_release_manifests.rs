@@ -0,0 +1,38 @@
+//! `SeaORM` Entity, handwritten for app_release_manifests table
+
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, ToSchema)]
+#[sea_orm(table_name = "app_release_manifests")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub release_id: i32,
+    pub base_path: String,
+    pub files: Json,
+    pub total_size: i64,
+    pub created_by: Option<i32>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::app_releases::Entity",
+        from = "Column::ReleaseId",
+        to = "super::app_releases::Column::Id"
+    )]
+    AppReleases,
+}
+
+impl Related<super::app_releases::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::AppReleases.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -18,6 +18,17 @@ pub struct Model {
     pub app_update_info: Option<String>,
     pub app_valid_key: String,
     pub trial_days: i32,
+    pub sign_public_key: String,
+    #[serde(skip_serializing)]
+    pub sign_private_key: String,
+    pub offline_grace_days: i32,
+    pub strict_code_format: bool,
+    pub allow_legacy_validate: bool,
+    pub max_transfers: i32,
+    pub transfer_cooldown_hours: i32,
+    /// 首次同步正式版前的 app_vername / app_vercode / app_download_url / app_update_info
+    #[serde(skip_serializing)]
+    pub base_version: Option<Json>,
     pub sort_order: i32,
     pub status: i16,
     pub created_at: DateTime<Utc>,
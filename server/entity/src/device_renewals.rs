@@ -0,0 +1,51 @@
+//! `SeaORM` Entity, handwritten for device_renewals table
+
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, ToSchema)]
+#[sea_orm(table_name = "device_renewals")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub app_id: i32,
+    pub device_id: i32,
+    pub reg_code_id: i32,
+    pub order_id: Option<i32>,
+    pub added_days: i32,
+    pub previous_expire: Option<DateTime<Utc>>,
+    pub new_expire: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::app_devices::Entity",
+        from = "Column::DeviceId",
+        to = "super::app_devices::Column::Id"
+    )]
+    AppDevices,
+    #[sea_orm(
+        belongs_to = "super::reg_codes::Entity",
+        from = "Column::RegCodeId",
+        to = "super::reg_codes::Column::Id"
+    )]
+    RegCodes,
+}
+
+impl Related<super::app_devices::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::AppDevices.def()
+    }
+}
+
+impl Related<super::reg_codes::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::RegCodes.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
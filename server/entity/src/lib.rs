@@ -1,12 +1,23 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen
 
 pub mod apps;
+pub mod app_announcement_dismissals;
+pub mod app_announcements;
+pub mod app_configs;
 pub mod app_devices;
+pub mod app_features;
+pub mod app_release_events;
+pub mod app_release_manifests;
+pub mod app_releases;
 pub mod casbin_rule;
+pub mod code_usages;
 pub mod coupons;
 pub mod coupons_apps;
 pub mod coupons_products;
+pub mod device_bans;
+pub mod device_renewals;
 pub mod invite_records;
+pub mod job_runs;
 pub mod order_coupons;
 pub mod order_products;
 pub mod order_reg_codes;
@@ -14,7 +25,14 @@ pub mod orders;
 pub mod pay_methods;
 pub mod prelude;
 pub mod products;
+pub mod reg_code_batches;
+pub mod reg_code_devices;
+pub mod reg_code_transfers;
 pub mod reg_codes;
+pub mod reseller_quota_ledger;
+pub mod resellers;
 pub mod resources;
 pub mod roles;
+pub mod trial_policies;
 pub mod users;
+pub mod validation_events;
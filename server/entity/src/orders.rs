@@ -17,6 +17,7 @@ pub struct Model {
     pub original_price: i64,
     pub final_price: i64,
     pub remark: Option<String>,
+    pub contact_email: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub created_by: i32,
@@ -1,18 +1,36 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen
 
 pub use super::apps::Entity as Apps;
+pub use super::app_announcement_dismissals::Entity as AppAnnouncementDismissals;
+pub use super::app_announcements::Entity as AppAnnouncements;
+pub use super::app_configs::Entity as AppConfigs;
+pub use super::app_features::Entity as AppFeatures;
+pub use super::app_release_events::Entity as AppReleaseEvents;
+pub use super::app_release_manifests::Entity as AppReleaseManifests;
+pub use super::app_releases::Entity as AppReleases;
 pub use super::casbin_rule::Entity as CasbinRule;
+pub use super::code_usages::Entity as CodeUsages;
 pub use super::coupons::Entity as Coupons;
 pub use super::coupons_apps::Entity as CouponsApps;
 pub use super::coupons_products::Entity as CouponsProducts;
+pub use super::device_bans::Entity as DeviceBans;
+pub use super::device_renewals::Entity as DeviceRenewals;
 pub use super::invite_records::Entity as InviteRecords;
+pub use super::job_runs::Entity as JobRuns;
 pub use super::order_coupons::Entity as OrderCoupons;
 pub use super::order_products::Entity as OrderProducts;
 pub use super::order_reg_codes::Entity as OrderRegCodes;
 pub use super::orders::Entity as Orders;
 pub use super::pay_methods::Entity as PayMethods;
 pub use super::products::Entity as Products;
+pub use super::reseller_quota_ledger::Entity as ResellerQuotaLedger;
+pub use super::resellers::Entity as Resellers;
 pub use super::resources::Entity as Resources;
+pub use super::reg_code_batches::Entity as RegCodeBatches;
+pub use super::reg_code_devices::Entity as RegCodeDevices;
+pub use super::reg_code_transfers::Entity as RegCodeTransfers;
 pub use super::reg_codes::Entity as RegCodes;
 pub use super::roles::Entity as Roles;
+pub use super::trial_policies::Entity as TrialPolicies;
 pub use super::users::Entity as Users;
+pub use super::validation_events::Entity as ValidationEvents;
@@ -19,6 +19,7 @@ pub struct Model {
     pub image_url: Option<String>,
     pub tags: Option<Vec<String>>,
     pub status: i16,
+    pub entitlements: Option<Json>,
     pub created_at: Option<DateTime<Utc>>,
     pub updated_at: Option<DateTime<Utc>>,
     pub remark: Option<String>,
@@ -0,0 +1,62 @@
+//! `SeaORM` Entity, handwritten for reg_code_batches table
+
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, ToSchema)]
+#[sea_orm(table_name = "reg_code_batches")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub app_id: i32,
+    pub name: String,
+    pub channel: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub notes: Option<String>,
+    pub created_by: Option<i32>,
+    pub reseller_id: Option<i32>,
+    pub voided_at: Option<DateTime<Utc>>,
+    pub void_reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::apps::Entity",
+        from = "Column::AppId",
+        to = "super::apps::Column::Id"
+    )]
+    Apps,
+    #[sea_orm(
+        belongs_to = "super::resellers::Entity",
+        from = "Column::ResellerId",
+        to = "super::resellers::Column::Id"
+    )]
+    Resellers,
+    #[sea_orm(has_many = "super::reg_codes::Entity")]
+    RegCodes,
+}
+
+impl Related<super::apps::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Apps.def()
+    }
+}
+
+impl Related<super::resellers::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Resellers.def()
+    }
+}
+
+impl Related<super::reg_codes::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::RegCodes.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
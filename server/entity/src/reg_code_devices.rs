@@ -0,0 +1,44 @@
+//! `SeaORM` Entity, handwritten for reg_code_devices table
+
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "reg_code_devices")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub reg_code_id: i32,
+    pub device_id: i32,
+    pub bind_time: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::reg_codes::Entity",
+        from = "Column::RegCodeId",
+        to = "super::reg_codes::Column::Id"
+    )]
+    RegCodes,
+    #[sea_orm(
+        belongs_to = "super::app_devices::Entity",
+        from = "Column::DeviceId",
+        to = "super::app_devices::Column::Id"
+    )]
+    AppDevices,
+}
+
+impl Related<super::reg_codes::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::RegCodes.def()
+    }
+}
+
+impl Related<super::app_devices::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::AppDevices.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,39 @@
+//! `SeaORM` Entity, handwritten for reg_code_transfers table
+
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, ToSchema)]
+#[sea_orm(table_name = "reg_code_transfers")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub app_id: i32,
+    pub reg_code_id: i32,
+    pub from_device: String,
+    pub to_device: Option<String>,
+    pub source: i16,
+    pub ip: Option<String>,
+    pub operator_id: Option<i32>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::reg_codes::Entity",
+        from = "Column::RegCodeId",
+        to = "super::reg_codes::Column::Id"
+    )]
+    RegCodes,
+}
+
+impl Related<super::reg_codes::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::RegCodes.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
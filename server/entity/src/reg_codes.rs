@@ -20,7 +20,11 @@ pub struct Model {
     pub expire_time: Option<DateTime<Utc>>,
     pub total_count: Option<i32>,
     pub use_count: i32,
-    pub device_id: Option<i32>,
+    pub revoked_reason: Option<String>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub restored_at: Option<DateTime<Utc>>,
+    pub entitlements: Option<Json>,
+    pub batch_id: Option<i32>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -33,14 +37,16 @@ pub enum Relation {
         to = "super::apps::Column::Id"
     )]
     Apps,
-    #[sea_orm(has_many = "super::order_reg_codes::Entity")]
-    OrderRegCodes,
     #[sea_orm(
-        belongs_to = "super::app_devices::Entity",
-        from = "Column::DeviceId",
-        to = "super::app_devices::Column::Id"
+        belongs_to = "super::reg_code_batches::Entity",
+        from = "Column::BatchId",
+        to = "super::reg_code_batches::Column::Id"
     )]
-    AppDevices,
+    RegCodeBatches,
+    #[sea_orm(has_many = "super::order_reg_codes::Entity")]
+    OrderRegCodes,
+    #[sea_orm(has_many = "super::reg_code_devices::Entity")]
+    RegCodeDevices,
 }
 
 impl Related<super::apps::Entity> for Entity {
@@ -49,15 +55,30 @@ impl Related<super::apps::Entity> for Entity {
     }
 }
 
+impl Related<super::reg_code_batches::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::RegCodeBatches.def()
+    }
+}
+
 impl Related<super::order_reg_codes::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::OrderRegCodes.def()
     }
 }
 
+impl Related<super::reg_code_devices::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::RegCodeDevices.def()
+    }
+}
+
 impl Related<super::app_devices::Entity> for Entity {
     fn to() -> RelationDef {
-        Relation::AppDevices.def()
+        super::reg_code_devices::Relation::AppDevices.def()
+    }
+    fn via() -> Option<RelationDef> {
+        Some(super::reg_code_devices::Relation::RegCodes.def().rev())
     }
 }
 
@@ -0,0 +1,51 @@
+//! `SeaORM` Entity, handwritten for reseller_quota_ledger table
+
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, ToSchema)]
+#[sea_orm(table_name = "reseller_quota_ledger")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub reseller_id: i32,
+    pub change: i32,
+    pub balance_after: i32,
+    pub kind: String,
+    pub batch_id: Option<i32>,
+    pub remark: Option<String>,
+    pub created_by: Option<i32>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::resellers::Entity",
+        from = "Column::ResellerId",
+        to = "super::resellers::Column::Id"
+    )]
+    Resellers,
+    #[sea_orm(
+        belongs_to = "super::reg_code_batches::Entity",
+        from = "Column::BatchId",
+        to = "super::reg_code_batches::Column::Id"
+    )]
+    RegCodeBatches,
+}
+
+impl Related<super::resellers::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Resellers.def()
+    }
+}
+
+impl Related<super::reg_code_batches::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::RegCodeBatches.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
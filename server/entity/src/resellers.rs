@@ -0,0 +1,55 @@
+//! `SeaORM` Entity, handwritten for resellers table
+
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, ToSchema)]
+#[sea_orm(table_name = "resellers")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    #[sea_orm(unique)]
+    pub user_id: i32,
+    pub name: String,
+    pub contact: Option<String>,
+    pub quota_balance: i32,
+    pub status: i16,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::users::Entity",
+        from = "Column::UserId",
+        to = "super::users::Column::Id"
+    )]
+    Users,
+    #[sea_orm(has_many = "super::reg_code_batches::Entity")]
+    RegCodeBatches,
+    #[sea_orm(has_many = "super::reseller_quota_ledger::Entity")]
+    ResellerQuotaLedger,
+}
+
+impl Related<super::users::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Users.def()
+    }
+}
+
+impl Related<super::reg_code_batches::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::RegCodeBatches.def()
+    }
+}
+
+impl Related<super::reseller_quota_ledger::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ResellerQuotaLedger.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -14,8 +14,12 @@ pub struct Model {
     pub name: String,
     pub object_key: String,
     pub url: String,
+    pub file_type: String,
     pub res_type: i16,
     pub path: String,
+    pub size: Option<i64>,
+    pub sha256: Option<String>,
+    pub etag: Option<String>,
     pub tags: Option<Vec<String>>,
     pub status: i16,
     pub remark: Option<String>,
@@ -0,0 +1,129 @@
+//! 加密的本地许可证缓存
+//!
+//! 文件内容为 `nonce(12) || AES-256-GCM 密文`，密钥由 app_key 和设备ID派生，
+//! 拷贝到其它机器或被改动后都无法解密，按没有缓存处理。
+
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const NONCE_LEN: usize = 12;
+
+/// 缓存内容
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct CachedLicense {
+    /// 激活时使用的注册码，之后校验不需要再次输入
+    pub code: Option<String>,
+    /// 最近一次联网校验成功时的许可证令牌
+    pub token: Option<String>,
+    /// 应用公钥
+    pub public_key: Option<String>,
+    /// 见过的最晚时间（unix 秒），用于发现本地时间被回拨
+    pub checked_at: i64,
+}
+
+pub struct LicenseCache {
+    path: PathBuf,
+    key: [u8; 32],
+}
+
+impl LicenseCache {
+    pub fn new(cache_dir: &Path, app_id: &str, app_key: &str, device_id: &str) -> Self {
+        let name = hex::encode(&Sha256::digest(app_id.as_bytes())[..8]);
+        let key = Sha256::new()
+            .chain_update(b"license-client/v1\0")
+            .chain_update(app_key.as_bytes())
+            .chain_update(b"\0")
+            .chain_update(device_id.as_bytes())
+            .finalize();
+        Self {
+            path: cache_dir.join(format!("{}.lic", name)),
+            key: key.into(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 读取缓存，文件不存在、损坏或无法解密时返回 None
+    pub fn load(&self) -> Option<CachedLicense> {
+        let data = fs::read(&self.path).ok()?;
+        if data.len() <= NONCE_LEN {
+            return None;
+        }
+        let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+        let plain = self
+            .cipher()
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .ok()?;
+        serde_json::from_slice(&plain).ok()
+    }
+
+    /// 先写临时文件再替换，避免中途退出留下损坏的缓存
+    pub fn save(&self, cached: &CachedLicense) -> io::Result<()> {
+        let plain = serde_json::to_vec(cached).map_err(io::Error::other)?;
+        let nonce = rand::random::<[u8; NONCE_LEN]>();
+        let ciphertext = self
+            .cipher()
+            .encrypt(Nonce::from_slice(&nonce), plain.as_slice())
+            .map_err(|_| io::Error::other("encrypt license cache failed"))?;
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let tmp = self.path.with_extension("tmp");
+        fs::write(&tmp, [nonce.as_slice(), ciphertext.as_slice()].concat())?;
+        fs::rename(tmp, &self.path)
+    }
+
+    pub fn clear(&self) -> io::Result<()> {
+        match fs::remove_file(&self.path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    fn cipher(&self) -> Aes256Gcm {
+        Aes256Gcm::new(&self.key.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_and_tamper() {
+        let dir = std::env::temp_dir().join(format!("license-client-cache-{}", std::process::id()));
+        let cache = LicenseCache::new(&dir, "com.example.app", "KEY", "dev-1");
+        assert_eq!(cache.load(), None);
+        let cached = CachedLicense {
+            code: Some("VIP-AAAAA".to_string()),
+            token: Some("payload.sig".to_string()),
+            public_key: None,
+            checked_at: 1_700_000_000,
+        };
+        cache.save(&cached).unwrap();
+        assert_eq!(cache.load(), Some(cached.clone()));
+        // 明文中不出现注册码
+        let raw = fs::read(cache.path()).unwrap();
+        assert!(!raw.windows(9).any(|w| w == b"VIP-AAAAA"));
+        // 换一台设备无法解密
+        assert_eq!(
+            LicenseCache::new(&dir, "com.example.app", "KEY", "dev-2").load(),
+            None
+        );
+        let mut raw = raw;
+        let last = raw.len() - 1;
+        raw[last] ^= 1;
+        fs::write(cache.path(), raw).unwrap();
+        assert_eq!(cache.load(), None);
+        cache.clear().unwrap();
+        cache.clear().unwrap();
+        let _ = fs::remove_dir_all(dir);
+    }
+}
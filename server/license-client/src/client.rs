@@ -0,0 +1,457 @@
+use crate::cache::{CachedLicense, LicenseCache};
+use crate::offline::{ActivationRequest, ActivationResponse};
+use crate::{Error, LicenseState, device, hmac_sha256_hex, token};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+// 服务器内部错误和外部服务错误，按无法联网处理，见服务端 constants.rs
+const SERVER_FAULT_CODES: [u16; 2] = [5002, 5008];
+// 注册码不存在、被吊销或设备被封禁、授权过期，服务器明确拒绝后清除缓存
+const NOT_FOUND_CODE: u16 = 5003;
+const LICENSE_REVOKED_CODE: u16 = 5010;
+const LICENSE_EXPIRED_CODE: u16 = 5011;
+// 允许的本地时间回拨，超过后不再信任缓存
+const CLOCK_SKEW_SECS: i64 = 300;
+
+pub struct ClientConfig {
+    /// 服务器地址，例如 `https://license.example.com`
+    pub server_url: String,
+    /// 应用的 app_id
+    pub app_id: String,
+    /// 应用的 app_valid_key
+    pub app_key: String,
+    /// 缓存目录，保存加密的许可证
+    pub cache_dir: PathBuf,
+    /// 注册码，为空时使用缓存中激活过的注册码，都没有时按试用校验
+    pub code: Option<String>,
+    /// 应用公钥（base64），建议内置在客户端；为空时首次联网从服务器获取并缓存
+    pub public_key: Option<String>,
+    /// 自定义设备ID，为空时使用 [`device::stable_id`]
+    pub device_id: Option<String>,
+    /// 上报的设备信息
+    pub device_info: Option<serde_json::Value>,
+    /// 请求超时
+    pub timeout: Duration,
+}
+
+impl ClientConfig {
+    pub fn new(
+        server_url: impl Into<String>,
+        app_id: impl Into<String>,
+        app_key: impl Into<String>,
+        cache_dir: impl Into<PathBuf>,
+    ) -> Self {
+        Self {
+            server_url: server_url.into(),
+            app_id: app_id.into(),
+            app_key: app_key.into(),
+            cache_dir: cache_dir.into(),
+            code: None,
+            public_key: None,
+            device_id: None,
+            device_info: None,
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+/// 服务端统一响应
+#[derive(Deserialize)]
+struct ApiResponse<T> {
+    success: bool,
+    #[serde(default)]
+    code: u16,
+    #[serde(default)]
+    message: String,
+    data: Option<T>,
+}
+
+#[derive(Deserialize)]
+struct ValidateData {
+    license_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct PublicKeyData {
+    public_key: String,
+}
+
+/// v2 签名响应，payload 为业务结果的 JSON
+#[derive(Deserialize)]
+struct SignedData {
+    payload: String,
+    timestamp: i64,
+    nonce: String,
+    sign: String,
+}
+
+/// 解绑结果
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Deactivation {
+    /// 已使用的自助解绑次数（含本次）
+    pub transfers_used: i32,
+    pub transfers_left: i32,
+    /// 下一次允许解绑的时间
+    pub next_transfer_at: DateTime<Utc>,
+}
+
+/// 一次联网请求的结果
+enum Outcome<T> {
+    Accepted(T),
+    /// 服务端错误码和提示
+    Rejected(u16, String),
+    Offline(String),
+}
+
+pub struct LicenseClient {
+    config: ClientConfig,
+    http: reqwest::Client,
+    device_id: String,
+    cache: LicenseCache,
+}
+
+impl LicenseClient {
+    pub fn new(config: ClientConfig) -> Result<Self, Error> {
+        if config.server_url.is_empty() || config.app_id.is_empty() || config.app_key.is_empty() {
+            return Err(Error::Config(
+                "server_url, app_id and app_key are required".to_string(),
+            ));
+        }
+        let device_id = match &config.device_id {
+            Some(id) => id.clone(),
+            None => device::stable_id(&config.app_id, &config.cache_dir)?,
+        };
+        let http = reqwest::Client::builder()
+            .timeout(config.timeout)
+            .build()
+            .map_err(|e| Error::Config(e.to_string()))?;
+        let cache = LicenseCache::new(
+            &config.cache_dir,
+            &config.app_id,
+            &config.app_key,
+            &device_id,
+        );
+        Ok(Self {
+            config,
+            http,
+            device_id,
+            cache,
+        })
+    }
+
+    pub fn device_id(&self) -> &str {
+        &self.device_id
+    }
+
+    /// 校验当前授权，联网失败时在离线宽限期内使用缓存
+    ///
+    /// 次数类注册码每次联网校验都会消耗一次次数。注册码不存在、被吊销或已过期时清除缓存，
+    /// 其它拒绝原因（例如服务端临时故障）不影响已缓存的授权。
+    pub async fn check(&self) -> Result<LicenseState, Error> {
+        let cached = self.cache.load().unwrap_or_default();
+        let code = self.config.code.clone().or_else(|| cached.code.clone());
+        self.validate(code, cached, true).await
+    }
+
+    /// 使用新的注册码激活，成功后保存到缓存，之后 check 不需要再传注册码
+    ///
+    /// 激活失败不影响已缓存的授权。
+    pub async fn activate(&self, code: &str) -> Result<LicenseState, Error> {
+        let cached = self.cache.load().unwrap_or_default();
+        self.validate(Some(code.to_string()), cached, false).await
+    }
+
+    /// 导出离线激活请求文件，交给管理员或在买家门户上传
+    pub fn export_activation_request(
+        &self,
+        code: &str,
+        path: impl AsRef<Path>,
+    ) -> Result<ActivationRequest, Error> {
+        let req = ActivationRequest::new(
+            &self.config.app_id,
+            &self.config.app_key,
+            code,
+            &self.device_id,
+            self.config.device_info.clone(),
+        );
+        req.save(path)?;
+        Ok(req)
+    }
+
+    /// 导入离线激活响应文件，校验通过后保存到缓存
+    ///
+    /// 设备无法联网获取公钥，需要在配置中内置应用公钥或之前联网校验过。
+    pub fn import_activation_response(
+        &self,
+        path: impl AsRef<Path>,
+    ) -> Result<LicenseState, Error> {
+        let resp = ActivationResponse::load(path)?;
+        let cached = self.cache.load().unwrap_or_default();
+        let public_key = self
+            .config
+            .public_key
+            .clone()
+            .or(cached.public_key)
+            .ok_or_else(|| {
+                Error::Config("public key is required for offline activation".to_string())
+            })?;
+        let license = token::verify(
+            &public_key,
+            &resp.license_token,
+            &self.config.app_id,
+            &self.device_id,
+        )?;
+        let now = Utc::now();
+        self.cache.save(&CachedLicense {
+            code: Some(resp.code),
+            token: Some(resp.license_token),
+            public_key: Some(public_key),
+            checked_at: cached.checked_at.max(now.timestamp()),
+        })?;
+        if license.is_expired(now) {
+            return Ok(LicenseState::Expired);
+        }
+        Ok(LicenseState::Valid(license))
+    }
+
+    /// 解绑当前设备，释放名额后可在新设备上激活同一注册码
+    ///
+    /// 成功后清除本地缓存。解绑次数和间隔由服务端按应用配置限制，超出时返回 [`Error::Rejected`]。
+    pub async fn deactivate(&self) -> Result<Deactivation, Error> {
+        let cached = self.cache.load().unwrap_or_default();
+        let code = self
+            .config
+            .code
+            .clone()
+            .or(cached.code)
+            .ok_or_else(|| Error::Config("no activated code to deactivate".to_string()))?;
+        let result = match self.signed_request("/api/reg/deactivate", Some(&code)).await? {
+            Outcome::Accepted(result) => result,
+            Outcome::Rejected(_, message) => return Err(Error::Rejected(message)),
+            Outcome::Offline(reason) => return Err(Error::Network(reason)),
+        };
+        self.cache.clear()?;
+        Ok(result)
+    }
+
+    /// 删除本地缓存
+    pub fn clear_cache(&self) -> Result<(), Error> {
+        Ok(self.cache.clear()?)
+    }
+
+    async fn validate(
+        &self,
+        code: Option<String>,
+        mut cached: CachedLicense,
+        clear_on_reject: bool,
+    ) -> Result<LicenseState, Error> {
+        let now = Utc::now();
+        let token = match self.request(code.as_deref()).await? {
+            Outcome::Accepted(token) => token,
+            Outcome::Rejected(status, message) => {
+                if clear_on_reject && is_definite_rejection(status) {
+                    // 服务器明确拒绝后不再允许离线使用，其它拒绝原因保留缓存
+                    self.cache.clear()?;
+                }
+                return Ok(rejected(status, message));
+            }
+            Outcome::Offline(reason) => return self.offline(cached, now, reason),
+        };
+        let public_key = match self.config.public_key.clone().or(cached.public_key.take()) {
+            Some(key) => key,
+            None => self.fetch_public_key().await?,
+        };
+        let license = token::verify(&public_key, &token, &self.config.app_id, &self.device_id)?;
+        self.cache.save(&CachedLicense {
+            code,
+            token: Some(token),
+            public_key: Some(public_key),
+            checked_at: cached.checked_at.max(now.timestamp()),
+        })?;
+        if license.is_expired(now) {
+            return Ok(LicenseState::Expired);
+        }
+        Ok(LicenseState::Valid(license))
+    }
+
+    fn offline(
+        &self,
+        mut cached: CachedLicense,
+        now: DateTime<Utc>,
+        reason: String,
+    ) -> Result<LicenseState, Error> {
+        let public_key = self
+            .config
+            .public_key
+            .as_ref()
+            .or(cached.public_key.as_ref());
+        let (Some(token), Some(public_key)) = (&cached.token, public_key) else {
+            return Ok(LicenseState::Unverified(reason));
+        };
+        let Ok(license) = token::verify(public_key, token, &self.config.app_id, &self.device_id)
+        else {
+            return Ok(LicenseState::Unverified(
+                "cached license invalid".to_string(),
+            ));
+        };
+        if now.timestamp() + CLOCK_SKEW_SECS < cached.checked_at {
+            return Ok(LicenseState::Unverified(
+                "system clock moved backwards".to_string(),
+            ));
+        }
+        if license.is_expired(now) {
+            return Ok(LicenseState::Expired);
+        }
+        let grace_until = license.grace_until();
+        if now > grace_until {
+            return Ok(LicenseState::Unverified(
+                "offline grace period exceeded".to_string(),
+            ));
+        }
+        if now.timestamp() > cached.checked_at {
+            cached.checked_at = now.timestamp();
+            self.cache.save(&cached)?;
+        }
+        Ok(LicenseState::Offline {
+            license,
+            grace_until,
+        })
+    }
+
+    /// 通过 v2 签名接口校验，app_key 只用于签名，不随请求发送
+    async fn request(&self, code: Option<&str>) -> Result<Outcome<String>, Error> {
+        let outcome = self
+            .signed_request::<ValidateData>("/api/reg/v2/validate", code)
+            .await?;
+        Ok(match outcome {
+            Outcome::Accepted(data) => Outcome::Accepted(
+                data.license_token
+                    .ok_or_else(|| Error::Protocol("missing license_token".to_string()))?,
+            ),
+            Outcome::Rejected(status, message) => Outcome::Rejected(status, message),
+            Outcome::Offline(reason) => Outcome::Offline(reason),
+        })
+    }
+
+    /// 按 v2 协议签名请求，校验响应签名后解析 payload
+    ///
+    /// 无法连接服务器或服务端内部错误时返回 [`Outcome::Offline`]。
+    async fn signed_request<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        code: Option<&str>,
+    ) -> Result<Outcome<T>, Error> {
+        let timestamp = Utc::now().timestamp();
+        let nonce = hex::encode(rand::random::<[u8; 16]>());
+        let content = format!(
+            "{}\n{}\n{}\n{}\n{}",
+            self.config.app_id,
+            self.device_id,
+            code.unwrap_or_default(),
+            timestamp,
+            nonce
+        );
+        let body = json!({
+            "app_id": self.config.app_id,
+            "device_id": self.device_id,
+            "code": code,
+            "timestamp": timestamp,
+            "nonce": nonce,
+            "sign": hmac_sha256_hex(&self.config.app_key, &content),
+            "device_info": self.config.device_info,
+        });
+        let resp = self.http.post(self.url(path)).json(&body).send().await;
+        let resp = match resp {
+            Ok(resp) if resp.status().is_server_error() => {
+                return Ok(Outcome::Offline(format!("server error {}", resp.status())));
+            }
+            Ok(resp) => resp,
+            Err(e) => return Ok(Outcome::Offline(e.to_string())),
+        };
+        let resp: ApiResponse<SignedData> = match resp.json().await {
+            Ok(resp) => resp,
+            Err(e) if e.is_timeout() => return Ok(Outcome::Offline(e.to_string())),
+            Err(e) => return Err(Error::Protocol(e.to_string())),
+        };
+        if !resp.success {
+            if SERVER_FAULT_CODES.contains(&resp.code) {
+                return Ok(Outcome::Offline(resp.message));
+            }
+            return Ok(Outcome::Rejected(resp.code, resp.message));
+        }
+        let data = resp
+            .data
+            .ok_or_else(|| Error::Protocol("missing data".to_string()))?;
+        let content = format!("{}\n{}\n{}", data.nonce, data.timestamp, data.payload);
+        if data.nonce != nonce || hmac_sha256_hex(&self.config.app_key, &content) != data.sign {
+            return Err(Error::Protocol("response signature mismatch".to_string()));
+        }
+        serde_json::from_str(&data.payload)
+            .map(Outcome::Accepted)
+            .map_err(|e| Error::Protocol(e.to_string()))
+    }
+
+    async fn fetch_public_key(&self) -> Result<String, Error> {
+        let url = self.url(&format!("/api/reg/public_key/{}", self.config.app_id));
+        let resp: ApiResponse<PublicKeyData> = self
+            .http
+            .get(url)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| Error::Protocol(e.to_string()))?
+            .json()
+            .await
+            .map_err(|e| Error::Protocol(e.to_string()))?;
+        resp.data
+            .map(|d| d.public_key)
+            .ok_or_else(|| Error::Protocol(format!("get public key failed: {}", resp.message)))
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.config.server_url.trim_end_matches('/'), path)
+    }
+}
+
+/// 按服务端错误码把过期、次数用完归为 Expired，其它拒绝原因原样返回
+fn rejected(status: u16, message: String) -> LicenseState {
+    if status == LICENSE_EXPIRED_CODE {
+        LicenseState::Expired
+    } else {
+        LicenseState::Rejected(message)
+    }
+}
+
+/// 注册码已不可用，不是临时故障或参数问题
+fn is_definite_rejection(status: u16) -> bool {
+    matches!(
+        status,
+        NOT_FOUND_CODE | LICENSE_REVOKED_CODE | LICENSE_EXPIRED_CODE
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rejection_by_code() {
+        assert_eq!(
+            rejected(LICENSE_EXPIRED_CODE, "code used up".to_string()),
+            LicenseState::Expired
+        );
+        // 提示里带 expired 但错误码不是过期的，不按过期处理
+        assert_eq!(
+            rejected(5000, "token expired".to_string()),
+            LicenseState::Rejected("token expired".to_string())
+        );
+        assert!(is_definite_rejection(LICENSE_REVOKED_CODE));
+        assert!(is_definite_rejection(NOT_FOUND_CODE));
+        assert!(!is_definite_rejection(5000));
+        assert!(!is_definite_rejection(5007));
+    }
+}
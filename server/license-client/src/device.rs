@@ -0,0 +1,95 @@
+//! 稳定的设备ID
+//!
+//! 优先读取操作系统的机器标识（Linux machine-id、macOS IOPlatformUUID、Windows MachineGuid），
+//! 与应用ID一起哈希后使用，不同应用拿到的设备ID互不相同，也不会泄露原始机器标识。
+//! 读取不到时生成随机标识保存在缓存目录代替机器标识，之后一直复用。
+
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// 缓存目录中保存随机机器标识的文件名
+pub const MACHINE_ID_FILE: &str = "machine_id";
+
+/// 返回当前机器上该应用的设备ID，多次调用结果相同
+pub fn stable_id(app_id: &str, cache_dir: &Path) -> std::io::Result<String> {
+    if let Some(machine_id) = machine_id() {
+        return Ok(derive(app_id, &machine_id));
+    }
+    let path = cache_dir.join(MACHINE_ID_FILE);
+    let seed = match fs::read_to_string(&path) {
+        Ok(seed) if !seed.trim().is_empty() => seed.trim().to_string(),
+        _ => {
+            let seed = hex::encode(rand::random::<[u8; 16]>());
+            fs::create_dir_all(cache_dir)?;
+            fs::write(&path, &seed)?;
+            seed
+        }
+    };
+    Ok(derive(app_id, &seed))
+}
+
+fn derive(app_id: &str, machine_id: &str) -> String {
+    let digest = Sha256::new()
+        .chain_update(app_id.as_bytes())
+        .chain_update(b":")
+        .chain_update(machine_id.as_bytes())
+        .finalize();
+    hex::encode(&digest[..16])
+}
+
+fn machine_id() -> Option<String> {
+    let id = if cfg!(target_os = "macos") {
+        command_output("ioreg", &["-rd1", "-c", "IOPlatformExpertDevice"]).and_then(|out| {
+            out.lines()
+                .find(|l| l.contains("IOPlatformUUID"))
+                .and_then(|l| l.split('"').nth(3))
+                .map(str::to_string)
+        })
+    } else if cfg!(windows) {
+        command_output(
+            "reg",
+            &[
+                "query",
+                r"HKLM\SOFTWARE\Microsoft\Cryptography",
+                "/v",
+                "MachineGuid",
+            ],
+        )
+        .and_then(|out| {
+            out.lines()
+                .find(|l| l.contains("MachineGuid"))
+                .and_then(|l| l.split_whitespace().last())
+                .map(str::to_string)
+        })
+    } else {
+        ["/etc/machine-id", "/var/lib/dbus/machine-id"]
+            .iter()
+            .find_map(|p| fs::read_to_string(p).ok())
+    };
+    id.map(|s| s.trim().to_string()).filter(|s| !s.is_empty())
+}
+
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let out = Command::new(program).args(args).output().ok()?;
+    out.status
+        .success()
+        .then(|| String::from_utf8_lossy(&out.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stable_per_app() {
+        let dir =
+            std::env::temp_dir().join(format!("license-client-device-{}", std::process::id()));
+        let a = stable_id("com.example.a", &dir).unwrap();
+        assert_eq!(a, stable_id("com.example.a", &dir).unwrap());
+        assert_ne!(a, stable_id("com.example.b", &dir).unwrap());
+        assert_eq!(a.len(), 32);
+        let _ = fs::remove_dir_all(dir);
+    }
+}
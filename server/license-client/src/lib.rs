@@ -0,0 +1,105 @@
+//! 注册码校验客户端
+//!
+//! 封装服务端 `/api/reg/validate` 接口，桌面应用只需要调用 [`LicenseClient::check`]：
+//!
+//! - 设备ID由机器标识和应用ID派生，同一台机器上保持不变，见 [`device`]
+//! - 每次联网校验成功后，签名许可证加密保存在本地缓存目录，见 [`cache`]
+//! - 无法联网时，使用缓存中的许可证，直到超出服务端下发的离线宽限期
+//! - 完全离线的设备通过请求/响应文件激活，见 [`offline`]
+//! - 更换设备前用 [`LicenseClient::deactivate`] 释放名额
+//!
+//! 许可证令牌使用应用公钥校验签名，宽限期以令牌中的签发时间为准，修改缓存或本地时间都无法延长。
+
+pub mod cache;
+mod client;
+pub mod device;
+pub mod offline;
+mod token;
+
+pub use client::{ClientConfig, Deactivation, LicenseClient};
+pub use offline::{ActivationRequest, ActivationResponse};
+pub use token::{CodeType, License};
+
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::fmt;
+
+/// 一次校验的结果
+#[derive(Debug, Clone, PartialEq)]
+pub enum LicenseState {
+    /// 服务器确认有效
+    Valid(License),
+    /// 无法联网，使用宽限期内的缓存许可证
+    Offline {
+        license: License,
+        /// 需要在此之前重新联网校验
+        grace_until: DateTime<Utc>,
+    },
+    /// 授权已过期或次数已用完
+    Expired,
+    /// 服务器明确拒绝，例如注册码被吊销、设备被封禁
+    Rejected(String),
+    /// 无法联网，且没有可用的缓存或已超出离线宽限期
+    Unverified(String),
+}
+
+impl LicenseState {
+    /// 当前是否允许使用
+    pub fn is_active(&self) -> bool {
+        matches!(self, LicenseState::Valid(_) | LicenseState::Offline { .. })
+    }
+
+    /// 可用时的许可证
+    pub fn license(&self) -> Option<&License> {
+        match self {
+            LicenseState::Valid(license) | LicenseState::Offline { license, .. } => Some(license),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    /// 配置不合法（服务器地址、公钥等）
+    Config(String),
+    /// 读写缓存失败
+    Io(std::io::Error),
+    /// 服务器返回的许可证签名不合法或与当前应用、设备不符
+    InvalidLicense(String),
+    /// 服务器响应无法解析
+    Protocol(String),
+    /// 无法连接服务器
+    Network(String),
+    /// 服务器拒绝请求，例如解绑次数已用完
+    Rejected(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Config(msg) => write!(f, "invalid config: {}", msg),
+            Error::Io(err) => write!(f, "cache io error: {}", err),
+            Error::InvalidLicense(msg) => write!(f, "invalid license: {}", msg),
+            Error::Protocol(msg) => write!(f, "unexpected server response: {}", msg),
+            Error::Network(msg) => write!(f, "network error: {}", msg),
+            Error::Rejected(msg) => write!(f, "rejected by server: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+/// hex(HMAC-SHA256(key, content))，与服务端 utils::signature 一致
+pub(crate) fn hmac_sha256_hex(key: &str, content: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(key.as_bytes()).expect("hmac accepts any key size");
+    mac.update(content.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
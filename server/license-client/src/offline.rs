@@ -0,0 +1,76 @@
+//! 离线激活文件
+//!
+//! 无法联网的设备导出 [`ActivationRequest`]，由管理员或买家在门户上传，
+//! 得到的 [`ActivationResponse`] 再导入设备。请求用 app_key 签名，响应中的许可证用应用公钥校验。
+
+use crate::{Error, hmac_sha256_hex};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// 离线激活请求文件，字段与服务端 OfflineActivationReq 一致
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ActivationRequest {
+    pub app_id: String,
+    pub code: String,
+    pub device_id: String,
+    pub device_info: Option<serde_json::Value>,
+    /// 生成时间（unix 秒）
+    pub created_at: i64,
+    pub nonce: String,
+    pub sign: String,
+}
+
+impl ActivationRequest {
+    pub(crate) fn new(
+        app_id: &str,
+        app_key: &str,
+        code: &str,
+        device_id: &str,
+        device_info: Option<serde_json::Value>,
+    ) -> Self {
+        let mut req = Self {
+            app_id: app_id.to_string(),
+            code: code.to_string(),
+            device_id: device_id.to_string(),
+            device_info,
+            created_at: chrono::Utc::now().timestamp(),
+            nonce: hex::encode(rand::random::<[u8; 16]>()),
+            sign: String::new(),
+        };
+        req.sign = hmac_sha256_hex(app_key, &req.sign_content());
+        req
+    }
+
+    fn sign_content(&self) -> String {
+        format!(
+            "offline\n{}\n{}\n{}\n{}\n{}",
+            self.app_id, self.device_id, self.code, self.created_at, self.nonce
+        )
+    }
+
+    /// 写入请求文件
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let body = serde_json::to_vec_pretty(self).map_err(|e| Error::Protocol(e.to_string()))?;
+        Ok(fs::write(path, body)?)
+    }
+}
+
+/// 服务端返回的离线激活响应文件
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ActivationResponse {
+    pub app_id: String,
+    pub device_id: String,
+    pub nonce: String,
+    pub code: String,
+    /// 签名许可证，导入时校验
+    pub license_token: String,
+}
+
+impl ActivationResponse {
+    /// 读取响应文件
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let body = fs::read(path)?;
+        serde_json::from_slice(&body).map_err(|e| Error::Protocol(e.to_string()))
+    }
+}
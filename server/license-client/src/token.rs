@@ -0,0 +1,146 @@
+use crate::Error;
+use base64::{
+    Engine,
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+};
+use chrono::{DateTime, Duration, Utc};
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+
+/// 注册码类型，与服务端 reg_codes.code_type 一致
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "i16", into = "i16")]
+pub enum CodeType {
+    /// 按时间授权，包括未使用注册码的试用期
+    Time,
+    /// 按次数授权，每次联网校验消耗一次
+    Count,
+    /// 浮动授权，需要使用租约接口
+    Floating,
+}
+
+impl From<i16> for CodeType {
+    fn from(value: i16) -> Self {
+        match value {
+            1 => CodeType::Count,
+            2 => CodeType::Floating,
+            _ => CodeType::Time,
+        }
+    }
+}
+
+impl From<CodeType> for i16 {
+    fn from(value: CodeType) -> Self {
+        match value {
+            CodeType::Time => 0,
+            CodeType::Count => 1,
+            CodeType::Floating => 2,
+        }
+    }
+}
+
+/// 服务端签发的许可证令牌载荷
+#[derive(Deserialize)]
+struct Claims {
+    app_id: String,
+    device_id: String,
+    code_type: CodeType,
+    expire_at: Option<i64>,
+    remaining_count: Option<i32>,
+    issued_at: i64,
+    offline_grace_days: i32,
+    #[serde(default)]
+    entitlements: serde_json::Value,
+    #[serde(default)]
+    offline_activation: bool,
+}
+
+/// 已校验签名的许可证
+#[derive(Debug, Clone, PartialEq)]
+pub struct License {
+    pub device_id: String,
+    pub code_type: CodeType,
+    /// 过期时间，None 表示不限时
+    pub expire_at: Option<DateTime<Utc>>,
+    pub remaining_count: Option<i32>,
+    /// 服务器签发时间
+    pub issued_at: DateTime<Utc>,
+    pub offline_grace_days: i32,
+    /// 功能开关与限额
+    pub entitlements: serde_json::Value,
+    /// 离线激活签发，有效期内不受离线宽限期限制
+    pub offline_activation: bool,
+    /// 原始令牌，可交给其它组件再次校验
+    pub token: String,
+}
+
+impl License {
+    /// 离线宽限期截止时间，离线激活的许可证以过期时间为准
+    pub fn grace_until(&self) -> DateTime<Utc> {
+        if self.offline_activation {
+            return self.expire_at.unwrap_or(DateTime::<Utc>::MAX_UTC);
+        }
+        self.issued_at + Duration::days(self.offline_grace_days.max(0) as i64)
+    }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expire_at.is_some_and(|t| t <= now)
+    }
+}
+
+fn timestamp(secs: i64) -> Result<DateTime<Utc>, Error> {
+    DateTime::from_timestamp(secs, 0)
+        .ok_or_else(|| Error::InvalidLicense(format!("invalid timestamp {}", secs)))
+}
+
+/// 校验令牌签名，并确认令牌属于当前应用和设备
+pub fn verify(
+    public_key: &str,
+    token: &str,
+    app_id: &str,
+    device_id: &str,
+) -> Result<License, Error> {
+    let key: [u8; 32] = STANDARD
+        .decode(public_key)
+        .ok()
+        .and_then(|b| b.try_into().ok())
+        .ok_or_else(|| Error::Config("invalid public key".to_string()))?;
+    let key = VerifyingKey::from_bytes(&key)
+        .map_err(|_| Error::Config("invalid public key".to_string()))?;
+    let (payload, signature) = token
+        .split_once('.')
+        .ok_or_else(|| Error::InvalidLicense("malformed token".to_string()))?;
+    let signature: [u8; 64] = URL_SAFE_NO_PAD
+        .decode(signature)
+        .ok()
+        .and_then(|b| b.try_into().ok())
+        .ok_or_else(|| Error::InvalidLicense("malformed signature".to_string()))?;
+    key.verify(payload.as_bytes(), &Signature::from_bytes(&signature))
+        .map_err(|_| Error::InvalidLicense("signature mismatch".to_string()))?;
+    let payload = URL_SAFE_NO_PAD
+        .decode(payload)
+        .map_err(|_| Error::InvalidLicense("malformed token".to_string()))?;
+    let claims: Claims =
+        serde_json::from_slice(&payload).map_err(|e| Error::InvalidLicense(e.to_string()))?;
+    if claims.app_id != app_id {
+        return Err(Error::InvalidLicense(
+            "license issued for another app".to_string(),
+        ));
+    }
+    if claims.device_id != device_id {
+        return Err(Error::InvalidLicense(
+            "license issued for another device".to_string(),
+        ));
+    }
+    Ok(License {
+        device_id: claims.device_id,
+        code_type: claims.code_type,
+        expire_at: claims.expire_at.map(timestamp).transpose()?,
+        remaining_count: claims.remaining_count,
+        issued_at: timestamp(claims.issued_at)?,
+        offline_grace_days: claims.offline_grace_days,
+        entitlements: claims.entitlements,
+        offline_activation: claims.offline_activation,
+        token: token.to_string(),
+    })
+}
@@ -0,0 +1,353 @@
+use license_client::{ClientConfig, CodeType, Error, LicenseClient, LicenseState};
+use serde_json::{Value, json};
+use std::env;
+use std::path::PathBuf;
+use std::process::Command;
+use tokio::sync::Mutex;
+
+// 每个测试都会重建数据库，不能并行
+static DB_LOCK: Mutex<()> = Mutex::const_new(());
+
+// 测试在 server 目录下运行，复用 .env.test、casbin_model.conf 和 init.sql
+fn prepare_env() {
+    env::set_current_dir(env!("CARGO_MANIFEST_DIR")).unwrap();
+    env::set_current_dir("..").unwrap();
+    dotenvy::from_filename(".env.test").unwrap();
+    let connect_url = format!(
+        "{}/{}",
+        env::var("DB_URL").expect("DB_URL not set"),
+        env::var("DB_NAME").expect("DB_NAME not set")
+    );
+    let output = Command::new("psql")
+        .env("PGCLIENTENCODING", "UTF8")
+        .arg(&connect_url)
+        .args(["-v", "ON_ERROR_STOP=1", "-q"])
+        .args(["-c", "SET client_min_messages = warning;"])
+        .args(["-f", "../pub/deploy/postgres/init/init.sql"])
+        .output()
+        .expect("failed to spawn psql");
+    assert!(
+        output.status.success(),
+        "psql init.sql failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+fn free_port() -> u16 {
+    std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+/// 在当前进程中启动 create_router 生成的服务，返回服务地址
+async fn start_server() -> String {
+    use salvo::prelude::*;
+    prepare_env();
+    let state = app_server::app::init_app().await.unwrap();
+    let service = app_server::router::create_router(state);
+    let addr = format!("127.0.0.1:{}", free_port());
+    let acceptor = TcpListener::new(addr.clone()).bind().await;
+    tokio::spawn(Server::new(acceptor).serve(service));
+    format!("http://{}", addr)
+}
+
+async fn admin_call(req: reqwest::RequestBuilder, token: &str) -> Value {
+    let json: Value = req
+        .bearer_auth(token)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(json["success"], true, "{}", json);
+    json["data"].clone()
+}
+
+async fn admin_login(http: &reqwest::Client, server: &str) -> String {
+    let login: Value = http
+        .post(format!("{}/api/login", server))
+        .json(&json!({"username": "admin", "password": "admin"}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    login["data"]["token"].as_str().unwrap().to_string()
+}
+
+fn cache_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("license-client-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+#[tokio::test]
+async fn test_check_online_offline_and_revoke() {
+    let _guard = DB_LOCK.lock().await;
+    let server = start_server().await;
+    let http = reqwest::Client::new();
+    let token = admin_login(&http, &server).await;
+    let app = admin_call(
+        http.post(format!("{}/api/admin/apps", server))
+            .json(&json!({
+                "name": "SDK App",
+                "app_id": "com.test.sdk",
+                "app_vername": "1.0.0",
+                "app_vercode": 1,
+                "app_download_url": "https://example.com/dl",
+                "app_res_url": "https://example.com/res",
+                "app_valid_key": "KEY_SDK",
+                "trial_days": 3,
+                "sort_order": 0,
+                "status": 1
+            })),
+        &token,
+    )
+    .await;
+    let app_id = app["id"].as_i64().unwrap();
+    let codes = admin_call(
+        http.post(format!("{}/api/admin/reg_codes/batch", server))
+            .json(&json!({
+                "app_id": app_id, "count": 1, "valid_days": 30, "max_devices": 1, "code_type": 0
+            })),
+        &token,
+    )
+    .await;
+    let code = codes["codes"][0]["code"].as_str().unwrap().to_string();
+    let code_id = codes["codes"][0]["id"].as_i64().unwrap();
+
+    let dir = cache_dir("flow");
+    let config = |url: &str| {
+        let mut config = ClientConfig::new(url, "com.test.sdk", "KEY_SDK", dir.clone());
+        config.device_id = Some("sdk-device-1".to_string());
+        config
+    };
+    let client = LicenseClient::new(config(&server)).unwrap();
+
+    // 未激活时为试用期
+    let state = client.check().await.unwrap();
+    let trial = state.license().cloned().expect("trial license");
+    assert!(matches!(state, LicenseState::Valid(_)));
+    assert_eq!(trial.code_type, CodeType::Time);
+    assert_eq!(trial.offline_grace_days, 7);
+
+    let state = client.activate(&code).await.unwrap();
+    let license = state.license().cloned().expect("activated license");
+    assert!(license.expire_at > trial.expire_at);
+    // 缓存中记住了注册码
+    let state = client.check().await.unwrap();
+    assert_eq!(state.license().unwrap().expire_at, license.expire_at);
+
+    // 服务器不可达时使用缓存
+    let offline = LicenseClient::new(config(&format!("http://127.0.0.1:{}", free_port()))).unwrap();
+    match offline.check().await.unwrap() {
+        LicenseState::Offline {
+            license: cached,
+            grace_until,
+        } => {
+            assert_eq!(cached.expire_at, license.expire_at);
+            assert_eq!(grace_until, cached.issued_at + chrono::Duration::days(7));
+        }
+        other => panic!("expected offline state, got {:?}", other),
+    }
+    // 其它设备无法使用这份缓存
+    let mut other = config(&format!("http://127.0.0.1:{}", free_port()));
+    other.device_id = Some("sdk-device-2".to_string());
+    let state = LicenseClient::new(other).unwrap().check().await.unwrap();
+    assert!(matches!(state, LicenseState::Unverified(_)), "{:?}", state);
+
+    // 宽限期为 0 时离线立即失效
+    admin_call(
+        http.put(format!("{}/api/admin/apps/{}", server, app_id))
+            .json(&json!({"offline_grace_days": 0})),
+        &token,
+    )
+    .await;
+    assert!(client.check().await.unwrap().is_active());
+    tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+    let state = offline.check().await.unwrap();
+    assert_eq!(
+        state,
+        LicenseState::Unverified("offline grace period exceeded".to_string())
+    );
+
+    // 解绑后缓存清除，回到试用期，同一注册码可重新激活
+    let result = client.deactivate().await.unwrap();
+    assert_eq!(result.transfers_used, 1);
+    let state = client.check().await.unwrap();
+    assert!(state.license().unwrap().expire_at < license.expire_at);
+    assert!(client.activate(&code).await.unwrap().is_active());
+    // 默认冷却时间内不能再次解绑
+    assert!(matches!(client.deactivate().await, Err(Error::Rejected(_))));
+
+    // 吊销后联网校验被拒绝，缓存清除
+    admin_call(
+        http.post(format!("{}/api/admin/reg_codes/{}/revoke", server, code_id))
+            .json(&json!({"reason": "refund"})),
+        &token,
+    )
+    .await;
+    let state = client.check().await.unwrap();
+    assert!(
+        matches!(&state, LicenseState::Rejected(msg) if msg.contains("refund")),
+        "{:?}",
+        state
+    );
+    assert!(matches!(
+        offline.check().await.unwrap(),
+        LicenseState::Unverified(_)
+    ));
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[tokio::test]
+async fn test_offline_activation_files() {
+    let _guard = DB_LOCK.lock().await;
+    let server = start_server().await;
+    let http = reqwest::Client::new();
+    let token = admin_login(&http, &server).await;
+    let app = admin_call(
+        http.post(format!("{}/api/admin/apps", server))
+            .json(&json!({
+                "name": "Offline App",
+                "app_id": "com.test.offline",
+                "app_vername": "1.0.0",
+                "app_vercode": 1,
+                "app_download_url": "https://example.com/dl",
+                "app_res_url": "https://example.com/res",
+                "app_valid_key": "KEY_OFFLINE",
+                "trial_days": 3,
+                "sort_order": 0,
+                "status": 1
+            })),
+        &token,
+    )
+    .await;
+    let codes = admin_call(
+        http.post(format!("{}/api/admin/reg_codes/batch", server))
+            .json(&json!({
+                "app_id": app["id"], "count": 1, "valid_days": 30, "max_devices": 1, "code_type": 0
+            })),
+        &token,
+    )
+    .await;
+    let code = codes["codes"][0]["code"].as_str().unwrap().to_string();
+    let key: Value = http
+        .get(format!("{}/api/reg/public_key/com.test.offline", server))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    // 设备完全离线，公钥内置在客户端中
+    let dir = cache_dir("offline");
+    let mut config = ClientConfig::new(
+        format!("http://127.0.0.1:{}", free_port()),
+        "com.test.offline",
+        "KEY_OFFLINE",
+        dir.clone(),
+    );
+    config.device_id = Some("air-gapped-1".to_string());
+    config.public_key = key["data"]["public_key"].as_str().map(str::to_string);
+    let client = LicenseClient::new(config).unwrap();
+    assert!(matches!(
+        client.check().await.unwrap(),
+        LicenseState::Unverified(_)
+    ));
+
+    std::fs::create_dir_all(&dir).unwrap();
+    let request_file = dir.join("request.json");
+    let req = client
+        .export_activation_request(&code, &request_file)
+        .unwrap();
+    let resp = http
+        .post(format!("{}/api/admin/offline_activations", server))
+        .bearer_auth(&token)
+        .header("Content-Type", "application/json")
+        .body(std::fs::read(&request_file).unwrap())
+        .send()
+        .await
+        .unwrap();
+    let disposition = resp.headers()["content-disposition"].to_str().unwrap();
+    assert!(disposition.contains(&req.nonce), "{}", disposition);
+    let response_file = dir.join("response.json");
+    std::fs::write(&response_file, resp.bytes().await.unwrap()).unwrap();
+
+    let state = client.import_activation_response(&response_file).unwrap();
+    let license = state.license().cloned().expect("activated license");
+    assert!(license.offline_activation);
+    assert_eq!(license.grace_until(), license.expire_at.unwrap());
+    // 之后离线校验不受宽限期限制
+    match client.check().await.unwrap() {
+        LicenseState::Offline { grace_until, .. } => {
+            assert_eq!(Some(grace_until), license.expire_at)
+        }
+        other => panic!("expected offline state, got {:?}", other),
+    }
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[tokio::test]
+async fn test_check_without_legacy_validate() {
+    let _guard = DB_LOCK.lock().await;
+    let server = start_server().await;
+    let http = reqwest::Client::new();
+    let token = admin_login(&http, &server).await;
+    let app = admin_call(
+        http.post(format!("{}/api/admin/apps", server))
+            .json(&json!({
+                "name": "Signed App",
+                "app_id": "com.test.signed",
+                "app_vername": "1.0.0",
+                "app_vercode": 1,
+                "app_download_url": "https://example.com/dl",
+                "app_res_url": "https://example.com/res",
+                "app_valid_key": "KEY_SIGNED",
+                "trial_days": 3,
+                "allow_legacy_validate": false,
+                "sort_order": 0,
+                "status": 1
+            })),
+        &token,
+    )
+    .await;
+    let codes = admin_call(
+        http.post(format!("{}/api/admin/reg_codes/batch", server))
+            .json(&json!({
+                "app_id": app["id"], "count": 1, "valid_days": 30, "max_devices": 1, "code_type": 0
+            })),
+        &token,
+    )
+    .await;
+    let code = codes["codes"][0]["code"].as_str().unwrap().to_string();
+
+    // 关闭旧版校验后 SDK 仍通过签名接口校验和激活
+    let dir = cache_dir("signed");
+    let mut config = ClientConfig::new(&server, "com.test.signed", "KEY_SIGNED", dir.clone());
+    config.device_id = Some("signed-device-1".to_string());
+    let client = LicenseClient::new(config).unwrap();
+    let state = client.check().await.unwrap();
+    assert!(matches!(state, LicenseState::Valid(_)), "{:?}", state);
+    let state = client.activate(&code).await.unwrap();
+    assert!(state.is_active(), "{:?}", state);
+    assert!(client.check().await.unwrap().is_active());
+
+    // 密钥不对时签名校验失败，不会当作离线处理
+    let wrong_dir = cache_dir("signed-wrong");
+    let mut config = ClientConfig::new(&server, "com.test.signed", "WRONG_KEY", wrong_dir.clone());
+    config.device_id = Some("signed-device-2".to_string());
+    let state = LicenseClient::new(config).unwrap().check().await.unwrap();
+    assert!(
+        matches!(&state, LicenseState::Rejected(msg) if msg.contains("SIGN_MISMATCH")),
+        "{:?}",
+        state
+    );
+    let _ = std::fs::remove_dir_all(dir);
+    let _ = std::fs::remove_dir_all(wrong_dir);
+}
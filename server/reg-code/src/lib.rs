@@ -0,0 +1,280 @@
+//! 带校验段的注册码格式
+//!
+//! 注册码形如 `PREFIX-XXXXX-XXXXX-XXXXX-CCCCC`，最后一段为校验段。
+//! 校验段由前面所有字符（不含分隔符）和可选的应用标识计算得出，
+//! 因此 [`verify`] 只需要注册码本身即可离线判断是否输错，无需知道生成时的模板。
+
+use std::fmt;
+
+/// 去掉易混淆字符（0/O、1/I/L）后的默认字符集，校验段固定使用该字符集
+pub const DEFAULT_CHARSET: &str = "23456789ABCDEFGHJKMNPQRSTUVWXYZ";
+/// 段分隔符
+pub const SEPARATOR: char = '-';
+/// 注册码最大长度
+pub const MAX_CODE_LEN: usize = 128;
+/// 校验段长度范围
+pub const MIN_CHECKSUM_LEN: usize = 3;
+pub const MAX_CHECKSUM_LEN: usize = 12;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// 格式模板不合法
+    InvalidFormat(&'static str),
+    /// 注册码结构不合法（空、过长、非法字符、缺少校验段）
+    Malformed(&'static str),
+    /// 校验段不匹配，通常是输错或用错了应用
+    ChecksumMismatch,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidFormat(msg) => write!(f, "invalid code format: {}", msg),
+            Error::Malformed(msg) => write!(f, "malformed code: {}", msg),
+            Error::ChecksumMismatch => write!(f, "code checksum mismatch"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// 注册码格式模板
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct CodeFormat {
+    /// 前缀，为空时不输出
+    pub prefix: String,
+    /// 随机段数量
+    pub segments: usize,
+    /// 每段长度（校验段长度相同）
+    pub segment_len: usize,
+    /// 随机段字符集，为空时使用 DEFAULT_CHARSET
+    pub charset: Option<String>,
+    /// 是否追加校验段
+    pub checksum: bool,
+}
+
+impl Default for CodeFormat {
+    fn default() -> Self {
+        Self {
+            prefix: String::new(),
+            segments: 4,
+            segment_len: 5,
+            charset: None,
+            checksum: true,
+        }
+    }
+}
+
+impl CodeFormat {
+    fn charset(&self) -> Vec<char> {
+        self.charset
+            .as_deref()
+            .filter(|c| !c.is_empty())
+            .unwrap_or(DEFAULT_CHARSET)
+            .chars()
+            .collect()
+    }
+
+    /// 检查模板是否合法
+    pub fn check(&self) -> Result<(), Error> {
+        if self.segments == 0 || self.segments > 8 {
+            return Err(Error::InvalidFormat("segments must be between 1 and 8"));
+        }
+        if self.segment_len < MIN_CHECKSUM_LEN || self.segment_len > MAX_CHECKSUM_LEN {
+            return Err(Error::InvalidFormat("segment_len must be between 3 and 12"));
+        }
+        if !self.prefix.chars().all(is_code_char) {
+            return Err(Error::InvalidFormat("prefix must be alphanumeric"));
+        }
+        let charset = self.charset();
+        let mut sorted = charset.clone();
+        sorted.sort_unstable();
+        sorted.dedup();
+        if charset.len() < 10 || sorted.len() != charset.len() {
+            return Err(Error::InvalidFormat(
+                "charset must contain at least 10 distinct characters",
+            ));
+        }
+        if !charset.iter().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(Error::InvalidFormat("charset must be alphanumeric"));
+        }
+        let len = self.prefix.len()
+            + self.segments * (self.segment_len + 1)
+            + if self.checksum { self.segment_len } else { 0 };
+        if len > MAX_CODE_LEN {
+            return Err(Error::InvalidFormat("code is too long"));
+        }
+        Ok(())
+    }
+
+    /// 随机段可组合出的注册码总数，超出 u128 时取 u128::MAX
+    pub fn capacity(&self) -> u128 {
+        let base = self.charset().len() as u128;
+        let exp = (self.segments * self.segment_len) as u32;
+        base.checked_pow(exp).unwrap_or(u128::MAX)
+    }
+
+    /// 随机生成一个注册码，discriminator 为应用标识，带校验段时参与计算
+    #[cfg(feature = "generate")]
+    pub fn generate(&self, discriminator: Option<&str>) -> String {
+        use rand::Rng;
+        let charset = self.charset();
+        let mut rng = rand::thread_rng();
+        let mut parts: Vec<String> = Vec::with_capacity(self.segments + 2);
+        if !self.prefix.is_empty() {
+            parts.push(self.prefix.clone());
+        }
+        for _ in 0..self.segments {
+            parts.push(
+                (0..self.segment_len)
+                    .map(|_| charset[rng.gen_range(0..charset.len())])
+                    .collect(),
+            );
+        }
+        if self.checksum {
+            let body = parts.concat();
+            parts.push(checksum(discriminator, &body, self.segment_len));
+        }
+        parts.join(&SEPARATOR.to_string())
+    }
+}
+
+fn is_code_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// 校验注册码结构与校验段，discriminator 需与生成时一致
+pub fn verify(code: &str, discriminator: Option<&str>) -> Result<(), Error> {
+    let code = code.trim();
+    if code.is_empty() {
+        return Err(Error::Malformed("empty code"));
+    }
+    if code.len() > MAX_CODE_LEN {
+        return Err(Error::Malformed("code is too long"));
+    }
+    if !code.chars().all(|c| is_code_char(c) || c == SEPARATOR) {
+        return Err(Error::Malformed("invalid character"));
+    }
+    let (body, sum) = code
+        .rsplit_once(SEPARATOR)
+        .ok_or(Error::Malformed("missing checksum segment"))?;
+    if sum.len() < MIN_CHECKSUM_LEN || sum.len() > MAX_CHECKSUM_LEN {
+        return Err(Error::Malformed("invalid checksum segment"));
+    }
+    if body.split(SEPARATOR).any(|s| s.is_empty()) {
+        return Err(Error::Malformed("empty segment"));
+    }
+    let body: String = body.split(SEPARATOR).collect();
+    if checksum(discriminator, &body, sum.len()) != sum {
+        return Err(Error::ChecksumMismatch);
+    }
+    Ok(())
+}
+
+/// 基于 FNV-1a 的校验段，长度为 len，字符取自 DEFAULT_CHARSET
+fn checksum(discriminator: Option<&str>, body: &str, len: usize) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let input = discriminator
+        .unwrap_or_default()
+        .bytes()
+        .chain(std::iter::once(0))
+        .chain(body.bytes());
+    for b in input {
+        hash ^= b as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    // 31^12 < 2^64，最长 12 位校验段也能完全由 hash 得出
+    let charset = DEFAULT_CHARSET.as_bytes();
+    let base = charset.len() as u64;
+    let mut out = String::with_capacity(len);
+    for _ in 0..len {
+        out.push(charset[(hash % base) as usize] as char);
+        hash /= base;
+    }
+    out
+}
+
+#[cfg(all(test, feature = "generate"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_and_verify() {
+        let format = CodeFormat {
+            prefix: "VIP".into(),
+            ..Default::default()
+        };
+        format.check().unwrap();
+        for _ in 0..100 {
+            let code = format.generate(Some("com.example.app"));
+            assert_eq!(code.split(SEPARATOR).count(), 6);
+            assert!(code.starts_with("VIP-"));
+            verify(&code, Some("com.example.app")).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_discriminator_mismatch() {
+        let format = CodeFormat::default();
+        let code = format.generate(Some("com.example.app"));
+        assert_eq!(
+            verify(&code, Some("com.other.app")),
+            Err(Error::ChecksumMismatch)
+        );
+        let code = format.generate(None);
+        verify(&code, None).unwrap();
+    }
+
+    #[test]
+    fn test_typo_detected() {
+        let code = CodeFormat::default().generate(None);
+        let mut chars: Vec<char> = code.chars().collect();
+        chars[0] = if chars[0] == 'A' { 'B' } else { 'A' };
+        let typo: String = chars.into_iter().collect();
+        assert_eq!(verify(&typo, None), Err(Error::ChecksumMismatch));
+    }
+
+    #[test]
+    fn test_malformed() {
+        assert!(matches!(verify("", None), Err(Error::Malformed(_))));
+        assert!(matches!(verify("ABCDE", None), Err(Error::Malformed(_))));
+        assert!(matches!(verify("AB CD-EFG", None), Err(Error::Malformed(_))));
+        assert!(matches!(verify("ABCDE--XYZ", None), Err(Error::Malformed(_))));
+    }
+
+    #[test]
+    fn test_invalid_format() {
+        let format = CodeFormat {
+            segment_len: 1,
+            ..Default::default()
+        };
+        assert!(format.check().is_err());
+        let format = CodeFormat {
+            charset: Some("AAB".into()),
+            ..Default::default()
+        };
+        assert!(format.check().is_err());
+    }
+
+    #[test]
+    fn test_capacity() {
+        let format = CodeFormat {
+            segments: 1,
+            segment_len: 3,
+            charset: Some("0123456789".into()),
+            ..Default::default()
+        };
+        assert_eq!(format.capacity(), 1000);
+        let format = CodeFormat {
+            segments: 8,
+            segment_len: 12,
+            ..Default::default()
+        };
+        assert_eq!(format.capacity(), u128::MAX);
+    }
+}
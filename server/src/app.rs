@@ -1,5 +1,7 @@
 use crate::database;
 use crate::services::casbin_service::CasbinService;
+use crate::services::scheduler;
+use crate::services::validation_log::ValidationLogger;
 use crate::types::config::Config;
 use crate::types::{common::AppState, error::AppError};
 use crate::utils::redis_cache::RedisCache;
@@ -24,6 +26,10 @@ pub async fn init_app() -> Result<AppState, AppError> {
     let config = Config::from_env()
         .map_err(|e| AppError::Message(format!("config load failed:{}", e.to_string())))?;
     tracing::info!("Configuration loaded successfully");
+    init_app_with_config(config).await
+}
+
+pub async fn init_app_with_config(config: Config) -> Result<AppState, AppError> {
     // 初始化数据库
     let db_pool = database::init_db(&config.database)
         .await
@@ -42,6 +48,8 @@ pub async fn init_app() -> Result<AppState, AppError> {
         .await
         .map_err(|e| AppError::Message(format!("casbin connection failed:{}", e)))?;
     tracing::info!("Casbin connected successfully");
+    // 校验日志后台批量写入
+    let validation_log = ValidationLogger::start(db_pool.clone());
     // 创建应用状态
     let app_state = AppState {
         db: db_pool,
@@ -52,8 +60,12 @@ pub async fn init_app() -> Result<AppState, AppError> {
             &config.oss.access_key_id,
             &config.oss.access_key_secret,
         )),
+        validation_log,
         config: Arc::new(config),
     };
+    if app_state.config.scheduler_enabled {
+        scheduler::start(app_state.clone());
+    }
     // 创建路由
     Ok(app_state)
 }
@@ -1,7 +1,9 @@
 pub const ADMIN_ROLE: &str = "admin";
 pub const USER_ROLE: &str = "user";
+pub const RESELLER_ROLE: &str = "reseller";
 pub const DEFAULT_ROLE_ID: i32 = 2;
 pub const ADMIN_ROLE_ID: i32 = 1;
+pub const RESELLER_ROLE_ID: i32 = 4;
 
 //stauts
 pub const APP_OK: u16 = 0;
@@ -15,3 +17,10 @@ pub const APP_FORBIDDEN: u16 = 5006;
 pub const APP_BUSINESS_LOGIC: u16 = 5007;
 pub const APP_EXTERNAL_SERVICE: u16 = 5008;
 pub const APP_USER_ALREADY_EXISTS: u16 = 5009;
+// 注册码被吊销或设备被封禁
+pub const APP_LICENSE_REVOKED: u16 = 5010;
+// 注册码或设备已过期、次数已用完
+pub const APP_LICENSE_EXPIRED: u16 = 5011;
+
+// 客户端 GET 接口通过请求头传递应用校验Key，不放在查询参数中，避免写入代理和访问日志
+pub const APP_KEY_HEADER: &str = "x-app-key";
@@ -0,0 +1,324 @@
+use crate::constants::APP_KEY_HEADER;
+use crate::handlers::app_configs_handler;
+use crate::types::app_announcements_types::*;
+use crate::types::app_configs_types::{ConfigAudience, vercode_in_range};
+crate::import_crud_macro!();
+use entity::{app_announcement_dismissals, app_announcements, apps};
+use salvo::{oapi::extract::JsonBody, prelude::*};
+use salvo_oapi::extract::PathParam;
+use sea_orm::Condition;
+use sea_orm::sea_query::OnConflict;
+use std::collections::HashSet;
+use validator::Validate;
+
+fn check_announcement(announcement: &app_announcements::ActiveModel) -> Result<(), AppError> {
+    if let (Some(start), Some(end)) = (announcement.start_at.as_ref(), announcement.end_at.as_ref())
+        && start >= end
+    {
+        return Err(AppError::validation("start_at must be earlier than end_at"));
+    }
+    let (min, max) = (
+        *announcement.min_vercode.as_ref(),
+        *announcement.max_vercode.as_ref(),
+    );
+    if max > 0 && min > max {
+        return Err(AppError::validation(
+            "min_vercode must not exceed max_vercode",
+        ));
+    }
+    Ok(())
+}
+
+async fn find_app_by_key(state: &AppState, app_key: &str) -> Result<apps::Model, AppError> {
+    if app_key.is_empty() {
+        return Err(AppError::validation("app_key is required"));
+    }
+    let app = apps::Entity::find()
+        .filter(apps::Column::AppValidKey.eq(app_key))
+        .one(&state.db)
+        .await?;
+    app.ok_or(AppError::not_found("apps".to_string(), None))
+}
+
+// Create AppAnnouncement
+#[handler]
+pub async fn add(
+    depot: &mut Depot,
+    req: JsonBody<CreateAnnouncementReq>,
+) -> Result<ApiResponse<app_announcements::Model>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let announcement = add_impl(state, req.into_inner()).await?;
+    Ok(ApiResponse::success(announcement))
+}
+
+pub async fn add_impl(
+    state: &AppState,
+    req: CreateAnnouncementReq,
+) -> Result<app_announcements::Model, AppError> {
+    req.validate()?;
+    apps::Entity::find_by_id(req.app_id)
+        .one(&state.db)
+        .await?
+        .ok_or_else(|| AppError::not_found("apps".to_string(), Some(req.app_id)))?;
+    let now = Utc::now();
+    let announcement = app_announcements::ActiveModel {
+        app_id: Set(req.app_id),
+        kind: Set(req.kind.into()),
+        title: Set(req.title),
+        content: Set(req.content),
+        link_url: Set(req.link_url),
+        start_at: Set(req.start_at),
+        end_at: Set(req.end_at),
+        min_vercode: Set(req.min_vercode),
+        max_vercode: Set(req.max_vercode),
+        audience: Set(req.audience.into()),
+        priority: Set(req.priority),
+        dismissible: Set(req.dismissible),
+        enabled: Set(req.enabled),
+        created_at: Set(now),
+        updated_at: Set(now),
+        ..Default::default()
+    };
+    check_announcement(&announcement)?;
+    let announcement = announcement.insert(&state.db).await?;
+    Ok(announcement)
+}
+
+// Update AppAnnouncement
+#[handler]
+pub async fn update(
+    depot: &mut Depot,
+    id: PathParam<i32>,
+    req: JsonBody<UpdateAnnouncementReq>,
+) -> Result<ApiResponse<app_announcements::Model>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let announcement = update_impl(state, id.into_inner(), req.into_inner()).await?;
+    Ok(ApiResponse::success(announcement))
+}
+
+pub async fn update_impl(
+    state: &AppState,
+    id: i32,
+    req: UpdateAnnouncementReq,
+) -> Result<app_announcements::Model, AppError> {
+    req.validate()?;
+    let announcement = app_announcements::Entity::find_by_id(id)
+        .one(&state.db)
+        .await?;
+    let announcement = announcement
+        .ok_or_else(|| AppError::not_found("app_announcements".to_string(), Some(id)))?;
+    let mut announcement: app_announcements::ActiveModel = announcement.into_active_model();
+    crate::update_field_if_some!(announcement, kind, req.kind.map(i16::from));
+    crate::update_field_if_some!(announcement, title, req.title);
+    crate::update_field_if_some!(announcement, content, req.content);
+    crate::update_field_if_some!(announcement, link_url, req.link_url, option);
+    crate::update_field_if_some!(announcement, start_at, req.start_at, option);
+    crate::update_field_if_some!(announcement, end_at, req.end_at, option);
+    crate::update_field_if_some!(announcement, min_vercode, req.min_vercode);
+    crate::update_field_if_some!(announcement, max_vercode, req.max_vercode);
+    crate::update_field_if_some!(announcement, audience, req.audience.map(i16::from));
+    crate::update_field_if_some!(announcement, priority, req.priority);
+    crate::update_field_if_some!(announcement, dismissible, req.dismissible);
+    crate::update_field_if_some!(announcement, enabled, req.enabled);
+    check_announcement(&announcement)?;
+    announcement.updated_at = Set(Utc::now());
+    let announcement = announcement.update(&state.db).await?;
+    Ok(announcement)
+}
+
+// Delete AppAnnouncement
+#[handler]
+pub async fn delete(depot: &mut Depot, id: PathParam<i32>) -> Result<ApiResponse<()>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    delete_impl(state, id.into_inner()).await?;
+    Ok(ApiResponse::success(()))
+}
+
+pub async fn delete_impl(state: &AppState, id: i32) -> Result<(), AppError> {
+    let res = app_announcements::Entity::delete_by_id(id)
+        .exec(&state.db)
+        .await?;
+    if res.rows_affected == 0 {
+        return Err(AppError::not_found(
+            "app_announcements".to_string(),
+            Some(id),
+        ));
+    }
+    Ok(())
+}
+
+// Get AppAnnouncements List
+#[handler]
+pub async fn get_list(
+    depot: &mut Depot,
+    req: &mut Request,
+) -> Result<ApiResponse<PagingResponse<app_announcements::Model>>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let params = req.parse_queries::<SearchAnnouncementsParams>()?;
+    let list = get_list_impl(state, params).await?;
+    Ok(ApiResponse::success(list))
+}
+
+pub async fn get_list_impl(
+    state: &AppState,
+    params: SearchAnnouncementsParams,
+) -> Result<PagingResponse<app_announcements::Model>, AppError> {
+    let page = params.pagination.page.unwrap_or(1);
+    let page_size = params.pagination.page_size.unwrap_or(20);
+    let mut query = app_announcements::Entity::find()
+        .order_by_desc(app_announcements::Column::Priority)
+        .order_by_desc(app_announcements::Column::Id);
+    crate::filter_if_some!(query, app_announcements::Column::AppId, params.app_id, eq);
+    crate::filter_if_some!(query, app_announcements::Column::Kind, params.kind, eq);
+    crate::filter_if_some!(
+        query,
+        app_announcements::Column::Title,
+        params.title,
+        contains
+    );
+    let paginator = query.paginate(&state.db, page_size);
+    let total = paginator.num_items().await?;
+    let list = paginator.fetch_page(page - 1).await?;
+    Ok(PagingResponse { list, total, page })
+}
+
+// Get AppAnnouncement by ID
+#[handler]
+pub async fn get_by_id(
+    depot: &mut Depot,
+    id: PathParam<i32>,
+) -> Result<ApiResponse<app_announcements::Model>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let announcement = get_by_id_impl(state, id.into_inner()).await?;
+    Ok(ApiResponse::success(announcement))
+}
+
+pub async fn get_by_id_impl(
+    state: &AppState,
+    id: i32,
+) -> Result<app_announcements::Model, AppError> {
+    let announcement = app_announcements::Entity::find_by_id(id)
+        .one(&state.db)
+        .await?;
+    announcement.ok_or_else(|| AppError::not_found("app_announcements".to_string(), Some(id)))
+}
+
+/// Get the active announcements of an app
+#[endpoint(
+    tags("app_announcements"),
+    parameters(
+        ("x-app-key"=String, Header, description = "应用校验Key"),
+        ("device_id"=Option<String>, Query, description = "设备ID，用于排除已关闭的公告和匹配已授权 / 试用设备的公告"),
+        ("vercode"=Option<i32>, Query, description = "客户端当前版本号")
+))]
+pub async fn get_active(
+    depot: &mut Depot,
+    req: &mut Request,
+) -> Result<ApiResponse<Vec<AnnouncementInfo>>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let mut params = req.parse_queries::<ActiveAnnouncementsParams>()?;
+    params.app_key = req.header::<String>(APP_KEY_HEADER).unwrap_or_default();
+    let list = get_active_impl(state, params).await?;
+    Ok(ApiResponse::success(list))
+}
+
+/// 返回生效时间窗口内、版本和授权状态匹配且设备未关闭的公告，按 priority 从高到低排列
+pub async fn get_active_impl(
+    state: &AppState,
+    params: ActiveAnnouncementsParams,
+) -> Result<Vec<AnnouncementInfo>, AppError> {
+    let app = find_app_by_key(state, &params.app_key).await?;
+    let now = Utc::now();
+    let announcements = app_announcements::Entity::find()
+        .filter(app_announcements::Column::AppId.eq(app.id))
+        .filter(app_announcements::Column::Enabled.eq(true))
+        .filter(
+            Condition::any()
+                .add(app_announcements::Column::StartAt.is_null())
+                .add(app_announcements::Column::StartAt.lte(now)),
+        )
+        .filter(
+            Condition::any()
+                .add(app_announcements::Column::EndAt.is_null())
+                .add(app_announcements::Column::EndAt.gt(now)),
+        )
+        .order_by_desc(app_announcements::Column::Priority)
+        .order_by_desc(app_announcements::Column::Id)
+        .all(&state.db)
+        .await?;
+    let device_id = params.device_id.filter(|d| !d.is_empty());
+    let (licensed, dismissed) = match &device_id {
+        Some(device_id) => {
+            let licensed = app_configs_handler::is_licensed(&state.db, app.id, device_id).await?;
+            let dismissed: HashSet<i32> = app_announcement_dismissals::Entity::find()
+                .filter(app_announcement_dismissals::Column::DeviceId.eq(device_id.as_str()))
+                .filter(
+                    app_announcement_dismissals::Column::AnnouncementId
+                        .is_in(announcements.iter().map(|a| a.id)),
+                )
+                .all(&state.db)
+                .await?
+                .into_iter()
+                .map(|d| d.announcement_id)
+                .collect();
+            (Some(licensed), dismissed)
+        }
+        None => (None, HashSet::new()),
+    };
+    Ok(announcements
+        .into_iter()
+        .filter(|a| vercode_in_range(params.vercode, a.min_vercode, a.max_vercode))
+        .filter(|a| ConfigAudience::from(a.audience).matches(licensed))
+        .filter(|a| !dismissed.contains(&a.id))
+        .map(AnnouncementInfo::from)
+        .collect())
+}
+
+/// Dismiss an announcement on a device
+#[endpoint(tags("app_announcements"))]
+pub async fn dismiss(
+    depot: &mut Depot,
+    req: JsonBody<DismissAnnouncementReq>,
+) -> Result<ApiResponse<()>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    dismiss_impl(state, req.into_inner()).await?;
+    Ok(ApiResponse::success(()))
+}
+
+/// 记录设备关闭公告，重复关闭不报错
+pub async fn dismiss_impl(state: &AppState, req: DismissAnnouncementReq) -> Result<(), AppError> {
+    req.validate()?;
+    let app = find_app_by_key(state, &req.app_key).await?;
+    let announcement = app_announcements::Entity::find_by_id(req.announcement_id)
+        .filter(app_announcements::Column::AppId.eq(app.id))
+        .one(&state.db)
+        .await?;
+    let announcement = announcement.ok_or_else(|| {
+        AppError::not_found("app_announcements".to_string(), Some(req.announcement_id))
+    })?;
+    if !announcement.dismissible {
+        return Err(AppError::business_logic(
+            "ANNOUNCEMENT_NOT_DISMISSIBLE",
+            "announcement cannot be dismissed",
+        ));
+    }
+    // 并发关闭时依赖唯一约束去重
+    let dismissal = app_announcement_dismissals::ActiveModel {
+        announcement_id: Set(announcement.id),
+        device_id: Set(req.device_id),
+        dismissed_at: Set(Utc::now()),
+        ..Default::default()
+    };
+    app_announcement_dismissals::Entity::insert(dismissal)
+        .on_conflict(
+            OnConflict::columns([
+                app_announcement_dismissals::Column::AnnouncementId,
+                app_announcement_dismissals::Column::DeviceId,
+            ])
+            .do_nothing()
+            .to_owned(),
+        )
+        .exec_without_returning(&state.db)
+        .await?;
+    Ok(())
+}
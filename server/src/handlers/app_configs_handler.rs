@@ -0,0 +1,330 @@
+use crate::constants::APP_KEY_HEADER;
+use crate::types::app_configs_types::*;
+use crate::types::reg_codes_types::{CodeType, RegCodeStatus};
+crate::import_crud_macro!();
+use entity::{app_configs, app_devices, apps, reg_code_devices, reg_codes};
+use salvo::http::HeaderValue;
+use salvo::http::header::{ETAG, IF_NONE_MATCH};
+use salvo::{oapi::extract::JsonBody, prelude::*};
+use salvo_oapi::extract::PathParam;
+use sea_orm::ConnectionTrait;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use validator::Validate;
+
+fn check_config_key(key: &str) -> Result<(), AppError> {
+    if key.is_empty()
+        || !key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'))
+    {
+        return Err(AppError::validation(format!("invalid config key: {}", key)));
+    }
+    Ok(())
+}
+
+fn check_rule(config: &app_configs::ActiveModel) -> Result<(), AppError> {
+    let value_type = ConfigValueType::from(*config.value_type.as_ref());
+    if !value_type.accepts(config.value.as_ref()) {
+        return Err(AppError::validation(format!(
+            "value does not match value_type {}",
+            i16::from(value_type)
+        )));
+    }
+    let (min, max) = (*config.min_vercode.as_ref(), *config.max_vercode.as_ref());
+    if max > 0 && min > max {
+        return Err(AppError::validation(
+            "min_vercode must not exceed max_vercode",
+        ));
+    }
+    Ok(())
+}
+
+// Create AppConfig
+#[handler]
+pub async fn add(
+    depot: &mut Depot,
+    req: JsonBody<CreateAppConfigReq>,
+) -> Result<ApiResponse<app_configs::Model>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let config = add_impl(state, req.into_inner()).await?;
+    Ok(ApiResponse::success(config))
+}
+
+pub async fn add_impl(
+    state: &AppState,
+    req: CreateAppConfigReq,
+) -> Result<app_configs::Model, AppError> {
+    req.validate()?;
+    check_config_key(&req.config_key)?;
+    apps::Entity::find_by_id(req.app_id)
+        .one(&state.db)
+        .await?
+        .ok_or_else(|| AppError::not_found("apps".to_string(), Some(req.app_id)))?;
+    let now = Utc::now();
+    let config = app_configs::ActiveModel {
+        app_id: Set(req.app_id),
+        config_key: Set(req.config_key),
+        value_type: Set(req.value_type.into()),
+        value: Set(req.value),
+        channel: Set(req.channel.as_str().to_string()),
+        min_vercode: Set(req.min_vercode),
+        max_vercode: Set(req.max_vercode),
+        audience: Set(req.audience.into()),
+        priority: Set(req.priority),
+        enabled: Set(req.enabled),
+        description: Set(req.description),
+        created_at: Set(now),
+        updated_at: Set(now),
+        ..Default::default()
+    };
+    check_rule(&config)?;
+    let config = config.insert(&state.db).await?;
+    Ok(config)
+}
+
+// Update AppConfig
+#[handler]
+pub async fn update(
+    depot: &mut Depot,
+    id: PathParam<i32>,
+    req: JsonBody<UpdateAppConfigReq>,
+) -> Result<ApiResponse<app_configs::Model>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let config = update_impl(state, id.into_inner(), req.into_inner()).await?;
+    Ok(ApiResponse::success(config))
+}
+
+pub async fn update_impl(
+    state: &AppState,
+    id: i32,
+    req: UpdateAppConfigReq,
+) -> Result<app_configs::Model, AppError> {
+    req.validate()?;
+    let config = app_configs::Entity::find_by_id(id).one(&state.db).await?;
+    let config = config.ok_or_else(|| AppError::not_found("app_configs".to_string(), Some(id)))?;
+    // config_key 是客户端读取配置的标识，不允许修改
+    let mut config: app_configs::ActiveModel = config.into_active_model();
+    crate::update_field_if_some!(config, value_type, req.value_type.map(i16::from));
+    crate::update_field_if_some!(config, value, req.value);
+    crate::update_field_if_some!(config, channel, req.channel.map(|c| c.as_str().to_string()));
+    crate::update_field_if_some!(config, min_vercode, req.min_vercode);
+    crate::update_field_if_some!(config, max_vercode, req.max_vercode);
+    crate::update_field_if_some!(config, audience, req.audience.map(i16::from));
+    crate::update_field_if_some!(config, priority, req.priority);
+    crate::update_field_if_some!(config, enabled, req.enabled);
+    crate::update_field_if_some!(config, description, req.description, option);
+    check_rule(&config)?;
+    config.updated_at = Set(Utc::now());
+    let config = config.update(&state.db).await?;
+    Ok(config)
+}
+
+// Delete AppConfig
+#[handler]
+pub async fn delete(depot: &mut Depot, id: PathParam<i32>) -> Result<ApiResponse<()>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    delete_impl(state, id.into_inner()).await?;
+    Ok(ApiResponse::success(()))
+}
+
+pub async fn delete_impl(state: &AppState, id: i32) -> Result<(), AppError> {
+    let res = app_configs::Entity::delete_by_id(id)
+        .exec(&state.db)
+        .await?;
+    if res.rows_affected == 0 {
+        return Err(AppError::not_found("app_configs".to_string(), Some(id)));
+    }
+    Ok(())
+}
+
+// Get AppConfigs List
+#[handler]
+pub async fn get_list(
+    depot: &mut Depot,
+    req: &mut Request,
+) -> Result<ApiResponse<PagingResponse<app_configs::Model>>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let params = req.parse_queries::<SearchAppConfigsParams>()?;
+    let list = get_list_impl(state, params).await?;
+    Ok(ApiResponse::success(list))
+}
+
+pub async fn get_list_impl(
+    state: &AppState,
+    params: SearchAppConfigsParams,
+) -> Result<PagingResponse<app_configs::Model>, AppError> {
+    let page = params.pagination.page.unwrap_or(1);
+    let page_size = params.pagination.page_size.unwrap_or(20);
+    let mut query = app_configs::Entity::find()
+        .order_by_asc(app_configs::Column::AppId)
+        .order_by_asc(app_configs::Column::ConfigKey)
+        .order_by_desc(app_configs::Column::Priority);
+    crate::filter_if_some!(query, app_configs::Column::AppId, params.app_id, eq);
+    crate::filter_if_some!(
+        query,
+        app_configs::Column::ConfigKey,
+        params.config_key,
+        contains
+    );
+    let paginator = query.paginate(&state.db, page_size);
+    let total = paginator.num_items().await?;
+    let list = paginator.fetch_page(page - 1).await?;
+    Ok(PagingResponse { list, total, page })
+}
+
+// Get AppConfig by ID
+#[handler]
+pub async fn get_by_id(
+    depot: &mut Depot,
+    id: PathParam<i32>,
+) -> Result<ApiResponse<app_configs::Model>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let config = get_by_id_impl(state, id.into_inner()).await?;
+    Ok(ApiResponse::success(config))
+}
+
+pub async fn get_by_id_impl(state: &AppState, id: i32) -> Result<app_configs::Model, AppError> {
+    let config = app_configs::Entity::find_by_id(id).one(&state.db).await?;
+    config.ok_or_else(|| AppError::not_found("app_configs".to_string(), Some(id)))
+}
+
+/// 设备是否绑定了仍然可用的注册码
+///
+/// 时长类以设备的有效期为准，次数类要求还有剩余次数，浮动类以注册码的有效期为准
+pub async fn is_licensed<C: ConnectionTrait>(
+    db: &C,
+    app_id: i32,
+    device_id: &str,
+) -> Result<bool, AppError> {
+    let device = app_devices::Entity::find()
+        .filter(
+            app_devices::Column::AppId
+                .eq(app_id)
+                .and(app_devices::Column::DeviceId.eq(device_id)),
+        )
+        .one(db)
+        .await?;
+    let Some(device) = device else {
+        return Ok(false);
+    };
+    let bound = reg_code_devices::Entity::find()
+        .filter(reg_code_devices::Column::DeviceId.eq(device.id))
+        .all(db)
+        .await?;
+    if bound.is_empty() {
+        return Ok(false);
+    }
+    let codes = reg_codes::Entity::find()
+        .filter(reg_codes::Column::Id.is_in(bound.iter().map(|b| b.reg_code_id)))
+        .filter(reg_codes::Column::Status.is_not_in([
+            i16::from(RegCodeStatus::Expired),
+            i16::from(RegCodeStatus::Revoked),
+        ]))
+        .all(db)
+        .await?;
+    let now = Utc::now();
+    Ok(codes.iter().any(|code| match CodeType::from(code.code_type) {
+        CodeType::Time => device.expire_time.is_none_or(|t| t > now),
+        CodeType::Count => code.use_count < code.total_count.unwrap_or(0),
+        CodeType::Floating => code.expire_time.is_none_or(|t| t > now),
+    }))
+}
+
+/// 规则是否命中客户端的通道、版本和授权状态
+fn matches(rule: &app_configs::Model, query: &ConfigQuery, licensed: Option<bool>) -> bool {
+    let channel =
+        rule.channel == ConfigChannel::All.as_str() || rule.channel == query.channel.as_str();
+    channel
+        && vercode_in_range(query.vercode, rule.min_vercode, rule.max_vercode)
+        && ConfigAudience::from(rule.audience).matches(licensed)
+}
+
+/// values 序列化后 SHA-256 的前 32 位，key 已排序，内容不变时 etag 不变
+fn config_etag(values: &BTreeMap<String, serde_json::Value>) -> Result<String, AppError> {
+    let digest = Sha256::digest(serde_json::to_vec(values)?);
+    Ok(hex::encode(&digest[..16]))
+}
+
+/// 按客户端环境合并配置，每个 key 取命中规则中 priority 最高的一条，相同时取较新的
+pub async fn resolve_impl<C: ConnectionTrait>(
+    db: &C,
+    app_id: i32,
+    query: &ConfigQuery,
+    licensed: Option<bool>,
+) -> Result<AppConfigResp, AppError> {
+    let rules = app_configs::Entity::find()
+        .filter(app_configs::Column::AppId.eq(app_id))
+        .filter(app_configs::Column::Enabled.eq(true))
+        .order_by_desc(app_configs::Column::Priority)
+        .order_by_desc(app_configs::Column::Id)
+        .all(db)
+        .await?;
+    let mut values = BTreeMap::new();
+    for rule in rules {
+        if !values.contains_key(&rule.config_key) && matches(&rule, query, licensed) {
+            values.insert(rule.config_key, rule.value);
+        }
+    }
+    let etag = config_etag(&values)?;
+    let not_modified = query.etag.as_deref() == Some(etag.as_str());
+    Ok(AppConfigResp {
+        etag,
+        not_modified,
+        values: (!not_modified).then_some(values),
+    })
+}
+
+/// Get the remote config of an app
+#[endpoint(
+    tags("app_configs"),
+    parameters(
+        ("x-app-key"=String, Header, description = "应用校验Key"),
+        ("device_id"=Option<String>, Query, description = "设备ID，用于匹配已授权 / 试用设备的规则"),
+        ("vercode"=Option<i32>, Query, description = "客户端当前版本号"),
+        ("channel"=Option<String>, Query, description = "stable / beta，默认 stable"),
+        ("etag"=Option<String>, Query, description = "上次获取到的 etag，也可以通过 If-None-Match 请求头传入")
+))]
+pub async fn get_config(
+    depot: &mut Depot,
+    req: &mut Request,
+    res: &mut Response,
+) -> Result<ApiResponse<AppConfigResp>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let mut params = req.parse_queries::<AppConfigParams>()?;
+    params.app_key = req.header::<String>(APP_KEY_HEADER).unwrap_or_default();
+    if params.etag.is_none() {
+        params.etag = req
+            .header::<String>(IF_NONE_MATCH)
+            .map(|v| v.trim_start_matches("W/").trim_matches('"').to_string());
+    }
+    let resp = get_config_impl(state, params).await?;
+    if let Ok(etag) = HeaderValue::from_str(&format!("\"{}\"", resp.etag)) {
+        res.headers_mut().insert(ETAG, etag);
+    }
+    Ok(ApiResponse::success(resp))
+}
+
+pub async fn get_config_impl(
+    state: &AppState,
+    params: AppConfigParams,
+) -> Result<AppConfigResp, AppError> {
+    if params.app_key.is_empty() {
+        return Err(AppError::validation("app_key is required"));
+    }
+    let app = apps::Entity::find()
+        .filter(apps::Column::AppValidKey.eq(params.app_key))
+        .one(&state.db)
+        .await?;
+    let app = app.ok_or(AppError::not_found("apps".to_string(), None))?;
+    let licensed = match params.device_id.filter(|d| !d.is_empty()) {
+        Some(device_id) => Some(is_licensed(&state.db, app.id, &device_id).await?),
+        None => None,
+    };
+    let query = ConfigQuery {
+        vercode: params.vercode,
+        channel: params.channel,
+        etag: params.etag,
+    };
+    resolve_impl(&state.db, app.id, &query, licensed).await
+}
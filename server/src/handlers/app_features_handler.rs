@@ -0,0 +1,245 @@
+use crate::handlers::product_handler;
+use crate::types::app_features_types::*;
+crate::import_crud_macro!();
+use entity::{app_features, apps, reg_codes};
+use salvo::{oapi::extract::JsonBody, prelude::*};
+use salvo_oapi::extract::PathParam;
+use sea_orm::ConnectionTrait;
+use validator::Validate;
+
+fn check_feature_key(key: &str) -> Result<(), AppError> {
+    if key.is_empty()
+        || !key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'))
+    {
+        return Err(AppError::validation(format!("invalid feature key: {}", key)));
+    }
+    Ok(())
+}
+
+// Create AppFeature
+#[handler]
+pub async fn add(
+    depot: &mut Depot,
+    req: JsonBody<CreateAppFeatureReq>,
+) -> Result<ApiResponse<app_features::Model>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let feature = add_impl(state, req.into_inner()).await?;
+    Ok(ApiResponse::success(feature))
+}
+
+pub async fn add_impl(
+    state: &AppState,
+    req: CreateAppFeatureReq,
+) -> Result<app_features::Model, AppError> {
+    req.validate()?;
+    check_feature_key(&req.feature_key)?;
+    apps::Entity::find_by_id(req.app_id)
+        .one(&state.db)
+        .await?
+        .ok_or_else(|| AppError::not_found("apps".to_string(), Some(req.app_id)))?;
+    let exist = app_features::Entity::find()
+        .filter(
+            app_features::Column::AppId
+                .eq(req.app_id)
+                .and(app_features::Column::FeatureKey.eq(req.feature_key.clone())),
+        )
+        .one(&state.db)
+        .await?;
+    if exist.is_some() {
+        return Err(AppError::business_logic(
+            "FEATURE_EXISTS",
+            format!("feature {} already exists", req.feature_key),
+        ));
+    }
+    let now = Utc::now();
+    let feature = app_features::ActiveModel {
+        app_id: Set(req.app_id),
+        feature_key: Set(req.feature_key),
+        name: Set(req.name),
+        feature_type: Set(req.feature_type.into()),
+        default_value: Set(req.default_value),
+        description: Set(req.description),
+        created_at: Set(now),
+        updated_at: Set(now),
+        ..Default::default()
+    }
+    .insert(&state.db)
+    .await?;
+    Ok(feature)
+}
+
+// Update AppFeature
+#[handler]
+pub async fn update(
+    depot: &mut Depot,
+    id: PathParam<i32>,
+    req: JsonBody<UpdateAppFeatureReq>,
+) -> Result<ApiResponse<app_features::Model>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let feature = update_impl(state, id.into_inner(), req.into_inner()).await?;
+    Ok(ApiResponse::success(feature))
+}
+
+pub async fn update_impl(
+    state: &AppState,
+    id: i32,
+    req: UpdateAppFeatureReq,
+) -> Result<app_features::Model, AppError> {
+    let feature = app_features::Entity::find_by_id(id).one(&state.db).await?;
+    let feature =
+        feature.ok_or_else(|| AppError::not_found("app_features".to_string(), Some(id)))?;
+    // feature_key 被商品和注册码引用，不允许修改
+    let mut feature: app_features::ActiveModel = feature.into_active_model();
+    crate::update_field_if_some!(feature, name, req.name);
+    crate::update_field_if_some!(feature, feature_type, req.feature_type.map(i16::from));
+    crate::update_field_if_some!(feature, default_value, req.default_value);
+    crate::update_field_if_some!(feature, description, req.description, option);
+    feature.updated_at = Set(Utc::now());
+    let feature = feature.update(&state.db).await?;
+    Ok(feature)
+}
+
+// Delete AppFeature
+#[handler]
+pub async fn delete(depot: &mut Depot, id: PathParam<i32>) -> Result<ApiResponse<()>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    delete_impl(state, id.into_inner()).await?;
+    Ok(ApiResponse::success(()))
+}
+
+pub async fn delete_impl(state: &AppState, id: i32) -> Result<(), AppError> {
+    let res = app_features::Entity::delete_by_id(id).exec(&state.db).await?;
+    if res.rows_affected == 0 {
+        return Err(AppError::not_found("app_features".to_string(), Some(id)));
+    }
+    Ok(())
+}
+
+// Get AppFeatures List
+#[handler]
+pub async fn get_list(
+    depot: &mut Depot,
+    req: &mut Request,
+) -> Result<ApiResponse<PagingResponse<app_features::Model>>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let params = req.parse_queries::<SearchAppFeaturesParams>()?;
+    let list = get_list_impl(state, params).await?;
+    Ok(ApiResponse::success(list))
+}
+
+pub async fn get_list_impl(
+    state: &AppState,
+    params: SearchAppFeaturesParams,
+) -> Result<PagingResponse<app_features::Model>, AppError> {
+    let page = params.pagination.page.unwrap_or(1);
+    let page_size = params.pagination.page_size.unwrap_or(20);
+    let mut query = app_features::Entity::find()
+        .order_by_asc(app_features::Column::AppId)
+        .order_by_asc(app_features::Column::FeatureKey);
+    crate::filter_if_some!(query, app_features::Column::AppId, params.app_id, eq);
+    crate::filter_if_some!(
+        query,
+        app_features::Column::FeatureKey,
+        params.feature_key,
+        contains
+    );
+    let paginator = query.paginate(&state.db, page_size);
+    let total = paginator.num_items().await?;
+    let list = paginator.fetch_page(page - 1).await?;
+    Ok(PagingResponse { list, total, page })
+}
+
+// Get AppFeature by ID
+#[handler]
+pub async fn get_by_id(
+    depot: &mut Depot,
+    id: PathParam<i32>,
+) -> Result<ApiResponse<app_features::Model>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let feature = get_by_id_impl(state, id.into_inner()).await?;
+    Ok(ApiResponse::success(feature))
+}
+
+pub async fn get_by_id_impl(state: &AppState, id: i32) -> Result<app_features::Model, AppError> {
+    let feature = app_features::Entity::find_by_id(id).one(&state.db).await?;
+    feature.ok_or_else(|| AppError::not_found("app_features".to_string(), Some(id)))
+}
+
+/// 检查授权内容中的 key 都已在应用中定义且类型匹配
+pub async fn check_entitlements<C: ConnectionTrait>(
+    db: &C,
+    app_id: i32,
+    entitlements: &Entitlements,
+) -> Result<(), AppError> {
+    let defs = app_features::Entity::find()
+        .filter(app_features::Column::AppId.eq(app_id))
+        .all(db)
+        .await?;
+    let defined = |key: &str, feature_type: FeatureType| {
+        defs.iter()
+            .any(|d| d.feature_key == key && FeatureType::from(d.feature_type) == feature_type)
+    };
+    if let Some(key) = entitlements
+        .features
+        .iter()
+        .find(|k| !defined(k, FeatureType::Flag))
+    {
+        return Err(AppError::validation(format!("undefined feature flag: {}", key)));
+    }
+    for (key, value) in &entitlements.limits {
+        if !defined(key, FeatureType::Limit) {
+            return Err(AppError::validation(format!("undefined feature limit: {}", key)));
+        }
+        if *value < 0 {
+            return Err(AppError::validation(format!("limit {} must not be negative", key)));
+        }
+    }
+    Ok(())
+}
+
+/// 计算最终授权：应用默认值 -> 注册码所属订单的商品 -> 注册码自身，开关取并集，限额取较大值
+/// 没有注册码（试用）时只返回默认值
+pub async fn resolve_entitlements<C: ConnectionTrait>(
+    db: &C,
+    app_id: i32,
+    reg_code: Option<&reg_codes::Model>,
+) -> Result<Entitlements, AppError> {
+    let defs = app_features::Entity::find()
+        .filter(app_features::Column::AppId.eq(app_id))
+        .all(db)
+        .await?;
+    let mut result = Entitlements::default();
+    for def in &defs {
+        match FeatureType::from(def.feature_type) {
+            FeatureType::Flag if def.default_value != 0 => {
+                result.features.insert(def.feature_key.clone());
+            }
+            FeatureType::Flag => {}
+            FeatureType::Limit => {
+                result.limits.insert(def.feature_key.clone(), def.default_value);
+            }
+        }
+    }
+    let Some(reg_code) = reg_code else {
+        return Ok(result);
+    };
+
+    let purchased = product_handler::find_by_reg_code(db, app_id, reg_code.id).await?;
+    for (_, product) in purchased {
+        result.merge(Entitlements::from_json(product.entitlements.as_ref())?);
+    }
+    result.merge(Entitlements::from_json(reg_code.entitlements.as_ref())?);
+
+    // 功能定义被删除后，已发放的授权里对应的 key 不再下发
+    result.features.retain(|k| {
+        defs.iter()
+            .any(|d| d.feature_key == *k && FeatureType::from(d.feature_type) == FeatureType::Flag)
+    });
+    result.limits.retain(|k, _| {
+        defs.iter()
+            .any(|d| d.feature_key == *k && FeatureType::from(d.feature_type) == FeatureType::Limit)
+    });
+    Ok(result)
+}
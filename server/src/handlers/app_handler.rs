@@ -6,6 +6,7 @@ use crate::types::app_types::*;
 use crate::types::common::*;
 use crate::types::error::*;
 use crate::types::response::*;
+use crate::utils::license;
 use sea_orm::{
     ActiveModelTrait, ColumnTrait, EntityTrait,  PaginatorTrait, QueryFilter,
     QueryOrder, Set, IntoActiveModel,
@@ -23,6 +24,7 @@ pub async fn add(
 }
 
 pub async fn add_impl(state: &AppState, req: AddAppReq) -> Result<apps::Model, AppError> {
+    let keypair = license::generate_keypair();
     let active_model = apps::ActiveModel {
         name: Set(req.name),
         app_id: Set(req.app_id),
@@ -33,6 +35,13 @@ pub async fn add_impl(state: &AppState, req: AddAppReq) -> Result<apps::Model, A
         app_update_info: Set(req.app_update_info),
         app_valid_key: Set(req.app_valid_key.unwrap_or_default()),
         trial_days: Set(req.trial_days.unwrap_or_default()),
+        sign_public_key: Set(keypair.public_key),
+        sign_private_key: Set(keypair.private_key),
+        offline_grace_days: Set(req.offline_grace_days.unwrap_or(7)),
+        strict_code_format: Set(req.strict_code_format.unwrap_or(false)),
+        allow_legacy_validate: Set(req.allow_legacy_validate.unwrap_or(true)),
+        max_transfers: Set(req.max_transfers.unwrap_or(3)),
+        transfer_cooldown_hours: Set(req.transfer_cooldown_hours.unwrap_or(72)),
         sort_order: Set(req.sort_order),
         created_at: Set(Utc::now()),
         status: Set(req.status),
@@ -71,6 +80,11 @@ pub async fn update_impl(
     crate::update_field_if_some!(app, app_update_info, req.app_update_info, option);
     crate::update_field_if_some!(app, app_valid_key, req.app_valid_key);
     crate::update_field_if_some!(app, trial_days, req.trial_days);
+    crate::update_field_if_some!(app, offline_grace_days, req.offline_grace_days);
+    crate::update_field_if_some!(app, strict_code_format, req.strict_code_format);
+    crate::update_field_if_some!(app, allow_legacy_validate, req.allow_legacy_validate);
+    crate::update_field_if_some!(app, max_transfers, req.max_transfers);
+    crate::update_field_if_some!(app, transfer_cooldown_hours, req.transfer_cooldown_hours);
     crate::update_field_if_some!(app, sort_order, req.sort_order);
     crate::update_field_if_some!(app, status, req.status);
     let app = app.update(&state.db).await?;
@@ -144,3 +158,16 @@ pub async fn get_by_id_impl(state: &AppState, id: i32) -> Result<apps::Model, Ap
     let app = query.ok_or_else(|| AppError::not_found("apps".to_string(), Some(id)))?;
     Ok(app)
 }
+
+/// 确保应用已有签名密钥对，旧数据没有密钥时补充生成
+pub async fn ensure_sign_key_impl(state: &AppState, app: apps::Model) -> Result<apps::Model, AppError> {
+    if !app.sign_private_key.is_empty() {
+        return Ok(app);
+    }
+    let keypair = license::generate_keypair();
+    let mut app = app.into_active_model();
+    app.sign_public_key = Set(keypair.public_key);
+    app.sign_private_key = Set(keypair.private_key);
+    let app = app.update(&state.db).await?;
+    Ok(app)
+}
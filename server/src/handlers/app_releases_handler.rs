@@ -0,0 +1,634 @@
+use crate::types::app_releases_types::*;
+use crate::types::common::Claims;
+crate::import_crud_macro!();
+use entity::{app_release_events, app_releases, apps};
+use salvo::{oapi::extract::JsonBody, prelude::*};
+use salvo_oapi::extract::PathParam;
+use sea_orm::{ConnectionTrait, QuerySelect, TransactionTrait};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use validator::Validate;
+
+fn check_downloads(downloads: &BTreeMap<String, String>) -> Result<(), AppError> {
+    for (platform, url) in downloads {
+        if platform.is_empty()
+            || !platform
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-'))
+        {
+            return Err(AppError::validation(format!(
+                "invalid platform: {}",
+                platform
+            )));
+        }
+        if url.is_empty() {
+            return Err(AppError::validation(format!(
+                "download url of {} is empty",
+                platform
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// 设备在某个版本灰度中的分桶 0-99，同一设备对同一版本结果固定，调大比例时已收到的设备不受影响
+pub fn rollout_bucket(release_id: i32, device_id: &str) -> u32 {
+    let digest = Sha256::digest(format!("{}:{}", release_id, device_id).as_bytes());
+    u32::from_be_bytes([digest[0], digest[1], digest[2], digest[3]]) % 100
+}
+
+fn in_rollout(release: &app_releases::Model, device_id: Option<&str>) -> bool {
+    match release.rollout_percent {
+        p if p >= 100 => true,
+        p if p <= 0 => false,
+        p => device_id.is_some_and(|d| rollout_bucket(release.id, d) < p as u32),
+    }
+}
+
+/// 旧客户端只读取 apps.app_download_url，优先取 downloads 中的 default，没有时取按平台名排序的第一个
+fn legacy_download_url(downloads: &serde_json::Value) -> Option<String> {
+    let downloads: BTreeMap<String, String> =
+        serde_json::from_value(downloads.clone()).unwrap_or_default();
+    downloads
+        .get("default")
+        .or_else(|| downloads.values().next())
+        .cloned()
+}
+
+/// 把 apps 上的版本字段同步为最新的全量正式版，兼容只读取 app_vername 的旧客户端
+/// 暂停或回滚后旧客户端同样回到上一个版本，没有可用的正式版时恢复首次同步前的版本字段；
+/// 版本没有下载地址时保留原来的 app_download_url
+async fn sync_app_version<C: ConnectionTrait>(db: &C, app_id: i32) -> Result<(), AppError> {
+    let latest = app_releases::Entity::find()
+        .filter(app_releases::Column::AppId.eq(app_id))
+        .filter(app_releases::Column::Channel.eq(ReleaseChannel::Stable.as_str()))
+        .filter(app_releases::Column::Status.eq(i16::from(ReleaseStatus::Published)))
+        .filter(app_releases::Column::RolloutPercent.gte(100))
+        .order_by_desc(app_releases::Column::Vercode)
+        .one(db)
+        .await?;
+    let app = apps::Entity::find_by_id(app_id).one(db).await?;
+    let Some(app) = app else {
+        return Ok(());
+    };
+    let current = AppVersionFields::of(&app);
+    let base: Option<AppVersionFields> = app
+        .base_version
+        .clone()
+        .and_then(|v| serde_json::from_value(v).ok());
+    let target = match latest {
+        Some(latest) => AppVersionFields {
+            app_download_url: legacy_download_url(&latest.downloads)
+                .unwrap_or_else(|| current.app_download_url.clone()),
+            app_vername: latest.vername,
+            app_vercode: latest.vercode,
+            app_update_info: latest.release_notes,
+        },
+        None => match &base {
+            Some(base) => base.clone(),
+            None => return Ok(()),
+        },
+    };
+    if target == current {
+        return Ok(());
+    }
+    let mut app = app.into_active_model();
+    if base.is_none() {
+        app.base_version = Set(Some(serde_json::to_value(&current).unwrap_or_default()));
+    }
+    app.app_vername = Set(target.app_vername);
+    app.app_vercode = Set(target.app_vercode);
+    app.app_download_url = Set(target.app_download_url);
+    app.app_update_info = Set(target.app_update_info);
+    app.updated_at = Set(Utc::now());
+    app.update(db).await?;
+    Ok(())
+}
+
+/// 对比前后两个版本，返回变更的字段 {"field": [旧值, 新值]}，新建或删除时另一侧为 null
+fn release_changes(
+    before: Option<&app_releases::Model>,
+    after: Option<&app_releases::Model>,
+) -> Option<serde_json::Value> {
+    let to_map = |m: Option<&app_releases::Model>| match m.map(serde_json::to_value) {
+        Some(Ok(serde_json::Value::Object(map))) => map,
+        _ => serde_json::Map::new(),
+    };
+    let (before, after) = (to_map(before), to_map(after));
+    let mut changes = serde_json::Map::new();
+    for key in before.keys().chain(after.keys()) {
+        if matches!(key.as_str(), "id" | "created_at" | "updated_at") {
+            continue;
+        }
+        let old = before.get(key).cloned().unwrap_or_default();
+        let new = after.get(key).cloned().unwrap_or_default();
+        if old != new {
+            changes.insert(key.clone(), serde_json::json!([old, new]));
+        }
+    }
+    (!changes.is_empty()).then_some(serde_json::Value::Object(changes))
+}
+
+/// 写入一条版本操作审计记录
+pub async fn record_event<C: ConnectionTrait>(
+    db: &C,
+    release: &app_releases::Model,
+    action: ReleaseAction,
+    changes: Option<serde_json::Value>,
+    operator_id: Option<i32>,
+) -> Result<(), AppError> {
+    app_release_events::ActiveModel {
+        app_id: Set(release.app_id),
+        release_id: Set(Some(release.id)),
+        channel: Set(release.channel.clone()),
+        vercode: Set(release.vercode),
+        action: Set(action.as_str().to_string()),
+        changes: Set(changes),
+        operator_id: Set(operator_id),
+        created_at: Set(Utc::now()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+    Ok(())
+}
+
+fn check_editable_status(status: Option<ReleaseStatus>) -> Result<(), AppError> {
+    match status {
+        Some(ReleaseStatus::Paused | ReleaseStatus::RolledBack) => Err(AppError::validation(
+            "use the pause / rollback endpoints to pause or roll back a release",
+        )),
+        _ => Ok(()),
+    }
+}
+
+// Create AppRelease
+#[handler]
+pub async fn add(
+    depot: &mut Depot,
+    req: JsonBody<CreateAppReleaseReq>,
+) -> Result<ApiResponse<app_releases::Model>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let operator_id = depot.obtain::<Claims>().ok().map(|c| c.sub);
+    let release = add_impl(state, req.into_inner(), operator_id).await?;
+    Ok(ApiResponse::success(release))
+}
+
+pub async fn add_impl(
+    state: &AppState,
+    req: CreateAppReleaseReq,
+    operator_id: Option<i32>,
+) -> Result<app_releases::Model, AppError> {
+    req.validate()?;
+    check_editable_status(Some(req.status))?;
+    check_downloads(&req.downloads)?;
+    if req.min_vercode < 0 || req.min_vercode > req.vercode {
+        return Err(AppError::validation(
+            "min_vercode must be between 0 and vercode",
+        ));
+    }
+    apps::Entity::find_by_id(req.app_id)
+        .one(&state.db)
+        .await?
+        .ok_or_else(|| AppError::not_found("apps".to_string(), Some(req.app_id)))?;
+    let exist = app_releases::Entity::find()
+        .filter(app_releases::Column::AppId.eq(req.app_id))
+        .filter(app_releases::Column::Channel.eq(req.channel.as_str()))
+        .filter(app_releases::Column::Vercode.eq(req.vercode))
+        .one(&state.db)
+        .await?;
+    if exist.is_some() {
+        return Err(AppError::business_logic(
+            "RELEASE_EXISTS",
+            format!(
+                "release {} already exists in {} channel",
+                req.vercode,
+                req.channel.as_str()
+            ),
+        ));
+    }
+    let now = Utc::now();
+    let published_at = (req.status == ReleaseStatus::Published).then_some(now);
+    let txn = state.db.begin().await?;
+    let release = app_releases::ActiveModel {
+        app_id: Set(req.app_id),
+        channel: Set(req.channel.as_str().to_string()),
+        vername: Set(req.vername),
+        vercode: Set(req.vercode),
+        downloads: Set(serde_json::to_value(&req.downloads).unwrap_or_default()),
+        release_notes: Set(req.release_notes),
+        min_vercode: Set(req.min_vercode),
+        force_update: Set(req.force_update),
+        rollout_percent: Set(req.rollout_percent),
+        status: Set(req.status.into()),
+        published_at: Set(published_at),
+        created_at: Set(now),
+        updated_at: Set(now),
+        ..Default::default()
+    }
+    .insert(&txn)
+    .await?;
+    let changes = release_changes(None, Some(&release));
+    record_event(&txn, &release, ReleaseAction::Create, changes, operator_id).await?;
+    sync_app_version(&txn, release.app_id).await?;
+    txn.commit().await?;
+    Ok(release)
+}
+
+// Update AppRelease
+#[handler]
+pub async fn update(
+    depot: &mut Depot,
+    id: PathParam<i32>,
+    req: JsonBody<UpdateAppReleaseReq>,
+) -> Result<ApiResponse<app_releases::Model>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let operator_id = depot.obtain::<Claims>().ok().map(|c| c.sub);
+    let release = update_impl(state, id.into_inner(), req.into_inner(), operator_id).await?;
+    Ok(ApiResponse::success(release))
+}
+
+pub async fn update_impl(
+    state: &AppState,
+    id: i32,
+    req: UpdateAppReleaseReq,
+    operator_id: Option<i32>,
+) -> Result<app_releases::Model, AppError> {
+    req.validate()?;
+    check_editable_status(req.status)?;
+    if let Some(downloads) = &req.downloads {
+        check_downloads(downloads)?;
+    }
+    let txn = state.db.begin().await?;
+    let release = app_releases::Entity::find_by_id(id)
+        .lock_exclusive()
+        .one(&txn)
+        .await?;
+    let release =
+        release.ok_or_else(|| AppError::not_found("app_releases".to_string(), Some(id)))?;
+    let status = ReleaseStatus::from(release.status);
+    if req.status.is_some_and(|s| s != status)
+        && matches!(status, ReleaseStatus::Paused | ReleaseStatus::RolledBack)
+    {
+        return Err(AppError::business_logic(
+            "INVALID_RELEASE_STATUS",
+            "paused releases must be resumed, rolled back releases cannot be published again",
+        ));
+    }
+    let min_vercode = req.min_vercode.unwrap_or(release.min_vercode);
+    if min_vercode < 0 || min_vercode > release.vercode {
+        return Err(AppError::validation(
+            "min_vercode must be between 0 and vercode",
+        ));
+    }
+    // 首次发布时记录发布时间，撤回为草稿后再发布保留原时间
+    let publish = req.status == Some(ReleaseStatus::Published) && release.published_at.is_none();
+    let action = match req.status {
+        Some(ReleaseStatus::Published) if status == ReleaseStatus::Draft => ReleaseAction::Publish,
+        _ => ReleaseAction::Update,
+    };
+    let before = release.clone();
+    // app_id / channel / vercode 构成版本标识，不允许修改
+    let mut release: app_releases::ActiveModel = release.into_active_model();
+    crate::update_field_if_some!(release, vername, req.vername);
+    crate::update_field_if_some!(
+        release,
+        downloads,
+        req.downloads
+            .map(|d| serde_json::to_value(&d).unwrap_or_default())
+    );
+    crate::update_field_if_some!(release, release_notes, req.release_notes, option);
+    crate::update_field_if_some!(release, min_vercode, req.min_vercode);
+    crate::update_field_if_some!(release, force_update, req.force_update);
+    crate::update_field_if_some!(release, rollout_percent, req.rollout_percent);
+    crate::update_field_if_some!(release, status, req.status.map(i16::from));
+    let now = Utc::now();
+    if publish {
+        release.published_at = Set(Some(now));
+    }
+    release.updated_at = Set(now);
+    let release = release.update(&txn).await?;
+    let changes = release_changes(Some(&before), Some(&release));
+    record_event(&txn, &release, action, changes, operator_id).await?;
+    sync_app_version(&txn, release.app_id).await?;
+    txn.commit().await?;
+    Ok(release)
+}
+
+/// 修改版本状态，只允许从 from 中的状态切换
+async fn change_status(
+    state: &AppState,
+    id: i32,
+    from: &[ReleaseStatus],
+    to: ReleaseStatus,
+    action: ReleaseAction,
+    operator_id: Option<i32>,
+) -> Result<app_releases::Model, AppError> {
+    let txn = state.db.begin().await?;
+    let release = app_releases::Entity::find_by_id(id)
+        .lock_exclusive()
+        .one(&txn)
+        .await?;
+    let release =
+        release.ok_or_else(|| AppError::not_found("app_releases".to_string(), Some(id)))?;
+    if !from.contains(&ReleaseStatus::from(release.status)) {
+        return Err(AppError::business_logic(
+            "INVALID_RELEASE_STATUS",
+            format!("cannot {} release {}", action.as_str(), release.vercode),
+        ));
+    }
+    let before = release.clone();
+    let mut release = release.into_active_model();
+    release.status = Set(to.into());
+    release.updated_at = Set(Utc::now());
+    let release = release.update(&txn).await?;
+    let changes = release_changes(Some(&before), Some(&release));
+    record_event(&txn, &release, action, changes, operator_id).await?;
+    sync_app_version(&txn, release.app_id).await?;
+    txn.commit().await?;
+    Ok(release)
+}
+
+// Pause the rollout of an AppRelease
+#[handler]
+pub async fn pause(
+    depot: &mut Depot,
+    id: PathParam<i32>,
+) -> Result<ApiResponse<app_releases::Model>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let operator_id = depot.obtain::<Claims>().ok().map(|c| c.sub);
+    let release = pause_impl(state, id.into_inner(), operator_id).await?;
+    Ok(ApiResponse::success(release))
+}
+
+/// 暂停已发布的版本，客户端重新看到上一个版本
+pub async fn pause_impl(
+    state: &AppState,
+    id: i32,
+    operator_id: Option<i32>,
+) -> Result<app_releases::Model, AppError> {
+    change_status(
+        state,
+        id,
+        &[ReleaseStatus::Published],
+        ReleaseStatus::Paused,
+        ReleaseAction::Pause,
+        operator_id,
+    )
+    .await
+}
+
+// Resume a paused AppRelease
+#[handler]
+pub async fn resume(
+    depot: &mut Depot,
+    id: PathParam<i32>,
+) -> Result<ApiResponse<app_releases::Model>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let operator_id = depot.obtain::<Claims>().ok().map(|c| c.sub);
+    let release = resume_impl(state, id.into_inner(), operator_id).await?;
+    Ok(ApiResponse::success(release))
+}
+
+pub async fn resume_impl(
+    state: &AppState,
+    id: i32,
+    operator_id: Option<i32>,
+) -> Result<app_releases::Model, AppError> {
+    change_status(
+        state,
+        id,
+        &[ReleaseStatus::Paused],
+        ReleaseStatus::Published,
+        ReleaseAction::Resume,
+        operator_id,
+    )
+    .await
+}
+
+// Roll back an AppRelease
+#[handler]
+pub async fn rollback(
+    depot: &mut Depot,
+    id: PathParam<i32>,
+) -> Result<ApiResponse<app_releases::Model>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let operator_id = depot.obtain::<Claims>().ok().map(|c| c.sub);
+    let release = rollback_impl(state, id.into_inner(), operator_id).await?;
+    Ok(ApiResponse::success(release))
+}
+
+/// 回滚版本，已安装该版本的客户端检查更新时会收到上一个可用版本
+pub async fn rollback_impl(
+    state: &AppState,
+    id: i32,
+    operator_id: Option<i32>,
+) -> Result<app_releases::Model, AppError> {
+    change_status(
+        state,
+        id,
+        &[ReleaseStatus::Published, ReleaseStatus::Paused],
+        ReleaseStatus::RolledBack,
+        ReleaseAction::Rollback,
+        operator_id,
+    )
+    .await
+}
+
+// Delete AppRelease
+#[handler]
+pub async fn delete(depot: &mut Depot, id: PathParam<i32>) -> Result<ApiResponse<()>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let operator_id = depot.obtain::<Claims>().ok().map(|c| c.sub);
+    delete_impl(state, id.into_inner(), operator_id).await?;
+    Ok(ApiResponse::success(()))
+}
+
+/// 删除版本，审计记录保留，release_id 置空
+pub async fn delete_impl(
+    state: &AppState,
+    id: i32,
+    operator_id: Option<i32>,
+) -> Result<(), AppError> {
+    let txn = state.db.begin().await?;
+    let release = app_releases::Entity::find_by_id(id)
+        .lock_exclusive()
+        .one(&txn)
+        .await?;
+    let release =
+        release.ok_or_else(|| AppError::not_found("app_releases".to_string(), Some(id)))?;
+    let changes = release_changes(Some(&release), None);
+    record_event(&txn, &release, ReleaseAction::Delete, changes, operator_id).await?;
+    app_releases::Entity::delete_by_id(id).exec(&txn).await?;
+    sync_app_version(&txn, release.app_id).await?;
+    txn.commit().await?;
+    Ok(())
+}
+
+// Get AppReleases List
+#[handler]
+pub async fn get_list(
+    depot: &mut Depot,
+    req: &mut Request,
+) -> Result<ApiResponse<PagingResponse<app_releases::Model>>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let params = req.parse_queries::<SearchAppReleasesParams>()?;
+    let list = get_list_impl(state, params).await?;
+    Ok(ApiResponse::success(list))
+}
+
+pub async fn get_list_impl(
+    state: &AppState,
+    params: SearchAppReleasesParams,
+) -> Result<PagingResponse<app_releases::Model>, AppError> {
+    let page = params.pagination.page.unwrap_or(1);
+    let page_size = params.pagination.page_size.unwrap_or(20);
+    let mut query = app_releases::Entity::find()
+        .order_by_asc(app_releases::Column::AppId)
+        .order_by_desc(app_releases::Column::Vercode)
+        .order_by_asc(app_releases::Column::Channel);
+    crate::filter_if_some!(query, app_releases::Column::AppId, params.app_id, eq);
+    crate::filter_if_some!(
+        query,
+        app_releases::Column::Channel,
+        params.channel.map(|c| c.as_str()),
+        eq
+    );
+    crate::filter_if_some!(query, app_releases::Column::Status, params.status, eq);
+    let paginator = query.paginate(&state.db, page_size);
+    let total = paginator.num_items().await?;
+    let list = paginator.fetch_page(page - 1).await?;
+    Ok(PagingResponse { list, total, page })
+}
+
+// Get AppRelease by ID
+#[handler]
+pub async fn get_by_id(
+    depot: &mut Depot,
+    id: PathParam<i32>,
+) -> Result<ApiResponse<app_releases::Model>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let release = get_by_id_impl(state, id.into_inner()).await?;
+    Ok(ApiResponse::success(release))
+}
+
+pub async fn get_by_id_impl(state: &AppState, id: i32) -> Result<app_releases::Model, AppError> {
+    let release = app_releases::Entity::find_by_id(id).one(&state.db).await?;
+    release.ok_or_else(|| AppError::not_found("app_releases".to_string(), Some(id)))
+}
+
+// Get AppRelease audit events
+#[handler]
+pub async fn get_events(
+    depot: &mut Depot,
+    req: &mut Request,
+) -> Result<ApiResponse<PagingResponse<app_release_events::Model>>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let params = req.parse_queries::<SearchAppReleaseEventsParams>()?;
+    let list = get_events_impl(state, params).await?;
+    Ok(ApiResponse::success(list))
+}
+
+pub async fn get_events_impl(
+    state: &AppState,
+    params: SearchAppReleaseEventsParams,
+) -> Result<PagingResponse<app_release_events::Model>, AppError> {
+    let page = params.pagination.page.unwrap_or(1);
+    let page_size = params.pagination.page_size.unwrap_or(20);
+    let mut query = app_release_events::Entity::find()
+        .order_by_desc(app_release_events::Column::CreatedAt)
+        .order_by_desc(app_release_events::Column::Id);
+    crate::filter_if_some!(query, app_release_events::Column::AppId, params.app_id, eq);
+    crate::filter_if_some!(
+        query,
+        app_release_events::Column::ReleaseId,
+        params.release_id,
+        eq
+    );
+    let paginator = query.paginate(&state.db, page_size);
+    let total = paginator.num_items().await?;
+    let list = paginator.fetch_page(page - 1).await?;
+    Ok(PagingResponse { list, total, page })
+}
+
+/// Check whether a newer release is available for the client
+#[endpoint(
+    tags("app_releases"),
+    parameters(
+        ("vercode"=i32, Query, description = "客户端当前版本号"),
+        ("channel"=Option<String>, Query, description = "stable / beta，默认 stable"),
+        ("platform"=Option<String>, Query, description = "平台标识，如 windows / macos"),
+        ("device_id"=Option<String>, Query, description = "设备 ID，用于灰度分桶")
+))]
+pub async fn check_update(
+    depot: &mut Depot,
+    app_id: PathParam<String>,
+    req: &mut Request,
+) -> Result<ApiResponse<CheckUpdateResp>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let params = req.parse_queries::<CheckUpdateParams>()?;
+    let resp = check_update_impl(state, app_id.into_inner(), params).await?;
+    Ok(ApiResponse::success(resp))
+}
+
+/// 返回客户端通道内比当前版本新的最高已发布版本，灰度中的版本只返回给分桶命中的设备
+/// 指定平台时跳过没有该平台下载地址的版本
+/// 当前版本已回滚且没有更新的版本时，返回低于当前版本的最新可用版本
+pub async fn check_update_impl(
+    state: &AppState,
+    app_id: String,
+    params: CheckUpdateParams,
+) -> Result<CheckUpdateResp, AppError> {
+    let vercode = params
+        .vercode
+        .ok_or_else(|| AppError::validation("vercode is required"))?;
+    let app = apps::Entity::find()
+        .filter(apps::Column::AppId.eq(app_id))
+        .one(&state.db)
+        .await?;
+    let app = app.ok_or(AppError::not_found("apps".to_string(), None))?;
+    let platform = params.platform.filter(|p| !p.is_empty());
+    let device_id = params.device_id.filter(|d| !d.is_empty());
+    let releases = app_releases::Entity::find()
+        .filter(app_releases::Column::AppId.eq(app.id))
+        .filter(app_releases::Column::Status.is_in([
+            i16::from(ReleaseStatus::Published),
+            i16::from(ReleaseStatus::RolledBack),
+        ]))
+        .filter(app_releases::Column::Channel.is_in(params.channel.visible()))
+        .order_by_desc(app_releases::Column::Vercode)
+        .all(&state.db)
+        .await?;
+    let rolled_back = releases.iter().any(|r| {
+        r.vercode == vercode && ReleaseStatus::from(r.status) == ReleaseStatus::RolledBack
+    });
+    let available: Vec<_> = releases
+        .into_iter()
+        .filter(|r| ReleaseStatus::from(r.status) == ReleaseStatus::Published)
+        .filter(|r| in_rollout(r, device_id.as_deref()))
+        .filter(|r| {
+            platform
+                .as_deref()
+                .is_none_or(|p| r.downloads.get(p).is_some_and(|url| url.is_string()))
+        })
+        .collect();
+    let newer: Vec<_> = available.iter().filter(|r| r.vercode > vercode).collect();
+    if let Some(latest) = newer.first() {
+        let force_update = latest.min_vercode > vercode || newer.iter().any(|r| r.force_update);
+        return Ok(CheckUpdateResp {
+            has_update: true,
+            force_update,
+            rollback: false,
+            release: Some(AppReleaseInfo::new((*latest).clone(), platform.as_deref())),
+        });
+    }
+    let previous = available
+        .into_iter()
+        .find(|r| rolled_back && r.vercode < vercode);
+    Ok(CheckUpdateResp {
+        has_update: previous.is_some(),
+        force_update: false,
+        rollback: previous.is_some(),
+        release: previous.map(|r| AppReleaseInfo::new(r, platform.as_deref())),
+    })
+}
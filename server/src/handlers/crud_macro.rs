@@ -37,6 +37,12 @@ macro_rules! filter_if_some {
             $query = $query.filter($column.lt(val));
         }
     };
+    //gte
+    ($query:expr, $column:expr, $value:expr, gte) => {
+        if let Some(val) = $value {
+            $query = $query.filter($column.gte(val));
+        }
+    };
     //gt
     //like
     ($query:expr, $column:expr, $value:expr, like) => {
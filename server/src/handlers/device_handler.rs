@@ -1,12 +1,14 @@
-use entity::{app_devices, apps};
+use entity::{app_devices, apps, device_bans, device_renewals};
 use salvo::{prelude::*};
 use crate::types::app_devices_types::*;
 use crate::types::common::*;
 use crate::types::error::*;
 use crate::types::response::*;
+use salvo::oapi::extract::JsonBody;
+use salvo_oapi::extract::PathParam;
 use sea_orm::{
-    ColumnTrait, EntityTrait,  PaginatorTrait, QueryFilter,
-    QueryOrder,
+    ActiveModelTrait, ColumnTrait, EntityTrait, IntoActiveModel, PaginatorTrait, QueryFilter,
+    QueryOrder, Set,
 };
 
 #[handler]
@@ -31,6 +33,7 @@ pub async fn get_list_impl(
         .order_by_desc(app_devices::Column::BindTime);
     crate::filter_if_some!(query, app_devices::Column::AppId, params.app_id, eq);
     crate::filter_if_some!(query, app_devices::Column::DeviceId, params.device_id, eq);
+    crate::filter_if_some!(query, app_devices::Column::Expired, params.expired, eq);
     let paginator = query.paginate(&state.db, page_size);
     let total = paginator.num_items().await.unwrap_or(0);
     let result= paginator.fetch_page(page - 1).await?;
@@ -40,3 +43,128 @@ pub async fn get_list_impl(
         .collect();
     Ok(PagingResponse { list, total, page })
 }
+
+// Ban a device
+#[handler]
+pub async fn add_ban(
+    depot: &mut Depot,
+    req: JsonBody<AddDeviceBanReq>,
+) -> Result<ApiResponse<device_bans::Model>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let ban = add_ban_impl(state, req.into_inner()).await?;
+    Ok(ApiResponse::success(ban))
+}
+
+pub async fn add_ban_impl(
+    state: &AppState,
+    req: AddDeviceBanReq,
+) -> Result<device_bans::Model, AppError> {
+    if req.device_id.is_empty() {
+        return Err(AppError::validation("device_id is required"));
+    }
+    let exist = device_bans::Entity::find()
+        .filter(
+            device_bans::Column::AppId
+                .eq(req.app_id)
+                .and(device_bans::Column::DeviceId.eq(req.device_id.clone())),
+        )
+        .one(&state.db)
+        .await?;
+    // 已解封的记录重新启用，同一设备只保留一条封禁记录
+    if let Some(exist) = exist {
+        if exist.lifted_at.is_none() {
+            return Ok(exist);
+        }
+        let mut ban = exist.into_active_model();
+        ban.reason = Set(req.reason);
+        ban.created_at = Set(chrono::Utc::now());
+        ban.lifted_at = Set(None);
+        return Ok(ban.update(&state.db).await?);
+    }
+    let ban = device_bans::ActiveModel {
+        app_id: Set(req.app_id),
+        device_id: Set(req.device_id),
+        reason: Set(req.reason),
+        created_at: Set(chrono::Utc::now()),
+        lifted_at: Set(None),
+        ..Default::default()
+    }
+    .insert(&state.db)
+    .await?;
+    Ok(ban)
+}
+
+// Unban a device
+#[handler]
+pub async fn delete_ban(depot: &mut Depot, id: PathParam<i32>) -> Result<ApiResponse<()>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    delete_ban_impl(state, id.into_inner()).await?;
+    Ok(ApiResponse::success(()))
+}
+
+/// 解封只记录解除时间，保留记录供吊销列表增量同步
+pub async fn delete_ban_impl(state: &AppState, id: i32) -> Result<(), AppError> {
+    let ban = device_bans::Entity::find_by_id(id)
+        .filter(device_bans::Column::LiftedAt.is_null())
+        .one(&state.db)
+        .await?;
+    let ban = ban.ok_or_else(|| AppError::not_found("device_bans".to_string(), Some(id)))?;
+    let mut ban = ban.into_active_model();
+    ban.lifted_at = Set(Some(chrono::Utc::now()));
+    ban.update(&state.db).await?;
+    Ok(())
+}
+
+#[handler]
+pub async fn get_ban_list(
+    depot: &mut Depot,
+    req: &mut Request,
+) -> Result<ApiResponse<PagingResponse<device_bans::Model>>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let params = req.parse_queries::<SearchDeviceBansParams>()?;
+    let list = get_ban_list_impl(state, params).await?;
+    Ok(ApiResponse::success(list))
+}
+
+pub async fn get_ban_list_impl(
+    state: &AppState,
+    params: SearchDeviceBansParams,
+) -> Result<PagingResponse<device_bans::Model>, AppError> {
+    let page = params.pagination.page.unwrap_or(1);
+    let page_size = params.pagination.page_size.unwrap_or(20);
+    let mut query = device_bans::Entity::find()
+        .filter(device_bans::Column::LiftedAt.is_null())
+        .order_by_desc(device_bans::Column::CreatedAt);
+    crate::filter_if_some!(query, device_bans::Column::AppId, params.app_id, eq);
+    crate::filter_if_some!(query, device_bans::Column::DeviceId, params.device_id, eq);
+    let paginator = query.paginate(&state.db, page_size);
+    let total = paginator.num_items().await?;
+    let list = paginator.fetch_page(page - 1).await?;
+    Ok(PagingResponse { list, total, page })
+}
+
+// Get renewal history of a device
+#[handler]
+pub async fn get_renewals(
+    depot: &mut Depot,
+    id: PathParam<i32>,
+) -> Result<ApiResponse<Vec<device_renewals::Model>>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let list = get_renewals_impl(state, id.into_inner()).await?;
+    Ok(ApiResponse::success(list))
+}
+
+pub async fn get_renewals_impl(
+    state: &AppState,
+    id: i32,
+) -> Result<Vec<device_renewals::Model>, AppError> {
+    let device = app_devices::Entity::find_by_id(id).one(&state.db).await?;
+    device.ok_or_else(|| AppError::not_found("app_devices".to_string(), Some(id)))?;
+    let list = device_renewals::Entity::find()
+        .filter(device_renewals::Column::DeviceId.eq(id))
+        .order_by_asc(device_renewals::Column::CreatedAt)
+        .order_by_asc(device_renewals::Column::Id)
+        .all(&state.db)
+        .await?;
+    Ok(list)
+}
@@ -0,0 +1,80 @@
+use crate::services::scheduler::{self, Trigger};
+use crate::types::common::{AppState, PagingResponse};
+use crate::types::error::AppError;
+use crate::types::jobs_types::*;
+use crate::types::response::ApiResponse;
+use entity::job_runs;
+use salvo::prelude::*;
+use salvo_oapi::extract::PathParam;
+use sea_orm::{ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter, QueryOrder};
+
+// Get registered jobs with their last run
+#[handler]
+pub async fn get_list(depot: &mut Depot) -> Result<ApiResponse<Vec<JobInfo>>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let list = get_list_impl(state).await?;
+    Ok(ApiResponse::success(list))
+}
+
+pub async fn get_list_impl(state: &AppState) -> Result<Vec<JobInfo>, AppError> {
+    let mut list = Vec::with_capacity(scheduler::JOBS.len());
+    for job in scheduler::JOBS {
+        let last_run = job_runs::Entity::find()
+            .filter(job_runs::Column::JobName.eq(job.name))
+            .order_by_desc(job_runs::Column::StartedAt)
+            .one(&state.db)
+            .await?;
+        list.push(JobInfo {
+            name: job.name.to_string(),
+            interval_secs: job.interval.as_secs(),
+            last_run,
+        });
+    }
+    Ok(list)
+}
+
+// Get job run history
+#[handler]
+pub async fn get_runs(
+    depot: &mut Depot,
+    req: &mut Request,
+) -> Result<ApiResponse<PagingResponse<job_runs::Model>>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let params = req.parse_queries::<SearchJobRunsParams>()?;
+    let list = get_runs_impl(state, params).await?;
+    Ok(ApiResponse::success(list))
+}
+
+pub async fn get_runs_impl(
+    state: &AppState,
+    params: SearchJobRunsParams,
+) -> Result<PagingResponse<job_runs::Model>, AppError> {
+    let page = params.pagination.page.unwrap_or(1);
+    let page_size = params.pagination.page_size.unwrap_or(20);
+    let mut query = job_runs::Entity::find()
+        .order_by_desc(job_runs::Column::StartedAt)
+        .order_by_desc(job_runs::Column::Id);
+    crate::filter_if_some!(query, job_runs::Column::JobName, params.job_name, eq);
+    crate::filter_if_some!(query, job_runs::Column::Success, params.success, eq);
+    let paginator = query.paginate(&state.db, page_size);
+    let total = paginator.num_items().await?;
+    let list = paginator.fetch_page(page - 1).await?;
+    Ok(PagingResponse { list, total, page })
+}
+
+// Run a job immediately
+#[handler]
+pub async fn run_now(
+    depot: &mut Depot,
+    name: PathParam<String>,
+) -> Result<ApiResponse<job_runs::Model>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let run = run_now_impl(state, name.into_inner()).await?;
+    Ok(ApiResponse::success(run))
+}
+
+pub async fn run_now_impl(state: &AppState, name: String) -> Result<job_runs::Model, AppError> {
+    let job = scheduler::find_job(&name)
+        .ok_or_else(|| AppError::not_found(format!("job {}", name), None))?;
+    scheduler::run_job(state, job, Trigger::Manual).await
+}
@@ -0,0 +1,273 @@
+use crate::handlers::{app_features_handler, reg_codes_handler};
+use crate::types::lease_types::*;
+use crate::types::common::AppState;
+use crate::types::error::AppError;
+use crate::types::reg_codes_types::*;
+use crate::types::response::ApiResponse;
+use chrono::{DateTime, Utc};
+use entity::{apps, reg_codes};
+use salvo::{oapi::extract::JsonBody, prelude::*};
+use salvo_oapi::extract::PathParam;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, EntityTrait, IntoActiveModel, QueryFilter, QuerySelect, Set,
+    TransactionTrait,
+};
+use std::time::Duration;
+
+// 租约有效期（秒），客户端需在到期前心跳续约，过期的租约在下次访问时回收
+const LEASE_TTL_SECS: i64 = 300;
+
+// 注册码全局唯一，直接用作 key
+fn lease_key(code: &str) -> String {
+    format!("reg:lease:{}", code)
+}
+
+/// 回收过期租约后返回仍有效的租约
+async fn live_leases(state: &AppState, code: &str) -> Result<Vec<LeaseInfo>, AppError> {
+    let key = lease_key(code);
+    state
+        .redis
+        .zrem_by_score_max(&key, Utc::now().timestamp())
+        .await?;
+    let members = state.redis.zrange_withscores(&key).await?;
+    Ok(members
+        .into_iter()
+        .map(|(device_id, expire_at)| LeaseInfo {
+            device_id,
+            expire_at: DateTime::from_timestamp(expire_at, 0).unwrap_or_default(),
+        })
+        .collect())
+}
+
+/// 写入或续期租约
+async fn grant_lease(
+    state: &AppState,
+    code: &str,
+    device_id: &str,
+) -> Result<LeaseInfo, AppError> {
+    let key = lease_key(code);
+    let expire_at = Utc::now() + chrono::Duration::seconds(LEASE_TTL_SECS);
+    state
+        .redis
+        .zadd(&key, device_id, expire_at.timestamp())
+        .await?;
+    // 所有租约都过期后整个 key 自动删除
+    state
+        .redis
+        .expire(&key, Duration::from_secs(LEASE_TTL_SECS as u64))
+        .await?;
+    Ok(LeaseInfo {
+        device_id: device_id.to_string(),
+        expire_at,
+    })
+}
+
+async fn find_floating_code(
+    state: &AppState,
+    app: &apps::Model,
+    code: Option<&str>,
+) -> Result<reg_codes::Model, AppError> {
+    let code = code
+        .map(str::trim)
+        .filter(|c| !c.is_empty())
+        .ok_or_else(|| AppError::validation("code is required"))?;
+    reg_codes_handler::check_strict_format(app, code)?;
+    let reg_code = reg_codes::Entity::find()
+        .filter(
+            reg_codes::Column::Code
+                .eq(code)
+                .and(reg_codes::Column::AppId.eq(app.id)),
+        )
+        .one(&state.db)
+        .await?;
+    let reg_code = reg_code.ok_or(AppError::not_found("reg_code".to_string(), None))?;
+    if CodeType::from(reg_code.code_type) != CodeType::Floating {
+        return Err(AppError::business_logic(
+            "NOT_FLOATING_CODE",
+            "only floating codes can check out a lease",
+        ));
+    }
+    if RegCodeStatus::from(reg_code.status) == RegCodeStatus::Revoked {
+        return Err(AppError::business_logic(
+            "CODE_REVOKED",
+            format!(
+                "reg code revoked: {}",
+                reg_code.revoked_reason.unwrap_or_default()
+            ),
+        ));
+    }
+    if reg_code.expire_time.is_some_and(|exp| Utc::now() > exp) {
+        return Err(AppError::business_logic("CODE_EXPIRED", "code expired"));
+    }
+    Ok(reg_code)
+}
+
+/// Check out a floating license seat
+#[endpoint(tags("reg_codes"))]
+pub async fn checkout(
+    depot: &mut Depot,
+    req: JsonBody<RegCodeValidateV2Req>,
+) -> Result<ApiResponse<RegCodeValidateV2Resp>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let resp = checkout_impl(state, req.into_inner()).await?;
+    Ok(ApiResponse::success(resp))
+}
+
+pub async fn checkout_impl(
+    state: &AppState,
+    req: RegCodeValidateV2Req,
+) -> Result<RegCodeValidateV2Resp, AppError> {
+    let app = reg_codes_handler::verify_signed_request(state, &req).await?;
+    reg_codes_handler::check_device_ban(&state.db, app.id, &req.device_id).await?;
+    let reg_code = find_floating_code(state, &app, req.code.as_deref()).await?;
+
+    let txn = state.db.begin().await?;
+    // 锁住注册码行，串行化同一注册码的 checkout，避免并发突破席位数
+    let reg_code = reg_codes::Entity::find_by_id(reg_code.id)
+        .lock_exclusive()
+        .one(&txn)
+        .await?
+        .ok_or_else(|| AppError::not_found("reg_codes".to_string(), Some(reg_code.id)))?;
+    let leases = live_leases(state, &reg_code.code).await?;
+    let held = leases.iter().any(|l| l.device_id == req.device_id);
+    if !held && leases.len() as i32 >= reg_code.max_devices {
+        return Err(AppError::business_logic(
+            "SEATS_EXHAUSTED",
+            "all seats are in use",
+        ));
+    }
+    let mut expire_time = reg_code.expire_time;
+    if reg_code.binding_time.is_none() {
+        // 首次使用时开始计算有效期
+        let now = Utc::now();
+        expire_time =
+            expire_time.or_else(|| Some(now + chrono::Duration::days(reg_code.valid_days as i64)));
+        let mut active = reg_code.clone().into_active_model();
+        active.status = Set(RegCodeStatus::Used.into());
+        active.binding_time = Set(Some(now));
+        active.expire_time = Set(expire_time);
+        active.updated_at = Set(now);
+        active.update(&txn).await?;
+    }
+    let lease = grant_lease(state, &reg_code.code, &req.device_id).await?;
+    txn.commit().await?;
+    let entitlements =
+        app_features_handler::resolve_entitlements(&state.db, app.id, Some(&reg_code)).await?;
+
+    let seats_used = leases.len() as i32 + if held { 0 } else { 1 };
+    reg_codes_handler::sign_response(
+        &app.app_valid_key,
+        req.nonce,
+        &LeaseResp {
+            lease,
+            heartbeat_interval: LEASE_TTL_SECS / 3,
+            seats_total: reg_code.max_devices,
+            seats_used,
+            expire_time,
+            entitlements,
+        },
+    )
+}
+
+/// Renew a floating license lease
+#[endpoint(tags("reg_codes"))]
+pub async fn heartbeat(
+    depot: &mut Depot,
+    req: JsonBody<RegCodeValidateV2Req>,
+) -> Result<ApiResponse<RegCodeValidateV2Resp>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let resp = heartbeat_impl(state, req.into_inner()).await?;
+    Ok(ApiResponse::success(resp))
+}
+
+pub async fn heartbeat_impl(
+    state: &AppState,
+    req: RegCodeValidateV2Req,
+) -> Result<RegCodeValidateV2Resp, AppError> {
+    let app = reg_codes_handler::verify_signed_request(state, &req).await?;
+    reg_codes_handler::check_device_ban(&state.db, app.id, &req.device_id).await?;
+    let reg_code = find_floating_code(state, &app, req.code.as_deref()).await?;
+    let txn = state.db.begin().await?;
+    // 与 checkout 持有同一把注册码行锁，避免租约刚过期、席位被他人占用后又被续回
+    reg_codes::Entity::find_by_id(reg_code.id)
+        .lock_exclusive()
+        .one(&txn)
+        .await?;
+    let leases = live_leases(state, &reg_code.code).await?;
+    if !leases.iter().any(|l| l.device_id == req.device_id) {
+        return Err(AppError::business_logic(
+            "LEASE_EXPIRED",
+            "lease expired, check out again",
+        ));
+    }
+    let lease = grant_lease(state, &reg_code.code, &req.device_id).await?;
+    txn.commit().await?;
+    let entitlements =
+        app_features_handler::resolve_entitlements(&state.db, app.id, Some(&reg_code)).await?;
+    reg_codes_handler::sign_response(
+        &app.app_valid_key,
+        req.nonce,
+        &LeaseResp {
+            lease,
+            heartbeat_interval: LEASE_TTL_SECS / 3,
+            seats_total: reg_code.max_devices,
+            seats_used: leases.len() as i32,
+            expire_time: reg_code.expire_time,
+            entitlements,
+        },
+    )
+}
+
+/// Release a floating license lease
+#[endpoint(tags("reg_codes"))]
+pub async fn checkin(
+    depot: &mut Depot,
+    req: JsonBody<RegCodeValidateV2Req>,
+) -> Result<ApiResponse<()>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    checkin_impl(state, req.into_inner()).await?;
+    Ok(ApiResponse::success(()))
+}
+
+pub async fn checkin_impl(state: &AppState, req: RegCodeValidateV2Req) -> Result<(), AppError> {
+    let app = reg_codes_handler::verify_signed_request(state, &req).await?;
+    let code = req.code.unwrap_or_default();
+    let reg_code = reg_codes::Entity::find()
+        .filter(
+            reg_codes::Column::Code
+                .eq(code)
+                .and(reg_codes::Column::AppId.eq(app.id)),
+        )
+        .one(&state.db)
+        .await?;
+    let reg_code = reg_code.ok_or(AppError::not_found("reg_code".to_string(), None))?;
+    state
+        .redis
+        .zrem(&lease_key(&reg_code.code), &req.device_id)
+        .await?;
+    Ok(())
+}
+
+// Get live seat usage of a floating code
+#[handler]
+pub async fn get_seat_usage(
+    depot: &mut Depot,
+    id: PathParam<i32>,
+) -> Result<ApiResponse<SeatUsageResp>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let usage = get_seat_usage_impl(state, id.into_inner()).await?;
+    Ok(ApiResponse::success(usage))
+}
+
+pub async fn get_seat_usage_impl(state: &AppState, id: i32) -> Result<SeatUsageResp, AppError> {
+    let reg_code = reg_codes::Entity::find_by_id(id).one(&state.db).await?;
+    let reg_code =
+        reg_code.ok_or_else(|| AppError::not_found("reg_codes".to_string(), Some(id)))?;
+    let leases = live_leases(state, &reg_code.code).await?;
+    Ok(SeatUsageResp {
+        reg_code_id: reg_code.id,
+        seats_total: reg_code.max_devices,
+        seats_used: leases.len() as i32,
+        leases,
+    })
+}
@@ -0,0 +1,348 @@
+use crate::handlers::{
+    offline_activation_handler, orders_handler, reg_codes_handler, transfer_handler,
+};
+use crate::types::common::AppState;
+use crate::types::error::AppError;
+use crate::types::license_portal_types::*;
+use crate::types::offline_activation_types::{OfflineActivationReq, OfflineActivationResp};
+use crate::types::reg_codes_types::{
+    RegCodeDeactivateResp, RegCodeInfo, RegCodeValidateResp, TransferSource,
+};
+use crate::types::response::ApiResponse;
+use crate::utils::client::ClientInfo;
+use crate::utils::export;
+use chrono::Utc;
+use entity::{app_devices, apps, orders, reg_code_devices, reg_codes};
+use salvo::{oapi::extract::JsonBody, prelude::*};
+use salvo_oapi::extract::PathParam;
+use sea_orm::sea_query::{Expr, Func};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder};
+use std::time::Duration;
+use validator::Validate;
+
+// 订单状态，见 orders.status 注释
+const ORDER_PAID: i16 = 1;
+// 登录链接有效期，使用一次后失效
+const MAGIC_LINK_TTL: Duration = Duration::from_secs(24 * 3600);
+// 门户会话有效期
+const SESSION_TTL: Duration = Duration::from_secs(2 * 3600);
+
+fn magic_key(token: &str) -> String {
+    format!("portal:magic:{}", token)
+}
+
+fn session_key(token: &str) -> String {
+    format!("portal:session:{}", token)
+}
+
+fn new_token() -> String {
+    uuid::Uuid::new_v4().simple().to_string()
+}
+
+/// 门户会话校验，Bearer 令牌对应的会话写入 depot
+#[handler]
+pub async fn portal_auth(req: &mut Request, depot: &mut Depot) -> Result<(), StatusCode> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let token = req
+        .headers()
+        .get("Authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+    let session = state
+        .redis
+        .get::<PortalSession>(&session_key(token))
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let session = session.ok_or(StatusCode::UNAUTHORIZED)?;
+    depot.inject(session);
+    Ok(())
+}
+
+async fn start_session(
+    state: &AppState,
+    session: PortalSession,
+) -> Result<PortalLoginResp, AppError> {
+    let token = new_token();
+    state
+        .redis
+        .set(&session_key(&token), &session, Some(SESSION_TTL))
+        .await?;
+    Ok(PortalLoginResp {
+        token,
+        email: session.email,
+        expires_at: Utc::now() + chrono::Duration::from_std(SESSION_TTL).unwrap(),
+    })
+}
+
+// Sign in with order number and contact email
+#[handler]
+pub async fn login(
+    depot: &mut Depot,
+    req: JsonBody<PortalLoginReq>,
+) -> Result<ApiResponse<PortalLoginResp>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let resp = login_impl(state, req.into_inner()).await?;
+    Ok(ApiResponse::success(resp))
+}
+
+pub async fn login_impl(
+    state: &AppState,
+    req: PortalLoginReq,
+) -> Result<PortalLoginResp, AppError> {
+    req.validate()?;
+    let email = req.email.trim().to_lowercase();
+    let order = orders::Entity::find()
+        .filter(orders::Column::OrderId.eq(req.order_no.trim()))
+        .one(&state.db)
+        .await?;
+    // 订单不存在和邮箱不匹配返回相同错误，避免探测订单号
+    let order = order
+        .filter(|o| o.status == ORDER_PAID)
+        .filter(|o| o.contact_email.as_deref().map(str::to_lowercase) == Some(email.clone()))
+        .ok_or_else(|| AppError::auth_failed("order number or email mismatch"))?;
+    start_session(
+        state,
+        PortalSession {
+            email,
+            order_id: Some(order.id),
+        },
+    )
+    .await
+}
+
+// Sign in with a one-time login link token
+#[handler]
+pub async fn magic_login(
+    depot: &mut Depot,
+    req: JsonBody<PortalMagicLoginReq>,
+) -> Result<ApiResponse<PortalLoginResp>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let resp = magic_login_impl(state, req.into_inner()).await?;
+    Ok(ApiResponse::success(resp))
+}
+
+pub async fn magic_login_impl(
+    state: &AppState,
+    req: PortalMagicLoginReq,
+) -> Result<PortalLoginResp, AppError> {
+    req.validate()?;
+    let email = state
+        .redis
+        .get_del::<String>(&magic_key(&req.token))
+        .await?;
+    let email = email.ok_or_else(|| AppError::auth_failed("login link invalid or expired"))?;
+    start_session(
+        state,
+        PortalSession {
+            email,
+            order_id: None,
+        },
+    )
+    .await
+}
+
+// Create a one-time portal login link for the buyer of an Order
+#[handler]
+pub async fn create_link(
+    depot: &mut Depot,
+    id: PathParam<i32>,
+) -> Result<ApiResponse<PortalLinkResp>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let resp = create_link_impl(state, id.into_inner()).await?;
+    Ok(ApiResponse::success(resp))
+}
+
+pub async fn create_link_impl(state: &AppState, order_id: i32) -> Result<PortalLinkResp, AppError> {
+    let order = orders::Entity::find_by_id(order_id).one(&state.db).await?;
+    let order = order.ok_or_else(|| AppError::not_found("orders".to_string(), Some(order_id)))?;
+    let email = order
+        .contact_email
+        .map(|e| e.trim().to_lowercase())
+        .filter(|e| !e.is_empty())
+        .ok_or_else(|| AppError::validation("order has no contact email"))?;
+    let token = new_token();
+    state
+        .redis
+        .set(&magic_key(&token), &email, Some(MAGIC_LINK_TTL))
+        .await?;
+    Ok(PortalLinkResp {
+        token,
+        email,
+        expires_at: Utc::now() + chrono::Duration::from_std(MAGIC_LINK_TTL).unwrap(),
+    })
+}
+
+/// 会话可访问的注册码ID
+async fn session_code_ids(state: &AppState, session: &PortalSession) -> Result<Vec<i32>, AppError> {
+    let order_ids = match session.order_id {
+        Some(id) => vec![id],
+        None => orders::Entity::find()
+            .filter(
+                Expr::expr(Func::lower(Expr::col(orders::Column::ContactEmail))).eq(&session.email),
+            )
+            .filter(orders::Column::Status.eq(ORDER_PAID))
+            .all(&state.db)
+            .await?
+            .into_iter()
+            .map(|o| o.id)
+            .collect(),
+    };
+    let mut ids = Vec::new();
+    for order_id in order_ids {
+        for id in orders_handler::order_reg_code_ids(&state.db, order_id).await? {
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+    }
+    Ok(ids)
+}
+
+/// 会话之外的注册码按不存在处理
+async fn find_own_code(
+    state: &AppState,
+    session: &PortalSession,
+    id: i32,
+) -> Result<reg_codes::Model, AppError> {
+    if !session_code_ids(state, session).await?.contains(&id) {
+        return Err(AppError::not_found("reg_codes".to_string(), Some(id)));
+    }
+    let code = reg_codes::Entity::find_by_id(id).one(&state.db).await?;
+    code.ok_or_else(|| AppError::not_found("reg_codes".to_string(), Some(id)))
+}
+
+// Get codes of the signed-in buyer with bound devices
+#[handler]
+pub async fn get_codes(depot: &mut Depot) -> Result<ApiResponse<Vec<PortalCodeInfo>>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let session = depot.obtain::<PortalSession>().unwrap();
+    let list = get_codes_impl(state, session).await?;
+    Ok(ApiResponse::success(list))
+}
+
+pub async fn get_codes_impl(
+    state: &AppState,
+    session: &PortalSession,
+) -> Result<Vec<PortalCodeInfo>, AppError> {
+    let ids = session_code_ids(state, session).await?;
+    let rows = reg_codes::Entity::find()
+        .find_also_related(apps::Entity)
+        .filter(reg_codes::Column::Id.is_in(ids.clone()))
+        .order_by_asc(reg_codes::Column::Id)
+        .all(&state.db)
+        .await?;
+    let mut devices = reg_codes_handler::load_devices(&state.db, ids).await?;
+    let mut list = Vec::with_capacity(rows.len());
+    for row in rows {
+        let mut info = RegCodeInfo::try_from(row)?;
+        info.devices = devices.remove(&info.id).unwrap_or_default();
+        list.push(PortalCodeInfo::from(info));
+    }
+    Ok(list)
+}
+
+// Deactivate a bound device to free a slot
+#[handler]
+pub async fn deactivate_device(
+    depot: &mut Depot,
+    id: PathParam<i32>,
+    device_id: PathParam<i32>,
+    req: &mut Request,
+) -> Result<ApiResponse<RegCodeDeactivateResp>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let session = depot.obtain::<PortalSession>().unwrap();
+    let client = ClientInfo::from_request(req, &state.config.trusted_proxies);
+    let resp = deactivate_device_impl(
+        state,
+        session,
+        id.into_inner(),
+        device_id.into_inner(),
+        &client,
+    )
+    .await?;
+    Ok(ApiResponse::success(resp))
+}
+
+/// 与客户端自助解绑共用次数和冷却时间限制
+pub async fn deactivate_device_impl(
+    state: &AppState,
+    session: &PortalSession,
+    id: i32,
+    device_id: i32,
+    client: &ClientInfo,
+) -> Result<RegCodeDeactivateResp, AppError> {
+    let code = find_own_code(state, session, id).await?;
+    let device = app_devices::Entity::find_by_id(device_id)
+        .one(&state.db)
+        .await?;
+    let device = device
+        .filter(|d| d.app_id == code.app_id)
+        .ok_or_else(|| AppError::not_found("reg_code_devices".to_string(), Some(device_id)))?;
+    transfer_handler::release_device(
+        state,
+        code.id,
+        &device,
+        TransferSource::Portal,
+        Some(client.ip.clone()).filter(|ip| !ip.is_empty()),
+    )
+    .await
+}
+
+// Re-download the license token of a bound device
+#[handler]
+pub async fn get_license(
+    depot: &mut Depot,
+    id: PathParam<i32>,
+    device_id: PathParam<i32>,
+) -> Result<ApiResponse<RegCodeValidateResp>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let session = depot.obtain::<PortalSession>().unwrap();
+    let resp = get_license_impl(state, session, id.into_inner(), device_id.into_inner()).await?;
+    Ok(ApiResponse::success(resp))
+}
+
+pub async fn get_license_impl(
+    state: &AppState,
+    session: &PortalSession,
+    id: i32,
+    device_id: i32,
+) -> Result<RegCodeValidateResp, AppError> {
+    let code = find_own_code(state, session, id).await?;
+    let binding = reg_code_devices::Entity::find()
+        .find_also_related(app_devices::Entity)
+        .filter(reg_code_devices::Column::RegCodeId.eq(code.id))
+        .filter(reg_code_devices::Column::DeviceId.eq(device_id))
+        .one(&state.db)
+        .await?;
+    let device = binding
+        .and_then(|(_, device)| device)
+        .ok_or_else(|| AppError::not_found("reg_code_devices".to_string(), Some(device_id)))?;
+    reg_codes_handler::reissue_license(state, &code, &device).await
+}
+
+// Activate an air-gapped device of the signed-in buyer with a request file
+#[handler]
+pub async fn offline_activate(
+    depot: &mut Depot,
+    req: &mut Request,
+    res: &mut Response,
+) -> Result<(), AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let session = depot.obtain::<PortalSession>().unwrap();
+    let json = req.parse_json::<OfflineActivationReq>().await?;
+    let client = ClientInfo::from_request(req, &state.config.trusted_proxies);
+    let resp = offline_activate_impl(state, session, json, &client).await?;
+    let file_name = format!("activation_{}", resp.nonce);
+    export::render_json_attachment(res, &file_name, &resp)
+}
+
+pub async fn offline_activate_impl(
+    state: &AppState,
+    session: &PortalSession,
+    req: OfflineActivationReq,
+    client: &ClientInfo,
+) -> Result<OfflineActivationResp, AppError> {
+    let ids = session_code_ids(state, session).await?;
+    offline_activation_handler::activate_impl(state, req, client, Some(ids)).await
+}
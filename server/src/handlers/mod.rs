@@ -1,19 +1,35 @@
 pub mod app_handler;
+pub mod app_announcements_handler;
+pub mod app_configs_handler;
+pub mod app_features_handler;
+pub mod app_releases_handler;
 pub mod auth;
 pub mod casbin_handler;
 pub mod casbin_middleware;
 pub mod coupons_handler;
 pub mod crud_macro;
 pub mod invite_records_handler;
+pub mod jobs_handler;
+pub mod license_portal_handler;
 pub mod middleware;
+pub mod offline_activation_handler;
 pub mod orders_handler;
 pub mod pay_method_handler;
 // pub mod payment_handler;
 pub mod oss_handler;
 pub mod product_handler;
+pub mod reg_code_batches_handler;
 pub mod reg_codes_handler;
+pub mod release_manifest_handler;
+pub mod reseller_handler;
+pub mod reseller_portal_handler;
 pub mod resource_handler;
 pub mod role_handler;
+pub mod transfer_handler;
+pub mod trial_handler;
+pub mod usage_handler;
 pub mod user_handler;
+pub mod validation_events_handler;
 pub mod vuefinder_handler;
-pub mod device_handler;
\ No newline at end of file
+pub mod device_handler;
+pub mod lease_handler;
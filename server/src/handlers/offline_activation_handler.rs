@@ -0,0 +1,148 @@
+use crate::handlers::reg_codes_handler;
+use crate::types::common::AppState;
+use crate::types::error::AppError;
+use crate::types::offline_activation_types::*;
+use crate::types::reg_codes_types::{CodeType, RegCodeValidateReq};
+use crate::types::validation_events_types::AppRef;
+use crate::utils::client::ClientInfo;
+use crate::utils::{export, signature};
+use chrono::Utc;
+use entity::{apps, reg_codes};
+use salvo::prelude::*;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+
+// 离线设备的时钟可能不准，请求文件的生成时间只要求在该范围内
+const REQUEST_MAX_AGE_DAYS: i64 = 30;
+// validation_events.api_version，见 init.sql
+const API_VERSION_OFFLINE: i16 = 3;
+
+// Upload an offline activation request file and download the signed response file
+#[handler]
+pub async fn activate(
+    depot: &mut Depot,
+    req: &mut Request,
+    res: &mut Response,
+) -> Result<(), AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let json = req.parse_json::<OfflineActivationReq>().await?;
+    let client = ClientInfo::from_request(req, &state.config.trusted_proxies);
+    let resp = activate_impl(state, json, &client, None).await?;
+    let file_name = format!("activation_{}", resp.nonce);
+    export::render_json_attachment(res, &file_name, &resp)
+}
+
+/// 校验离线激活请求并签发许可证，绑定规则与在线校验相同，激活记录写入 app_devices
+/// allowed_codes 不为空时只允许激活其中的注册码（门户按会话限制）
+pub async fn activate_impl(
+    state: &AppState,
+    req: OfflineActivationReq,
+    client: &ClientInfo,
+    allowed_codes: Option<Vec<i32>>,
+) -> Result<OfflineActivationResp, AppError> {
+    let app_ref = AppRef::AppId(req.app_id.clone());
+    let (device_id, code) = (req.device_id.clone(), req.code.clone());
+    let result = activate_for_request(state, req, client, allowed_codes).await;
+    reg_codes_handler::record_validation(
+        state,
+        client,
+        app_ref,
+        device_id,
+        Some(code),
+        API_VERSION_OFFLINE,
+        &result,
+    );
+    result
+}
+
+async fn activate_for_request(
+    state: &AppState,
+    mut req: OfflineActivationReq,
+    client: &ClientInfo,
+    allowed_codes: Option<Vec<i32>>,
+) -> Result<OfflineActivationResp, AppError> {
+    let app = apps::Entity::find()
+        .filter(apps::Column::AppId.eq(&req.app_id))
+        .one(&state.db)
+        .await?;
+    let app = app.ok_or(AppError::not_found("apps".to_string(), None))?;
+    // 空密钥谁都能算出签名，不能作为 HMAC 密钥
+    if app.app_valid_key.trim().is_empty() {
+        return Err(AppError::business_logic(
+            "SIGN_KEY_MISSING",
+            "app has no valid key, signed requests are disabled",
+        ));
+    }
+    if !signature::verify_hmac_sha256_hex(&app.app_valid_key, &req.sign_content(), &req.sign) {
+        return Err(AppError::business_logic(
+            "SIGN_MISMATCH",
+            "invalid request signature",
+        ));
+    }
+    let age = Utc::now().timestamp() - req.created_at;
+    if age.abs() > REQUEST_MAX_AGE_DAYS * 86400 {
+        return Err(AppError::business_logic(
+            "REQUEST_EXPIRED",
+            "activation request expired, export a new one on the device",
+        ));
+    }
+    if req.nonce.len() < 8
+        || req.nonce.len() > 64
+        || !req
+            .nonce
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(AppError::validation(
+            "nonce must be 8-64 letters, digits, '-' or '_'",
+        ));
+    }
+    if req.device_id.is_empty() {
+        return Err(AppError::validation("device_id is required"));
+    }
+    // 签名按原始内容计算，验签之后再去掉首尾空白，与在线校验一致
+    req.code = req.code.trim().to_string();
+    let reg_code = reg_codes::Entity::find()
+        .filter(reg_codes::Column::Code.eq(&req.code))
+        .filter(reg_codes::Column::AppId.eq(app.id))
+        .one(&state.db)
+        .await?;
+    let reg_code = reg_code
+        .filter(|c| allowed_codes.as_ref().is_none_or(|ids| ids.contains(&c.id)))
+        .ok_or(AppError::not_found("reg_codes".to_string(), None))?;
+    // 次数类和浮动授权需要联网计数或续租，无法离线使用
+    if CodeType::from(reg_code.code_type) != CodeType::Time {
+        return Err(AppError::business_logic(
+            "OFFLINE_UNSUPPORTED",
+            "only time based codes can be activated offline",
+        ));
+    }
+    let (app_id, app_key) = (app.app_id.clone(), app.app_valid_key.clone());
+    let resp = reg_codes_handler::validate_for_app(
+        state,
+        app,
+        RegCodeValidateReq {
+            code: Some(req.code.clone()),
+            app_key,
+            device_id: req.device_id.clone(),
+            device_info: req.device_info,
+            config: None,
+        },
+        client,
+        true,
+    )
+    .await?;
+    let license_token = resp
+        .license_token
+        .ok_or_else(|| AppError::Message("license token not issued".to_string()))?;
+    Ok(OfflineActivationResp {
+        app_id,
+        device_id: req.device_id,
+        nonce: req.nonce,
+        code: req.code,
+        code_type: resp.code_type,
+        expire_time: resp.expire_time,
+        entitlements: resp.entitlements,
+        license_token,
+        issued_at: Utc::now(),
+    })
+}
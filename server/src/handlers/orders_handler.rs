@@ -1,5 +1,6 @@
 use crate::types::orders_types::*;
-use entity::orders;
+use entity::{order_reg_codes, orders, reg_codes};
+use validator::Validate;
 crate::import_crud_macro!();
 use salvo::{prelude::*, oapi::extract::JsonBody};
 use salvo_oapi::extract::{PathParam};
@@ -15,6 +16,7 @@ pub async fn add(
 }
 
 pub async fn add_impl(state: &AppState, req: CreateOrderReq) -> Result<orders::Model, AppError> {
+    req.validate()?;
     let active_model = orders::ActiveModel {
         order_id: Set(req.order_id),
         user_info: Set(req.user_info),
@@ -23,6 +25,7 @@ pub async fn add_impl(state: &AppState, req: CreateOrderReq) -> Result<orders::M
         original_price: Set(req.original_price),
         final_price: Set(req.final_price),
         remark: Set(req.remark),
+        contact_email: Set(req.contact_email),
         created_by: Set(req.created_by),
         updated_by: Set(req.updated_by),
         created_at: Set(Utc::now()),
@@ -50,6 +53,7 @@ pub async fn update_impl(
     id: i32,
     req: UpdateOrderReq,
 ) -> Result<orders::Model, AppError> {
+    req.validate()?;
     let order = orders::Entity::find_by_id(id).one(&state.db).await?;
     let order = order.ok_or_else(|| AppError::not_found("orders".to_string(), Some(id)))?;
     let mut order: orders::ActiveModel = order.into_active_model();
@@ -60,6 +64,7 @@ pub async fn update_impl(
     crate::update_field_if_some!(order, original_price, req.original_price);
     crate::update_field_if_some!(order, final_price, req.final_price);
     crate::update_field_if_some!(order, remark, req.remark, option);
+    crate::update_field_if_some!(order, contact_email, req.contact_email, option);
     crate::update_field_if_some!(order, updated_by, req.updated_by);
     let order = order.update(&state.db).await?;
     Ok(order)
@@ -106,6 +111,7 @@ pub async fn get_list_impl(
     crate::filter_if_some!(query, orders::Column::OrderId, params.order_id, contains);
     crate::filter_if_some!(query, orders::Column::Status, params.status, eq);
     crate::filter_if_some!(query, orders::Column::PayMethodId, params.pay_method_id, eq);
+    crate::filter_if_some!(query, orders::Column::ContactEmail, params.contact_email, contains);
     crate::filter_if_some!(query, orders::Column::CreatedBy, params.created_by, eq);
     let paginator = query.paginate(&state.db, page_size);
     let total = paginator.num_items().await.unwrap_or(0);
@@ -130,3 +136,65 @@ pub async fn get_by_id(
     let order = OrderInfo::try_from(order)?;
     Ok(ApiResponse::success(order))
 }
+
+// Link RegCodes to an Order
+#[handler]
+pub async fn link_reg_codes(
+    depot: &mut Depot,
+    id: PathParam<i32>,
+    req: JsonBody<LinkOrderRegCodesReq>,
+) -> Result<ApiResponse<Vec<i32>>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let ids = link_reg_codes_impl(state, id.into_inner(), req.into_inner()).await?;
+    Ok(ApiResponse::success(ids))
+}
+
+/// 关联注册码到订单，已关联的跳过，返回订单下全部注册码ID
+pub async fn link_reg_codes_impl(
+    state: &AppState,
+    id: i32,
+    req: LinkOrderRegCodesReq,
+) -> Result<Vec<i32>, AppError> {
+    req.validate()?;
+    let order = orders::Entity::find_by_id(id).one(&state.db).await?;
+    order.ok_or_else(|| AppError::not_found("orders".to_string(), Some(id)))?;
+    let found = reg_codes::Entity::find()
+        .filter(reg_codes::Column::Id.is_in(req.reg_code_ids.clone()))
+        .all(&state.db)
+        .await?;
+    if let Some(missing) = req
+        .reg_code_ids
+        .iter()
+        .find(|rid| !found.iter().any(|c| c.id == **rid))
+    {
+        return Err(AppError::not_found("reg_codes".to_string(), Some(*missing)));
+    }
+    let mut linked = order_reg_code_ids(&state.db, id).await?;
+    for reg_code in found {
+        if linked.contains(&reg_code.id) {
+            continue;
+        }
+        order_reg_codes::ActiveModel {
+            order_id: Set(id),
+            reg_code_id: Set(reg_code.id),
+            ..Default::default()
+        }
+        .insert(&state.db)
+        .await?;
+        linked.push(reg_code.id);
+    }
+    Ok(linked)
+}
+
+/// 订单关联的注册码ID
+pub async fn order_reg_code_ids<C: sea_orm::ConnectionTrait>(
+    db: &C,
+    order_id: i32,
+) -> Result<Vec<i32>, AppError> {
+    let rows = order_reg_codes::Entity::find()
+        .filter(order_reg_codes::Column::OrderId.eq(order_id))
+        .order_by_asc(order_reg_codes::Column::Id)
+        .all(db)
+        .await?;
+    Ok(rows.into_iter().map(|r| r.reg_code_id).collect())
+}
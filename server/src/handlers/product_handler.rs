@@ -1,8 +1,10 @@
+use crate::handlers::app_features_handler;
 use crate::types::product_types::*;
 crate::import_crud_macro!();
-use entity::products;
+use entity::{order_products, order_reg_codes, products};
 use salvo::{prelude::*, oapi::extract::JsonBody};
 use salvo_oapi::extract::{PathParam};
+use sea_orm::{ConnectionTrait, QuerySelect};
 
 // Create Product
 #[handler]
@@ -19,6 +21,9 @@ pub async fn add_impl(
     state: &AppState,
     req: ProductCreatePayload,
 ) -> Result<products::Model, AppError> {
+    if let Some(entitlements) = &req.entitlements {
+        app_features_handler::check_entitlements(&state.db, req.app_id, entitlements).await?;
+    }
     let active_model = products::ActiveModel {
         name: Set(req.name),
         price: Set(req.price),
@@ -29,6 +34,7 @@ pub async fn add_impl(
         tags: Set(req.tags),
         status: Set(req.status),
         remark: Set(req.remark),
+        entitlements: Set(req.entitlements.map(|e| e.to_json())),
         ..Default::default()
     };
     let entity = active_model.insert(&state.db).await?;
@@ -54,6 +60,10 @@ pub async fn update_impl(
 ) -> Result<products::Model, AppError> {
     let product = products::Entity::find_by_id(id).one(&state.db).await?;
     let product = product.ok_or_else(|| AppError::not_found("products".to_string(), Some(id)))?;
+    if let Some(entitlements) = &req.entitlements {
+        let app_id = req.app_id.unwrap_or(product.app_id);
+        app_features_handler::check_entitlements(&state.db, app_id, entitlements).await?;
+    }
     let mut product: products::ActiveModel = product.into_active_model();
     crate::update_field_if_some!(product, name, req.name);
     crate::update_field_if_some!(product, price, req.price);
@@ -64,6 +74,7 @@ pub async fn update_impl(
     crate::update_field_if_some!(product, tags, req.tags, option);
     crate::update_field_if_some!(product, remark, req.remark, option);
     crate::update_field_if_some!(product, status, req.status);
+    crate::update_field_if_some!(product, entitlements, req.entitlements.map(|e| e.to_json()), option);
     let product = product.update(&state.db).await?;
     Ok(product)
 }
@@ -138,3 +149,32 @@ pub async fn get_by_id_impl(state: &AppState, id: i32) -> Result<products::Model
     let product = query.ok_or_else(|| AppError::not_found("products".to_string(), Some(id)))?;
     Ok(product)
 }
+
+/// 注册码通过订单购买时对应的订单商品，只返回属于该应用的商品
+pub async fn find_by_reg_code<C: ConnectionTrait>(
+    db: &C,
+    app_id: i32,
+    reg_code_id: i32,
+) -> Result<Vec<(order_products::Model, products::Model)>, AppError> {
+    let order_ids: Vec<i32> = order_reg_codes::Entity::find()
+        .select_only()
+        .column(order_reg_codes::Column::OrderId)
+        .filter(order_reg_codes::Column::RegCodeId.eq(reg_code_id))
+        .into_tuple()
+        .all(db)
+        .await?;
+    if order_ids.is_empty() {
+        return Ok(vec![]);
+    }
+    let items = order_products::Entity::find()
+        .find_also_related(products::Entity)
+        .filter(order_products::Column::OrderId.is_in(order_ids))
+        .filter(products::Column::AppId.eq(app_id))
+        .order_by_asc(order_products::Column::Id)
+        .all(db)
+        .await?;
+    Ok(items
+        .into_iter()
+        .filter_map(|(item, product)| product.map(|p| (item, p)))
+        .collect())
+}
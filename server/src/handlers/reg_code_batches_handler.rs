@@ -0,0 +1,422 @@
+use crate::types::common::Claims;
+use crate::types::reg_code_batches_types::*;
+use crate::types::reg_codes_types::{RegCodeExportRow, RegCodeStatus};
+use crate::utils::export::render_attachment;
+crate::import_crud_macro!();
+use entity::{apps, app_devices, device_renewals, reg_code_batches, reg_code_devices, reg_codes};
+use salvo::{oapi::extract::JsonBody, prelude::*};
+use salvo_oapi::extract::PathParam;
+use sea_orm::sea_query::Expr;
+use sea_orm::{ConnectionTrait, QuerySelect, QueryTrait, RelationTrait, TransactionTrait};
+use validator::Validate;
+
+// Create RegCode batch
+#[handler]
+pub async fn add(
+    depot: &mut Depot,
+    req: JsonBody<CreateRegCodeBatchReq>,
+) -> Result<ApiResponse<reg_code_batches::Model>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let created_by = depot.obtain::<Claims>().ok().map(|c| c.sub);
+    let batch = add_impl(state, req.into_inner(), created_by).await?;
+    Ok(ApiResponse::success(batch))
+}
+
+pub async fn add_impl(
+    state: &AppState,
+    req: CreateRegCodeBatchReq,
+    created_by: Option<i32>,
+) -> Result<reg_code_batches::Model, AppError> {
+    let app = apps::Entity::find_by_id(req.app_id).one(&state.db).await?;
+    app.ok_or_else(|| AppError::not_found("apps".to_string(), Some(req.app_id)))?;
+    insert_batch(&state.db, req, created_by, None).await
+}
+
+/// 新建批次，生成和导入注册码时在同一事务中调用
+pub async fn insert_batch<C: ConnectionTrait>(
+    db: &C,
+    req: CreateRegCodeBatchReq,
+    created_by: Option<i32>,
+    reseller_id: Option<i32>,
+) -> Result<reg_code_batches::Model, AppError> {
+    req.validate()?;
+    let now = Utc::now();
+    let batch = reg_code_batches::ActiveModel {
+        app_id: Set(req.app_id),
+        name: Set(req.name),
+        channel: Set(req.channel),
+        notes: Set(req.notes),
+        created_by: Set(created_by),
+        reseller_id: Set(reseller_id),
+        created_at: Set(now),
+        updated_at: Set(now),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+    Ok(batch)
+}
+
+/// 查找可加入注册码的批次，必须属于同一应用且未作废
+pub async fn find_open_batch<C: ConnectionTrait>(
+    db: &C,
+    app_id: i32,
+    id: i32,
+) -> Result<reg_code_batches::Model, AppError> {
+    let batch = reg_code_batches::Entity::find_by_id(id).one(db).await?;
+    let batch = batch.ok_or_else(|| AppError::not_found("reg_code_batches".to_string(), Some(id)))?;
+    if batch.app_id != app_id {
+        return Err(AppError::validation("batch belongs to another app"));
+    }
+    check_not_voided(&batch)?;
+    Ok(batch)
+}
+
+/// 在事务中锁住未作废的批次，作废和顺延串行执行，不会同时通过作废检查
+async fn lock_open_batch<C: ConnectionTrait>(
+    db: &C,
+    id: i32,
+) -> Result<reg_code_batches::Model, AppError> {
+    let batch = reg_code_batches::Entity::find_by_id(id)
+        .lock_exclusive()
+        .one(db)
+        .await?;
+    let batch = batch.ok_or_else(|| AppError::not_found("reg_code_batches".to_string(), Some(id)))?;
+    check_not_voided(&batch)?;
+    Ok(batch)
+}
+
+fn check_not_voided(batch: &reg_code_batches::Model) -> Result<(), AppError> {
+    if batch.voided_at.is_some() {
+        return Err(AppError::business_logic(
+            "BATCH_VOIDED",
+            format!("batch {} has been voided", batch.id),
+        ));
+    }
+    Ok(())
+}
+
+// Get RegCode batches list
+#[handler]
+pub async fn get_list(
+    depot: &mut Depot,
+    req: &mut Request,
+) -> Result<ApiResponse<PagingResponse<reg_code_batches::Model>>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let params = req.parse_queries::<SearchRegCodeBatchesParams>()?;
+    let list = get_list_impl(state, params).await?;
+    Ok(ApiResponse::success(list))
+}
+
+pub async fn get_list_impl(
+    state: &AppState,
+    params: SearchRegCodeBatchesParams,
+) -> Result<PagingResponse<reg_code_batches::Model>, AppError> {
+    let page = params.pagination.page.unwrap_or(1);
+    let page_size = params.pagination.page_size.unwrap_or(20);
+    let mut query = reg_code_batches::Entity::find()
+        .order_by_desc(reg_code_batches::Column::CreatedAt)
+        .order_by_desc(reg_code_batches::Column::Id);
+    crate::filter_if_some!(query, reg_code_batches::Column::AppId, params.app_id, eq);
+    crate::filter_if_some!(query, reg_code_batches::Column::Channel, params.channel, eq);
+    crate::filter_if_some!(query, reg_code_batches::Column::Name, params.name, contains);
+    crate::filter_if_some!(query, reg_code_batches::Column::ResellerId, params.reseller_id, eq);
+    match params.voided {
+        Some(true) => query = query.filter(reg_code_batches::Column::VoidedAt.is_not_null()),
+        Some(false) => query = query.filter(reg_code_batches::Column::VoidedAt.is_null()),
+        None => {}
+    }
+    let paginator = query.paginate(&state.db, page_size);
+    let total = paginator.num_items().await?;
+    let list = paginator.fetch_page(page - 1).await?;
+    Ok(PagingResponse { list, total, page })
+}
+
+// Get RegCode batch by ID
+#[handler]
+pub async fn get_by_id(
+    depot: &mut Depot,
+    id: PathParam<i32>,
+) -> Result<ApiResponse<reg_code_batches::Model>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let batch = get_by_id_impl(state, id.into_inner()).await?;
+    Ok(ApiResponse::success(batch))
+}
+
+pub async fn get_by_id_impl(state: &AppState, id: i32) -> Result<reg_code_batches::Model, AppError> {
+    let batch = reg_code_batches::Entity::find_by_id(id).one(&state.db).await?;
+    batch.ok_or_else(|| AppError::not_found("reg_code_batches".to_string(), Some(id)))
+}
+
+// Update RegCode batch
+#[handler]
+pub async fn update(
+    depot: &mut Depot,
+    id: PathParam<i32>,
+    req: JsonBody<UpdateRegCodeBatchReq>,
+) -> Result<ApiResponse<reg_code_batches::Model>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let batch = update_impl(state, id.into_inner(), req.into_inner()).await?;
+    Ok(ApiResponse::success(batch))
+}
+
+pub async fn update_impl(
+    state: &AppState,
+    id: i32,
+    req: UpdateRegCodeBatchReq,
+) -> Result<reg_code_batches::Model, AppError> {
+    req.validate()?;
+    let batch = get_by_id_impl(state, id).await?;
+    let mut active = batch.into_active_model();
+    crate::update_field_if_some!(active, name, req.name);
+    crate::update_field_if_some!(active, channel, req.channel, option);
+    crate::update_field_if_some!(active, notes, req.notes, option);
+    active.updated_at = Set(Utc::now());
+    let batch = active.update(&state.db).await?;
+    Ok(batch)
+}
+
+// Void a batch, revoking all its codes
+#[handler]
+pub async fn void(
+    depot: &mut Depot,
+    id: PathParam<i32>,
+    req: JsonBody<VoidRegCodeBatchReq>,
+) -> Result<ApiResponse<RegCodeBatchActionResp>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let resp = void_impl(state, id.into_inner(), req.into_inner()).await?;
+    Ok(ApiResponse::success(resp))
+}
+
+pub async fn void_impl(
+    state: &AppState,
+    id: i32,
+    req: VoidRegCodeBatchReq,
+) -> Result<RegCodeBatchActionResp, AppError> {
+    if req.reason.trim().is_empty() {
+        return Err(AppError::validation("reason is required"));
+    }
+    let now = Utc::now();
+    let txn = state.db.begin().await?;
+    let batch = lock_open_batch(&txn, id).await?;
+    let mut active = batch.into_active_model();
+    active.voided_at = Set(Some(now));
+    active.void_reason = Set(Some(req.reason.clone()));
+    active.updated_at = Set(now);
+    let batch = active.update(&txn).await?;
+    // 吊销后进入吊销列表，客户端下次同步时失效
+    let codes = reg_codes::Entity::update_many()
+        .col_expr(
+            reg_codes::Column::Status,
+            Expr::value(i16::from(RegCodeStatus::Revoked)),
+        )
+        .col_expr(reg_codes::Column::RevokedReason, Expr::value(req.reason))
+        .col_expr(reg_codes::Column::RevokedAt, Expr::value(now))
+        .col_expr(reg_codes::Column::RestoredAt, Expr::value(Option::<chrono::DateTime<Utc>>::None))
+        .col_expr(reg_codes::Column::UpdatedAt, Expr::value(now))
+        .filter(reg_codes::Column::BatchId.eq(id))
+        .filter(reg_codes::Column::Status.ne(i16::from(RegCodeStatus::Revoked)))
+        .exec(&txn)
+        .await?;
+    let devices = count_bound_devices(&txn, id).await?;
+    txn.commit().await?;
+    Ok(RegCodeBatchActionResp {
+        batch,
+        codes: codes.rows_affected,
+        devices,
+    })
+}
+
+// Extend expiry of all codes in a batch
+#[handler]
+pub async fn extend(
+    depot: &mut Depot,
+    id: PathParam<i32>,
+    req: JsonBody<ExtendRegCodeBatchReq>,
+) -> Result<ApiResponse<RegCodeBatchActionResp>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let resp = extend_impl(state, id.into_inner(), req.into_inner()).await?;
+    Ok(ApiResponse::success(resp))
+}
+
+/// 延长批次内未吊销注册码的有效期：
+/// 已有过期时间的顺延，未兑换且未设过期时间的增加 valid_days，
+/// 已通过批次内注册码续期的设备同步顺延（每台设备只顺延一次）
+pub async fn extend_impl(
+    state: &AppState,
+    id: i32,
+    req: ExtendRegCodeBatchReq,
+) -> Result<RegCodeBatchActionResp, AppError> {
+    if req.days <= 0 {
+        return Err(AppError::validation("days must be positive"));
+    }
+    let now = Utc::now();
+    let interval = format!("INTERVAL '{} days'", req.days);
+    let txn = state.db.begin().await?;
+    let batch = lock_open_batch(&txn, id).await?;
+    let dated = reg_codes::Entity::update_many()
+        .col_expr(
+            reg_codes::Column::ExpireTime,
+            Expr::cust(format!("expire_time + {}", interval)),
+        )
+        .col_expr(reg_codes::Column::UpdatedAt, Expr::value(now))
+        .filter(reg_codes::Column::BatchId.eq(id))
+        .filter(reg_codes::Column::Status.ne(i16::from(RegCodeStatus::Revoked)))
+        .filter(reg_codes::Column::ExpireTime.is_not_null())
+        .exec(&txn)
+        .await?;
+    let undated = reg_codes::Entity::update_many()
+        .col_expr(
+            reg_codes::Column::ValidDays,
+            Expr::col(reg_codes::Column::ValidDays).add(req.days),
+        )
+        .col_expr(reg_codes::Column::UpdatedAt, Expr::value(now))
+        .filter(reg_codes::Column::BatchId.eq(id))
+        .filter(reg_codes::Column::Status.eq(i16::from(RegCodeStatus::Unused)))
+        .filter(reg_codes::Column::ExpireTime.is_null())
+        .exec(&txn)
+        .await?;
+    // 顺延后重新有效的过期注册码恢复为未使用或已使用
+    reg_codes::Entity::update_many()
+        .col_expr(
+            reg_codes::Column::Status,
+            Expr::cust(format!(
+                "CASE WHEN binding_time IS NULL THEN {} ELSE {} END",
+                i16::from(RegCodeStatus::Unused),
+                i16::from(RegCodeStatus::Used)
+            )),
+        )
+        .filter(reg_codes::Column::BatchId.eq(id))
+        .filter(reg_codes::Column::Status.eq(i16::from(RegCodeStatus::Expired)))
+        .filter(reg_codes::Column::ExpireTime.gte(now))
+        .exec(&txn)
+        .await?;
+    let renewed = device_renewals::Entity::find()
+        .select_only()
+        .column(device_renewals::Column::DeviceId)
+        .join(
+            sea_orm::JoinType::InnerJoin,
+            device_renewals::Relation::RegCodes.def(),
+        )
+        .filter(reg_codes::Column::BatchId.eq(id))
+        .filter(reg_codes::Column::Status.ne(i16::from(RegCodeStatus::Revoked)))
+        .into_query();
+    let devices = app_devices::Entity::update_many()
+        .col_expr(
+            app_devices::Column::ExpireTime,
+            Expr::cust(format!("expire_time + {}", interval)),
+        )
+        .col_expr(
+            app_devices::Column::Expired,
+            Expr::cust(format!("expire_time + {} < CURRENT_TIMESTAMP", interval)),
+        )
+        .filter(app_devices::Column::ExpireTime.is_not_null())
+        .filter(app_devices::Column::Id.in_subquery(renewed))
+        .exec(&txn)
+        .await?;
+    let mut active = batch.into_active_model();
+    active.updated_at = Set(now);
+    let batch = active.update(&txn).await?;
+    txn.commit().await?;
+    Ok(RegCodeBatchActionResp {
+        batch,
+        codes: dated.rows_affected + undated.rows_affected,
+        devices: devices.rows_affected,
+    })
+}
+
+// Export codes of a batch
+#[handler]
+pub async fn export(
+    depot: &mut Depot,
+    id: PathParam<i32>,
+    req: &mut Request,
+    res: &mut Response,
+) -> Result<(), AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let params = req.parse_queries::<ExportRegCodeBatchParams>()?;
+    let id = id.into_inner();
+    let rows = export_impl(state, id).await?;
+    let file_name = format!("reg_code_batch_{}_{}", id, Utc::now().format("%Y%m%d%H%M%S"));
+    render_attachment(res, params.format, &file_name, &rows)
+}
+
+pub async fn export_impl(state: &AppState, id: i32) -> Result<Vec<RegCodeExportRow>, AppError> {
+    get_by_id_impl(state, id).await?;
+    let codes = reg_codes::Entity::find()
+        .filter(reg_codes::Column::BatchId.eq(id))
+        .order_by_asc(reg_codes::Column::Id)
+        .all(&state.db)
+        .await?;
+    Ok(codes.into_iter().map(RegCodeExportRow::from).collect())
+}
+
+// Get redemption statistics of a batch
+#[handler]
+pub async fn get_stats(
+    depot: &mut Depot,
+    id: PathParam<i32>,
+) -> Result<ApiResponse<RegCodeBatchStats>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let stats = get_stats_impl(state, id.into_inner()).await?;
+    Ok(ApiResponse::success(stats))
+}
+
+pub async fn get_stats_impl(state: &AppState, id: i32) -> Result<RegCodeBatchStats, AppError> {
+    get_by_id_impl(state, id).await?;
+    let status_count = |status: RegCodeStatus| {
+        Expr::cust(format!(
+            "COUNT(*) FILTER (WHERE status = {})",
+            i16::from(status)
+        ))
+    };
+    let counts = reg_codes::Entity::find()
+        .select_only()
+        .column_as(Expr::cust("COUNT(*)"), "total")
+        .column_as(status_count(RegCodeStatus::Unused), "unused")
+        .column_as(status_count(RegCodeStatus::Used), "used")
+        .column_as(status_count(RegCodeStatus::Expired), "expired")
+        .column_as(status_count(RegCodeStatus::Revoked), "revoked")
+        .column_as(
+            Expr::cust("COUNT(*) FILTER (WHERE binding_time IS NOT NULL)"),
+            "redeemed",
+        )
+        .column_as(Expr::cust("COALESCE(SUM(use_count), 0)::BIGINT"), "use_count")
+        .filter(reg_codes::Column::BatchId.eq(id))
+        .into_tuple::<(i64, i64, i64, i64, i64, i64, i64)>()
+        .one(&state.db)
+        .await?
+        .unwrap_or_default();
+    let (total, unused, used, expired, revoked, redeemed, use_count) = counts;
+    let devices = count_bound_devices(&state.db, id).await?;
+    let redeem_rate = if total > 0 {
+        (redeemed as f64 * 10000.0 / total as f64).round() / 100.0
+    } else {
+        0.0
+    };
+    Ok(RegCodeBatchStats {
+        batch_id: id,
+        total,
+        unused,
+        used,
+        expired,
+        revoked,
+        redeemed,
+        redeem_rate,
+        use_count,
+        devices: devices as i64,
+    })
+}
+
+/// 批次内注册码当前绑定的设备数
+async fn count_bound_devices<C: ConnectionTrait>(db: &C, id: i32) -> Result<u64, AppError> {
+    let count = reg_code_devices::Entity::find()
+        .join(
+            sea_orm::JoinType::InnerJoin,
+            reg_code_devices::Relation::RegCodes.def(),
+        )
+        .filter(reg_codes::Column::BatchId.eq(id))
+        .count(db)
+        .await?;
+    Ok(count)
+}
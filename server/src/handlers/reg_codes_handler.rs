@@ -1,8 +1,39 @@
+use crate::handlers::{
+    app_configs_handler, app_features_handler, app_handler, product_handler,
+    reg_code_batches_handler, reseller_handler, transfer_handler, usage_handler,
+};
+use crate::services::{trial_guard, validation_log};
+use crate::types::app_features_types::Entitlements;
+use crate::types::common::Claims;
+use crate::types::reg_code_batches_types::CreateRegCodeBatchReq;
 use crate::types::reg_codes_types::*;
+use crate::types::reseller_types::{QuotaChange, QuotaKind};
+use crate::types::validation_events_types::{AppRef, ValidationEvent};
+use crate::utils::client::ClientInfo;
+use crate::utils::{export, license, signature};
 crate::import_crud_macro!();
-use entity::{app_devices, apps, reg_codes};
+use entity::{
+    app_devices, apps, device_bans, device_renewals, reg_code_batches, reg_code_devices, reg_codes,
+    resellers,
+};
 use salvo::{oapi::extract::JsonBody, prelude::*};
 use salvo_oapi::extract::PathParam;
+use sea_orm::sea_query::{Expr, Func, SimpleExpr};
+use sea_orm::{Condition, ConnectionTrait, QuerySelect, QueryTrait, TransactionTrait};
+use std::collections::{HashMap, HashSet};
+use sha2::{Digest, Sha256};
+use validator::Validate;
+
+// 批量生成时每次插入的行数
+const BATCH_INSERT_SIZE: usize = 1000;
+// 批量生成时与已有注册码冲突后的最大重试轮数
+const MAX_GENERATE_ROUNDS: usize = 5;
+// 单次生成数量不得超过格式可组合总数的 1/CODE_SPACE_FRACTION
+const CODE_SPACE_FRACTION: u128 = 10;
+// 每轮随机生成的尝试次数上限为缺口数量的倍数
+const GENERATE_ATTEMPTS_FACTOR: usize = 10;
+// v2 校验允许的客户端时钟偏差（秒）
+const VALIDATE_MAX_CLOCK_SKEW_SECS: i64 = 300;
 
 // Create RegCode
 #[handler]
@@ -17,6 +48,19 @@ pub async fn add(
 }
 
 pub async fn add_impl(state: &AppState, req: CreateRegCodeReq) -> Result<RegCodeInfo, AppError> {
+    req.validate()?;
+    check_total_count(req.code_type, req.total_count)?;
+    let app = apps::Entity::find_by_id(req.app_id)
+        .one(&state.db)
+        .await?
+        .ok_or_else(|| AppError::not_found("apps".to_string(), Some(req.app_id)))?;
+    check_strict_format(&app, &req.code)?;
+    if let Some(entitlements) = &req.entitlements {
+        app_features_handler::check_entitlements(&state.db, req.app_id, entitlements).await?;
+    }
+    if let Some(batch_id) = req.batch_id {
+        reg_code_batches_handler::find_open_batch(&state.db, req.app_id, batch_id).await?;
+    }
     let active_model = reg_codes::ActiveModel {
         code: Set(req.code),
         app_id: Set(req.app_id),
@@ -27,26 +71,476 @@ pub async fn add_impl(state: &AppState, req: CreateRegCodeReq) -> Result<RegCode
         expire_time: Set(req.expire_time),
         total_count: Set(req.total_count),
         use_count: Set(0),
+        entitlements: Set(req.entitlements.map(|e| e.to_json())),
+        batch_id: Set(req.batch_id),
         created_at: Set(Utc::now()),
         updated_at: Set(Utc::now()),
         ..Default::default()
     };
     let entity = active_model.insert(&state.db).await?;
+    get_by_id_impl(state, entity.id).await
+}
 
-    // Fetch with app information for response
-    let result = reg_codes::Entity::find_by_id(entity.id)
-        .find_also_related(apps::Entity)
-        .find_also_related(app_devices::Entity)
+/// 开启严格格式的应用只接受带正确校验段的注册码
+pub(crate) fn check_strict_format(app: &apps::Model, code: &str) -> Result<(), reg_code::Error> {
+    if app.strict_code_format {
+        reg_code::verify(code, Some(&app.app_id))?;
+    }
+    Ok(())
+}
+
+/// 计次码必须指定总次数，否则创建后即为用完状态
+fn check_total_count(code_type: CodeType, total_count: Option<i32>) -> Result<(), AppError> {
+    if code_type == CodeType::Count && total_count.is_none() {
+        return Err(AppError::validation("total_count is required for count codes"));
+    }
+    Ok(())
+}
+
+// Batch create RegCodes
+#[handler]
+pub async fn batch_add(
+    depot: &mut Depot,
+    req: &mut Request,
+    res: &mut Response,
+) -> Result<(), AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let params = req.parse_queries::<BatchCreateRegCodesParams>()?;
+    let json = req.parse_json::<BatchCreateRegCodesReq>().await?;
+    let created_by = depot.obtain::<Claims>().ok().map(|c| c.sub);
+    let rows = batch_add_impl(state, json, created_by, None).await?;
+    match params.export {
+        Some(format) => {
+            let file_name = format!("reg_codes_{}", Utc::now().format("%Y%m%d%H%M%S"));
+            export::render_attachment(res, format, &file_name, &rows)?;
+        }
+        None => res.render(Json(ApiResponse::success(BatchCreateRegCodesResp {
+            count: rows.len(),
+            codes: rows,
+        }))),
+    }
+    Ok(())
+}
+
+/// 批量生成注册码，生成的注册码归入指定批次，未指定时新建批次
+/// 代理商生成时在同一事务中扣减额度
+pub async fn batch_add_impl(
+    state: &AppState,
+    req: BatchCreateRegCodesReq,
+    created_by: Option<i32>,
+    reseller: Option<&resellers::Model>,
+) -> Result<Vec<RegCodeExportRow>, AppError> {
+    req.validate()?;
+    check_total_count(req.code_type, req.total_count)?;
+    let format = &req.format;
+    format.check()?;
+    if req.count as u128 > format.capacity() / CODE_SPACE_FRACTION {
+        return Err(AppError::business_logic(
+            "CODE_SPACE_EXHAUSTED",
+            "count is too large for the code format, use a longer format",
+        ));
+    }
+    let app = apps::Entity::find_by_id(req.app_id)
         .one(&state.db)
+        .await?
+        .ok_or_else(|| AppError::not_found("apps".to_string(), Some(req.app_id)))?;
+    if let Some(entitlements) = &req.entitlements {
+        app_features_handler::check_entitlements(&state.db, app.id, entitlements).await?;
+    }
+    let entitlements = req.entitlements.as_ref().map(Entitlements::to_json);
+    if let Some(batch_id) = req.batch_id {
+        reg_code_batches_handler::find_open_batch(&state.db, app.id, batch_id).await?;
+    }
+
+    // 生成不重复的注册码，与库中已有注册码冲突的重新生成
+    // 校验段以应用的 app_id 作为应用标识，客户端可离线预校验
+    let count = req.count as usize;
+    let mut codes: HashSet<String> = HashSet::with_capacity(count);
+    for _ in 0..MAX_GENERATE_ROUNDS {
+        let mut fresh: HashSet<String> = HashSet::new();
+        let mut attempts = (count - codes.len()) * GENERATE_ATTEMPTS_FACTOR;
+        while codes.len() + fresh.len() < count && attempts > 0 {
+            attempts -= 1;
+            let code = format.generate(Some(&app.app_id));
+            if !codes.contains(&code) {
+                fresh.insert(code);
+            }
+        }
+        let existing: HashSet<String> = reg_codes::Entity::find()
+            .select_only()
+            .column(reg_codes::Column::Code)
+            .filter(reg_codes::Column::Code.is_in(fresh.iter().cloned()))
+            .into_tuple::<String>()
+            .all(&state.db)
+            .await?
+            .into_iter()
+            .collect();
+        codes.extend(fresh.into_iter().filter(|c| !existing.contains(c)));
+        if codes.len() >= count {
+            break;
+        }
+    }
+    if codes.len() < count {
+        return Err(AppError::business_logic(
+            "CODE_SPACE_EXHAUSTED",
+            "unable to generate enough unique codes, use a longer format",
+        ));
+    }
+
+    let now = Utc::now();
+    let codes: Vec<String> = codes.into_iter().collect();
+    let txn = state.db.begin().await?;
+    let batch_id = match req.batch_id {
+        Some(batch_id) => batch_id,
+        None => {
+            let batch = CreateRegCodeBatchReq {
+                app_id: app.id,
+                name: req
+                    .batch_name
+                    .clone()
+                    .unwrap_or_else(|| format!("batch {}", now.format("%Y%m%d%H%M%S"))),
+                channel: req.channel.clone(),
+                notes: req.notes.clone(),
+            };
+            let reseller_id = reseller.map(|r| r.id);
+            reg_code_batches_handler::insert_batch(&txn, batch, created_by, reseller_id)
+                .await?
+                .id
+        }
+    };
+    if let Some(reseller) = reseller {
+        let change = QuotaChange {
+            change: -(count as i32),
+            kind: QuotaKind::Generate,
+            batch_id: Some(batch_id),
+            remark: None,
+            created_by,
+        };
+        reseller_handler::change_quota(&txn, reseller.id, change).await?;
+    }
+    for chunk in codes.chunks(BATCH_INSERT_SIZE) {
+        let models = chunk.iter().map(|code| reg_codes::ActiveModel {
+            code: Set(code.clone()),
+            app_id: Set(req.app_id),
+            valid_days: Set(req.valid_days),
+            max_devices: Set(req.max_devices),
+            status: Set(i16::from(RegCodeStatus::Unused)),
+            code_type: Set(i16::from(req.code_type)),
+            expire_time: Set(req.expire_time),
+            total_count: Set(req.total_count),
+            use_count: Set(0),
+            entitlements: Set(entitlements.clone()),
+            batch_id: Set(Some(batch_id)),
+            created_at: Set(now),
+            updated_at: Set(now),
+            ..Default::default()
+        });
+        reg_codes::Entity::insert_many(models).exec(&txn).await?;
+    }
+    let created = reg_codes::Entity::find()
+        .filter(reg_codes::Column::Code.is_in(codes))
+        .order_by_asc(reg_codes::Column::Id)
+        .all(&txn)
         .await?;
+    txn.commit().await?;
+    Ok(created.into_iter().map(RegCodeExportRow::from).collect())
+}
 
-    match result {
-        Some((reg_code, app, device)) => Ok(RegCodeInfo::try_from((reg_code, app, device))?),
-        None => Err(AppError::not_found(
-            "reg_codes".to_string(),
-            Some(entity.id),
-        )),
+// Import RegCodes from CSV / JSON
+#[handler]
+pub async fn import(
+    depot: &mut Depot,
+    req: &mut Request,
+) -> Result<ApiResponse<ImportRegCodesResp>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let params = req.parse_queries::<ImportRegCodesParams>()?;
+    let body = req.payload().await?.to_vec();
+    let created_by = depot.obtain::<Claims>().ok().map(|c| c.sub);
+    let resp = import_impl(state, params, &body, created_by).await?;
+    Ok(ApiResponse::success(resp))
+}
+
+/// 从其它授权系统迁移注册码和已绑定的设备
+/// 先逐行校验，有任一行出错或 dry_run 时只返回校验结果，否则在一个事务中写入
+/// 写入的注册码归入指定批次，未指定时新建一个导入批次
+pub async fn import_impl(
+    state: &AppState,
+    params: ImportRegCodesParams,
+    body: &[u8],
+    created_by: Option<i32>,
+) -> Result<ImportRegCodesResp, AppError> {
+    let app = apps::Entity::find_by_id(params.app_id)
+        .one(&state.db)
+        .await?
+        .ok_or_else(|| AppError::not_found("apps".to_string(), Some(params.app_id)))?;
+    if let Some(batch_id) = params.batch_id {
+        reg_code_batches_handler::find_open_batch(&state.db, app.id, batch_id).await?;
+    }
+    let rows = export::parse_rows::<ImportRegCodeRow>(params.format, body)?;
+    let mut resp = ImportRegCodesResp {
+        dry_run: params.dry_run.unwrap_or(false),
+        total: rows.len(),
+        ..Default::default()
+    };
+    let mut valid = Vec::with_capacity(rows.len());
+    let mut seen = HashSet::new();
+    for (i, row) in rows.into_iter().enumerate() {
+        let row_no = i + 1;
+        let checked = row.and_then(|row| {
+            check_import_row(&row)?;
+            check_strict_format(&app, &row.code).map_err(|e| e.to_string())?;
+            Ok(row)
+        });
+        match checked {
+            Ok(row) if !seen.insert(row.code.clone()) => resp.errors.push(ImportRowError {
+                row: row_no,
+                code: Some(row.code),
+                message: "duplicate code in file".to_string(),
+            }),
+            Ok(row) => valid.push((row_no, row)),
+            Err(message) => resp.errors.push(ImportRowError {
+                row: row_no,
+                code: None,
+                message,
+            }),
+        }
+    }
+
+    let existing: HashMap<String, reg_codes::Model> = reg_codes::Entity::find()
+        .filter(reg_codes::Column::Code.is_in(valid.iter().map(|(_, row)| row.code.clone())))
+        .all(&state.db)
+        .await?
+        .into_iter()
+        .map(|c| (c.code.clone(), c))
+        .collect();
+    let mut bound = load_devices(&state.db, existing.values().map(|c| c.id).collect()).await?;
+    let mut plan = Vec::with_capacity(valid.len());
+    for (row_no, row) in valid {
+        let exist = existing.get(&row.code);
+        let error = match exist {
+            Some(exist) if exist.app_id != app.id => Some("code already exists in another app"),
+            Some(_) if params.on_duplicate == DuplicateMode::Fail => Some("code already exists"),
+            _ => None,
+        };
+        if let Some(message) = error {
+            resp.errors.push(ImportRowError {
+                row: row_no,
+                code: Some(row.code),
+                message: message.to_string(),
+            });
+            continue;
+        }
+        if exist.is_some() && params.on_duplicate == DuplicateMode::Skip {
+            resp.skipped += 1;
+            continue;
+        }
+        if let Err(message) = resolve_import_counts(&row, exist) {
+            resp.errors.push(ImportRowError {
+                row: row_no,
+                code: Some(row.code),
+                message,
+            });
+            continue;
+        }
+        // 覆盖时保留已绑定的设备，合并后不能超过设备上限
+        let bound_ids: Vec<String> = exist
+            .and_then(|c| bound.remove(&c.id))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|d| d.device_id)
+            .collect();
+        let new_devices = row.devices.iter().filter(|d| !bound_ids.contains(d)).count();
+        let max_devices = row
+            .max_devices
+            .or(exist.map(|c| c.max_devices))
+            .unwrap_or(1);
+        if (bound_ids.len() + new_devices) as i32 > max_devices {
+            resp.errors.push(ImportRowError {
+                row: row_no,
+                code: Some(row.code),
+                message: format!("devices exceed max_devices {}", max_devices),
+            });
+            continue;
+        }
+        resp.devices_bound += new_devices;
+        match exist {
+            Some(_) => resp.updated += 1,
+            None => resp.created += 1,
+        }
+        plan.push((row, exist.cloned()));
+    }
+    resp.errors.sort_by_key(|e| e.row);
+    if resp.dry_run || !resp.errors.is_empty() {
+        return Ok(resp);
+    }
+
+    let txn = state.db.begin().await?;
+    let batch_id = match params.batch_id {
+        Some(batch_id) => Some(batch_id),
+        None if plan.is_empty() => None,
+        None => {
+            let batch = CreateRegCodeBatchReq {
+                app_id: app.id,
+                name: format!("import {}", Utc::now().format("%Y%m%d%H%M%S")),
+                ..Default::default()
+            };
+            let batch = reg_code_batches_handler::insert_batch(&txn, batch, created_by, None).await?;
+            Some(batch.id)
+        }
+    };
+    for (row, exist) in plan {
+        apply_import_row(&txn, &app, batch_id, row, exist).await?;
+    }
+    txn.commit().await?;
+    resp.applied = true;
+    Ok(resp)
+}
+
+/// 单行数据本身的校验，不涉及数据库
+fn check_import_row(row: &ImportRegCodeRow) -> Result<(), String> {
+    if row.code.is_empty() {
+        return Err("code is required".to_string());
+    }
+    if row.valid_days.is_some_and(|v| v < 0) {
+        return Err("valid_days must not be negative".to_string());
     }
+    if row.max_devices.is_some_and(|v| v < 1) {
+        return Err("max_devices must be at least 1".to_string());
+    }
+    if row.total_count.is_some_and(|v| v < 1) {
+        return Err("total_count must be at least 1".to_string());
+    }
+    Ok(())
+}
+
+/// 合并已有注册码后的类型、总次数和已用次数
+fn resolve_import_counts(
+    row: &ImportRegCodeRow,
+    exist: Option<&reg_codes::Model>,
+) -> Result<(CodeType, Option<i32>, i32), String> {
+    let exist_type = exist.map(|c| CodeType::from(c.code_type));
+    let code_type = row.code_type.or(exist_type).unwrap_or_default();
+    if code_type != CodeType::Count {
+        if row.total_count.is_some() || row.remaining_count.is_some() {
+            return Err("total_count and remaining_count only apply to count codes".to_string());
+        }
+        return Ok((code_type, None, 0));
+    }
+    // 原本就是计次码时沿用原有的总次数和已用次数
+    let exist = exist.filter(|_| exist_type == Some(CodeType::Count));
+    let total = row
+        .total_count
+        .or(exist.and_then(|c| c.total_count))
+        .ok_or("total_count is required for count codes")?;
+    let use_count = match row.remaining_count {
+        Some(remaining) if remaining < 0 || remaining > total => {
+            return Err(format!("remaining_count must be between 0 and {}", total));
+        }
+        Some(remaining) => total - remaining,
+        None => exist.map_or(0, |c| c.use_count),
+    };
+    if use_count > total {
+        return Err(format!("total_count is less than used count {}", use_count));
+    }
+    Ok((code_type, Some(total), use_count))
+}
+
+/// 写入一行导入数据，覆盖已有注册码时未提供的字段保留原值
+/// 状态由原状态和本行的变化决定：吊销以 revoked 为准，有设备、使用记录或绑定时间时为已使用
+async fn apply_import_row<C: ConnectionTrait>(
+    db: &C,
+    app: &apps::Model,
+    batch_id: Option<i32>,
+    row: ImportRegCodeRow,
+    exist: Option<reg_codes::Model>,
+) -> Result<(), AppError> {
+    let now = Utc::now();
+    let (code_type, total_count, use_count) =
+        resolve_import_counts(&row, exist.as_ref()).map_err(AppError::validation)?;
+    let bound: HashSet<i32> = match &exist {
+        Some(exist) => reg_code_devices::Entity::find()
+            .filter(reg_code_devices::Column::RegCodeId.eq(exist.id))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|b| b.device_id)
+            .collect(),
+        None => HashSet::new(),
+    };
+    let exist_status = exist.as_ref().map(|c| RegCodeStatus::from(c.status));
+    let was_revoked = exist_status == Some(RegCodeStatus::Revoked);
+    let binding_time = row
+        .binding_time
+        .or(exist.as_ref().and_then(|c| c.binding_time));
+    let used = !bound.is_empty() || !row.devices.is_empty() || use_count > 0 || binding_time.is_some();
+    let status = match exist_status {
+        _ if row.revoked.unwrap_or(was_revoked) => RegCodeStatus::Revoked,
+        // 过期状态由定时任务维护，导入不改变
+        Some(RegCodeStatus::Expired) => RegCodeStatus::Expired,
+        _ if used => RegCodeStatus::Used,
+        _ => RegCodeStatus::Unused,
+    };
+    let is_new = exist.is_none();
+    let mut active = match exist {
+        Some(exist) => exist.into_active_model(),
+        None => reg_codes::ActiveModel {
+            code: Set(row.code.clone()),
+            app_id: Set(app.id),
+            valid_days: Set(0),
+            max_devices: Set(1),
+            created_at: Set(now),
+            ..Default::default()
+        },
+    };
+    active.code_type = Set(i16::from(code_type));
+    crate::update_field_if_some!(active, valid_days, row.valid_days);
+    crate::update_field_if_some!(active, max_devices, row.max_devices);
+    crate::update_field_if_some!(active, expire_time, row.expire_time, option);
+    active.total_count = Set(total_count);
+    active.use_count = Set(use_count);
+    active.status = Set(i16::from(status));
+    active.binding_time = Set(binding_time.or(used.then_some(now)));
+    crate::update_field_if_some!(active, batch_id, batch_id, option);
+    if status == RegCodeStatus::Revoked && !was_revoked {
+        active.revoked_reason = Set(Some("imported".to_string()));
+        active.revoked_at = Set(Some(now));
+        active.restored_at = Set(None);
+    } else if status != RegCodeStatus::Revoked && was_revoked {
+        active.revoked_reason = Set(None);
+        active.revoked_at = Set(None);
+        active.restored_at = Set(Some(now));
+    }
+    active.updated_at = Set(now);
+    let reg_code = if is_new {
+        active.insert(db).await?
+    } else {
+        active.update(db).await?
+    };
+
+    for device_id in row.devices {
+        let device = find_or_create_device(db, app, &device_id).await?;
+        // 时长码的设备有效期取注册码过期时间，已有更晚的有效期时保留
+        if code_type == CodeType::Time
+            && let Some(expire) = row.expire_time
+            && device.expire_time.is_none_or(|t| t < expire)
+        {
+            let mut active = device.clone().into_active_model();
+            active.expire_time = Set(Some(expire));
+            active.update(db).await?;
+        }
+        if bound.contains(&device.id) {
+            continue;
+        }
+        reg_code_devices::ActiveModel {
+            reg_code_id: Set(reg_code.id),
+            device_id: Set(device.id),
+            bind_time: Set(row.binding_time.unwrap_or(now)),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+    }
+    Ok(())
 }
 
 // Update RegCode
@@ -66,36 +560,60 @@ pub async fn update_impl(
     id: i32,
     req: UpdateRegCodeReq,
 ) -> Result<RegCodeInfo, AppError> {
+    req.validate()?;
     let reg_code = reg_codes::Entity::find_by_id(id).one(&state.db).await?;
     let reg_code =
         reg_code.ok_or_else(|| AppError::not_found("reg_codes".to_string(), Some(id)))?;
+    let was_revoked = RegCodeStatus::from(reg_code.status) == RegCodeStatus::Revoked;
+    // 吊销必须填写原因，只能走吊销接口
+    if !was_revoked && req.status.map(RegCodeStatus::from) == Some(RegCodeStatus::Revoked) {
+        return Err(AppError::validation(
+            "use POST /api/admin/reg_codes/{id}/revoke to revoke a code",
+        ));
+    }
+    check_total_count(
+        req.code_type.unwrap_or(CodeType::from(reg_code.code_type)),
+        req.total_count.or(reg_code.total_count),
+    )?;
+    let code = req.code.as_deref().map(str::trim).map(str::to_string);
+    // 改注册码或换应用后，注册码仍要符合目标应用的严格格式
+    if code.is_some() || req.app_id.is_some() {
+        let app_id = req.app_id.unwrap_or(reg_code.app_id);
+        let app = apps::Entity::find_by_id(app_id)
+            .one(&state.db)
+            .await?
+            .ok_or_else(|| AppError::not_found("apps".to_string(), Some(app_id)))?;
+        check_strict_format(&app, code.as_deref().unwrap_or(&reg_code.code))?;
+    }
+    if let Some(entitlements) = &req.entitlements {
+        let app_id = req.app_id.unwrap_or(reg_code.app_id);
+        app_features_handler::check_entitlements(&state.db, app_id, entitlements).await?;
+    }
 
+    let now = Utc::now();
     let mut reg_code: reg_codes::ActiveModel = reg_code.into_active_model();
-    crate::update_field_if_some!(reg_code, code, req.code);
+    crate::update_field_if_some!(reg_code, code, code);
     crate::update_field_if_some!(reg_code, app_id, req.app_id);
     crate::update_field_if_some!(reg_code, valid_days, req.valid_days);
     crate::update_field_if_some!(reg_code, max_devices, req.max_devices);
     crate::update_field_if_some!(reg_code, status, req.status);
     crate::update_field_if_some!(reg_code, code_type, req.code_type.map(|v| i16::from(v)));
     crate::update_field_if_some!(reg_code, total_count, req.total_count, option);
-    reg_code.updated_at = Set(Utc::now());
+    crate::update_field_if_some!(reg_code, entitlements, req.entitlements.map(|e| e.to_json()), option);
+    // 从吊销状态恢复时清空吊销信息并记录恢复时间
+    if let Some(status) = req.status
+        && RegCodeStatus::from(status) != RegCodeStatus::Revoked
+    {
+        reg_code.revoked_reason = Set(None);
+        reg_code.revoked_at = Set(None);
+        if was_revoked {
+            reg_code.restored_at = Set(Some(now));
+        }
+    }
+    reg_code.updated_at = Set(now);
 
     let updated_reg_code = reg_code.update(&state.db).await?;
-
-    // Fetch with app information for response
-    let result = reg_codes::Entity::find_by_id(updated_reg_code.id)
-        .find_also_related(apps::Entity)
-        .find_also_related(app_devices::Entity)
-        .one(&state.db)
-        .await?;
-
-    match result {
-        Some((reg_code, app, device)) => Ok(RegCodeInfo::try_from((reg_code, app, device))?),
-        None => Err(AppError::not_found(
-            "reg_codes".to_string(),
-            Some(updated_reg_code.id),
-        )),
-    }
+    get_by_id_impl(state, updated_reg_code.id).await
 }
 
 // Delete RegCode
@@ -135,13 +653,21 @@ pub async fn get_list_impl(
 
     let mut query = reg_codes::Entity::find()
         .find_also_related(apps::Entity)
-        .find_also_related(app_devices::Entity)
         .order_by_desc(reg_codes::Column::CreatedAt);
 
     crate::filter_if_some!(query, reg_codes::Column::Id, params.id, eq);
     crate::filter_if_some!(query, reg_codes::Column::Code, params.code, contains);
     crate::filter_if_some!(query, reg_codes::Column::AppId, params.app_id, eq);
     crate::filter_if_some!(query, reg_codes::Column::Status, params.status, eq);
+    crate::filter_if_some!(query, reg_codes::Column::BatchId, params.batch_id, eq);
+    if let Some(reseller_id) = params.reseller_id {
+        let batches = reg_code_batches::Entity::find()
+            .select_only()
+            .column(reg_code_batches::Column::Id)
+            .filter(reg_code_batches::Column::ResellerId.eq(reseller_id))
+            .into_query();
+        query = query.filter(reg_codes::Column::BatchId.in_subquery(batches));
+    }
     crate::filter_if_some!(
         query,
         reg_codes::Column::CodeType,
@@ -153,9 +679,18 @@ pub async fn get_list_impl(
     let total = paginator.num_items().await.unwrap_or(0);
     let results = paginator.fetch_page(page - 1).await?;
 
+    let mut devices = load_devices(
+        &state.db,
+        results.iter().map(|(reg_code, _)| reg_code.id).collect(),
+    )
+    .await?;
     let list: Result<Vec<RegCodeInfo>, AppError> = results
         .into_iter()
-        .map(|(reg_code, app, device)| RegCodeInfo::try_from((reg_code, app, device)))
+        .map(|item| {
+            let mut info = RegCodeInfo::try_from(item)?;
+            info.devices = devices.remove(&info.id).unwrap_or_default();
+            Ok(info)
+        })
         .collect();
 
     Ok(PagingResponse {
@@ -179,13 +714,396 @@ pub async fn get_by_id(
 pub async fn get_by_id_impl(state: &AppState, id: i32) -> Result<RegCodeInfo, AppError> {
     let result = reg_codes::Entity::find_by_id(id)
         .find_also_related(apps::Entity)
-        .find_also_related(app_devices::Entity)
         .one(&state.db)
         .await?;
+    let result = result.ok_or_else(|| AppError::not_found("reg_codes".to_string(), Some(id)))?;
+    let mut info = RegCodeInfo::try_from(result)?;
+    info.devices = load_devices(&state.db, vec![id])
+        .await?
+        .remove(&id)
+        .unwrap_or_default();
+    Ok(info)
+}
+
+/// 查询注册码绑定的设备，按 reg_code_id 分组
+pub async fn load_devices<C: ConnectionTrait>(
+    db: &C,
+    reg_code_ids: Vec<i32>,
+) -> Result<HashMap<i32, Vec<RegCodeDeviceInfo>>, AppError> {
+    let mut map: HashMap<i32, Vec<RegCodeDeviceInfo>> = HashMap::new();
+    if reg_code_ids.is_empty() {
+        return Ok(map);
+    }
+    let rows = reg_code_devices::Entity::find()
+        .find_also_related(app_devices::Entity)
+        .filter(reg_code_devices::Column::RegCodeId.is_in(reg_code_ids))
+        .order_by_asc(reg_code_devices::Column::BindTime)
+        .all(db)
+        .await?;
+    for (binding, device) in rows {
+        if let Some(device) = device {
+            map.entry(binding.reg_code_id)
+                .or_default()
+                .push(RegCodeDeviceInfo::from((binding, device)));
+        }
+    }
+    Ok(map)
+}
+
+// List devices bound to a RegCode
+#[handler]
+pub async fn get_devices(
+    depot: &mut Depot,
+    id: PathParam<i32>,
+) -> Result<ApiResponse<Vec<RegCodeDeviceInfo>>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let info = get_by_id_impl(state, id.into_inner()).await?;
+    Ok(ApiResponse::success(info.devices))
+}
+
+// Unbind a device from a RegCode
+#[handler]
+pub async fn unbind_device(
+    depot: &mut Depot,
+    id: PathParam<i32>,
+    device_id: PathParam<i32>,
+) -> Result<ApiResponse<()>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let operator_id = depot.obtain::<Claims>().ok().map(|c| c.sub);
+    unbind_device_impl(state, id.into_inner(), device_id.into_inner(), operator_id).await?;
+    Ok(ApiResponse::success(()))
+}
+
+/// 管理员解绑设备，不受自助解绑次数限制，撤回注册码给该设备叠加的时长，记录到 reg_code_transfers
+pub async fn unbind_device_impl(
+    state: &AppState,
+    id: i32,
+    device_id: i32,
+    operator_id: Option<i32>,
+) -> Result<(), AppError> {
+    let txn = state.db.begin().await?;
+    let binding = reg_code_devices::Entity::find()
+        .find_also_related(app_devices::Entity)
+        .filter(
+            reg_code_devices::Column::RegCodeId
+                .eq(id)
+                .and(reg_code_devices::Column::DeviceId.eq(device_id)),
+        )
+        .one(&txn)
+        .await?;
+    let Some((binding, Some(device))) = binding else {
+        return Err(AppError::not_found("reg_code_devices".to_string(), Some(device_id)));
+    };
+    let reg_code = reg_codes::Entity::find_by_id(id).one(&txn).await?;
+    let reg_code = reg_code.ok_or_else(|| AppError::not_found("reg_codes".to_string(), Some(id)))?;
+    reg_code_devices::Entity::delete_by_id(binding.id)
+        .exec(&txn)
+        .await?;
+    transfer_handler::revoke_renewal(&txn, reg_code.id, device.id).await?;
+    transfer_handler::record_transfer(
+        &txn,
+        &reg_code,
+        &device.device_id,
+        None,
+        TransferSource::AdminUnbind,
+        None,
+        operator_id,
+    )
+    .await?;
+    txn.commit().await?;
+    Ok(())
+}
+
+// Move a device binding of a RegCode to another device
+#[handler]
+pub async fn transfer_device(
+    depot: &mut Depot,
+    id: PathParam<i32>,
+    device_id: PathParam<i32>,
+    req: JsonBody<TransferRegCodeDeviceReq>,
+) -> Result<ApiResponse<RegCodeInfo>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let operator_id = depot.obtain::<Claims>().ok().map(|c| c.sub);
+    let info = transfer_device_impl(
+        state,
+        id.into_inner(),
+        device_id.into_inner(),
+        req.into_inner(),
+        operator_id,
+    )
+    .await?;
+    Ok(ApiResponse::success(info))
+}
+
+/// 管理员把设备绑定转移到另一台设备，撤回注册码给原设备叠加的时长，记录到 reg_code_transfers
+pub async fn transfer_device_impl(
+    state: &AppState,
+    id: i32,
+    device_id: i32,
+    req: TransferRegCodeDeviceReq,
+    operator_id: Option<i32>,
+) -> Result<RegCodeInfo, AppError> {
+    let txn = state.db.begin().await?;
+    let binding = reg_code_devices::Entity::find()
+        .find_also_related(reg_codes::Entity)
+        .filter(
+            reg_code_devices::Column::RegCodeId
+                .eq(id)
+                .and(reg_code_devices::Column::DeviceId.eq(device_id)),
+        )
+        .one(&txn)
+        .await?;
+    let (binding, reg_code) = binding
+        .ok_or_else(|| AppError::not_found("reg_code_devices".to_string(), Some(device_id)))?;
+    let reg_code = reg_code.ok_or_else(|| AppError::not_found("reg_codes".to_string(), Some(id)))?;
+    let app = apps::Entity::find_by_id(reg_code.app_id).one(&txn).await?;
+    let app = app.ok_or_else(|| AppError::not_found("apps".to_string(), Some(reg_code.app_id)))?;
+    let target = find_or_create_device(&txn, &app, &req.to_device_id).await?;
+    let exists = reg_code_devices::Entity::find()
+        .filter(
+            reg_code_devices::Column::RegCodeId
+                .eq(id)
+                .and(reg_code_devices::Column::DeviceId.eq(target.id)),
+        )
+        .one(&txn)
+        .await?;
+    if exists.is_some() {
+        return Err(AppError::validation("target device already bound"));
+    }
+    let source = app_devices::Entity::find_by_id(device_id).one(&txn).await?;
+    let source =
+        source.ok_or_else(|| AppError::not_found("app_devices".to_string(), Some(device_id)))?;
+    let mut binding = binding.into_active_model();
+    binding.device_id = Set(target.id);
+    binding.bind_time = Set(Utc::now());
+    binding.update(&txn).await?;
+    transfer_handler::revoke_renewal(&txn, reg_code.id, source.id).await?;
+    transfer_handler::record_transfer(
+        &txn,
+        &reg_code,
+        &source.device_id,
+        Some(target.device_id),
+        TransferSource::AdminTransfer,
+        None,
+        operator_id,
+    )
+    .await?;
+    txn.commit().await?;
+    get_by_id_impl(state, id).await
+}
+
+/// 查找设备，不存在时创建，新设备不发放试用期
+///
+/// 试用期只在不带注册码的首次校验中按试用策略发放，见 check_code_impl
+pub async fn find_or_create_device<C: ConnectionTrait>(
+    db: &C,
+    app: &apps::Model,
+    device_id: &str,
+) -> Result<app_devices::Model, AppError> {
+    let dev = app_devices::Entity::find()
+        .filter(
+            app_devices::Column::AppId
+                .eq(app.id)
+                .and(app_devices::Column::DeviceId.eq(device_id)),
+        )
+        .one(db)
+        .await?;
+    if let Some(dev) = dev {
+        return Ok(dev);
+    }
+    let now = Utc::now();
+    let dev = app_devices::ActiveModel {
+        app_id: Set(app.id),
+        device_id: Set(device_id.to_string()),
+        device_info: Set(None),
+        bind_time: Set(Some(now)),
+        expire_time: Set(Some(now)),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+    Ok(dev)
+}
+
+// Revoke RegCode
+#[handler]
+pub async fn revoke(
+    depot: &mut Depot,
+    id: PathParam<i32>,
+    req: JsonBody<RevokeRegCodeReq>,
+) -> Result<ApiResponse<RegCodeInfo>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let reg_code = revoke_impl(state, id.into_inner(), req.into_inner()).await?;
+    Ok(ApiResponse::success(reg_code))
+}
+
+pub async fn revoke_impl(
+    state: &AppState,
+    id: i32,
+    req: RevokeRegCodeReq,
+) -> Result<RegCodeInfo, AppError> {
+    let reg_code = reg_codes::Entity::find_by_id(id).one(&state.db).await?;
+    let reg_code =
+        reg_code.ok_or_else(|| AppError::not_found("reg_codes".to_string(), Some(id)))?;
+    let now = Utc::now();
+    let mut reg_code = reg_code.into_active_model();
+    reg_code.status = Set(i16::from(RegCodeStatus::Revoked));
+    reg_code.revoked_reason = Set(Some(req.reason));
+    reg_code.revoked_at = Set(Some(now));
+    reg_code.restored_at = Set(None);
+    reg_code.updated_at = Set(now);
+    reg_code.update(&state.db).await?;
+    get_by_id_impl(state, id).await
+}
 
-    match result {
-        Some((reg_code, app, device)) => Ok(RegCodeInfo::try_from((reg_code, app, device))?),
-        None => Err(AppError::not_found("reg_codes".to_string(), Some(id))),
+/// 将设备绑定到注册码，已绑定时直接返回，超过 max_devices 时报错
+pub async fn bind_device(state: &AppState, reg_code_id: i32, device_id: i32) -> Result<(), AppError> {
+    let txn = state.db.begin().await?;
+    // 锁住注册码行，避免并发激活突破设备上限
+    let reg_code = reg_codes::Entity::find_by_id(reg_code_id)
+        .lock_exclusive()
+        .one(&txn)
+        .await?;
+    let reg_code =
+        reg_code.ok_or_else(|| AppError::not_found("reg_codes".to_string(), Some(reg_code_id)))?;
+    bind_device_locked(&txn, &reg_code, device_id).await?;
+    txn.commit().await?;
+    Ok(())
+}
+
+/// 同 bind_device，调用方需已在事务中锁住注册码行
+pub async fn bind_device_locked<C: ConnectionTrait>(
+    db: &C,
+    reg_code: &reg_codes::Model,
+    device_id: i32,
+) -> Result<(), AppError> {
+    let bound = reg_code_devices::Entity::find()
+        .filter(reg_code_devices::Column::RegCodeId.eq(reg_code.id))
+        .all(db)
+        .await?;
+    if bound.iter().any(|b| b.device_id == device_id) {
+        return Ok(());
+    }
+    if bound.len() as i32 >= reg_code.max_devices {
+        return Err(AppError::business_logic(
+            "DEVICE_LIMIT_REACHED",
+            "device limit reached",
+        ));
+    }
+    reg_code_devices::ActiveModel {
+        reg_code_id: Set(reg_code.id),
+        device_id: Set(device_id),
+        bind_time: Set(Utc::now()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+    Ok(())
+}
+
+/// 时长注册码兑换到设备：首次兑换时把时长叠加到设备有效期上，返回设备有效期
+///
+/// 绑定设备、更新注册码状态和写入续期记录在同一个事务中完成
+async fn redeem_time_code(
+    state: &AppState,
+    reg_code: &reg_codes::Model,
+    dev_id: i32,
+) -> Result<chrono::DateTime<Utc>, AppError> {
+    let now = Utc::now();
+    let (order_id, days) = renewal_days(state, reg_code).await?;
+    let txn = state.db.begin().await?;
+    // 锁住注册码行和设备行，避免并发兑换突破设备上限或重复叠加
+    let code_id = reg_code.id;
+    let reg_code = reg_codes::Entity::find_by_id(code_id)
+        .lock_exclusive()
+        .one(&txn)
+        .await?;
+    let reg_code =
+        reg_code.ok_or_else(|| AppError::not_found("reg_codes".to_string(), Some(code_id)))?;
+    let device = app_devices::Entity::find_by_id(dev_id)
+        .lock_exclusive()
+        .one(&txn)
+        .await?;
+    let device =
+        device.ok_or_else(|| AppError::not_found("app_devices".to_string(), Some(dev_id)))?;
+    let history = device_renewals::Entity::find()
+        .filter(device_renewals::Column::DeviceId.eq(dev_id))
+        .all(&txn)
+        .await?;
+    if let Some(renewal) = history.iter().find(|r| r.reg_code_id == reg_code.id) {
+        // 已兑换过，以设备有效期为准，注册码自身过期不影响已叠加的时长
+        let expire = device.expire_time.unwrap_or(renewal.new_expire);
+        if now > expire {
+            return Err(AppError::business_logic("DEVICE_EXPIRED", "device expired"));
+        }
+        bind_device_locked(&txn, &reg_code, dev_id).await?;
+        txn.commit().await?;
+        return Ok(expire);
+    }
+
+    let code_expire = reg_code
+        .expire_time
+        .unwrap_or(now + chrono::Duration::days(days as i64));
+    if now > code_expire {
+        let mut active = reg_code.into_active_model();
+        active.status = Set(RegCodeStatus::Expired.into());
+        active.update(&txn).await?;
+        txn.commit().await?;
+        return Err(AppError::business_logic("CODE_EXPIRED", "code expired"));
+    }
+    bind_device_locked(&txn, &reg_code, dev_id).await?;
+    if reg_code.binding_time.is_none() {
+        let mut active = reg_code.clone().into_active_model();
+        active.status = Set(RegCodeStatus::Used.into());
+        active.binding_time = Set(Some(now));
+        active.expire_time = Set(Some(code_expire));
+        active.update(&txn).await?;
+    }
+    // 设备已有未过期的正式授权时在其基础上续期，否则（试用或已过期）从注册码有效期开始
+    // 只设置了绝对过期时间的注册码 days 为 0，续期结果不早于注册码自身的过期时间
+    let new_expire = match device.expire_time {
+        Some(exp) if !history.is_empty() && exp > now => {
+            (exp + chrono::Duration::days(days as i64)).max(code_expire)
+        }
+        _ => code_expire,
+    };
+    device_renewals::ActiveModel {
+        app_id: Set(device.app_id),
+        device_id: Set(dev_id),
+        reg_code_id: Set(reg_code.id),
+        order_id: Set(order_id),
+        added_days: Set(days),
+        previous_expire: Set(device.expire_time),
+        new_expire: Set(new_expire),
+        created_at: Set(now),
+        ..Default::default()
+    }
+    .insert(&txn)
+    .await?;
+    let mut device = device.into_active_model();
+    device.expire_time = Set(Some(new_expire));
+    device.trial_refused_reason = Set(None);
+    let device = device.update(&txn).await?;
+    txn.commit().await?;
+    Ok(device.expire_time.unwrap_or(new_expire))
+}
+
+/// 注册码叠加的天数；通过订单购买且商品设置了 add_valid_days 时以商品为准
+///
+/// 订单数量为 N 时会生成 N 个注册码，每个注册码只叠加一份商品时长
+async fn renewal_days(
+    state: &AppState,
+    reg_code: &reg_codes::Model,
+) -> Result<(Option<i32>, i32), AppError> {
+    let purchased =
+        product_handler::find_by_reg_code(&state.db, reg_code.app_id, reg_code.id).await?;
+    let order_id = purchased.first().map(|(item, _)| item.order_id);
+    let product = purchased
+        .iter()
+        .find(|(_, product)| product.add_valid_days > 0);
+    match product {
+        Some((item, product)) => Ok((Some(item.order_id), product.add_valid_days)),
+        None => Ok((order_id, reg_code.valid_days)),
     }
 }
 
@@ -196,10 +1114,16 @@ pub async fn get_by_id_impl(state: &AppState, id: i32) -> Result<RegCodeInfo, Ap
 pub async fn validate_code(
     depot: &mut Depot,
     req: JsonBody<RegCodeValidateReq>,
+    request: &mut Request,
 ) -> Result<ApiResponse<RegCodeValidateResp>, AppError> {
     let state = depot.obtain::<AppState>().unwrap();
-    let resp = validate_code_impl(&state, req.into_inner()).await?;
-    Ok(ApiResponse::success(resp))
+    let req = req.into_inner();
+    let app = AppRef::ValidKey(req.app_key.clone());
+    let (device_id, code) = (req.device_id.clone(), req.code.clone());
+    let client = ClientInfo::from_request(request, &state.config.trusted_proxies);
+    let resp = validate_code_impl(state, req, &client).await;
+    record_validation(state, &client, app, device_id, code, 1, &resp);
+    Ok(ApiResponse::success(resp?))
 }
 
 /// Validate registration code for device (GET)
@@ -217,13 +1141,171 @@ pub async fn validate_code_get(
 ) -> Result<ApiResponse<RegCodeValidateResp>, AppError> {
     let state = depot.obtain::<AppState>().unwrap();
     let json = req.parse_queries::<RegCodeValidateReq>()?;
-    let resp = validate_code_impl(&state, json).await?;
+    let app = AppRef::ValidKey(json.app_key.clone());
+    let (device_id, code) = (json.device_id.clone(), json.code.clone());
+    let client = ClientInfo::from_request(req, &state.config.trusted_proxies);
+    let resp = validate_code_impl(state, json, &client).await;
+    record_validation(state, &client, app, device_id, code, 1, &resp);
+    Ok(ApiResponse::success(resp?))
+}
+
+/// Get the signed revocation list of an app
+#[endpoint(
+    tags("reg_codes"),
+    parameters(
+        ("kind"=Option<String>, Query, description = "codes / devices"),
+        ("since"=Option<i64>, Query, description = "只返回该时间（unix 秒）之后的记录"),
+        ("page"=Option<u64>, Query, description = "页码"),
+        ("page_size"=Option<u64>, Query, description = "每页数量，最大 1000")
+))]
+pub async fn get_revocations(
+    depot: &mut Depot,
+    app_id: PathParam<String>,
+    req: &mut Request,
+) -> Result<ApiResponse<RevocationListResp>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let params = req.parse_queries::<RevocationListParams>()?;
+    let resp = get_revocations_impl(state, app_id.into_inner(), params).await?;
+    Ok(ApiResponse::success(resp))
+}
+
+pub async fn get_revocations_impl(
+    state: &AppState,
+    app_id: String,
+    params: RevocationListParams,
+) -> Result<RevocationListResp, AppError> {
+    let app = apps::Entity::find()
+        .filter(apps::Column::AppId.eq(app_id))
+        .one(&state.db)
+        .await?;
+    let app = app.ok_or(AppError::not_found("apps".to_string(), None))?;
+    let app = app_handler::ensure_sign_key_impl(state, app).await?;
+    let page = params.pagination.page.unwrap_or(1).max(1);
+    let page_size = params.pagination.page_size.unwrap_or(100).clamp(1, 1000);
+    let since = params
+        .since
+        .and_then(|s| chrono::DateTime::from_timestamp(s, 0));
+
+    // 全量拉取只返回当前有效的记录；增量拉取还返回 since 之后解除的记录，客户端据此从本地列表移除
+    let (items, total) = match params.kind {
+        RevocationKind::Codes => {
+            let revoked = reg_codes::Column::Status.eq(i16::from(RegCodeStatus::Revoked));
+            let filter = match since {
+                Some(since) => Condition::any()
+                    .add(revoked.clone().and(reg_codes::Column::RevokedAt.gte(since)))
+                    .add(revoked.not().and(reg_codes::Column::RestoredAt.gte(since))),
+                None => Condition::all().add(revoked),
+            };
+            let changed_at = SimpleExpr::from(Func::coalesce([
+                Expr::col(reg_codes::Column::RevokedAt).into(),
+                Expr::col(reg_codes::Column::RestoredAt).into(),
+            ]));
+            let query = reg_codes::Entity::find()
+                .filter(reg_codes::Column::AppId.eq(app.id))
+                .filter(filter)
+                .order_by_asc(changed_at)
+                .order_by_asc(reg_codes::Column::Id);
+            let paginator = query.paginate(&state.db, page_size);
+            let total = paginator.num_items().await?;
+            let items = paginator
+                .fetch_page(page - 1)
+                .await?
+                .into_iter()
+                .map(|c| {
+                    let restored = RegCodeStatus::from(c.status) != RegCodeStatus::Revoked;
+                    let changed_at = if restored { c.restored_at } else { c.revoked_at };
+                    RevocationItem {
+                        value: hex::encode(Sha256::digest(c.code.as_bytes())),
+                        reason: c.revoked_reason.unwrap_or_default(),
+                        revoked_at: changed_at.unwrap_or(c.updated_at),
+                        restored,
+                    }
+                })
+                .collect();
+            (items, total)
+        }
+        RevocationKind::Devices => {
+            let filter = match since {
+                Some(since) => Condition::any()
+                    .add(
+                        device_bans::Column::LiftedAt
+                            .is_null()
+                            .and(device_bans::Column::CreatedAt.gte(since)),
+                    )
+                    .add(device_bans::Column::LiftedAt.gte(since)),
+                None => Condition::all().add(device_bans::Column::LiftedAt.is_null()),
+            };
+            let changed_at = SimpleExpr::from(Func::coalesce([
+                Expr::col(device_bans::Column::LiftedAt).into(),
+                Expr::col(device_bans::Column::CreatedAt).into(),
+            ]));
+            let query = device_bans::Entity::find()
+                .filter(device_bans::Column::AppId.eq(app.id))
+                .filter(filter)
+                .order_by_asc(changed_at)
+                .order_by_asc(device_bans::Column::Id);
+            let paginator = query.paginate(&state.db, page_size);
+            let total = paginator.num_items().await?;
+            let items = paginator
+                .fetch_page(page - 1)
+                .await?
+                .into_iter()
+                .map(|b| RevocationItem {
+                    value: b.device_id,
+                    reason: b.reason,
+                    revoked_at: b.lifted_at.unwrap_or(b.created_at),
+                    restored: b.lifted_at.is_some(),
+                })
+                .collect();
+            (items, total)
+        }
+    };
+    let list = RevocationList {
+        app_id: app.app_id.clone(),
+        kind: params.kind,
+        page,
+        total,
+        items,
+        issued_at: Utc::now().timestamp(),
+    };
+    Ok(RevocationListResp {
+        token: license::sign_token(&app.sign_private_key, &list)?,
+    })
+}
+
+/// Get the license signing public key of an app
+#[endpoint(tags("reg_codes"))]
+pub async fn get_public_key(
+    depot: &mut Depot,
+    app_id: PathParam<String>,
+) -> Result<ApiResponse<AppPublicKeyResp>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let resp = get_public_key_impl(state, app_id.into_inner()).await?;
     Ok(ApiResponse::success(resp))
 }
 
+pub async fn get_public_key_impl(
+    state: &AppState,
+    app_id: String,
+) -> Result<AppPublicKeyResp, AppError> {
+    let app = apps::Entity::find()
+        .filter(apps::Column::AppId.eq(app_id))
+        .one(&state.db)
+        .await?;
+    let app = app.ok_or(AppError::not_found("apps".to_string(), None))?;
+    let app = app_handler::ensure_sign_key_impl(state, app).await?;
+    Ok(AppPublicKeyResp {
+        app_id: app.app_id,
+        algorithm: license::SIGN_ALGORITHM.to_string(),
+        public_key: app.sign_public_key,
+        offline_grace_days: app.offline_grace_days,
+    })
+}
+
 pub async fn validate_code_impl(
     state: &AppState,
     req: RegCodeValidateReq,
+    client: &ClientInfo,
 ) -> Result<RegCodeValidateResp, AppError> {
     // find app by app_valid_key
     let app = apps::Entity::find()
@@ -231,6 +1313,281 @@ pub async fn validate_code_impl(
         .one(&state.db)
         .await?;
     let app = app.ok_or(AppError::not_found("apps".to_string(), None))?;
+    if !app.allow_legacy_validate {
+        return Err(AppError::business_logic(
+            "LEGACY_VALIDATE_DISABLED",
+            "legacy validation is disabled for this app, use /api/reg/v2/validate",
+        ));
+    }
+    validate_for_app(state, app, req, client, false).await
+}
+
+/// Validate registration code with a signed request (v2)
+#[endpoint(tags("reg_codes"))]
+pub async fn validate_code_v2(
+    depot: &mut Depot,
+    req: JsonBody<RegCodeValidateV2Req>,
+    request: &mut Request,
+) -> Result<ApiResponse<RegCodeValidateV2Resp>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let req = req.into_inner();
+    let app = AppRef::AppId(req.app_id.clone());
+    let (device_id, code) = (req.device_id.clone(), req.code.clone());
+    let client = ClientInfo::from_request(request, &state.config.trusted_proxies);
+    let resp = validate_code_v2_impl(state, req, &client).await;
+    record_validation(state, &client, app, device_id, code, 2, &resp);
+    Ok(ApiResponse::success(resp?))
+}
+
+/// 记录一次校验调用，写库由 ValidationLogger 在后台完成
+pub fn record_validation<T>(
+    state: &AppState,
+    client: &ClientInfo,
+    app: AppRef,
+    device_id: String,
+    code: Option<String>,
+    api_version: i16,
+    result: &Result<T, AppError>,
+) {
+    state.validation_log.record(ValidationEvent {
+        app,
+        device_id,
+        // 与校验时一样去掉首尾空白，同一注册码只记一个哈希
+        code: code
+            .as_deref()
+            .map(str::trim)
+            .filter(|c| !c.is_empty())
+            .map(validation_log::code_hash),
+        api_version,
+        ip: client.ip.clone(),
+        user_agent: client.user_agent.clone(),
+        error: result
+            .as_ref()
+            .err()
+            .map(|e| (e.error_code() as i32, e.to_string())),
+        created_at: Utc::now(),
+    });
+}
+
+pub async fn validate_code_v2_impl(
+    state: &AppState,
+    req: RegCodeValidateV2Req,
+    client: &ClientInfo,
+) -> Result<RegCodeValidateV2Resp, AppError> {
+    let app = verify_signed_request(state, &req).await?;
+    let secret = app.app_valid_key.clone();
+    let result = validate_for_app(
+        state,
+        app,
+        RegCodeValidateReq {
+            code: req.code,
+            app_key: secret.clone(),
+            device_id: req.device_id,
+            device_info: req.device_info,
+            config: req.config,
+        },
+        client,
+        false,
+    )
+    .await?;
+    sign_response(&secret, req.nonce, &result)
+}
+
+/// 校验 v2 签名请求（签名、时间戳、nonce），返回对应的应用
+pub async fn verify_signed_request<R: SignedRequest>(
+    state: &AppState,
+    req: &R,
+) -> Result<apps::Model, AppError> {
+    let app = apps::Entity::find()
+        .filter(apps::Column::AppId.eq(req.app_id()))
+        .one(&state.db)
+        .await?;
+    let app = app.ok_or(AppError::not_found("apps".to_string(), None))?;
+    // 空密钥谁都能算出签名，不能作为 HMAC 密钥
+    if app.app_valid_key.trim().is_empty() {
+        return Err(AppError::business_logic(
+            "SIGN_KEY_MISSING",
+            "app has no valid key, signed requests are disabled",
+        ));
+    }
+    if !signature::verify_hmac_sha256_hex(&app.app_valid_key, &req.sign_content(), req.sign()) {
+        return Err(AppError::business_logic("SIGN_MISMATCH", "invalid request signature"));
+    }
+    let now = Utc::now().timestamp();
+    if (now - req.timestamp()).abs() > VALIDATE_MAX_CLOCK_SKEW_SECS {
+        return Err(AppError::business_logic(
+            "TIMESTAMP_EXPIRED",
+            "request timestamp out of range, check the device clock",
+        ));
+    }
+    if req.nonce().len() < 8 || req.nonce().len() > 64 {
+        return Err(AppError::validation("nonce must be 8-64 characters"));
+    }
+    // nonce 只需在时间窗口内保留，窗口外的请求已被时间戳拒绝
+    let nonce_key = format!("reg:nonce:{}:{}", app.app_id, req.nonce());
+    let ttl = std::time::Duration::from_secs(VALIDATE_MAX_CLOCK_SKEW_SECS as u64 * 2);
+    if !state.redis.set_nx(&nonce_key, &req.timestamp(), ttl).await? {
+        return Err(AppError::business_logic("NONCE_REPLAYED", "nonce already used"));
+    }
+    Ok(app)
+}
+
+/// 用应用密钥对响应签名，payload 为 result 的 JSON
+pub fn sign_response<T: serde::Serialize>(
+    secret: &str,
+    nonce: String,
+    result: &T,
+) -> Result<RegCodeValidateV2Resp, AppError> {
+    let mut resp = RegCodeValidateV2Resp {
+        payload: serde_json::to_string(result)?,
+        timestamp: Utc::now().timestamp(),
+        nonce,
+        sign: String::new(),
+    };
+    resp.sign = signature::hmac_sha256_hex(secret, &resp.sign_content());
+    Ok(resp)
+}
+
+/// 设备在黑名单中时报错
+pub async fn check_device_ban<C: ConnectionTrait>(
+    db: &C,
+    app_id: i32,
+    device_id: &str,
+) -> Result<(), AppError> {
+    let ban = device_bans::Entity::find()
+        .filter(
+            device_bans::Column::AppId
+                .eq(app_id)
+                .and(device_bans::Column::DeviceId.eq(device_id)),
+        )
+        .filter(device_bans::Column::LiftedAt.is_null())
+        .one(db)
+        .await?;
+    if let Some(ban) = ban {
+        return Err(AppError::business_logic(
+            "DEVICE_BANNED",
+            format!("device banned: {}", ban.reason),
+        ));
+    }
+    Ok(())
+}
+
+/// 按应用校验注册码并签发许可证，离线激活签发的许可证不受离线宽限期限制
+pub async fn validate_for_app(
+    state: &AppState,
+    app: apps::Model,
+    mut req: RegCodeValidateReq,
+    client: &ClientInfo,
+    offline_activation: bool,
+) -> Result<RegCodeValidateResp, AppError> {
+    // 校验和查库使用同一个去掉首尾空白的注册码
+    req.code = req.code.map(|c| c.trim().to_string());
+    // 格式不合法的注册码直接拒绝，不再查库
+    if let Some(code) = req.code.as_deref().filter(|c| !c.is_empty()) {
+        check_strict_format(&app, code)?;
+    }
+    check_device_ban(&state.db, app.id, &req.device_id).await?;
+    let app = app_handler::ensure_sign_key_impl(state, app).await?;
+    let device_id = req.device_id.clone();
+    let config = req.config.clone();
+    let mut resp = check_code_impl(state, &app, req, client).await?;
+    if let Some(query) = config {
+        let licensed = app_configs_handler::is_licensed(&state.db, app.id, &device_id).await?;
+        resp.config =
+            Some(app_configs_handler::resolve_impl(&state.db, app.id, &query, Some(licensed)).await?);
+    }
+    resp.license_token = Some(sign_license(&app, device_id, &resp, offline_activation)?);
+    Ok(resp)
+}
+
+/// 生成签名许可证令牌
+fn sign_license(
+    app: &apps::Model,
+    device_id: String,
+    resp: &RegCodeValidateResp,
+    offline_activation: bool,
+) -> Result<String, AppError> {
+    let claims = LicenseClaims {
+        app_id: app.app_id.clone(),
+        device_id,
+        code_type: resp.code_type,
+        expire_at: resp.expire_time.map(|t| t.timestamp()),
+        remaining_count: resp.remaining_count,
+        issued_at: Utc::now().timestamp(),
+        offline_grace_days: app.offline_grace_days,
+        entitlements: resp.entitlements.clone(),
+        offline_activation,
+    };
+    license::sign_token(&app.sign_private_key, &claims)
+}
+
+/// 按注册码和已绑定设备的当前状态重新签发许可证，不消耗次数也不延长有效期
+pub async fn reissue_license(
+    state: &AppState,
+    reg_code: &reg_codes::Model,
+    device: &app_devices::Model,
+) -> Result<RegCodeValidateResp, AppError> {
+    if RegCodeStatus::from(reg_code.status) == RegCodeStatus::Revoked {
+        return Err(AppError::business_logic(
+            "CODE_REVOKED",
+            format!(
+                "reg code revoked: {}",
+                reg_code.revoked_reason.clone().unwrap_or_default()
+            ),
+        ));
+    }
+    check_device_ban(&state.db, reg_code.app_id, &device.device_id).await?;
+    let app = apps::Entity::find_by_id(reg_code.app_id).one(&state.db).await?;
+    let app =
+        app.ok_or_else(|| AppError::not_found("apps".to_string(), Some(reg_code.app_id)))?;
+    let app = app_handler::ensure_sign_key_impl(state, app).await?;
+    let entitlements =
+        app_features_handler::resolve_entitlements(&state.db, app.id, Some(reg_code)).await?;
+    let mut resp = match reg_code.code_type.into() {
+        CodeType::Time => {
+            let expire = device.expire_time.filter(|t| *t > Utc::now());
+            let expire = expire
+                .ok_or_else(|| AppError::business_logic("DEVICE_EXPIRED", "device expired"))?;
+            RegCodeValidateResp {
+                code_type: CodeType::Time,
+                expire_time: Some(expire),
+                remaining_count: None,
+                license_token: None,
+                entitlements,
+                config: None,
+            }
+        }
+        CodeType::Count => {
+            let remaining = reg_code.total_count.unwrap_or(0) - reg_code.use_count;
+            if remaining <= 0 {
+                return Err(AppError::business_logic("CODE_USED_UP", "code used up"));
+            }
+            RegCodeValidateResp {
+                code_type: CodeType::Count,
+                expire_time: None,
+                remaining_count: Some(remaining),
+                license_token: None,
+                entitlements,
+                config: None,
+            }
+        }
+        CodeType::Floating => {
+            return Err(AppError::business_logic(
+                "FLOATING_CODE",
+                "floating codes must use /api/reg/lease/checkout",
+            ));
+        }
+    };
+    resp.license_token = Some(sign_license(&app, device.device_id.clone(), &resp, false)?);
+    Ok(resp)
+}
+
+async fn check_code_impl(
+    state: &AppState,
+    app: &apps::Model,
+    req: RegCodeValidateReq,
+    client: &ClientInfo,
+) -> Result<RegCodeValidateResp, AppError> {
     let now = chrono::Utc::now();
     // let app_expire = now + chrono::Duration::days(app.trial_days as i64);
     let code = req.code.clone();
@@ -243,26 +1600,55 @@ pub async fn validate_code_impl(
         .one(&state.db)
         .await?;
     let mut device_expire = now + chrono::Duration::days(app.trial_days as i64);
-    let mut _dev_id = None;
+    let dev_id;
     let code_is_none = code.is_none() || code.unwrap().is_empty();
-    if dev.is_none() {
-        //bind device
-        let dev_tmp = app_devices::ActiveModel {
-            app_id: Set(app.id),
-            device_id: Set(req.device_id.clone()),
-            device_info: Set(None),
-            bind_time: Set(Some(Utc::now())),
-            expire_time: Set(Some(device_expire)),
-            ..Default::default()
+    match &dev {
+        None => {
+            // 只有不带注册码的首次校验才按试用策略检查
+            let ip = client.ip_addr();
+            let refused = if code_is_none {
+                trial_guard::check_new_trial(
+                    &state.db,
+                    app.id,
+                    &req.device_id,
+                    ip,
+                    req.device_info.as_ref(),
+                )
+                .await?
+            } else {
+                None
+            };
+            // 带注册码首次校验的设备不发放试用期，否则编造一个注册码就能绕过试用策略
+            if refused.is_some() || !code_is_none {
+                device_expire = now;
+            }
+            //bind device
+            let dev_tmp = app_devices::ActiveModel {
+                app_id: Set(app.id),
+                device_id: Set(req.device_id.clone()),
+                device_info: Set(req.device_info.clone()),
+                bind_time: Set(Some(Utc::now())),
+                expire_time: Set(Some(device_expire)),
+                bind_ip: Set(ip.map(|ip| ip.to_string())),
+                trial_refused_reason: Set(refused.clone()),
+                ..Default::default()
+            }
+            .insert(&state.db)
+            .await?;
+            dev_id = dev_tmp.id;
+            if let Some(reason) = refused {
+                return Err(AppError::business_logic("TRIAL_REFUSED", reason));
+            }
         }
-        .insert(&state.db)
-        .await?;
-        _dev_id = Some(dev_tmp.id);
-    } else {
-        device_expire = dev.as_ref().unwrap().expire_time.unwrap();
-        _dev_id = Some(dev.as_ref().unwrap().id);
-        if now > device_expire && code_is_none {
-            return Err(AppError::Message("device expired".into()));
+        Some(dev) => {
+            device_expire = dev.expire_time.unwrap_or(device_expire);
+            dev_id = dev.id;
+            if now > device_expire && code_is_none {
+                if let Some(reason) = &dev.trial_refused_reason {
+                    return Err(AppError::business_logic("TRIAL_REFUSED", reason.clone()));
+                }
+                return Err(AppError::business_logic("DEVICE_EXPIRED", "device expired"));
+            }
         }
     }
     if code_is_none {
@@ -271,6 +1657,10 @@ pub async fn validate_code_impl(
             code_type: CodeType::Time,
             expire_time: Some(device_expire),
             remaining_count: None,
+            license_token: None,
+            entitlements: app_features_handler::resolve_entitlements(&state.db, app.id, None)
+                .await?,
+            config: None,
         });
     }
     // find reg code
@@ -283,77 +1673,49 @@ pub async fn validate_code_impl(
         .one(&state.db)
         .await?;
     let regcode_model = regcode_model.ok_or(AppError::not_found("reg_code".to_string(), None))?;
+    if RegCodeStatus::from(regcode_model.status) == RegCodeStatus::Revoked {
+        return Err(AppError::business_logic(
+            "CODE_REVOKED",
+            format!(
+                "reg code revoked: {}",
+                regcode_model.revoked_reason.unwrap_or_default()
+            ),
+        ));
+    }
+    let entitlements =
+        app_features_handler::resolve_entitlements(&state.db, app.id, Some(&regcode_model)).await?;
     // logic by type
-    let mut active = regcode_model.clone().into_active_model();
     match regcode_model.code_type.into() {
         CodeType::Time => {
-            // time-based
-            let reg_code_expire = regcode_model.expire_time.or_else(|| {
-                //如果注册码没有过期时间，则使用注册码的过期时间
-                Some(now + chrono::Duration::days(regcode_model.valid_days as i64))
-            });
-            if let Some(exp) = reg_code_expire {
-                if now > exp {
-                    active.status = Set(RegCodeStatus::Expired.into());
-                    active.update(&state.db).await?;
-                    return Err(AppError::Message("code expired".into()));
-                }
-            }
-            // bind device id
-            if regcode_model.device_id.is_none() {
-                // create or find app_device
-                active.device_id = Set(_dev_id);
-                active.status = Set(RegCodeStatus::Used.into());
-                active.binding_time = Set(Some(Utc::now()));
-                active.expire_time = Set(reg_code_expire);
-                active.update(&state.db).await?;
-            }
+            let expire_time = redeem_time_code(state, &regcode_model, dev_id).await?;
             Ok(RegCodeValidateResp {
                 code_type: CodeType::Time,
-                expire_time: reg_code_expire,
+                expire_time: Some(expire_time),
                 remaining_count: None,
+                license_token: None,
+                entitlements,
+                config: None,
             })
         }
         CodeType::Count => {
-            // count-based
-            let total = regcode_model.total_count.unwrap_or(0);
-            let used = regcode_model.use_count;
-            if used >= total {
-                return Err(AppError::Message("code used up".into()));
+            // count-based，每次校验消耗一次
+            if regcode_model.use_count >= regcode_model.total_count.unwrap_or(0) {
+                return Err(AppError::business_logic("CODE_USED_UP", "code used up"));
             }
-            active.use_count = Set(used + 1);
-            if regcode_model.device_id.is_none() {
-                let dev = app_devices::Entity::find()
-                    .filter(
-                        app_devices::Column::AppId
-                            .eq(app.id)
-                            .and(app_devices::Column::DeviceId.eq(req.device_id.clone())),
-                    )
-                    .one(&state.db)
-                    .await?;
-                let dev_id = if let Some(d) = dev {
-                    d.id
-                } else {
-                    app_devices::ActiveModel {
-                        app_id: Set(app.id),
-                        device_id: Set(req.device_id.clone()),
-                        device_info: Set(None),
-                        bind_time: Set(Some(Utc::now())),
-                        ..Default::default()
-                    }
-                    .insert(&state.db)
-                    .await?
-                    .id
-                };
-                active.device_id = Set(Some(dev_id));
-            }
-            active.status = Set(1);
-            active.update(&state.db).await?;
+            let (usage, _) =
+                usage_handler::consume_count(state, regcode_model.id, dev_id, 1, None).await?;
             Ok(RegCodeValidateResp {
                 code_type: CodeType::Count,
                 expire_time: None,
-                remaining_count: Some(total - used - 1),
+                remaining_count: Some(usage.remaining),
+                license_token: None,
+                entitlements,
+                config: None,
             })
         }
+        CodeType::Floating => Err(AppError::business_logic(
+            "FLOATING_CODE",
+            "floating codes must use /api/reg/lease/checkout",
+        )),
     }
 }
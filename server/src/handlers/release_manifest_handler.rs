@@ -0,0 +1,308 @@
+use crate::handlers::{app_handler, app_releases_handler};
+use crate::types::app_releases_types::{ReleaseAction, ReleaseStatus};
+use crate::types::common::{AppState, Claims};
+use crate::types::error::AppError;
+use crate::types::release_manifest_types::*;
+use crate::types::response::ApiResponse;
+use crate::utils::{license, oss};
+use chrono::Utc;
+use entity::{app_release_manifests, app_releases, apps, resources};
+use salvo::{oapi::extract::JsonBody, prelude::*};
+use salvo_oapi::extract::PathParam;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, EntityTrait, IntoActiveModel, QueryFilter, Set,
+    TransactionTrait,
+};
+use std::collections::{BTreeMap, HashMap};
+use validator::Validate;
+
+// resources.res_type，见 init.sql
+const RES_TYPE_OSS: i16 = 1;
+
+fn normalize(path: &str) -> String {
+    format!("/{}", path.trim().trim_matches('/'))
+}
+
+/// 资源在清单中的路径：相对 base 的目录加文件名，不在 base 下时返回 None
+fn relative_path(base: &str, resource: &resources::Model) -> Option<String> {
+    let dir = normalize(&resource.path);
+    let rest = if dir == base {
+        ""
+    } else if base == "/" {
+        &dir[1..]
+    } else {
+        dir.strip_prefix(base)?.strip_prefix('/')?
+    };
+    Some(match rest {
+        "" => resource.name.clone(),
+        rest => format!("{}/{}", rest, resource.name),
+    })
+}
+
+/// 读取 OSS 上的文件信息，ETag 未变化时沿用已记录的 sha256
+/// 信息有变化时回写到 resources
+async fn stat_resource(
+    state: &AppState,
+    resource: &resources::Model,
+) -> Result<(i64, String), AppError> {
+    let stat = oss::head_object(&state.config.oss, &resource.object_key).await?;
+    let cached = resource
+        .sha256
+        .clone()
+        .filter(|_| resource.etag.as_deref() == Some(stat.etag.as_str()));
+    let sha256 = match stat.sha256.clone().or(cached) {
+        Some(sha256) => sha256,
+        None => oss::object_sha256(&state.config.oss, &resource.object_key, &stat).await?,
+    };
+    if resource.size != Some(stat.size)
+        || resource.sha256.as_deref() != Some(sha256.as_str())
+        || resource.etag.as_deref() != Some(stat.etag.as_str())
+    {
+        let mut active = resource.clone().into_active_model();
+        active.size = Set(Some(stat.size));
+        active.sha256 = Set(Some(sha256.clone()));
+        active.etag = Set(Some(stat.etag));
+        active.updated_at = Set(Some(Utc::now()));
+        active.update(&state.db).await?;
+    }
+    Ok((stat.size, sha256))
+}
+
+// Build the file manifest of an AppRelease from OSS resources
+#[handler]
+pub async fn build(
+    depot: &mut Depot,
+    id: PathParam<i32>,
+    req: JsonBody<BuildManifestReq>,
+) -> Result<ApiResponse<app_release_manifests::Model>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let operator_id = depot.obtain::<Claims>().ok().map(|c| c.sub);
+    let manifest = build_impl(state, id.into_inner(), req.into_inner(), operator_id).await?;
+    Ok(ApiResponse::success(manifest))
+}
+
+/// 列出 base_path 目录（含子目录）下启用的 OSS 资源，用 HeadObject 读取大小和哈希后生成清单
+pub async fn build_impl(
+    state: &AppState,
+    release_id: i32,
+    req: BuildManifestReq,
+    operator_id: Option<i32>,
+) -> Result<app_release_manifests::Model, AppError> {
+    req.validate()?;
+    let release = app_releases::Entity::find_by_id(release_id)
+        .one(&state.db)
+        .await?;
+    let release =
+        release.ok_or_else(|| AppError::not_found("app_releases".to_string(), Some(release_id)))?;
+    let base = normalize(&req.base_path);
+    let rows = resources::Entity::find()
+        .filter(resources::Column::ResType.eq(RES_TYPE_OSS))
+        .filter(resources::Column::Status.eq(1))
+        .filter(resources::Column::Path.contains(base.trim_start_matches('/')))
+        .all(&state.db)
+        .await?;
+    let mut entries = BTreeMap::new();
+    for resource in rows {
+        let Some(path) = relative_path(&base, &resource) else {
+            continue;
+        };
+        if entries.insert(path.clone(), resource).is_some() {
+            return Err(AppError::validation(format!(
+                "duplicate file in manifest: {}",
+                path
+            )));
+        }
+    }
+    if entries.is_empty() {
+        return Err(AppError::validation(format!(
+            "no oss resources under {}",
+            base
+        )));
+    }
+    let mut files = Vec::with_capacity(entries.len());
+    for (path, resource) in entries {
+        let (size, sha256) = stat_resource(state, &resource).await?;
+        files.push(ManifestFile {
+            path,
+            size,
+            sha256,
+            object_key: resource.object_key,
+            url: resource.url,
+        });
+    }
+    let total_size = files.iter().map(|f| f.size).sum();
+
+    let now = Utc::now();
+    let txn = state.db.begin().await?;
+    let exist = app_release_manifests::Entity::find()
+        .filter(app_release_manifests::Column::ReleaseId.eq(release.id))
+        .one(&txn)
+        .await?;
+    let old_count = exist
+        .as_ref()
+        .and_then(|m| m.files.as_array().map(|f| f.len()));
+    let old_size = exist.as_ref().map(|m| m.total_size);
+    let changes = serde_json::json!({
+        "base_path": [exist.as_ref().map(|m| m.base_path.clone()), base],
+        "files": [old_count, files.len()],
+        "total_size": [old_size, total_size],
+    });
+    let is_new = exist.is_none();
+    let mut manifest = match exist {
+        Some(m) => m.into_active_model(),
+        None => app_release_manifests::ActiveModel {
+            release_id: Set(release.id),
+            created_at: Set(now),
+            ..Default::default()
+        },
+    };
+    manifest.base_path = Set(base);
+    manifest.files = Set(serde_json::to_value(&files)?);
+    manifest.total_size = Set(total_size);
+    manifest.created_by = Set(operator_id);
+    manifest.updated_at = Set(now);
+    let manifest = if is_new {
+        manifest.insert(&txn).await?
+    } else {
+        manifest.update(&txn).await?
+    };
+    app_releases_handler::record_event(
+        &txn,
+        &release,
+        ReleaseAction::Manifest,
+        Some(changes),
+        operator_id,
+    )
+    .await?;
+    txn.commit().await?;
+    Ok(manifest)
+}
+
+// Get the file manifest of an AppRelease
+#[handler]
+pub async fn get_by_release(
+    depot: &mut Depot,
+    id: PathParam<i32>,
+) -> Result<ApiResponse<app_release_manifests::Model>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let manifest = find_by_release(&state.db, id.into_inner()).await?;
+    let manifest =
+        manifest.ok_or_else(|| AppError::not_found("app_release_manifests".to_string(), None))?;
+    Ok(ApiResponse::success(manifest))
+}
+
+async fn find_by_release<C: ConnectionTrait>(
+    db: &C,
+    release_id: i32,
+) -> Result<Option<app_release_manifests::Model>, AppError> {
+    let manifest = app_release_manifests::Entity::find()
+        .filter(app_release_manifests::Column::ReleaseId.eq(release_id))
+        .one(db)
+        .await?;
+    Ok(manifest)
+}
+
+fn manifest_files(manifest: &app_release_manifests::Model) -> Result<Vec<ManifestFile>, AppError> {
+    serde_json::from_value(manifest.files.clone()).map_err(|e| AppError::InternalError {
+        message: format!("invalid manifest files: {}", e),
+    })
+}
+
+/// Get the signed file manifest of a release
+#[endpoint(
+    tags("app_releases"),
+    parameters(
+        ("vercode"=i32, Query, description = "版本号"),
+        ("channel"=Option<String>, Query, description = "stable / beta，默认 stable"),
+        ("from_vercode"=Option<i32>, Query, description = "客户端当前版本号，用于计算增量文件")
+))]
+pub async fn get_manifest(
+    depot: &mut Depot,
+    app_id: PathParam<String>,
+    req: &mut Request,
+) -> Result<ApiResponse<ReleaseManifestResp>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let params = req.parse_queries::<ManifestParams>()?;
+    let resp = get_manifest_impl(state, app_id.into_inner(), params).await?;
+    Ok(ApiResponse::success(resp))
+}
+
+/// 返回已发布版本的签名清单，传入 from_vercode 时对比两个版本的文件哈希
+/// 客户端版本不是当前通道可见的已发布版本或没有清单时按全量下载处理
+pub async fn get_manifest_impl(
+    state: &AppState,
+    app_id: String,
+    params: ManifestParams,
+) -> Result<ReleaseManifestResp, AppError> {
+    let vercode = params
+        .vercode
+        .ok_or_else(|| AppError::validation("vercode is required"))?;
+    let app = apps::Entity::find()
+        .filter(apps::Column::AppId.eq(app_id))
+        .one(&state.db)
+        .await?;
+    let app = app.ok_or(AppError::not_found("apps".to_string(), None))?;
+    let app = app_handler::ensure_sign_key_impl(state, app).await?;
+    let release = app_releases::Entity::find()
+        .filter(app_releases::Column::AppId.eq(app.id))
+        .filter(app_releases::Column::Vercode.eq(vercode))
+        .filter(app_releases::Column::Channel.is_in(params.channel.visible()))
+        .filter(app_releases::Column::Status.eq(i16::from(ReleaseStatus::Published)))
+        .all(&state.db)
+        .await?;
+    // 同一版本号在两个通道都发布时优先使用请求的通道
+    let release = release
+        .into_iter()
+        .max_by_key(|r| r.channel == params.channel.as_str())
+        .ok_or(AppError::not_found("app_releases".to_string(), None))?;
+    let manifest = find_by_release(&state.db, release.id).await?;
+    let manifest =
+        manifest.ok_or_else(|| AppError::not_found("app_release_manifests".to_string(), None))?;
+    let files = manifest_files(&manifest)?;
+
+    let mut previous: HashMap<String, String> = HashMap::new();
+    if let Some(from) = params.from_vercode {
+        let from_release = app_releases::Entity::find()
+            .filter(app_releases::Column::AppId.eq(app.id))
+            .filter(app_releases::Column::Vercode.eq(from))
+            .filter(app_releases::Column::Channel.is_in(params.channel.visible()))
+            .filter(app_releases::Column::Status.eq(i16::from(ReleaseStatus::Published)))
+            .all(&state.db)
+            .await?
+            .into_iter()
+            .max_by_key(|r| r.channel == release.channel);
+        if let Some(from_release) = from_release
+            && let Some(from_manifest) = find_by_release(&state.db, from_release.id).await?
+        {
+            previous = manifest_files(&from_manifest)?
+                .into_iter()
+                .map(|f| (f.path, f.sha256))
+                .collect();
+        }
+    }
+    let changed: Vec<&ManifestFile> = files
+        .iter()
+        .filter(|f| previous.get(&f.path) != Some(&f.sha256))
+        .collect();
+    let mut removed: Vec<String> = previous
+        .into_keys()
+        .filter(|path| !files.iter().any(|f| f.path == *path))
+        .collect();
+    removed.sort();
+    let manifest = ReleaseManifest {
+        app_id: app.app_id.clone(),
+        channel: release.channel,
+        vername: release.vername,
+        vercode: release.vercode,
+        total_size: manifest.total_size,
+        from_vercode: params.from_vercode,
+        download_size: changed.iter().map(|f| f.size).sum(),
+        changed: changed.into_iter().map(|f| f.path.clone()).collect(),
+        removed,
+        files,
+        issued_at: Utc::now().timestamp(),
+    };
+    Ok(ReleaseManifestResp {
+        token: license::sign_token(&app.sign_private_key, &manifest)?,
+    })
+}
@@ -0,0 +1,354 @@
+use crate::constants;
+use crate::types::common::Claims;
+use crate::types::reseller_types::*;
+crate::import_crud_macro!();
+use entity::{apps, reseller_quota_ledger, resellers, users};
+use salvo::{oapi::extract::JsonBody, prelude::*};
+use salvo_oapi::extract::PathParam;
+use sea_orm::{ConnectionTrait, QuerySelect, TransactionTrait};
+use validator::Validate;
+
+// 代理商生成注册码的 casbin 动作，策略为 (user_id, /apps/<app_id>, resell)
+const RESELL_ACTION: &str = "resell";
+
+fn app_object(app_id: i32) -> String {
+    format!("/apps/{}", app_id)
+}
+
+/// 通过 casbin 策略检查代理商是否可为该应用生成注册码
+pub async fn check_app_scope(
+    state: &AppState,
+    reseller: &resellers::Model,
+    app_id: i32,
+) -> Result<(), AppError> {
+    let allowed = state
+        .casbin
+        .enforce(&reseller.user_id.to_string(), &app_object(app_id), RESELL_ACTION)
+        .await?;
+    if !allowed {
+        return Err(AppError::Forbidden {
+            action: format!("resell app {}", app_id),
+        });
+    }
+    Ok(())
+}
+
+/// 代理商已授权的应用
+pub async fn assigned_app_ids(state: &AppState, user_id: i32) -> Result<Vec<i32>, AppError> {
+    let policies = state
+        .casbin
+        .get_filtered_policy(0, vec![user_id.to_string()])
+        .await?;
+    let mut app_ids: Vec<i32> = policies
+        .iter()
+        .filter(|p| p.get(2).map(String::as_str) == Some(RESELL_ACTION))
+        .filter_map(|p| p.get(1)?.strip_prefix("/apps/")?.parse().ok())
+        .collect();
+    app_ids.sort_unstable();
+    Ok(app_ids)
+}
+
+/// 在代理商行锁内变动额度并记录流水，余额不足时报错
+pub async fn change_quota<C: ConnectionTrait>(
+    db: &C,
+    reseller_id: i32,
+    change: QuotaChange,
+) -> Result<reseller_quota_ledger::Model, AppError> {
+    let reseller = resellers::Entity::find_by_id(reseller_id)
+        .lock_exclusive()
+        .one(db)
+        .await?;
+    let reseller =
+        reseller.ok_or_else(|| AppError::not_found("resellers".to_string(), Some(reseller_id)))?;
+    let balance = reseller.quota_balance + change.change;
+    if balance < 0 {
+        return Err(AppError::business_logic(
+            "QUOTA_EXCEEDED",
+            format!("only {} codes left in quota", reseller.quota_balance),
+        ));
+    }
+    let now = Utc::now();
+    let mut active = reseller.into_active_model();
+    active.quota_balance = Set(balance);
+    active.updated_at = Set(now);
+    active.update(db).await?;
+    let ledger = reseller_quota_ledger::ActiveModel {
+        reseller_id: Set(reseller_id),
+        change: Set(change.change),
+        balance_after: Set(balance),
+        kind: Set(change.kind.as_str().to_string()),
+        batch_id: Set(change.batch_id),
+        remark: Set(change.remark),
+        created_by: Set(change.created_by),
+        created_at: Set(now),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+    Ok(ledger)
+}
+
+// Create reseller
+#[handler]
+pub async fn add(
+    depot: &mut Depot,
+    req: JsonBody<CreateResellerReq>,
+) -> Result<ApiResponse<ResellerInfo>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let reseller = add_impl(state, req.into_inner()).await?;
+    Ok(ApiResponse::success(reseller))
+}
+
+pub async fn add_impl(state: &AppState, req: CreateResellerReq) -> Result<ResellerInfo, AppError> {
+    req.validate()?;
+    let user = users::Entity::find_by_id(req.user_id).one(&state.db).await?;
+    let user = user.ok_or_else(|| AppError::not_found("users".to_string(), Some(req.user_id)))?;
+    if user.role_id == constants::ADMIN_ROLE_ID {
+        return Err(AppError::validation("admin users can not be resellers"));
+    }
+    for app_id in &req.app_ids {
+        let app = apps::Entity::find_by_id(*app_id).one(&state.db).await?;
+        app.ok_or_else(|| AppError::not_found("apps".to_string(), Some(*app_id)))?;
+    }
+    let now = Utc::now();
+    let txn = state.db.begin().await?;
+    let reseller = resellers::ActiveModel {
+        user_id: Set(user.id),
+        name: Set(req.name),
+        contact: Set(req.contact),
+        quota_balance: Set(0),
+        status: Set(RESELLER_ACTIVE),
+        created_at: Set(now),
+        updated_at: Set(now),
+        ..Default::default()
+    }
+    .insert(&txn)
+    .await?;
+    // 登录令牌中的角色取自 users.role_id，同时写入 casbin 角色继承
+    let mut active = user.into_active_model();
+    active.role_id = Set(constants::RESELLER_ROLE_ID);
+    active.update(&txn).await?;
+    txn.commit().await?;
+    let user_id = reseller.user_id.to_string();
+    state
+        .casbin
+        .add_role_for_user(&user_id, constants::RESELLER_ROLE)
+        .await?;
+    for app_id in &req.app_ids {
+        state
+            .casbin
+            .add_policy(&user_id, &app_object(*app_id), RESELL_ACTION)
+            .await?;
+    }
+    reseller_info(state, reseller).await
+}
+
+pub async fn reseller_info(
+    state: &AppState,
+    reseller: resellers::Model,
+) -> Result<ResellerInfo, AppError> {
+    let app_ids = assigned_app_ids(state, reseller.user_id).await?;
+    Ok(ResellerInfo { reseller, app_ids })
+}
+
+// Get resellers list
+#[handler]
+pub async fn get_list(
+    depot: &mut Depot,
+    req: &mut Request,
+) -> Result<ApiResponse<PagingResponse<resellers::Model>>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let params = req.parse_queries::<SearchResellersParams>()?;
+    let list = get_list_impl(state, params).await?;
+    Ok(ApiResponse::success(list))
+}
+
+pub async fn get_list_impl(
+    state: &AppState,
+    params: SearchResellersParams,
+) -> Result<PagingResponse<resellers::Model>, AppError> {
+    let page = params.pagination.page.unwrap_or(1);
+    let page_size = params.pagination.page_size.unwrap_or(20);
+    let mut query = resellers::Entity::find().order_by_desc(resellers::Column::Id);
+    crate::filter_if_some!(query, resellers::Column::Name, params.name, contains);
+    crate::filter_if_some!(query, resellers::Column::Status, params.status, eq);
+    let paginator = query.paginate(&state.db, page_size);
+    let total = paginator.num_items().await?;
+    let list = paginator.fetch_page(page - 1).await?;
+    Ok(PagingResponse { list, total, page })
+}
+
+// Get reseller by ID
+#[handler]
+pub async fn get_by_id(
+    depot: &mut Depot,
+    id: PathParam<i32>,
+) -> Result<ApiResponse<ResellerInfo>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let reseller = find_reseller(state, id.into_inner()).await?;
+    let info = reseller_info(state, reseller).await?;
+    Ok(ApiResponse::success(info))
+}
+
+pub async fn find_reseller(state: &AppState, id: i32) -> Result<resellers::Model, AppError> {
+    let reseller = resellers::Entity::find_by_id(id).one(&state.db).await?;
+    reseller.ok_or_else(|| AppError::not_found("resellers".to_string(), Some(id)))
+}
+
+// Update reseller
+#[handler]
+pub async fn update(
+    depot: &mut Depot,
+    id: PathParam<i32>,
+    req: JsonBody<UpdateResellerReq>,
+) -> Result<ApiResponse<ResellerInfo>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let reseller = update_impl(state, id.into_inner(), req.into_inner()).await?;
+    Ok(ApiResponse::success(reseller))
+}
+
+pub async fn update_impl(
+    state: &AppState,
+    id: i32,
+    req: UpdateResellerReq,
+) -> Result<ResellerInfo, AppError> {
+    req.validate()?;
+    if req
+        .status
+        .is_some_and(|s| s != RESELLER_ACTIVE && s != RESELLER_DISABLED)
+    {
+        return Err(AppError::validation("status must be 0 or 1"));
+    }
+    let reseller = find_reseller(state, id).await?;
+    let mut active = reseller.into_active_model();
+    crate::update_field_if_some!(active, name, req.name);
+    crate::update_field_if_some!(active, contact, req.contact, option);
+    crate::update_field_if_some!(active, status, req.status);
+    active.updated_at = Set(Utc::now());
+    let reseller = active.update(&state.db).await?;
+    reseller_info(state, reseller).await
+}
+
+// Credit or adjust reseller quota
+#[handler]
+pub async fn adjust_quota(
+    depot: &mut Depot,
+    id: PathParam<i32>,
+    req: JsonBody<AdjustQuotaReq>,
+) -> Result<ApiResponse<reseller_quota_ledger::Model>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let created_by = depot.obtain::<Claims>().ok().map(|c| c.sub);
+    let ledger = adjust_quota_impl(state, id.into_inner(), req.into_inner(), created_by).await?;
+    Ok(ApiResponse::success(ledger))
+}
+
+pub async fn adjust_quota_impl(
+    state: &AppState,
+    id: i32,
+    req: AdjustQuotaReq,
+    created_by: Option<i32>,
+) -> Result<reseller_quota_ledger::Model, AppError> {
+    if req.change == 0 {
+        return Err(AppError::validation("change must not be zero"));
+    }
+    match req.kind {
+        QuotaKind::Generate => {
+            return Err(AppError::validation("generate entries are written by code generation"));
+        }
+        QuotaKind::Purchase if req.change < 0 => {
+            return Err(AppError::validation("purchase must be positive"));
+        }
+        _ => {}
+    }
+    let txn = state.db.begin().await?;
+    let ledger = change_quota(
+        &txn,
+        id,
+        QuotaChange {
+            change: req.change,
+            kind: req.kind,
+            batch_id: None,
+            remark: req.remark,
+            created_by,
+        },
+    )
+    .await?;
+    txn.commit().await?;
+    Ok(ledger)
+}
+
+// Get quota ledger of a reseller
+#[handler]
+pub async fn get_ledger(
+    depot: &mut Depot,
+    id: PathParam<i32>,
+    req: &mut Request,
+) -> Result<ApiResponse<PagingResponse<reseller_quota_ledger::Model>>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let params = req.parse_queries::<SearchQuotaLedgerParams>()?;
+    let list = get_ledger_impl(state, id.into_inner(), params).await?;
+    Ok(ApiResponse::success(list))
+}
+
+pub async fn get_ledger_impl(
+    state: &AppState,
+    reseller_id: i32,
+    params: SearchQuotaLedgerParams,
+) -> Result<PagingResponse<reseller_quota_ledger::Model>, AppError> {
+    let page = params.pagination.page.unwrap_or(1);
+    let page_size = params.pagination.page_size.unwrap_or(20);
+    let mut query = reseller_quota_ledger::Entity::find()
+        .filter(reseller_quota_ledger::Column::ResellerId.eq(reseller_id))
+        .order_by_desc(reseller_quota_ledger::Column::Id);
+    crate::filter_if_some!(
+        query,
+        reseller_quota_ledger::Column::Kind,
+        params.kind.map(QuotaKind::as_str),
+        eq
+    );
+    let paginator = query.paginate(&state.db, page_size);
+    let total = paginator.num_items().await?;
+    let list = paginator.fetch_page(page - 1).await?;
+    Ok(PagingResponse { list, total, page })
+}
+
+// Allow a reseller to generate codes for an app
+#[handler]
+pub async fn assign_app(
+    depot: &mut Depot,
+    id: PathParam<i32>,
+    req: JsonBody<AssignResellerAppReq>,
+) -> Result<ApiResponse<ResellerInfo>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let reseller = find_reseller(state, id.into_inner()).await?;
+    let app_id = req.into_inner().app_id;
+    let app = apps::Entity::find_by_id(app_id).one(&state.db).await?;
+    app.ok_or_else(|| AppError::not_found("apps".to_string(), Some(app_id)))?;
+    state
+        .casbin
+        .add_policy(&reseller.user_id.to_string(), &app_object(app_id), RESELL_ACTION)
+        .await?;
+    let info = reseller_info(state, reseller).await?;
+    Ok(ApiResponse::success(info))
+}
+
+// Revoke an app from a reseller, existing codes are kept
+#[handler]
+pub async fn unassign_app(
+    depot: &mut Depot,
+    id: PathParam<i32>,
+    app_id: PathParam<i32>,
+) -> Result<ApiResponse<ResellerInfo>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let reseller = find_reseller(state, id.into_inner()).await?;
+    state
+        .casbin
+        .remove_policy(
+            &reseller.user_id.to_string(),
+            &app_object(app_id.into_inner()),
+            RESELL_ACTION,
+        )
+        .await?;
+    let info = reseller_info(state, reseller).await?;
+    Ok(ApiResponse::success(info))
+}
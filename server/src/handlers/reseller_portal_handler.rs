@@ -0,0 +1,304 @@
+use crate::handlers::{reg_code_batches_handler, reg_codes_handler, reseller_handler};
+use crate::types::app_features_types::Entitlements;
+use crate::types::common::{AppState, Claims, PagingResponse};
+use crate::types::error::AppError;
+use crate::types::reg_code_batches_types::*;
+use crate::types::reg_codes_types::*;
+use crate::types::reseller_types::*;
+use crate::types::response::ApiResponse;
+use crate::utils::export::render_attachment;
+use chrono::Utc;
+use entity::{apps, products, reg_code_batches, reg_codes, reseller_quota_ledger, resellers};
+use salvo::prelude::*;
+use salvo_oapi::extract::PathParam;
+use sea_orm::sea_query::Expr;
+use sea_orm::{
+    ColumnTrait, EntityTrait, JoinType, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect,
+    RelationTrait,
+};
+use validator::Validate;
+
+// 代理商生成的注册码只能绑定一台设备
+const RESELLER_CODE_MAX_DEVICES: i32 = 1;
+
+/// 当前登录用户对应的代理商，停用后不能再使用代理商接口
+async fn current_reseller(state: &AppState, user_id: i32) -> Result<resellers::Model, AppError> {
+    let reseller = resellers::Entity::find()
+        .filter(resellers::Column::UserId.eq(user_id))
+        .one(&state.db)
+        .await?;
+    let reseller = reseller.ok_or_else(|| AppError::Forbidden {
+        action: "reseller only".to_string(),
+    })?;
+    if reseller.status != RESELLER_ACTIVE {
+        return Err(AppError::Forbidden {
+            action: "reseller disabled".to_string(),
+        });
+    }
+    Ok(reseller)
+}
+
+/// 代理商只能访问自己生成的批次，其它批次按不存在处理
+async fn find_own_batch(
+    state: &AppState,
+    reseller: &resellers::Model,
+    id: i32,
+) -> Result<reg_code_batches::Model, AppError> {
+    let batch = reg_code_batches_handler::get_by_id_impl(state, id).await?;
+    if batch.reseller_id != Some(reseller.id) {
+        return Err(AppError::not_found("reg_code_batches".to_string(), Some(id)));
+    }
+    Ok(batch)
+}
+
+// Get current reseller with quota balance
+#[handler]
+pub async fn get_me(depot: &mut Depot) -> Result<ApiResponse<ResellerInfo>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let claims = depot.obtain::<Claims>().unwrap();
+    let reseller = current_reseller(state, claims.sub).await?;
+    let info = reseller_handler::reseller_info(state, reseller).await?;
+    Ok(ApiResponse::success(info))
+}
+
+// Get apps the reseller can generate codes for
+#[handler]
+pub async fn get_apps(depot: &mut Depot) -> Result<ApiResponse<Vec<ResellerAppInfo>>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let claims = depot.obtain::<Claims>().unwrap();
+    let reseller = current_reseller(state, claims.sub).await?;
+    let app_ids = reseller_handler::assigned_app_ids(state, reseller.user_id).await?;
+    let list = apps::Entity::find()
+        .filter(apps::Column::Id.is_in(app_ids.clone()))
+        .all(&state.db)
+        .await?;
+    let products = products::Entity::find()
+        .filter(products::Column::AppId.is_in(app_ids))
+        .filter(products::Column::DeletedAt.is_null())
+        .order_by_asc(products::Column::Id)
+        .all(&state.db)
+        .await?;
+    let mut list: Vec<ResellerAppInfo> = list.into_iter().map(ResellerAppInfo::from).collect();
+    for product in products {
+        if let Some(app) = list.iter_mut().find(|a| a.id == product.app_id) {
+            app.products.push(product.into());
+        }
+    }
+    Ok(ApiResponse::success(list))
+}
+
+// Generate codes within the reseller quota
+#[handler]
+pub async fn batch_add(
+    depot: &mut Depot,
+    req: &mut Request,
+    res: &mut Response,
+) -> Result<(), AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let claims = depot.obtain::<Claims>().unwrap();
+    let params = req.parse_queries::<BatchCreateRegCodesParams>()?;
+    let json = req.parse_json::<ResellerBatchCreateReq>().await?;
+    let rows = batch_add_impl(state, claims.sub, json).await?;
+    match params.export {
+        Some(format) => {
+            let file_name = format!("reg_codes_{}", Utc::now().format("%Y%m%d%H%M%S"));
+            render_attachment(res, format, &file_name, &rows)?;
+        }
+        None => res.render(Json(ApiResponse::success(BatchCreateRegCodesResp {
+            count: rows.len(),
+            codes: rows,
+        }))),
+    }
+    Ok(())
+}
+
+/// 按商品生成注册码，注册码的类型、时长、设备数、功能授权和格式不由代理商决定
+pub async fn batch_add_impl(
+    state: &AppState,
+    user_id: i32,
+    req: ResellerBatchCreateReq,
+) -> Result<Vec<RegCodeExportRow>, AppError> {
+    req.validate()?;
+    let reseller = current_reseller(state, user_id).await?;
+    let product = products::Entity::find_by_id(req.product_id)
+        .filter(products::Column::DeletedAt.is_null())
+        .one(&state.db)
+        .await?
+        .ok_or_else(|| AppError::not_found("products".to_string(), Some(req.product_id)))?;
+    reseller_handler::check_app_scope(state, &reseller, product.app_id).await?;
+    if let Some(batch_id) = req.batch_id {
+        find_own_batch(state, &reseller, batch_id).await?;
+    }
+    // 先按当前余额快速失败，事务内加锁后再次校验
+    if req.count as i64 > reseller.quota_balance as i64 {
+        return Err(AppError::business_logic(
+            "QUOTA_EXCEEDED",
+            format!("only {} codes left in quota", reseller.quota_balance),
+        ));
+    }
+    let entitlements = product
+        .entitlements
+        .as_ref()
+        .map(|e| Entitlements::from_json(Some(e)))
+        .transpose()?;
+    let req = BatchCreateRegCodesReq {
+        app_id: product.app_id,
+        count: req.count,
+        valid_days: product.add_valid_days,
+        max_devices: RESELLER_CODE_MAX_DEVICES,
+        code_type: CodeType::Time,
+        expire_time: None,
+        total_count: None,
+        entitlements,
+        format: reg_code::CodeFormat::default(),
+        batch_id: req.batch_id,
+        batch_name: req.batch_name,
+        channel: req.channel.or_else(|| Some(reseller.name.clone())),
+        notes: req.notes,
+    };
+    reg_codes_handler::batch_add_impl(state, req, Some(user_id), Some(&reseller)).await
+}
+
+// Get batches generated by the reseller
+#[handler]
+pub async fn get_batches(
+    depot: &mut Depot,
+    req: &mut Request,
+) -> Result<ApiResponse<PagingResponse<reg_code_batches::Model>>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let claims = depot.obtain::<Claims>().unwrap();
+    let reseller = current_reseller(state, claims.sub).await?;
+    let mut params = req.parse_queries::<SearchRegCodeBatchesParams>()?;
+    params.reseller_id = Some(reseller.id);
+    let list = reg_code_batches_handler::get_list_impl(state, params).await?;
+    Ok(ApiResponse::success(list))
+}
+
+// Get redemption statistics of an own batch
+#[handler]
+pub async fn get_batch_stats(
+    depot: &mut Depot,
+    id: PathParam<i32>,
+) -> Result<ApiResponse<RegCodeBatchStats>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let claims = depot.obtain::<Claims>().unwrap();
+    let reseller = current_reseller(state, claims.sub).await?;
+    let batch = find_own_batch(state, &reseller, id.into_inner()).await?;
+    let stats = reg_code_batches_handler::get_stats_impl(state, batch.id).await?;
+    Ok(ApiResponse::success(stats))
+}
+
+// Export codes of an own batch
+#[handler]
+pub async fn export_batch(
+    depot: &mut Depot,
+    id: PathParam<i32>,
+    req: &mut Request,
+    res: &mut Response,
+) -> Result<(), AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let claims = depot.obtain::<Claims>().unwrap();
+    let reseller = current_reseller(state, claims.sub).await?;
+    let params = req.parse_queries::<ExportRegCodeBatchParams>()?;
+    let batch = find_own_batch(state, &reseller, id.into_inner()).await?;
+    let rows = reg_code_batches_handler::export_impl(state, batch.id).await?;
+    let file_name = format!(
+        "reg_code_batch_{}_{}",
+        batch.id,
+        Utc::now().format("%Y%m%d%H%M%S")
+    );
+    render_attachment(res, params.format, &file_name, &rows)
+}
+
+// Get codes generated by the reseller
+#[handler]
+pub async fn get_codes(
+    depot: &mut Depot,
+    req: &mut Request,
+) -> Result<ApiResponse<PagingResponse<RegCodeInfo>>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let claims = depot.obtain::<Claims>().unwrap();
+    let reseller = current_reseller(state, claims.sub).await?;
+    let mut params = req.parse_queries::<SearchRegCodesParams>()?;
+    params.reseller_id = Some(reseller.id);
+    let list = reg_codes_handler::get_list_impl(state, params).await?;
+    Ok(ApiResponse::success(list))
+}
+
+// Get own quota ledger
+#[handler]
+pub async fn get_ledger(
+    depot: &mut Depot,
+    req: &mut Request,
+) -> Result<ApiResponse<PagingResponse<reseller_quota_ledger::Model>>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let claims = depot.obtain::<Claims>().unwrap();
+    let reseller = current_reseller(state, claims.sub).await?;
+    let params = req.parse_queries::<SearchQuotaLedgerParams>()?;
+    let list = reseller_handler::get_ledger_impl(state, reseller.id, params).await?;
+    Ok(ApiResponse::success(list))
+}
+
+// Get own quota and redemption summary
+#[handler]
+pub async fn get_stats(depot: &mut Depot) -> Result<ApiResponse<ResellerStats>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let claims = depot.obtain::<Claims>().unwrap();
+    let reseller = current_reseller(state, claims.sub).await?;
+    let stats = get_stats_impl(state, reseller).await?;
+    Ok(ApiResponse::success(stats))
+}
+
+pub async fn get_stats_impl(
+    state: &AppState,
+    reseller: resellers::Model,
+) -> Result<ResellerStats, AppError> {
+    let generated = reseller_quota_ledger::Entity::find()
+        .select_only()
+        .column_as(Expr::cust("COALESCE(SUM(-change), 0)::BIGINT"), "generated")
+        .filter(reseller_quota_ledger::Column::ResellerId.eq(reseller.id))
+        .filter(reseller_quota_ledger::Column::Kind.eq(QuotaKind::Generate.as_str()))
+        .into_tuple::<i64>()
+        .one(&state.db)
+        .await?
+        .unwrap_or_default();
+    let batches = reg_code_batches::Entity::find()
+        .filter(reg_code_batches::Column::ResellerId.eq(reseller.id))
+        .count(&state.db)
+        .await?;
+    let (codes, redeemed, revoked) = reg_codes::Entity::find()
+        .select_only()
+        .column_as(Expr::cust("COUNT(*)"), "codes")
+        .column_as(
+            Expr::cust("COUNT(*) FILTER (WHERE reg_codes.binding_time IS NOT NULL)"),
+            "redeemed",
+        )
+        .column_as(
+            Expr::cust(format!(
+                "COUNT(*) FILTER (WHERE reg_codes.status = {})",
+                i16::from(RegCodeStatus::Revoked)
+            )),
+            "revoked",
+        )
+        .join(JoinType::InnerJoin, reg_codes::Relation::RegCodeBatches.def())
+        .filter(reg_code_batches::Column::ResellerId.eq(reseller.id))
+        .into_tuple::<(i64, i64, i64)>()
+        .one(&state.db)
+        .await?
+        .unwrap_or_default();
+    let redeem_rate = if codes > 0 {
+        (redeemed as f64 * 10000.0 / codes as f64).round() / 100.0
+    } else {
+        0.0
+    };
+    Ok(ResellerStats {
+        reseller_id: reseller.id,
+        quota_balance: reseller.quota_balance,
+        generated,
+        batches: batches as i64,
+        codes,
+        redeemed,
+        revoked,
+        redeem_rate,
+    })
+}
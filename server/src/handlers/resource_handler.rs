@@ -30,6 +30,8 @@ pub async fn add_impl(
         object_key: Set(req.object_key),
         url: Set(req.url),
         path: Set(req.path),
+        file_type: Set(req.file_type),
+        res_type: Set(req.res_type),
         tags: Set(req.tags),
         status: Set(req.status),
         remark: Set(req.remark),
@@ -63,6 +65,8 @@ pub async fn update_impl(
     crate::update_field_if_some!(model, object_key, req.object_key);
     crate::update_field_if_some!(model, url, req.url);
     crate::update_field_if_some!(model, path, req.path);
+    crate::update_field_if_some!(model, file_type, req.file_type);
+    crate::update_field_if_some!(model, res_type, req.res_type);
     crate::update_field_if_some!(model, tags, req.tags, option);
     crate::update_field_if_some!(model, remark, req.remark, option);
     crate::update_field_if_some!(model, status, req.status);
@@ -0,0 +1,241 @@
+use crate::handlers::reg_codes_handler;
+use crate::types::common::AppState;
+use crate::types::error::AppError;
+use crate::types::reg_codes_types::*;
+use crate::types::response::ApiResponse;
+use crate::utils::client::ClientInfo;
+use chrono::{Duration, Utc};
+use entity::{app_devices, apps, device_renewals, reg_code_devices, reg_code_transfers, reg_codes};
+use salvo::{oapi::extract::JsonBody, prelude::*};
+use salvo_oapi::extract::PathParam;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, EntityTrait, IntoActiveModel, QueryFilter,
+    QueryOrder, QuerySelect, Set, TransactionTrait,
+};
+
+/// 写入一条解绑/转移记录
+pub async fn record_transfer<C: ConnectionTrait>(
+    db: &C,
+    reg_code: &reg_codes::Model,
+    from_device: &str,
+    to_device: Option<String>,
+    source: TransferSource,
+    ip: Option<String>,
+    operator_id: Option<i32>,
+) -> Result<(), AppError> {
+    reg_code_transfers::ActiveModel {
+        app_id: Set(reg_code.app_id),
+        reg_code_id: Set(reg_code.id),
+        from_device: Set(from_device.to_string()),
+        to_device: Set(to_device),
+        source: Set(source.into()),
+        ip: Set(ip),
+        operator_id: Set(operator_id),
+        created_at: Set(Utc::now()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+    Ok(())
+}
+
+/// 撤回时长注册码给设备叠加的时长，避免解绑后设备仍以试用身份用到原有效期
+/// 兑换前设备仍有效时只扣除叠加部分，否则回到兑换时刻
+pub(crate) async fn revoke_renewal<C: ConnectionTrait>(
+    db: &C,
+    reg_code_id: i32,
+    device_id: i32,
+) -> Result<(), AppError> {
+    let renewal = device_renewals::Entity::find()
+        .filter(
+            device_renewals::Column::RegCodeId
+                .eq(reg_code_id)
+                .and(device_renewals::Column::DeviceId.eq(device_id)),
+        )
+        .one(db)
+        .await?;
+    let Some(renewal) = renewal else {
+        return Ok(());
+    };
+    let base = renewal
+        .previous_expire
+        .filter(|t| *t > renewal.created_at)
+        .unwrap_or(renewal.created_at);
+    let added = (renewal.new_expire - base).max(Duration::zero());
+    let device = app_devices::Entity::find_by_id(device_id)
+        .lock_exclusive()
+        .one(db)
+        .await?;
+    if let Some(device) = device
+        && let Some(expire) = device.expire_time
+    {
+        let mut device = device.into_active_model();
+        device.expire_time = Set(Some(expire - added));
+        device.update(db).await?;
+    }
+    device_renewals::Entity::delete_by_id(renewal.id)
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+/// 用户自助解绑设备，受应用的解绑次数和冷却时间限制
+/// 解绑后释放设备名额，撤回注册码给该设备叠加的时长，并记录到 reg_code_transfers
+pub async fn release_device(
+    state: &AppState,
+    reg_code_id: i32,
+    device: &app_devices::Model,
+    source: TransferSource,
+    ip: Option<String>,
+) -> Result<RegCodeDeactivateResp, AppError> {
+    let now = Utc::now();
+    let txn = state.db.begin().await?;
+    // 锁住注册码行，串行化同一注册码的解绑，避免并发突破次数限制
+    let reg_code = reg_codes::Entity::find_by_id(reg_code_id)
+        .lock_exclusive()
+        .one(&txn)
+        .await?;
+    let reg_code =
+        reg_code.ok_or_else(|| AppError::not_found("reg_codes".to_string(), Some(reg_code_id)))?;
+    if CodeType::from(reg_code.code_type) == CodeType::Floating {
+        return Err(AppError::business_logic(
+            "FLOATING_CODE",
+            "floating codes must use /api/reg/lease/checkin",
+        ));
+    }
+    let app = apps::Entity::find_by_id(reg_code.app_id).one(&txn).await?;
+    let app = app.ok_or_else(|| AppError::not_found("apps".to_string(), Some(reg_code.app_id)))?;
+    let binding = reg_code_devices::Entity::find()
+        .filter(
+            reg_code_devices::Column::RegCodeId
+                .eq(reg_code.id)
+                .and(reg_code_devices::Column::DeviceId.eq(device.id)),
+        )
+        .one(&txn)
+        .await?;
+    let binding = binding
+        .ok_or_else(|| AppError::not_found("reg_code_devices".to_string(), Some(device.id)))?;
+    if app.max_transfers <= 0 {
+        return Err(AppError::business_logic(
+            "TRANSFER_DISABLED",
+            "self-service transfer is disabled, contact support",
+        ));
+    }
+    let history: Vec<_> = reg_code_transfers::Entity::find()
+        .filter(reg_code_transfers::Column::RegCodeId.eq(reg_code.id))
+        .order_by_desc(reg_code_transfers::Column::CreatedAt)
+        .all(&txn)
+        .await?
+        .into_iter()
+        .filter(|t| TransferSource::from(t.source).is_self_service())
+        .collect();
+    let used = history.len() as i32;
+    if used >= app.max_transfers {
+        return Err(AppError::business_logic(
+            "TRANSFER_LIMIT_REACHED",
+            format!(
+                "transfer limit reached ({}), contact support",
+                app.max_transfers
+            ),
+        ));
+    }
+    let cooldown = Duration::hours(app.transfer_cooldown_hours.max(0) as i64);
+    if let Some(last) = history.first() {
+        let next = last.created_at + cooldown;
+        if now < next {
+            return Err(AppError::business_logic(
+                "TRANSFER_COOLDOWN",
+                format!("transfer not available until {}", next.to_rfc3339()),
+            ));
+        }
+    }
+    reg_code_devices::Entity::delete_by_id(binding.id)
+        .exec(&txn)
+        .await?;
+    revoke_renewal(&txn, reg_code.id, device.id).await?;
+    record_transfer(&txn, &reg_code, &device.device_id, None, source, ip, None).await?;
+    txn.commit().await?;
+    Ok(RegCodeDeactivateResp {
+        transfers_used: used + 1,
+        transfers_left: (app.max_transfers - used - 1).max(0),
+        next_transfer_at: now + cooldown,
+    })
+}
+
+/// Deactivate the current device to free its slot for another device
+#[endpoint(tags("reg_codes"))]
+pub async fn deactivate(
+    depot: &mut Depot,
+    req: JsonBody<RegCodeValidateV2Req>,
+    request: &mut Request,
+) -> Result<ApiResponse<RegCodeValidateV2Resp>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let client = ClientInfo::from_request(request, &state.config.trusted_proxies);
+    let resp = deactivate_impl(state, req.into_inner(), &client).await?;
+    Ok(ApiResponse::success(resp))
+}
+
+/// 客户端解绑当前设备，签名方式与 v2 校验相同，需同时提供注册码
+pub async fn deactivate_impl(
+    state: &AppState,
+    req: RegCodeValidateV2Req,
+    client: &ClientInfo,
+) -> Result<RegCodeValidateV2Resp, AppError> {
+    let app = reg_codes_handler::verify_signed_request(state, &req).await?;
+    let code = req
+        .code
+        .as_deref()
+        .filter(|c| !c.is_empty())
+        .ok_or_else(|| AppError::validation("code is required"))?;
+    let reg_code = reg_codes::Entity::find()
+        .filter(
+            reg_codes::Column::Code
+                .eq(code)
+                .and(reg_codes::Column::AppId.eq(app.id)),
+        )
+        .one(&state.db)
+        .await?;
+    let reg_code = reg_code.ok_or(AppError::not_found("reg_codes".to_string(), None))?;
+    let device = app_devices::Entity::find()
+        .filter(
+            app_devices::Column::AppId
+                .eq(app.id)
+                .and(app_devices::Column::DeviceId.eq(req.device_id.clone())),
+        )
+        .one(&state.db)
+        .await?;
+    let device = device.ok_or(AppError::not_found("app_devices".to_string(), None))?;
+    let result = release_device(
+        state,
+        reg_code.id,
+        &device,
+        TransferSource::Client,
+        Some(client.ip.clone()).filter(|ip| !ip.is_empty()),
+    )
+    .await?;
+    reg_codes_handler::sign_response(&app.app_valid_key, req.nonce, &result)
+}
+
+// List device transfers of a RegCode
+#[handler]
+pub async fn get_list(
+    depot: &mut Depot,
+    id: PathParam<i32>,
+) -> Result<ApiResponse<Vec<RegCodeTransferInfo>>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let list = get_list_impl(state, id.into_inner()).await?;
+    Ok(ApiResponse::success(list))
+}
+
+pub async fn get_list_impl(
+    state: &AppState,
+    reg_code_id: i32,
+) -> Result<Vec<RegCodeTransferInfo>, AppError> {
+    let rows = reg_code_transfers::Entity::find()
+        .filter(reg_code_transfers::Column::RegCodeId.eq(reg_code_id))
+        .order_by_desc(reg_code_transfers::Column::CreatedAt)
+        .order_by_desc(reg_code_transfers::Column::Id)
+        .all(&state.db)
+        .await?;
+    Ok(rows.into_iter().map(RegCodeTransferInfo::from).collect())
+}
@@ -0,0 +1,186 @@
+use crate::types::app_devices_types::DeviceInfo;
+use crate::types::common::AppState;
+use crate::types::error::AppError;
+use crate::types::response::ApiResponse;
+use crate::types::trial_types::*;
+use chrono::Utc;
+use entity::{app_devices, apps, reg_code_devices, trial_policies};
+use salvo::oapi::extract::JsonBody;
+use salvo::prelude::*;
+use salvo_oapi::extract::PathParam;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, EntityTrait, IntoActiveModel, PaginatorTrait, QueryFilter, Set,
+};
+
+// Get trial policy of an app
+#[handler]
+pub async fn get_policy(
+    depot: &mut Depot,
+    id: PathParam<i32>,
+) -> Result<ApiResponse<Option<trial_policies::Model>>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let policy = get_policy_impl(state, id.into_inner()).await?;
+    Ok(ApiResponse::success(policy))
+}
+
+pub async fn get_policy_impl(
+    state: &AppState,
+    app_id: i32,
+) -> Result<Option<trial_policies::Model>, AppError> {
+    let policy = trial_policies::Entity::find()
+        .filter(trial_policies::Column::AppId.eq(app_id))
+        .one(&state.db)
+        .await?;
+    Ok(policy)
+}
+
+// Create or update trial policy of an app
+#[handler]
+pub async fn upsert_policy(
+    depot: &mut Depot,
+    id: PathParam<i32>,
+    req: JsonBody<UpsertTrialPolicyReq>,
+) -> Result<ApiResponse<trial_policies::Model>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let policy = upsert_policy_impl(state, id.into_inner(), req.into_inner()).await?;
+    Ok(ApiResponse::success(policy))
+}
+
+pub async fn upsert_policy_impl(
+    state: &AppState,
+    app_id: i32,
+    req: UpsertTrialPolicyReq,
+) -> Result<trial_policies::Model, AppError> {
+    if req.window_hours.is_some_and(|v| v <= 0) {
+        return Err(AppError::validation("window_hours must be positive"));
+    }
+    if req.max_trials_per_ip.is_some_and(|v| v < 0)
+        || req.max_trials_per_subnet.is_some_and(|v| v < 0)
+    {
+        return Err(AppError::validation("max trials must not be negative"));
+    }
+    if req.subnet_prefix.is_some_and(|v| !(8..=32).contains(&v)) {
+        return Err(AppError::validation("subnet_prefix must be between 8 and 32"));
+    }
+    if req.fingerprint_threshold.is_some_and(|v| !(0..=100).contains(&v)) {
+        return Err(AppError::validation("fingerprint_threshold must be between 0 and 100"));
+    }
+    let app = apps::Entity::find_by_id(app_id).one(&state.db).await?;
+    app.ok_or_else(|| AppError::not_found("apps".to_string(), Some(app_id)))?;
+    let now = Utc::now();
+    let policy = match get_policy_impl(state, app_id).await? {
+        Some(policy) => {
+            let mut active = policy.into_active_model();
+            crate::update_field_if_some!(active, window_hours, req.window_hours);
+            crate::update_field_if_some!(active, max_trials_per_ip, req.max_trials_per_ip);
+            crate::update_field_if_some!(active, max_trials_per_subnet, req.max_trials_per_subnet);
+            crate::update_field_if_some!(active, subnet_prefix, req.subnet_prefix);
+            crate::update_field_if_some!(active, fingerprint_threshold, req.fingerprint_threshold);
+            active.updated_at = Set(now);
+            active.update(&state.db).await?
+        }
+        None => {
+            trial_policies::ActiveModel {
+                app_id: Set(app_id),
+                window_hours: Set(req.window_hours.unwrap_or(24)),
+                max_trials_per_ip: Set(req.max_trials_per_ip.unwrap_or(0)),
+                max_trials_per_subnet: Set(req.max_trials_per_subnet.unwrap_or(0)),
+                subnet_prefix: Set(req.subnet_prefix.unwrap_or(24)),
+                fingerprint_threshold: Set(req.fingerprint_threshold.unwrap_or(0)),
+                created_at: Set(now),
+                updated_at: Set(now),
+                ..Default::default()
+            }
+            .insert(&state.db)
+            .await?
+        }
+    };
+    Ok(policy)
+}
+
+// Delete trial policy of an app, trials are no longer restricted
+#[handler]
+pub async fn delete_policy(depot: &mut Depot, id: PathParam<i32>) -> Result<ApiResponse<()>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    trial_policies::Entity::delete_many()
+        .filter(trial_policies::Column::AppId.eq(id.into_inner()))
+        .exec(&state.db)
+        .await?;
+    Ok(ApiResponse::success(()))
+}
+
+// Reset the trial of a device, starting again from now
+#[handler]
+pub async fn reset_trial(
+    depot: &mut Depot,
+    id: PathParam<i32>,
+) -> Result<ApiResponse<DeviceInfo>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let device = reset_trial_impl(state, id.into_inner()).await?;
+    Ok(ApiResponse::success(device))
+}
+
+pub async fn reset_trial_impl(state: &AppState, id: i32) -> Result<DeviceInfo, AppError> {
+    let (device, app) = find_trial_device(state, id).await?;
+    let now = Utc::now();
+    let mut active = device.into_active_model();
+    active.bind_time = Set(Some(now));
+    active.expire_time = Set(Some(now + chrono::Duration::days(app.trial_days as i64)));
+    active.trial_refused_reason = Set(None);
+    let device = active.update(&state.db).await?;
+    DeviceInfo::try_from((device, Some(app)))
+}
+
+// Extend the trial of a device
+#[handler]
+pub async fn extend_trial(
+    depot: &mut Depot,
+    id: PathParam<i32>,
+    req: JsonBody<ExtendTrialReq>,
+) -> Result<ApiResponse<DeviceInfo>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let device = extend_trial_impl(state, id.into_inner(), req.into_inner()).await?;
+    Ok(ApiResponse::success(device))
+}
+
+pub async fn extend_trial_impl(
+    state: &AppState,
+    id: i32,
+    req: ExtendTrialReq,
+) -> Result<DeviceInfo, AppError> {
+    if req.days <= 0 {
+        return Err(AppError::validation("days must be positive"));
+    }
+    let (device, app) = find_trial_device(state, id).await?;
+    let now = Utc::now();
+    let base = device.expire_time.filter(|t| *t > now).unwrap_or(now);
+    let mut active = device.into_active_model();
+    active.expire_time = Set(Some(base + chrono::Duration::days(req.days as i64)));
+    active.trial_refused_reason = Set(None);
+    let device = active.update(&state.db).await?;
+    DeviceInfo::try_from((device, Some(app)))
+}
+
+/// 查找设备，已绑定注册码的设备有效期由注册码决定，不能再调整试用
+async fn find_trial_device(
+    state: &AppState,
+    id: i32,
+) -> Result<(app_devices::Model, apps::Model), AppError> {
+    let device = app_devices::Entity::find_by_id(id)
+        .find_also_related(apps::Entity)
+        .one(&state.db)
+        .await?;
+    let (device, app) = device.ok_or_else(|| AppError::not_found("app_devices".to_string(), Some(id)))?;
+    let app = app.ok_or_else(|| AppError::not_found("apps".to_string(), Some(device.app_id)))?;
+    let licensed = reg_code_devices::Entity::find()
+        .filter(reg_code_devices::Column::DeviceId.eq(id))
+        .count(&state.db)
+        .await?;
+    if licensed > 0 {
+        return Err(AppError::business_logic(
+            "DEVICE_LICENSED",
+            "device is bound to a reg code, its expiry is managed by the code",
+        ));
+    }
+    Ok((device, app))
+}
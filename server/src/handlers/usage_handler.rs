@@ -0,0 +1,195 @@
+use crate::handlers::reg_codes_handler;
+use crate::types::common::{AppState, PagingResponse};
+use crate::types::error::AppError;
+use crate::types::reg_codes_types::*;
+use crate::types::response::ApiResponse;
+use crate::types::usage_types::*;
+use chrono::Utc;
+use entity::{code_usages, reg_codes};
+use salvo::{oapi::extract::JsonBody, prelude::*};
+use salvo_oapi::extract::PathParam;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, EntityTrait, IntoActiveModel, PaginatorTrait, QueryFilter,
+    QueryOrder, QuerySelect, Set, TransactionTrait,
+};
+
+/// 只有未吊销的计次注册码可以扣减
+fn check_consumable(reg_code: &reg_codes::Model) -> Result<(), AppError> {
+    if CodeType::from(reg_code.code_type) != CodeType::Count {
+        return Err(AppError::business_logic(
+            "NOT_COUNT_CODE",
+            "only count codes can be consumed",
+        ));
+    }
+    if RegCodeStatus::from(reg_code.status) == RegCodeStatus::Revoked {
+        return Err(AppError::business_logic(
+            "CODE_REVOKED",
+            format!(
+                "reg code revoked: {}",
+                reg_code.revoked_reason.clone().unwrap_or_default()
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// 在注册码行锁内扣减计次注册码，返回消耗记录以及是否为幂等重放
+/// idempotency_key 已存在时直接返回原记录，不再扣减
+/// 所有检查通过后才在同一事务中绑定设备，被拒绝的请求不占用设备名额
+pub async fn consume_count(
+    state: &AppState,
+    reg_code_id: i32,
+    device_id: i32,
+    amount: i32,
+    idempotency_key: Option<&str>,
+) -> Result<(code_usages::Model, bool), AppError> {
+    if amount <= 0 {
+        return Err(AppError::validation("amount must be positive"));
+    }
+    let txn = state.db.begin().await?;
+    // 锁住注册码行，串行化同一注册码的扣减和同一幂等键的重试
+    let reg_code = reg_codes::Entity::find_by_id(reg_code_id)
+        .lock_exclusive()
+        .one(&txn)
+        .await?;
+    let reg_code =
+        reg_code.ok_or_else(|| AppError::not_found("reg_codes".to_string(), Some(reg_code_id)))?;
+    if let Some(key) = idempotency_key {
+        let exist = code_usages::Entity::find()
+            .filter(
+                code_usages::Column::RegCodeId
+                    .eq(reg_code_id)
+                    .and(code_usages::Column::IdempotencyKey.eq(key)),
+            )
+            .one(&txn)
+            .await?;
+        if let Some(exist) = exist {
+            if exist.amount != amount || exist.device_id != device_id {
+                return Err(AppError::business_logic(
+                    "IDEMPOTENCY_KEY_REUSED",
+                    "idempotency key already used with a different request",
+                ));
+            }
+            return Ok((exist, true));
+        }
+    }
+    check_consumable(&reg_code)?;
+    let remaining = reg_code.total_count.unwrap_or(0) - reg_code.use_count;
+    if remaining <= 0 {
+        return Err(AppError::business_logic("CODE_USED_UP", "code used up"));
+    }
+    if amount > remaining {
+        return Err(AppError::business_logic(
+            "INSUFFICIENT_BALANCE",
+            format!("only {} uses remaining", remaining),
+        ));
+    }
+    reg_codes_handler::bind_device_locked(&txn, &reg_code, device_id).await?;
+    let now = Utc::now();
+    let mut active = reg_code.clone().into_active_model();
+    active.use_count = Set(reg_code.use_count + amount);
+    if reg_code.binding_time.is_none() {
+        active.binding_time = Set(Some(now));
+    }
+    active.status = Set(RegCodeStatus::Used.into());
+    active.updated_at = Set(now);
+    active.update(&txn).await?;
+    let usage = code_usages::ActiveModel {
+        app_id: Set(reg_code.app_id),
+        reg_code_id: Set(reg_code_id),
+        device_id: Set(device_id),
+        amount: Set(amount),
+        remaining: Set(remaining - amount),
+        idempotency_key: Set(idempotency_key.map(|k| k.to_string())),
+        created_at: Set(now),
+        ..Default::default()
+    }
+    .insert(&txn)
+    .await?;
+    txn.commit().await?;
+    Ok((usage, false))
+}
+
+/// Consume uses of a count code
+#[endpoint(tags("reg_codes"))]
+pub async fn consume(
+    depot: &mut Depot,
+    req: JsonBody<UsageConsumeReq>,
+) -> Result<ApiResponse<RegCodeValidateV2Resp>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let resp = consume_impl(state, req.into_inner()).await?;
+    Ok(ApiResponse::success(resp))
+}
+
+pub async fn consume_impl(
+    state: &AppState,
+    req: UsageConsumeReq,
+) -> Result<RegCodeValidateV2Resp, AppError> {
+    if req.idempotency_key.len() < 8 || req.idempotency_key.len() > 64 {
+        return Err(AppError::validation("idempotency_key must be 8-64 characters"));
+    }
+    let app = reg_codes_handler::verify_signed_request(state, &req).await?;
+    reg_codes_handler::check_device_ban(&state.db, app.id, &req.device_id).await?;
+    let reg_code = reg_codes::Entity::find()
+        .filter(
+            reg_codes::Column::Code
+                .eq(req.code.clone())
+                .and(reg_codes::Column::AppId.eq(app.id)),
+        )
+        .one(&state.db)
+        .await?;
+    let reg_code = reg_code.ok_or(AppError::not_found("reg_code".to_string(), None))?;
+    // 先排除不能扣减的注册码再创建设备，余额在 consume_count 中加锁后检查
+    check_consumable(&reg_code)?;
+    let device = reg_codes_handler::find_or_create_device(&state.db, &app, &req.device_id).await?;
+    let (usage, replayed) = consume_count(
+        state,
+        reg_code.id,
+        device.id,
+        req.amount,
+        Some(&req.idempotency_key),
+    )
+    .await?;
+    reg_codes_handler::sign_response(
+        &app.app_valid_key,
+        req.nonce,
+        &UsageConsumeResp {
+            usage_id: usage.id,
+            amount: usage.amount,
+            remaining: usage.remaining,
+            total: reg_code.total_count.unwrap_or(0),
+            replayed,
+            consumed_at: usage.created_at,
+        },
+    )
+}
+
+// Get usage history of a count code
+#[handler]
+pub async fn get_list(
+    depot: &mut Depot,
+    id: PathParam<i32>,
+    req: &mut Request,
+) -> Result<ApiResponse<PagingResponse<code_usages::Model>>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let params = req.parse_queries::<SearchCodeUsagesParams>()?;
+    let list = get_list_impl(state, id.into_inner(), params).await?;
+    Ok(ApiResponse::success(list))
+}
+
+pub async fn get_list_impl(
+    state: &AppState,
+    reg_code_id: i32,
+    params: SearchCodeUsagesParams,
+) -> Result<PagingResponse<code_usages::Model>, AppError> {
+    let page = params.pagination.page.unwrap_or(1);
+    let page_size = params.pagination.page_size.unwrap_or(20);
+    let query = code_usages::Entity::find()
+        .filter(code_usages::Column::RegCodeId.eq(reg_code_id))
+        .order_by_desc(code_usages::Column::CreatedAt)
+        .order_by_desc(code_usages::Column::Id);
+    let paginator = query.paginate(&state.db, page_size);
+    let total = paginator.num_items().await?;
+    let list = paginator.fetch_page(page - 1).await?;
+    Ok(PagingResponse { list, total, page })
+}
@@ -0,0 +1,87 @@
+use crate::services::validation_log;
+use crate::types::common::{AppState, PagingResponse};
+use crate::types::error::AppError;
+use crate::types::response::ApiResponse;
+use crate::types::validation_events_types::*;
+use chrono::Utc;
+use entity::validation_events;
+use salvo::prelude::*;
+use sea_orm::sea_query::Expr;
+use sea_orm::{ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect};
+
+// Get ValidationEvents List
+#[handler]
+pub async fn get_list(
+    depot: &mut Depot,
+    req: &mut Request,
+) -> Result<ApiResponse<PagingResponse<validation_events::Model>>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let params = req.parse_queries::<SearchValidationEventsParams>()?;
+    let list = get_list_impl(state, params).await?;
+    Ok(ApiResponse::success(list))
+}
+
+pub async fn get_list_impl(
+    state: &AppState,
+    params: SearchValidationEventsParams,
+) -> Result<PagingResponse<validation_events::Model>, AppError> {
+    let page = params.pagination.page.unwrap_or(1);
+    let page_size = params.pagination.page_size.unwrap_or(20);
+    let mut query = validation_events::Entity::find()
+        .order_by_desc(validation_events::Column::CreatedAt)
+        .order_by_desc(validation_events::Column::Id);
+    crate::filter_if_some!(query, validation_events::Column::AppId, params.app_id, eq);
+    crate::filter_if_some!(query, validation_events::Column::DeviceId, params.device_id, eq);
+    let code = params.code.as_deref().map(validation_log::code_hash);
+    crate::filter_if_some!(query, validation_events::Column::Code, code, eq);
+    crate::filter_if_some!(query, validation_events::Column::Ip, params.ip, eq);
+    crate::filter_if_some!(query, validation_events::Column::Success, params.success, eq);
+    crate::filter_if_some!(query, validation_events::Column::CreatedAt, params.start_time, gte);
+    crate::filter_if_some!(query, validation_events::Column::CreatedAt, params.end_time, lt);
+    let paginator = query.paginate(&state.db, page_size);
+    let total = paginator.num_items().await?;
+    let list = paginator.fetch_page(page - 1).await?;
+    Ok(PagingResponse { list, total, page })
+}
+
+// Get daily validation counts per app
+#[handler]
+pub async fn get_stats(
+    depot: &mut Depot,
+    req: &mut Request,
+) -> Result<ApiResponse<Vec<ValidationDailyStats>>, AppError> {
+    let state = depot.obtain::<AppState>().unwrap();
+    let params = req.parse_queries::<ValidationStatsParams>()?;
+    let stats = get_stats_impl(state, params).await?;
+    Ok(ApiResponse::success(stats))
+}
+
+pub async fn get_stats_impl(
+    state: &AppState,
+    params: ValidationStatsParams,
+) -> Result<Vec<ValidationDailyStats>, AppError> {
+    let end_time = params.end_time.unwrap_or_else(Utc::now);
+    let start_time = params
+        .start_time
+        .unwrap_or(end_time - chrono::Duration::days(30));
+    let mut query = validation_events::Entity::find()
+        .select_only()
+        .column(validation_events::Column::AppId)
+        .column_as(Expr::cust("DATE(created_at)"), "day")
+        .column_as(Expr::cust("COUNT(*)"), "total")
+        .column_as(Expr::cust("COUNT(*) FILTER (WHERE success)"), "success")
+        .column_as(Expr::cust("COUNT(*) FILTER (WHERE NOT success)"), "failed")
+        .filter(validation_events::Column::AppId.is_not_null())
+        .filter(validation_events::Column::CreatedAt.gte(start_time))
+        .filter(validation_events::Column::CreatedAt.lt(end_time))
+        .group_by(validation_events::Column::AppId)
+        .group_by(Expr::cust("DATE(created_at)"))
+        .order_by_asc(Expr::cust("DATE(created_at)"))
+        .order_by_asc(validation_events::Column::AppId);
+    crate::filter_if_some!(query, validation_events::Column::AppId, params.app_id, eq);
+    let stats = query
+        .into_model::<ValidationDailyStats>()
+        .all(&state.db)
+        .await?;
+    Ok(stats)
+}
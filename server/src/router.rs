@@ -26,12 +26,45 @@ pub fn create_router(app_state: AppState) -> Service {
         .push(Router::with_path("apps/{id}").get(handlers::app_handler::get_by_id))
         .push(Router::with_path("apps/{id}").put(handlers::app_handler::update))
         .push(Router::with_path("apps/{id}").delete(handlers::app_handler::delete))
+        .push(Router::with_path("apps/{id}/trial_policy").get(handlers::trial_handler::get_policy))
+        .push(Router::with_path("apps/{id}/trial_policy").put(handlers::trial_handler::upsert_policy))
+        .push(Router::with_path("apps/{id}/trial_policy").delete(handlers::trial_handler::delete_policy))
         //roles
         .push(Router::with_path("roles").post(handlers::role_handler::add))
         .push(Router::with_path("roles/list").get(handlers::role_handler::get_list))
         .push(Router::with_path("roles/{id}").get(handlers::role_handler::get_by_id))
         .push(Router::with_path("roles/{id}").put(handlers::role_handler::update))
         .push(Router::with_path("roles/{id}").delete(handlers::role_handler::delete))
+        //app features
+        .push(Router::with_path("app_features").post(handlers::app_features_handler::add))
+        .push(Router::with_path("app_features/list").get(handlers::app_features_handler::get_list))
+        .push(Router::with_path("app_features/{id}").get(handlers::app_features_handler::get_by_id))
+        .push(Router::with_path("app_features/{id}").put(handlers::app_features_handler::update))
+        .push(Router::with_path("app_features/{id}").delete(handlers::app_features_handler::delete))
+        //app announcements
+        .push(Router::with_path("app_announcements").post(handlers::app_announcements_handler::add))
+        .push(Router::with_path("app_announcements/list").get(handlers::app_announcements_handler::get_list))
+        .push(Router::with_path("app_announcements/{id}").get(handlers::app_announcements_handler::get_by_id))
+        .push(Router::with_path("app_announcements/{id}").put(handlers::app_announcements_handler::update))
+        .push(Router::with_path("app_announcements/{id}").delete(handlers::app_announcements_handler::delete))
+        //app configs
+        .push(Router::with_path("app_configs").post(handlers::app_configs_handler::add))
+        .push(Router::with_path("app_configs/list").get(handlers::app_configs_handler::get_list))
+        .push(Router::with_path("app_configs/{id}").get(handlers::app_configs_handler::get_by_id))
+        .push(Router::with_path("app_configs/{id}").put(handlers::app_configs_handler::update))
+        .push(Router::with_path("app_configs/{id}").delete(handlers::app_configs_handler::delete))
+        //app releases
+        .push(Router::with_path("app_releases").post(handlers::app_releases_handler::add))
+        .push(Router::with_path("app_releases/list").get(handlers::app_releases_handler::get_list))
+        .push(Router::with_path("app_releases/{id}").get(handlers::app_releases_handler::get_by_id))
+        .push(Router::with_path("app_releases/{id}").put(handlers::app_releases_handler::update))
+        .push(Router::with_path("app_releases/{id}").delete(handlers::app_releases_handler::delete))
+        .push(Router::with_path("app_releases/{id}/pause").post(handlers::app_releases_handler::pause))
+        .push(Router::with_path("app_releases/{id}/resume").post(handlers::app_releases_handler::resume))
+        .push(Router::with_path("app_releases/{id}/rollback").post(handlers::app_releases_handler::rollback))
+        .push(Router::with_path("app_releases/{id}/manifest").post(handlers::release_manifest_handler::build))
+        .push(Router::with_path("app_releases/{id}/manifest").get(handlers::release_manifest_handler::get_by_release))
+        .push(Router::with_path("app_release_events/list").get(handlers::app_releases_handler::get_events))
         //products
         .push(Router::with_path("products").post(handlers::product_handler::add))
         .push(Router::with_path("products/list").get(handlers::product_handler::get_list))
@@ -59,15 +92,52 @@ pub fn create_router(app_state: AppState) -> Service {
         //reg_codes
         .push(Router::with_path("reg_codes").post(handlers::reg_codes_handler::add))
         .push(Router::with_path("reg_codes/list").get(handlers::reg_codes_handler::get_list))
+        .push(Router::with_path("reg_codes/batch").post(handlers::reg_codes_handler::batch_add))
+        .push(Router::with_path("reg_codes/import").post(handlers::reg_codes_handler::import))
         .push(Router::with_path("reg_codes/{id}").get(handlers::reg_codes_handler::get_by_id))
         .push(Router::with_path("reg_codes/{id}").put(handlers::reg_codes_handler::update))
         .push(Router::with_path("reg_codes/{id}").delete(handlers::reg_codes_handler::delete))
+        .push(Router::with_path("reg_codes/{id}/revoke").post(handlers::reg_codes_handler::revoke))
+        .push(Router::with_path("reg_codes/{id}/leases").get(handlers::lease_handler::get_seat_usage))
+        .push(Router::with_path("reg_codes/{id}/usages").get(handlers::usage_handler::get_list))
+        .push(Router::with_path("reg_codes/{id}/devices").get(handlers::reg_codes_handler::get_devices))
+        .push(Router::with_path("reg_codes/{id}/devices/{device_id}").delete(handlers::reg_codes_handler::unbind_device))
+        .push(Router::with_path("reg_codes/{id}/devices/{device_id}/transfer").post(handlers::reg_codes_handler::transfer_device))
+        .push(Router::with_path("reg_codes/{id}/transfers").get(handlers::transfer_handler::get_list))
+        .push(Router::with_path("offline_activations").post(handlers::offline_activation_handler::activate))
+        //reg_code_batches
+        .push(Router::with_path("reg_code_batches").post(handlers::reg_code_batches_handler::add))
+        .push(Router::with_path("reg_code_batches/list").get(handlers::reg_code_batches_handler::get_list))
+        .push(Router::with_path("reg_code_batches/{id}").get(handlers::reg_code_batches_handler::get_by_id))
+        .push(Router::with_path("reg_code_batches/{id}").put(handlers::reg_code_batches_handler::update))
+        .push(Router::with_path("reg_code_batches/{id}/void").post(handlers::reg_code_batches_handler::void))
+        .push(Router::with_path("reg_code_batches/{id}/extend").post(handlers::reg_code_batches_handler::extend))
+        .push(Router::with_path("reg_code_batches/{id}/export").get(handlers::reg_code_batches_handler::export))
+        .push(Router::with_path("reg_code_batches/{id}/stats").get(handlers::reg_code_batches_handler::get_stats))
+        //resellers
+        .push(Router::with_path("resellers").post(handlers::reseller_handler::add))
+        .push(Router::with_path("resellers/list").get(handlers::reseller_handler::get_list))
+        .push(Router::with_path("resellers/{id}").get(handlers::reseller_handler::get_by_id))
+        .push(Router::with_path("resellers/{id}").put(handlers::reseller_handler::update))
+        .push(Router::with_path("resellers/{id}/quota").post(handlers::reseller_handler::adjust_quota))
+        .push(Router::with_path("resellers/{id}/ledger").get(handlers::reseller_handler::get_ledger))
+        .push(Router::with_path("resellers/{id}/apps").post(handlers::reseller_handler::assign_app))
+        .push(Router::with_path("resellers/{id}/apps/{app_id}").delete(handlers::reseller_handler::unassign_app))
+        //validation events
+        .push(Router::with_path("validation_events/list").get(handlers::validation_events_handler::get_list))
+        .push(Router::with_path("validation_events/stats").get(handlers::validation_events_handler::get_stats))
+        //jobs
+        .push(Router::with_path("jobs/list").get(handlers::jobs_handler::get_list))
+        .push(Router::with_path("jobs/runs").get(handlers::jobs_handler::get_runs))
+        .push(Router::with_path("jobs/{name}/run").post(handlers::jobs_handler::run_now))
         //orders
         .push(Router::with_path("orders/list").get(handlers::orders_handler::get_list))
         .push(Router::with_path("orders/{id}").get(handlers::orders_handler::get_by_id))
         .push(Router::with_path("orders/{id}").put(handlers::orders_handler::update))
         .push(Router::with_path("orders/{id}").delete(handlers::orders_handler::delete))
         .push(Router::with_path("orders").post(handlers::orders_handler::add))
+        .push(Router::with_path("orders/{id}/reg_codes").post(handlers::orders_handler::link_reg_codes))
+        .push(Router::with_path("orders/{id}/portal_link").post(handlers::license_portal_handler::create_link))
         //coupons
         .push(Router::with_path("coupons").post(handlers::coupons_handler::add))
         .push(Router::with_path("coupons/list").get(handlers::coupons_handler::get_list))
@@ -86,7 +156,41 @@ pub fn create_router(app_state: AppState) -> Service {
         .push(Router::with_path("permissions/check").post(handlers::casbin_handler::check_permission))
         .push(Router::with_path("permissions/reload").post(handlers::casbin_handler::reload_policies))
         //devices
-        .push(Router::with_path("devices/list").get(handlers::device_handler::get_list));
+        .push(Router::with_path("devices/list").get(handlers::device_handler::get_list))
+        .push(Router::with_path("devices/{id}/renewals").get(handlers::device_handler::get_renewals))
+        .push(Router::with_path("devices/{id}/trial/reset").post(handlers::trial_handler::reset_trial))
+        .push(Router::with_path("devices/{id}/trial/extend").post(handlers::trial_handler::extend_trial))
+        .push(Router::with_path("devices/bans").post(handlers::device_handler::add_ban))
+        .push(Router::with_path("devices/bans/list").get(handlers::device_handler::get_ban_list))
+        .push(Router::with_path("devices/bans/{id}").delete(handlers::device_handler::delete_ban));
+
+    // 代理商自助接口，casbin 只放行 reseller 角色访问 /api/reseller/*
+    let reseller_routes = Router::with_path("/api/reseller")
+        .hoop(middleware::auth)
+        .hoop(middleware::error_handler)
+        .hoop(casbin_middleware::casbin_auth)
+        .push(Router::with_path("me").get(handlers::reseller_portal_handler::get_me))
+        .push(Router::with_path("apps").get(handlers::reseller_portal_handler::get_apps))
+        .push(Router::with_path("stats").get(handlers::reseller_portal_handler::get_stats))
+        .push(Router::with_path("ledger").get(handlers::reseller_portal_handler::get_ledger))
+        .push(Router::with_path("reg_codes/list").get(handlers::reseller_portal_handler::get_codes))
+        .push(Router::with_path("reg_codes/batch").post(handlers::reseller_portal_handler::batch_add))
+        .push(Router::with_path("batches/list").get(handlers::reseller_portal_handler::get_batches))
+        .push(Router::with_path("batches/{id}/stats").get(handlers::reseller_portal_handler::get_batch_stats))
+        .push(Router::with_path("batches/{id}/export").get(handlers::reseller_portal_handler::export_batch));
+
+    // 买家自助门户，订单号加邮箱或一次性登录链接换取会话令牌
+    let portal_routes = Router::with_path("/api/portal")
+        .push(Router::with_path("login").post(handlers::license_portal_handler::login))
+        .push(Router::with_path("magic_login").post(handlers::license_portal_handler::magic_login))
+        .push(
+            Router::new()
+                .hoop(handlers::license_portal_handler::portal_auth)
+                .push(Router::with_path("codes").get(handlers::license_portal_handler::get_codes))
+                .push(Router::with_path("codes/{id}/devices/{device_id}/deactivate").post(handlers::license_portal_handler::deactivate_device))
+                .push(Router::with_path("codes/{id}/devices/{device_id}/license").get(handlers::license_portal_handler::get_license))
+                .push(Router::with_path("offline_activations").post(handlers::license_portal_handler::offline_activate)),
+        );
 
     let cors = Cors::new()
     .allow_origin(AllowOrigin::any())
@@ -100,7 +204,22 @@ pub fn create_router(app_state: AppState) -> Service {
         .push  (Router::with_path("/api/reg/validate").post(handlers::reg_codes_handler::validate_code))
         .push(Router::with_path("/api/reg/validate").post(handlers::reg_codes_handler::validate_code))
         .push(Router::with_path("/api/reg/validate").get(handlers::reg_codes_handler::validate_code_get))
+        .push(Router::with_path("/api/reg/v2/validate").post(handlers::reg_codes_handler::validate_code_v2))
+        .push(Router::with_path("/api/reg/lease/checkout").post(handlers::lease_handler::checkout))
+        .push(Router::with_path("/api/reg/lease/heartbeat").post(handlers::lease_handler::heartbeat))
+        .push(Router::with_path("/api/reg/lease/checkin").post(handlers::lease_handler::checkin))
+        .push(Router::with_path("/api/reg/usage/consume").post(handlers::usage_handler::consume))
+        .push(Router::with_path("/api/reg/deactivate").post(handlers::transfer_handler::deactivate))
+        .push(Router::with_path("/api/reg/revocations/{app_id}").get(handlers::reg_codes_handler::get_revocations))
+        .push(Router::with_path("/api/reg/public_key/{app_id}").get(handlers::reg_codes_handler::get_public_key))
+        .push(Router::with_path("/api/app/check_update/{app_id}").get(handlers::app_releases_handler::check_update))
+        .push(Router::with_path("/api/app/manifest/{app_id}").get(handlers::release_manifest_handler::get_manifest))
+        .push(Router::with_path("/api/app/config").get(handlers::app_configs_handler::get_config))
+        .push(Router::with_path("/api/app/announcements").get(handlers::app_announcements_handler::get_active))
+        .push(Router::with_path("/api/app/announcements/dismiss").post(handlers::app_announcements_handler::dismiss))
         .push( admin_routes)
+        .push(reseller_routes)
+        .push(portal_routes)
         .push(Router::with_path("/api/vuefinder/list").get(handlers::vuefinder_handler::list));
     if register_open {
         router=router.push(Router::with_path("/api/register").post(handlers::auth::register));
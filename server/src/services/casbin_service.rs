@@ -81,6 +81,16 @@ impl CasbinService {
         Ok(e.get_policy())
     }
 
+    // 按字段过滤策略，field_index 为起始字段（0: sub 1: obj 2: act）
+    pub async fn get_filtered_policy(
+        &self,
+        field_index: usize,
+        values: Vec<String>,
+    ) -> Result<Vec<Vec<String>>, AppError> {
+        let e = self.enforcer.read().await;
+        Ok(e.get_filtered_policy(field_index, values))
+    }
+
     // 获取所有角色继承关系
     pub async fn get_grouping_policy(&self) -> Result<Vec<Vec<String>>, AppError> {
         let e = self.enforcer.read().await;
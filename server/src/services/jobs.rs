@@ -0,0 +1,72 @@
+use crate::types::common::AppState;
+use crate::types::error::AppError;
+use crate::types::reg_codes_types::RegCodeStatus;
+use chrono::Utc;
+use entity::{app_devices, orders, reg_codes};
+use futures::future::BoxFuture;
+use sea_orm::sea_query::Expr;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+
+// 待支付订单超过该时长未支付时自动取消
+const ORDER_PAY_TIMEOUT_HOURS: i64 = 24;
+// 订单状态，见 orders.status 注释
+const ORDER_PENDING: i16 = 0;
+const ORDER_CANCELLED: i16 = 2;
+
+/// 把已过有效期的注册码标记为过期，校验时的惰性更新保留
+pub fn expire_reg_codes(state: &AppState) -> BoxFuture<'_, Result<u64, AppError>> {
+    Box::pin(async move {
+        let now = Utc::now();
+        let result = reg_codes::Entity::update_many()
+            .col_expr(
+                reg_codes::Column::Status,
+                Expr::value(i16::from(RegCodeStatus::Expired)),
+            )
+            .col_expr(reg_codes::Column::UpdatedAt, Expr::value(now))
+            .filter(reg_codes::Column::Status.is_in([
+                i16::from(RegCodeStatus::Unused),
+                i16::from(RegCodeStatus::Used),
+            ]))
+            .filter(reg_codes::Column::ExpireTime.lt(now))
+            .exec(&state.db)
+            .await?;
+        Ok(result.rows_affected)
+    })
+}
+
+/// 按 expire_time 同步设备的过期标记，续期后的设备会被重新标记为未过期
+pub fn expire_devices(state: &AppState) -> BoxFuture<'_, Result<u64, AppError>> {
+    Box::pin(async move {
+        let now = Utc::now();
+        let expired = app_devices::Entity::update_many()
+            .col_expr(app_devices::Column::Expired, Expr::value(true))
+            .filter(app_devices::Column::Expired.eq(false))
+            .filter(app_devices::Column::ExpireTime.lt(now))
+            .exec(&state.db)
+            .await?;
+        let renewed = app_devices::Entity::update_many()
+            .col_expr(app_devices::Column::Expired, Expr::value(false))
+            .filter(app_devices::Column::Expired.eq(true))
+            .filter(app_devices::Column::ExpireTime.gte(now))
+            .exec(&state.db)
+            .await?;
+        Ok(expired.rows_affected + renewed.rows_affected)
+    })
+}
+
+/// 取消超时未支付的订单
+pub fn close_stale_orders(state: &AppState) -> BoxFuture<'_, Result<u64, AppError>> {
+    Box::pin(async move {
+        let now = Utc::now();
+        let result = orders::Entity::update_many()
+            .col_expr(orders::Column::Status, Expr::value(ORDER_CANCELLED))
+            .col_expr(orders::Column::UpdatedAt, Expr::value(now))
+            .filter(orders::Column::Status.eq(ORDER_PENDING))
+            .filter(
+                orders::Column::CreatedAt.lt(now - chrono::Duration::hours(ORDER_PAY_TIMEOUT_HOURS)),
+            )
+            .exec(&state.db)
+            .await?;
+        Ok(result.rows_affected)
+    })
+}
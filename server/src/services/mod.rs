@@ -1 +1,5 @@
 pub mod casbin_service;
+pub mod jobs;
+pub mod scheduler;
+pub mod trial_guard;
+pub mod validation_log;
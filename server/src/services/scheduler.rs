@@ -0,0 +1,113 @@
+use crate::services::jobs;
+use crate::types::common::AppState;
+use crate::types::error::AppError;
+use chrono::Utc;
+use entity::job_runs;
+use futures::future::BoxFuture;
+use sea_orm::{ActiveModelTrait, Set};
+use std::sync::LazyLock;
+use std::time::Duration;
+
+/// 定时任务，run 返回处理的记录数
+pub struct Job {
+    pub name: &'static str,
+    pub interval: Duration,
+    pub run: for<'a> fn(&'a AppState) -> BoxFuture<'a, Result<u64, AppError>>,
+}
+
+/// 所有定时任务，新增任务在这里注册
+pub static JOBS: &[Job] = &[
+    Job {
+        name: "expire_reg_codes",
+        interval: Duration::from_secs(60),
+        run: jobs::expire_reg_codes,
+    },
+    Job {
+        name: "expire_devices",
+        interval: Duration::from_secs(300),
+        run: jobs::expire_devices,
+    },
+    Job {
+        name: "close_stale_orders",
+        interval: Duration::from_secs(600),
+        run: jobs::close_stale_orders,
+    },
+];
+
+#[derive(Debug, Clone, Copy)]
+pub enum Trigger {
+    Schedule,
+    Manual,
+}
+
+impl Trigger {
+    fn as_str(self) -> &'static str {
+        match self {
+            Trigger::Schedule => "schedule",
+            Trigger::Manual => "manual",
+        }
+    }
+}
+
+// 当前实例标识，写入执行记录和 Redis 锁
+static INSTANCE: LazyLock<String> = LazyLock::new(|| {
+    let host = std::env::var("HOSTNAME").unwrap_or_else(|_| "localhost".to_string());
+    format!("{}-{}", host, std::process::id())
+});
+
+pub fn find_job(name: &str) -> Option<&'static Job> {
+    JOBS.iter().find(|job| job.name == name)
+}
+
+/// 为每个任务启动后台循环
+pub fn start(state: AppState) {
+    for job in JOBS {
+        let state = state.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(job.interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                // 锁在一个周期内不释放，多实例时每个周期只有抢到锁的实例执行
+                let lock_key = format!("scheduler:lock:{}", job.name);
+                match state.redis.set_nx(&lock_key, &*INSTANCE, job.interval).await {
+                    Ok(true) => {
+                        if let Err(e) = run_job(&state, job, Trigger::Schedule).await {
+                            tracing::error!("record job run {} failed: {}", job.name, e);
+                        }
+                    }
+                    Ok(false) => {}
+                    Err(e) => tracing::warn!("acquire job lock {} failed: {}", job.name, e),
+                }
+            }
+        });
+    }
+    tracing::info!("Scheduler started with {} jobs", JOBS.len());
+}
+
+/// 执行一次任务并写入执行记录
+pub async fn run_job(
+    state: &AppState,
+    job: &Job,
+    trigger: Trigger,
+) -> Result<job_runs::Model, AppError> {
+    let started_at = Utc::now();
+    let result = (job.run)(state).await;
+    if let Err(e) = &result {
+        tracing::error!("job {} failed: {}", job.name, e);
+    }
+    let run = job_runs::ActiveModel {
+        job_name: Set(job.name.to_string()),
+        instance: Set(INSTANCE.clone()),
+        trigger: Set(trigger.as_str().to_string()),
+        success: Set(result.is_ok()),
+        affected: Set(*result.as_ref().unwrap_or(&0) as i64),
+        error: Set(result.err().map(|e| e.to_string())),
+        started_at: Set(started_at),
+        finished_at: Set(Utc::now()),
+        ..Default::default()
+    }
+    .insert(&state.db)
+    .await?;
+    Ok(run)
+}
@@ -0,0 +1,136 @@
+use crate::types::error::AppError;
+use entity::{app_devices, trial_policies};
+use sea_orm::sea_query::Expr;
+use sea_orm::{
+    ColumnTrait, Condition, ConnectionTrait, EntityTrait, PaginatorTrait, QueryFilter, QueryOrder,
+    QuerySelect,
+};
+use serde_json::{Map, Value};
+use std::net::IpAddr;
+
+// 指纹比对最多取多少台候选设备
+const FINGERPRINT_CANDIDATES: u64 = 200;
+// IPv6 按 /64 统计网段
+const IPV6_SUBNET_PREFIX: u8 = 64;
+// 取不到客户端 IP 时拒绝原因中显示的地址
+const UNKNOWN_IP: &str = "unknown";
+
+/// 新设备首次试用时按应用的试用策略检查，返回拒绝原因；没有策略时不限制
+pub async fn check_new_trial<C: ConnectionTrait>(
+    db: &C,
+    app_id: i32,
+    device_id: &str,
+    ip: Option<IpAddr>,
+    device_info: Option<&Value>,
+) -> Result<Option<String>, AppError> {
+    let policy = trial_policies::Entity::find()
+        .filter(trial_policies::Column::AppId.eq(app_id))
+        .one(db)
+        .await?;
+    let Some(policy) = policy else {
+        return Ok(None);
+    };
+    let since = chrono::Utc::now() - chrono::Duration::hours(policy.window_hours as i64);
+    // 只统计窗口内首次出现且未被拒绝的设备
+    let recent = app_devices::Entity::find()
+        .filter(app_devices::Column::AppId.eq(app_id))
+        .filter(app_devices::Column::BindTime.gte(since))
+        .filter(app_devices::Column::TrialRefusedReason.is_null());
+    // 取不到 IP 的设备归为同一个 IP 和网段统计，不能因为缺少 IP 跳过限制
+    let ip_label = ip.map_or_else(|| UNKNOWN_IP.to_string(), |ip| ip.to_string());
+    if policy.max_trials_per_ip > 0 {
+        let same_ip = match ip {
+            Some(ip) => app_devices::Column::BindIp.eq(ip.to_string()),
+            None => app_devices::Column::BindIp.is_null(),
+        };
+        let count = recent.clone().filter(same_ip).count(db).await?;
+        if count >= policy.max_trials_per_ip as u64 {
+            return Ok(Some(format!(
+                "too many trials from ip {} in the last {} hours",
+                ip_label, policy.window_hours
+            )));
+        }
+    }
+    if policy.max_trials_per_subnet > 0 {
+        let (same_subnet, subnet) = match ip {
+            Some(ip) => {
+                let subnet = subnet_of(ip, policy.subnet_prefix as u8);
+                let expr = Expr::cust_with_values("bind_ip::inet <<= $1::cidr", [subnet.clone()]);
+                (Condition::all().add(expr), subnet)
+            }
+            None => (
+                Condition::all().add(app_devices::Column::BindIp.is_null()),
+                UNKNOWN_IP.to_string(),
+            ),
+        };
+        let count = recent.filter(same_subnet).count(db).await?;
+        if count >= policy.max_trials_per_subnet as u64 {
+            return Ok(Some(format!(
+                "too many trials from subnet {} in the last {} hours",
+                subnet, policy.window_hours
+            )));
+        }
+    }
+    if policy.fingerprint_threshold > 0
+        && let Some(info) = device_info.and_then(Value::as_object).filter(|m| !m.is_empty())
+    {
+        // 先用 JSONB 包含查询筛出至少有一项相同的设备，再逐台计算相似度
+        let mut any = Condition::any();
+        for (key, value) in info {
+            let mut pair = Map::new();
+            pair.insert(key.clone(), value.clone());
+            any = any.add(Expr::cust_with_values(
+                "device_info @> $1::jsonb",
+                [Value::Object(pair).to_string()],
+            ));
+        }
+        let candidates = app_devices::Entity::find()
+            .filter(app_devices::Column::AppId.eq(app_id))
+            .filter(app_devices::Column::DeviceId.ne(device_id))
+            .filter(any)
+            .order_by_desc(app_devices::Column::BindTime)
+            .limit(FINGERPRINT_CANDIDATES)
+            .all(db)
+            .await?;
+        for candidate in candidates {
+            let Some(other) = candidate.device_info.as_ref().and_then(Value::as_object) else {
+                continue;
+            };
+            let score = similarity(info, other);
+            if score >= policy.fingerprint_threshold as u8 {
+                return Ok(Some(format!(
+                    "device fingerprint {}% similar to an existing device",
+                    score
+                )));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// 两份设备信息顶层字段的相似度（相同字段数 / 字段并集数，百分比）
+pub fn similarity(a: &Map<String, Value>, b: &Map<String, Value>) -> u8 {
+    let same = a.iter().filter(|(k, v)| b.get(*k) == Some(*v)).count();
+    let union = a.len() + b.keys().filter(|k| !a.contains_key(*k)).count();
+    if union == 0 {
+        return 0;
+    }
+    (same * 100 / union) as u8
+}
+
+/// ip 所在网段，IPv4 按 prefix 计算，IPv6 固定 /64
+pub fn subnet_of(ip: IpAddr, prefix: u8) -> String {
+    match ip {
+        IpAddr::V4(v4) => {
+            let prefix = prefix.clamp(8, 32);
+            let mask = u32::MAX << (32 - prefix as u32);
+            let net = std::net::Ipv4Addr::from(u32::from(v4) & mask);
+            format!("{}/{}", net, prefix)
+        }
+        IpAddr::V6(v6) => {
+            let mask = u128::MAX << (128 - IPV6_SUBNET_PREFIX as u32);
+            let net = std::net::Ipv6Addr::from(u128::from(v6) & mask);
+            format!("{}/{}", net, IPV6_SUBNET_PREFIX)
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use crate::types::validation_events_types::{AppRef, ValidationEvent};
+use entity::{apps, validation_events};
+use sea_orm::{ColumnTrait, Condition, DatabaseConnection, DbErr, EntityTrait, QueryFilter, Set};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+
+// 队列长度，写库跟不上时丢弃新事件而不是阻塞校验
+const QUEUE_SIZE: usize = 10_000;
+// 每批最多写入的行数
+const BATCH_SIZE: usize = 500;
+// 收到第一条事件后最多等待多久再写库
+const FLUSH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// 校验事件的异步批量写入器，校验接口只负责把事件放入队列
+#[derive(Clone)]
+pub struct ValidationLogger {
+    tx: mpsc::Sender<ValidationEvent>,
+}
+
+impl ValidationLogger {
+    /// 启动后台写入任务
+    pub fn start(db: DatabaseConnection) -> Self {
+        let (tx, rx) = mpsc::channel(QUEUE_SIZE);
+        tokio::spawn(run(db, rx));
+        Self { tx }
+    }
+
+    pub fn record(&self, event: ValidationEvent) {
+        if let Err(e) = self.tx.try_send(event) {
+            tracing::warn!("validation event dropped: {}", e);
+        }
+    }
+}
+
+/// 日志中保存的注册码哈希，不落库明文，按注册码查询时用同样的方式转换
+pub fn code_hash(code: &str) -> String {
+    hex::encode(Sha256::digest(code.as_bytes()))
+}
+
+async fn run(db: DatabaseConnection, mut rx: mpsc::Receiver<ValidationEvent>) {
+    let mut buf = Vec::with_capacity(BATCH_SIZE);
+    while let Some(event) = rx.recv().await {
+        buf.push(event);
+        let deadline = Instant::now() + FLUSH_INTERVAL;
+        while buf.len() < BATCH_SIZE {
+            match tokio::time::timeout_at(deadline, rx.recv()).await {
+                Ok(Some(event)) => buf.push(event),
+                _ => break,
+            }
+        }
+        if let Err(e) = flush(&db, &buf).await {
+            tracing::error!("write validation events failed: {}", e);
+        }
+        buf.clear();
+    }
+}
+
+async fn flush(db: &DatabaseConnection, buf: &[ValidationEvent]) -> Result<(), DbErr> {
+    let mut keys = vec![];
+    let mut app_ids = vec![];
+    for event in buf.iter() {
+        match &event.app {
+            AppRef::ValidKey(key) => keys.push(key.clone()),
+            AppRef::AppId(id) => app_ids.push(id.clone()),
+        }
+    }
+    let apps = apps::Entity::find()
+        .filter(
+            Condition::any()
+                .add(apps::Column::AppValidKey.is_in(keys))
+                .add(apps::Column::AppId.is_in(app_ids)),
+        )
+        .all(db)
+        .await?;
+    let mut resolved = HashMap::new();
+    for app in apps {
+        resolved.insert(AppRef::ValidKey(app.app_valid_key), app.id);
+        resolved.insert(AppRef::AppId(app.app_id), app.id);
+    }
+    let models = buf.iter().map(|event| {
+        let (error_code, reason) = match &event.error {
+            Some((code, reason)) => (Some(*code), Some(reason.clone())),
+            None => (None, None),
+        };
+        validation_events::ActiveModel {
+            app_id: Set(resolved.get(&event.app).copied()),
+            device_id: Set(event.device_id.clone()),
+            code: Set(event.code.clone()),
+            api_version: Set(event.api_version),
+            ip: Set(event.ip.clone()),
+            user_agent: Set(event.user_agent.clone()),
+            success: Set(event.error.is_none()),
+            error_code: Set(error_code),
+            reason: Set(reason),
+            created_at: Set(event.created_at),
+            ..Default::default()
+        }
+    });
+    validation_events::Entity::insert_many(models).exec(db).await?;
+    Ok(())
+}
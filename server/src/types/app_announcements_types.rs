@@ -0,0 +1,152 @@
+use crate::types::app_configs_types::ConfigAudience;
+use crate::types::common::ListParamsReq;
+use crate::utils::convert::from_str_optional;
+use chrono::{DateTime, Utc};
+use entity::app_announcements;
+use salvo_oapi::ToSchema;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ToSchema)]
+#[repr(i16)]
+#[serde(from = "i16", into = "i16")]
+pub enum AnnouncementKind {
+    #[default]
+    Notice = 0, // 通知
+    Maintenance = 1, // 维护
+    Promotion = 2,   // 促销
+}
+
+impl From<i16> for AnnouncementKind {
+    fn from(value: i16) -> Self {
+        match value {
+            1 => AnnouncementKind::Maintenance,
+            2 => AnnouncementKind::Promotion,
+            _ => AnnouncementKind::Notice,
+        }
+    }
+}
+
+impl From<AnnouncementKind> for i16 {
+    fn from(value: AnnouncementKind) -> Self {
+        value as i16
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Serialize, Deserialize, Debug, Validate, ToSchema)]
+pub struct CreateAnnouncementReq {
+    pub app_id: i32,
+    #[serde(default)]
+    pub kind: AnnouncementKind,
+    #[validate(length(min = 1, max = 128))]
+    pub title: String,
+    #[validate(length(min = 1))]
+    pub content: String,
+    pub link_url: Option<String>,
+    /// 生效时间，不传时立即生效
+    pub start_at: Option<DateTime<Utc>>,
+    /// 结束时间，不传时一直有效
+    pub end_at: Option<DateTime<Utc>>,
+    /// 客户端版本号下限（含），0 为不限
+    #[serde(default)]
+    #[validate(range(min = 0))]
+    pub min_vercode: i32,
+    /// 客户端版本号上限（含），0 为不限
+    #[serde(default)]
+    #[validate(range(min = 0))]
+    pub max_vercode: i32,
+    #[serde(default)]
+    pub audience: ConfigAudience,
+    /// 越大越靠前
+    #[serde(default)]
+    pub priority: i32,
+    #[serde(default = "default_true")]
+    pub dismissible: bool,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Validate, ToSchema)]
+pub struct UpdateAnnouncementReq {
+    pub kind: Option<AnnouncementKind>,
+    #[validate(length(min = 1, max = 128))]
+    pub title: Option<String>,
+    #[validate(length(min = 1))]
+    pub content: Option<String>,
+    pub link_url: Option<String>,
+    pub start_at: Option<DateTime<Utc>>,
+    pub end_at: Option<DateTime<Utc>>,
+    #[validate(range(min = 0))]
+    pub min_vercode: Option<i32>,
+    #[validate(range(min = 0))]
+    pub max_vercode: Option<i32>,
+    pub audience: Option<ConfigAudience>,
+    pub priority: Option<i32>,
+    pub dismissible: Option<bool>,
+    pub enabled: Option<bool>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct SearchAnnouncementsParams {
+    #[serde(flatten)]
+    pub pagination: ListParamsReq,
+    #[serde(deserialize_with = "from_str_optional", default)]
+    pub app_id: Option<i32>,
+    #[serde(deserialize_with = "from_str_optional", default)]
+    pub kind: Option<i16>,
+    pub title: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct ActiveAnnouncementsParams {
+    /// 从 X-App-Key 请求头读取，不接受查询参数
+    #[serde(skip)]
+    pub app_key: String,
+    /// 设备 ID，不传时只返回面向全部设备的公告，也不排除已关闭的公告
+    pub device_id: Option<String>,
+    /// 客户端当前版本号，不传时只返回不限版本的公告
+    #[serde(deserialize_with = "from_str_optional", default)]
+    pub vercode: Option<i32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Validate, ToSchema)]
+pub struct DismissAnnouncementReq {
+    pub app_key: String,
+    #[validate(length(min = 1))]
+    pub device_id: String,
+    pub announcement_id: i32,
+}
+
+/// 客户端可见的公告
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct AnnouncementInfo {
+    pub id: i32,
+    pub kind: AnnouncementKind,
+    pub title: String,
+    pub content: String,
+    pub link_url: Option<String>,
+    pub start_at: Option<DateTime<Utc>>,
+    pub end_at: Option<DateTime<Utc>>,
+    pub priority: i32,
+    pub dismissible: bool,
+}
+
+impl From<app_announcements::Model> for AnnouncementInfo {
+    fn from(announcement: app_announcements::Model) -> Self {
+        Self {
+            id: announcement.id,
+            kind: announcement.kind.into(),
+            title: announcement.title,
+            content: announcement.content,
+            link_url: announcement.link_url,
+            start_at: announcement.start_at,
+            end_at: announcement.end_at,
+            priority: announcement.priority,
+            dismissible: announcement.dismissible,
+        }
+    }
+}
@@ -0,0 +1,207 @@
+use crate::types::app_releases_types::ReleaseChannel;
+use crate::types::common::ListParamsReq;
+use crate::utils::convert::from_str_optional;
+use salvo_oapi::ToSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use validator::Validate;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ToSchema)]
+#[repr(i16)]
+#[serde(from = "i16", into = "i16")]
+pub enum ConfigValueType {
+    #[default]
+    String = 0,
+    Integer = 1,
+    Float = 2,
+    Bool = 3,
+    Json = 4, // 任意 JSON 值
+}
+
+impl From<i16> for ConfigValueType {
+    fn from(value: i16) -> Self {
+        match value {
+            1 => ConfigValueType::Integer,
+            2 => ConfigValueType::Float,
+            3 => ConfigValueType::Bool,
+            4 => ConfigValueType::Json,
+            _ => ConfigValueType::String,
+        }
+    }
+}
+
+impl From<ConfigValueType> for i16 {
+    fn from(value: ConfigValueType) -> Self {
+        value as i16
+    }
+}
+
+impl ConfigValueType {
+    /// 值是否符合该类型
+    pub fn accepts(self, value: &serde_json::Value) -> bool {
+        match self {
+            ConfigValueType::String => value.is_string(),
+            ConfigValueType::Integer => value.is_i64() || value.is_u64(),
+            ConfigValueType::Float => value.is_number(),
+            ConfigValueType::Bool => value.is_boolean(),
+            ConfigValueType::Json => true,
+        }
+    }
+}
+
+/// 配置规则生效的通道
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfigChannel {
+    #[default]
+    All,
+    Stable,
+    Beta,
+}
+
+impl ConfigChannel {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ConfigChannel::All => "all",
+            ConfigChannel::Stable => "stable",
+            ConfigChannel::Beta => "beta",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ToSchema)]
+#[repr(i16)]
+#[serde(from = "i16", into = "i16")]
+pub enum ConfigAudience {
+    #[default]
+    All = 0, // 全部设备
+    Licensed = 1, // 绑定了有效注册码的设备
+    Trial = 2,    // 未绑定注册码的设备
+}
+
+impl From<i16> for ConfigAudience {
+    fn from(value: i16) -> Self {
+        match value {
+            1 => ConfigAudience::Licensed,
+            2 => ConfigAudience::Trial,
+            _ => ConfigAudience::All,
+        }
+    }
+}
+
+impl From<ConfigAudience> for i16 {
+    fn from(value: ConfigAudience) -> Self {
+        value as i16
+    }
+}
+
+impl ConfigAudience {
+    /// licensed 为 None 时（未知设备）只命中面向全部设备的规则
+    pub fn matches(self, licensed: Option<bool>) -> bool {
+        match self {
+            ConfigAudience::All => true,
+            ConfigAudience::Licensed => licensed == Some(true),
+            ConfigAudience::Trial => licensed == Some(false),
+        }
+    }
+}
+
+/// 客户端版本号是否在 [min, max] 内，0 为不限；未知版本只命中不限版本的规则
+pub fn vercode_in_range(vercode: Option<i32>, min: i32, max: i32) -> bool {
+    match vercode {
+        Some(v) => v >= min && (max == 0 || v <= max),
+        None => min == 0 && max == 0,
+    }
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+#[derive(Serialize, Deserialize, Debug, Validate, ToSchema)]
+pub struct CreateAppConfigReq {
+    pub app_id: i32,
+    /// 配置项标识，仅允许字母、数字、`_`、`.`、`-`
+    #[validate(length(min = 1, max = 64))]
+    pub config_key: String,
+    #[serde(default)]
+    pub value_type: ConfigValueType,
+    pub value: serde_json::Value,
+    #[serde(default)]
+    pub channel: ConfigChannel,
+    /// 客户端版本号下限（含），0 为不限
+    #[serde(default)]
+    #[validate(range(min = 0))]
+    pub min_vercode: i32,
+    /// 客户端版本号上限（含），0 为不限
+    #[serde(default)]
+    #[validate(range(min = 0))]
+    pub max_vercode: i32,
+    #[serde(default)]
+    pub audience: ConfigAudience,
+    /// 同一 key 命中多条规则时取较大者
+    #[serde(default)]
+    pub priority: i32,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    pub description: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Validate, ToSchema)]
+pub struct UpdateAppConfigReq {
+    pub value_type: Option<ConfigValueType>,
+    pub value: Option<serde_json::Value>,
+    pub channel: Option<ConfigChannel>,
+    #[validate(range(min = 0))]
+    pub min_vercode: Option<i32>,
+    #[validate(range(min = 0))]
+    pub max_vercode: Option<i32>,
+    pub audience: Option<ConfigAudience>,
+    pub priority: Option<i32>,
+    pub enabled: Option<bool>,
+    pub description: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct SearchAppConfigsParams {
+    #[serde(flatten)]
+    pub pagination: ListParamsReq,
+    #[serde(deserialize_with = "from_str_optional", default)]
+    pub app_id: Option<i32>,
+    pub config_key: Option<String>,
+}
+
+/// 客户端环境，用于匹配配置规则
+#[derive(Serialize, Deserialize, Debug, Clone, Default, ToSchema)]
+pub struct ConfigQuery {
+    /// 客户端当前版本号，不传时只能命中不限版本的规则
+    pub vercode: Option<i32>,
+    #[serde(default)]
+    pub channel: ReleaseChannel,
+    /// 上次获取到的 etag，配置未变化时不再返回 values
+    pub etag: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct AppConfigParams {
+    /// 从 X-App-Key 请求头读取，不接受查询参数
+    #[serde(skip)]
+    pub app_key: String,
+    /// 设备 ID，不传时只能命中面向全部设备的规则
+    pub device_id: Option<String>,
+    #[serde(deserialize_with = "from_str_optional", default)]
+    pub vercode: Option<i32>,
+    #[serde(default)]
+    pub channel: ReleaseChannel,
+    pub etag: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct AppConfigResp {
+    /// values 的摘要，同时通过 ETag 响应头返回
+    pub etag: String,
+    /// 与请求的 etag 一致，values 为空
+    pub not_modified: bool,
+    /// 命中的配置，key 为 config_key
+    pub values: Option<BTreeMap<String, serde_json::Value>>,
+}
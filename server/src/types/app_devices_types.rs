@@ -2,6 +2,7 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use salvo_oapi::ToSchema;
 use crate::types::common::ListParamsReq;
+use crate::utils::convert::from_str_optional;
 
 #[derive(Serialize, Deserialize, Debug, ToSchema)]
 pub struct DeviceInfo {
@@ -12,6 +13,11 @@ pub struct DeviceInfo {
     pub device_info: Option<serde_json::Value>,
     pub bind_time: Option<DateTime<Utc>>,
     pub expire_time: Option<DateTime<Utc>>,
+    pub bind_ip: Option<String>,
+    /// 试用被拒绝的原因
+    pub trial_refused_reason: Option<String>,
+    /// 定时任务维护的过期标记
+    pub expired: bool,
 }
 
 impl TryFrom<(entity::app_devices::Model,Option<entity::apps::Model>)> for DeviceInfo {
@@ -26,6 +32,9 @@ impl TryFrom<(entity::app_devices::Model,Option<entity::apps::Model>)> for Devic
             device_info: app_device.device_info,
             bind_time: app_device.bind_time,
             expire_time: app_device.expire_time,
+            bind_ip: app_device.bind_ip,
+            trial_refused_reason: app_device.trial_refused_reason,
+            expired: app_device.expired,
         })
     }
 }
@@ -36,4 +45,22 @@ pub struct SearchDevicesParams {
     pub pagination: ListParamsReq,
     pub app_id: Option<i32>,
     pub device_id: Option<String>,
-}
\ No newline at end of file
+    #[serde(deserialize_with = "from_str_optional", default)]
+    pub expired: Option<bool>,
+}
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct AddDeviceBanReq {
+    pub app_id: i32,
+    /// 客户端上报的 device_id
+    pub device_id: String,
+    #[serde(default)]
+    pub reason: String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct SearchDeviceBansParams {
+    #[serde(flatten)]
+    pub pagination: ListParamsReq,
+    pub app_id: Option<i32>,
+    pub device_id: Option<String>,
+}
@@ -0,0 +1,98 @@
+use crate::types::common::ListParamsReq;
+use crate::types::error::AppError;
+use crate::utils::convert::from_str_optional;
+use salvo_oapi::ToSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use validator::Validate;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ToSchema)]
+#[repr(i16)]
+#[serde(from = "i16", into = "i16")]
+pub enum FeatureType {
+    #[default]
+    Flag = 0,  // 功能开关
+    Limit = 1, // 数值限额
+}
+
+impl From<i16> for FeatureType {
+    fn from(value: i16) -> Self {
+        match value {
+            1 => FeatureType::Limit,
+            _ => FeatureType::Flag,
+        }
+    }
+}
+
+impl From<FeatureType> for i16 {
+    fn from(value: FeatureType) -> Self {
+        value as i16
+    }
+}
+
+/// 授权内容：开启的功能开关 + 数值限额
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq, ToSchema)]
+pub struct Entitlements {
+    #[serde(default)]
+    pub features: BTreeSet<String>,
+    #[serde(default)]
+    pub limits: BTreeMap<String, i64>,
+}
+
+impl Entitlements {
+    /// 合并另一份授权，开关取并集，限额取较大值
+    pub fn merge(&mut self, other: Entitlements) {
+        self.features.extend(other.features);
+        for (key, value) in other.limits {
+            let limit = self.limits.entry(key).or_insert(value);
+            *limit = (*limit).max(value);
+        }
+    }
+
+    /// 从 JSONB 列解析，空值视为没有授权
+    pub fn from_json(value: Option<&serde_json::Value>) -> Result<Self, AppError> {
+        match value {
+            Some(value) if !value.is_null() => serde_json::from_value(value.clone())
+                .map_err(|e| AppError::InternalError {
+                    message: format!("invalid entitlements: {}", e),
+                }),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::to_value(self).unwrap_or_default()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Validate, ToSchema)]
+pub struct CreateAppFeatureReq {
+    pub app_id: i32,
+    /// 功能标识，仅允许字母、数字、`_`、`.`、`-`
+    #[validate(length(min = 1, max = 64))]
+    pub feature_key: String,
+    pub name: String,
+    #[serde(default)]
+    pub feature_type: FeatureType,
+    /// 未授权时的默认值，开关 0/1，限额为数值
+    #[serde(default)]
+    pub default_value: i64,
+    pub description: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Validate, ToSchema)]
+pub struct UpdateAppFeatureReq {
+    pub name: Option<String>,
+    pub feature_type: Option<FeatureType>,
+    pub default_value: Option<i64>,
+    pub description: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct SearchAppFeaturesParams {
+    #[serde(flatten)]
+    pub pagination: ListParamsReq,
+    #[serde(deserialize_with = "from_str_optional", default)]
+    pub app_id: Option<i32>,
+    pub feature_key: Option<String>,
+}
@@ -0,0 +1,236 @@
+use crate::types::common::ListParamsReq;
+use crate::utils::convert::from_str_optional;
+use chrono::{DateTime, Utc};
+use entity::{app_releases, apps};
+use salvo_oapi::ToSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use validator::Validate;
+
+/// 更新通道
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ReleaseChannel {
+    /// 正式版
+    #[default]
+    Stable,
+    /// 测试版
+    Beta,
+}
+
+impl ReleaseChannel {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ReleaseChannel::Stable => "stable",
+            ReleaseChannel::Beta => "beta",
+        }
+    }
+
+    /// 客户端可以收到的通道，测试版客户端同时接收正式版
+    pub fn visible(self) -> Vec<&'static str> {
+        match self {
+            ReleaseChannel::Stable => vec!["stable"],
+            ReleaseChannel::Beta => vec!["stable", "beta"],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ToSchema)]
+#[repr(i16)]
+#[serde(from = "i16", into = "i16")]
+pub enum ReleaseStatus {
+    #[default]
+    Draft = 0, // 草稿，客户端不可见
+    Published = 1,  // 已发布
+    Paused = 2,     // 暂停灰度，客户端回到上一个版本，可恢复
+    RolledBack = 3, // 已回滚，不能再次发布
+}
+
+impl From<i16> for ReleaseStatus {
+    fn from(value: i16) -> Self {
+        match value {
+            1 => ReleaseStatus::Published,
+            2 => ReleaseStatus::Paused,
+            3 => ReleaseStatus::RolledBack,
+            _ => ReleaseStatus::Draft,
+        }
+    }
+}
+
+impl From<ReleaseStatus> for i16 {
+    fn from(value: ReleaseStatus) -> Self {
+        value as i16
+    }
+}
+
+/// 版本操作类型，写入 app_release_events.action
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ReleaseAction {
+    Create,
+    Update,
+    Publish,
+    Pause,
+    Resume,
+    Rollback,
+    Delete,
+    Manifest,
+}
+
+impl ReleaseAction {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ReleaseAction::Create => "create",
+            ReleaseAction::Update => "update",
+            ReleaseAction::Publish => "publish",
+            ReleaseAction::Pause => "pause",
+            ReleaseAction::Resume => "resume",
+            ReleaseAction::Rollback => "rollback",
+            ReleaseAction::Delete => "delete",
+            ReleaseAction::Manifest => "manifest",
+        }
+    }
+}
+
+fn default_rollout_percent() -> i16 {
+    100
+}
+
+#[derive(Serialize, Deserialize, Debug, Validate, ToSchema)]
+pub struct CreateAppReleaseReq {
+    pub app_id: i32,
+    #[serde(default)]
+    pub channel: ReleaseChannel,
+    #[validate(length(min = 1, max = 64))]
+    pub vername: String,
+    #[validate(range(min = 1))]
+    pub vercode: i32,
+    /// 各平台下载地址，key 为平台标识，如 windows / macos / linux / android
+    #[serde(default)]
+    pub downloads: BTreeMap<String, String>,
+    pub release_notes: Option<String>,
+    /// 最低支持的版本号，低于该版本的客户端必须更新
+    #[serde(default)]
+    pub min_vercode: i32,
+    #[serde(default)]
+    pub force_update: bool,
+    /// 灰度比例 0-100，按设备 ID 哈希决定设备是否收到该版本
+    #[serde(default = "default_rollout_percent")]
+    #[validate(range(min = 0, max = 100))]
+    pub rollout_percent: i16,
+    /// 只能为草稿或已发布，暂停和回滚使用单独的接口
+    #[serde(default)]
+    pub status: ReleaseStatus,
+}
+
+#[derive(Serialize, Deserialize, Debug, Validate, ToSchema)]
+pub struct UpdateAppReleaseReq {
+    #[validate(length(min = 1, max = 64))]
+    pub vername: Option<String>,
+    pub downloads: Option<BTreeMap<String, String>>,
+    pub release_notes: Option<String>,
+    pub min_vercode: Option<i32>,
+    pub force_update: Option<bool>,
+    #[validate(range(min = 0, max = 100))]
+    pub rollout_percent: Option<i16>,
+    /// 只能为草稿或已发布，暂停和回滚使用单独的接口
+    pub status: Option<ReleaseStatus>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct SearchAppReleasesParams {
+    #[serde(flatten)]
+    pub pagination: ListParamsReq,
+    #[serde(deserialize_with = "from_str_optional", default)]
+    pub app_id: Option<i32>,
+    pub channel: Option<ReleaseChannel>,
+    #[serde(deserialize_with = "from_str_optional", default)]
+    pub status: Option<i16>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct CheckUpdateParams {
+    /// 客户端当前版本号
+    #[serde(deserialize_with = "from_str_optional", default)]
+    pub vercode: Option<i32>,
+    #[serde(default)]
+    pub channel: ReleaseChannel,
+    /// 只返回提供了该平台下载地址的版本
+    pub platform: Option<String>,
+    /// 设备 ID，用于灰度分桶，不传时只能收到全量发布的版本
+    pub device_id: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct SearchAppReleaseEventsParams {
+    #[serde(flatten)]
+    pub pagination: ListParamsReq,
+    #[serde(deserialize_with = "from_str_optional", default)]
+    pub app_id: Option<i32>,
+    #[serde(deserialize_with = "from_str_optional", default)]
+    pub release_id: Option<i32>,
+}
+
+/// apps 上兼容旧客户端的版本字段
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AppVersionFields {
+    pub app_vername: String,
+    pub app_vercode: i32,
+    pub app_download_url: String,
+    pub app_update_info: Option<String>,
+}
+
+impl AppVersionFields {
+    pub fn of(app: &apps::Model) -> Self {
+        Self {
+            app_vername: app.app_vername.clone(),
+            app_vercode: app.app_vercode,
+            app_download_url: app.app_download_url.clone(),
+            app_update_info: app.app_update_info.clone(),
+        }
+    }
+}
+
+/// 客户端可见的版本信息
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct AppReleaseInfo {
+    pub channel: String,
+    pub vername: String,
+    pub vercode: i32,
+    pub downloads: BTreeMap<String, String>,
+    /// 请求中指定平台时为该平台的下载地址
+    pub download_url: Option<String>,
+    pub release_notes: Option<String>,
+    pub min_vercode: i32,
+    pub rollout_percent: i16,
+    pub published_at: Option<DateTime<Utc>>,
+}
+
+impl AppReleaseInfo {
+    pub fn new(release: app_releases::Model, platform: Option<&str>) -> Self {
+        let downloads: BTreeMap<String, String> =
+            serde_json::from_value(release.downloads).unwrap_or_default();
+        let download_url = platform.and_then(|p| downloads.get(p).cloned());
+        Self {
+            channel: release.channel,
+            vername: release.vername,
+            vercode: release.vercode,
+            downloads,
+            download_url,
+            release_notes: release.release_notes,
+            min_vercode: release.min_vercode,
+            rollout_percent: release.rollout_percent,
+            published_at: release.published_at,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct CheckUpdateResp {
+    pub has_update: bool,
+    /// 当前版本低于最新版的 min_vercode，或跳过的版本中有强制更新
+    pub force_update: bool,
+    /// 当前版本已被回滚，release 为应降级到的版本
+    pub rollback: bool,
+    pub release: Option<AppReleaseInfo>,
+}
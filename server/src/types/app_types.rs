@@ -17,6 +17,15 @@ pub struct AddAppReq {
     pub app_update_info: Option<String>,
     pub app_valid_key: Option<String>,
     pub trial_days: Option<i32>,
+    pub offline_grace_days: Option<i32>,
+    /// 校验注册码前先检查校验段
+    pub strict_code_format: Option<bool>,
+    /// 是否允许旧版 /api/reg/validate 接口
+    pub allow_legacy_validate: Option<bool>,
+    /// 每个注册码允许自助解绑的次数，0 表示不允许
+    pub max_transfers: Option<i32>,
+    /// 两次自助解绑的最小间隔（小时）
+    pub transfer_cooldown_hours: Option<i32>,
     pub sort_order: i32,
     pub status: i16,
 }
@@ -32,6 +41,15 @@ pub struct UpdateAppReq {
     pub app_update_info: Option<String>,
     pub app_valid_key: Option<String>,
     pub trial_days: Option<i32>,
+    pub offline_grace_days: Option<i32>,
+    /// 校验注册码前先检查校验段
+    pub strict_code_format: Option<bool>,
+    /// 是否允许旧版 /api/reg/validate 接口
+    pub allow_legacy_validate: Option<bool>,
+    /// 每个注册码允许自助解绑的次数，0 表示不允许
+    pub max_transfers: Option<i32>,
+    /// 两次自助解绑的最小间隔（小时）
+    pub transfer_cooldown_hours: Option<i32>,
     pub sort_order: Option<i32>,
     pub status: Option<i16>,
 }
@@ -1,5 +1,6 @@
 use super::config::Config;
 use crate::services::casbin_service::CasbinService;
+use crate::services::validation_log::ValidationLogger;
 use crate::utils::convert::from_str_optional;
 use crate::utils::redis_cache::RedisCache;
 use aliyun_sts::StsClient;
@@ -39,6 +40,7 @@ pub struct AppState {
     pub config: Arc<Config>,
     pub casbin: Arc<CasbinService>,
     pub aliyun_sts: Arc<StsClient>,
+    pub validation_log: ValidationLogger,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
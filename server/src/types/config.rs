@@ -1,5 +1,7 @@
 use crate::types::error::AppError;
+use ipnet::IpNet;
 use std::env;
+use std::net::IpAddr;
 
 #[derive(Debug, Clone)]
 pub struct Config {
@@ -9,6 +11,10 @@ pub struct Config {
     pub server: ServerConfig,
     pub oss: OssConfig,
     pub register_open: bool,
+    /// 是否在本实例运行定时任务，多实例部署时由 Redis 锁保证同一任务只有一个实例执行
+    pub scheduler_enabled: bool,
+    /// 受信任的反向代理，只有来自这些地址的请求才采用 X-Forwarded-For / X-Real-IP
+    pub trusted_proxies: Vec<IpNet>,
 }
 
 #[derive(Debug, Clone)]
@@ -42,7 +48,8 @@ pub struct ServerConfig {
 pub struct OssConfig {
     pub region: String,
     pub bucket: String,
-    // pub endpoint: String,
+    /// 绑定到 bucket 的自定义域名，为空时使用 region 的默认域名
+    pub endpoint: String,
     pub role_arn: String,
     pub access_key_id: String,
     pub sts_expire_secs: u32,
@@ -61,10 +68,29 @@ impl Config {
                 .unwrap_or_else(|_| "false".to_string())
                 .parse()
                 .map_err(|_| AppError::Message("Invalid REGISTER_OPEN value".to_string()))?,
+            scheduler_enabled: env::var("SCHEDULER_ENABLED")
+                .unwrap_or_else(|_| "true".to_string())
+                .parse()
+                .map_err(|_| AppError::Message("Invalid SCHEDULER_ENABLED value".to_string()))?,
+            trusted_proxies: parse_trusted_proxies(&env::var("TRUSTED_PROXIES").unwrap_or_default())?,
         })
     }
 }
 
+/// 逗号分隔的 IP 或网段，例如 127.0.0.1,10.0.0.0/8
+fn parse_trusted_proxies(value: &str) -> Result<Vec<IpNet>, AppError> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            s.parse::<IpNet>()
+                .or_else(|_| s.parse::<IpAddr>().map(IpNet::from))
+                .map_err(|_| AppError::Message(format!("Invalid TRUSTED_PROXIES value: {}", s)))
+        })
+        .collect()
+}
+
 impl DatabaseConfig {
     fn from_env() -> Result<Self, AppError> {
         Ok(DatabaseConfig {
@@ -129,7 +155,7 @@ impl OssConfig {
         Ok(OssConfig {
             region: env::var("OSS_REGION").unwrap_or_default(),
             bucket: env::var("OSS_BUCKET").unwrap_or_default(),
-            // endpoint: env::var("OSS_ENDPOINT").unwrap_or_default(),
+            endpoint: env::var("OSS_ENDPOINT").unwrap_or_default(),
             role_arn: env::var("OSS_ROLE_ARN").unwrap_or_default(),
             sts_expire_secs: env::var("OSS_STS_EXPIRE_SECS")
                 .unwrap_or_else(|_| "3600".to_string())
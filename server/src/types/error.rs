@@ -93,7 +93,13 @@ impl AppError {
             Self::Validation { .. } => crate::constants::APP_VALIDATION_ERROR,
             Self::AuthFailed { .. } => crate::constants::APP_AUTH_FAILED,
             Self::Forbidden { .. } => crate::constants::APP_FORBIDDEN,
-            Self::BusinessLogic { .. } => crate::constants::APP_BUSINESS_LOGIC,
+            Self::BusinessLogic { code, .. } => match code.as_str() {
+                "CODE_REVOKED" | "DEVICE_BANNED" => crate::constants::APP_LICENSE_REVOKED,
+                "CODE_EXPIRED" | "DEVICE_EXPIRED" | "CODE_USED_UP" => {
+                    crate::constants::APP_LICENSE_EXPIRED
+                }
+                _ => crate::constants::APP_BUSINESS_LOGIC,
+            },
             Self::ExternalService { .. } => crate::constants::APP_EXTERNAL_SERVICE,
             Self::UserAlreadyExists => crate::constants::APP_USER_ALREADY_EXISTS,
             Self::NotImplemented { .. } => crate::constants::APP_NOT_IMPLEMENTED,
@@ -170,6 +176,15 @@ impl From<ParseError> for AppError {
     }
 }
 
+impl From<reg_code::Error> for AppError {
+    fn from(err: reg_code::Error) -> Self {
+        match err {
+            reg_code::Error::InvalidFormat(_) => Self::validation(err.to_string()),
+            _ => Self::business_logic("INVALID_CODE_FORMAT", err.to_string()),
+        }
+    }
+}
+
 #[salvo::async_trait]
 impl Writer for AppError {
     async fn write(self, _req: &mut Request, _depot: &mut Depot, res: &mut Response) {
@@ -0,0 +1,20 @@
+use crate::types::common::ListParamsReq;
+use crate::utils::convert::from_str_optional;
+use serde::{Deserialize, Serialize};
+
+/// 已注册的定时任务及最近一次执行记录
+#[derive(Serialize, Debug)]
+pub struct JobInfo {
+    pub name: String,
+    pub interval_secs: u64,
+    pub last_run: Option<entity::job_runs::Model>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct SearchJobRunsParams {
+    #[serde(flatten)]
+    pub pagination: ListParamsReq,
+    pub job_name: Option<String>,
+    #[serde(deserialize_with = "from_str_optional", default)]
+    pub success: Option<bool>,
+}
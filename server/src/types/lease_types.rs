@@ -0,0 +1,35 @@
+use crate::types::app_features_types::Entitlements;
+use chrono::{DateTime, Utc};
+use salvo_oapi::ToSchema;
+use serde::{Deserialize, Serialize};
+
+/// 浮动授权的租约
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct LeaseInfo {
+    pub device_id: String,
+    /// 租约到期时间，到期前需心跳续约
+    pub expire_at: DateTime<Utc>,
+}
+
+/// checkout / heartbeat 的结果，签名后放在 RegCodeValidateV2Resp.payload 中
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct LeaseResp {
+    pub lease: LeaseInfo,
+    /// 建议的心跳间隔（秒）
+    pub heartbeat_interval: i64,
+    pub seats_total: i32,
+    pub seats_used: i32,
+    /// 注册码本身的过期时间
+    pub expire_time: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub entitlements: Entitlements,
+}
+
+/// 注册码的席位占用情况
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct SeatUsageResp {
+    pub reg_code_id: i32,
+    pub seats_total: i32,
+    pub seats_used: i32,
+    pub leases: Vec<LeaseInfo>,
+}
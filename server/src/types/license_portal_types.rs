@@ -0,0 +1,77 @@
+use crate::types::reg_codes_types::{CodeType, RegCodeDeviceInfo, RegCodeInfo};
+use chrono::{DateTime, Utc};
+use salvo_oapi::ToSchema;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+/// 买家用订单号加联系邮箱登录
+#[derive(Serialize, Deserialize, Debug, Validate, ToSchema)]
+pub struct PortalLoginReq {
+    /// 订单号（orders.order_id）
+    #[validate(length(min = 1))]
+    pub order_no: String,
+    #[validate(email)]
+    pub email: String,
+}
+
+/// 买家用一次性登录链接中的令牌登录
+#[derive(Serialize, Deserialize, Debug, Validate, ToSchema)]
+pub struct PortalMagicLoginReq {
+    #[validate(length(min = 1))]
+    pub token: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct PortalLoginResp {
+    /// 门户会话令牌，后续请求放在 Authorization: Bearer 中
+    pub token: String,
+    pub email: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// 管理员为订单生成的一次性登录链接令牌
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct PortalLinkResp {
+    pub token: String,
+    pub email: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// 门户会话，订单号登录只能访问该订单，登录链接可访问该邮箱下全部已支付订单
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PortalSession {
+    pub email: String,
+    pub order_id: Option<i32>,
+}
+
+/// 买家可见的注册码信息
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct PortalCodeInfo {
+    pub id: i32,
+    pub code: String,
+    pub app_name: Option<String>,
+    pub code_type: CodeType,
+    pub status: i16,
+    pub max_devices: i32,
+    pub expire_time: Option<DateTime<Utc>>,
+    pub total_count: Option<i32>,
+    pub use_count: i32,
+    pub devices: Vec<RegCodeDeviceInfo>,
+}
+
+impl From<RegCodeInfo> for PortalCodeInfo {
+    fn from(info: RegCodeInfo) -> Self {
+        Self {
+            id: info.id,
+            code: info.code,
+            app_name: info.app_name,
+            code_type: info.code_type,
+            status: info.status,
+            max_devices: info.max_devices,
+            expire_time: info.expire_time,
+            total_count: info.total_count,
+            use_count: info.use_count,
+            devices: info.devices,
+        }
+    }
+}
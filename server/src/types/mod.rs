@@ -1,17 +1,31 @@
 pub mod app_types;
+pub mod app_announcements_types;
+pub mod app_configs_types;
+pub mod app_features_types;
+pub mod app_releases_types;
 pub mod casbin_types;
 pub mod common;
 pub mod config;
 pub mod coupons_types;
 pub mod error;
 pub mod invite_records_types;
+pub mod jobs_types;
+pub mod license_portal_types;
 pub mod orders_types;
 pub mod pay_method_types;
 pub mod pay_types;
 pub mod product_types;
 pub mod resource_types;
 pub mod reg_codes_types;
+pub mod reg_code_batches_types;
+pub mod release_manifest_types;
+pub mod reseller_types;
 pub mod response;
 pub mod role_types;
 pub mod user_types;
-pub mod app_devices_types;
\ No newline at end of file
+pub mod app_devices_types;
+pub mod lease_types;
+pub mod offline_activation_types;
+pub mod validation_events_types;
+pub mod trial_types;
+pub mod usage_types;
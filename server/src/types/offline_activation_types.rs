@@ -0,0 +1,50 @@
+use crate::types::app_features_types::Entitlements;
+use crate::types::reg_codes_types::CodeType;
+use chrono::{DateTime, Utc};
+use salvo_oapi::ToSchema;
+use serde::{Deserialize, Serialize};
+
+/// 离线激活请求文件，由无法联网的客户端生成后交给管理员或买家上传
+/// sign = hex(HMAC-SHA256(app_valid_key, sign_content()))
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct OfflineActivationReq {
+    /// 应用的 app_id
+    pub app_id: String,
+    pub code: String,
+    pub device_id: String,
+    /// 客户端采集的设备指纹，不参与签名
+    #[serde(default)]
+    pub device_info: Option<serde_json::Value>,
+    /// 生成时间（unix 秒）
+    pub created_at: i64,
+    /// 随机串，原样返回在响应文件中，客户端据此匹配请求
+    pub nonce: String,
+    pub sign: String,
+}
+
+impl OfflineActivationReq {
+    /// 待签名内容：固定前缀 offline 与 app_id、device_id、code、created_at、nonce 以换行拼接
+    pub fn sign_content(&self) -> String {
+        format!(
+            "offline\n{}\n{}\n{}\n{}\n{}",
+            self.app_id, self.device_id, self.code, self.created_at, self.nonce
+        )
+    }
+}
+
+/// 离线激活响应文件，客户端导入后用应用公钥校验 license_token
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct OfflineActivationResp {
+    pub app_id: String,
+    pub device_id: String,
+    pub nonce: String,
+    /// 激活的注册码，客户端保存后联网时继续使用
+    pub code: String,
+    pub code_type: CodeType,
+    pub expire_time: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub entitlements: Entitlements,
+    /// 签名许可证，载荷中 offline_activation 为 true
+    pub license_token: String,
+    pub issued_at: DateTime<Utc>,
+}
@@ -12,6 +12,8 @@ pub struct CreateOrderReq {
     pub original_price: i64,
     pub final_price: i64,
     pub remark: Option<String>,
+    #[validate(email)]
+    pub contact_email: Option<String>,
     pub created_by: i32,
     pub updated_by: i32,
 }
@@ -25,6 +27,8 @@ pub struct UpdateOrderReq {
     pub original_price: Option<i64>,
     pub final_price: Option<i64>,
     pub remark: Option<String>,
+    #[validate(email)]
+    pub contact_email: Option<String>,
     pub updated_by: Option<i32>,
 }
 
@@ -41,6 +45,8 @@ pub struct SearchOrdersParams {
     #[serde(default)]
     pub pay_method_id: Option<i32>,
     #[serde(default)]
+    pub contact_email: Option<String>,
+    #[serde(default)]
     pub created_by: Option<i32>,
 }
 
@@ -54,6 +60,7 @@ pub struct OrderInfo {
     pub original_price: i64,
     pub final_price: i64,
     pub remark: Option<String>,
+    pub contact_email: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub created_by: i32,
@@ -79,6 +86,7 @@ impl TryFrom<(entity::orders::Model, Option<entity::pay_methods::Model>, Option<
             original_price: order.original_price,
             final_price: order.final_price,
             remark: order.remark,
+            contact_email: order.contact_email,
             created_at: order.created_at,
             updated_at: order.updated_at,
             created_by: order.created_by,
@@ -103,6 +111,7 @@ impl TryFrom<entity::orders::Model> for OrderInfo {
             original_price: order.original_price,
             final_price: order.final_price,
             remark: order.remark,
+            contact_email: order.contact_email,
             created_at: order.created_at,
             updated_at: order.updated_at,
             created_by: order.created_by,
@@ -112,4 +121,9 @@ impl TryFrom<entity::orders::Model> for OrderInfo {
             updated_by_username: None,
         })
     }
-} 
\ No newline at end of file
+} 
+#[derive(Serialize, Deserialize, Debug, Validate)]
+pub struct LinkOrderRegCodesReq {
+    #[validate(length(min = 1))]
+    pub reg_code_ids: Vec<i32>,
+}
@@ -2,6 +2,7 @@ use serde::{Deserialize, Serialize};
 use validator::Validate;
 use crate::utils::convert::from_str_optional;
 
+use crate::types::app_features_types::Entitlements;
 use crate::types::common::ListParamsReq;
 
 #[derive(Deserialize, Debug, Validate)]
@@ -15,6 +16,8 @@ pub struct ProductCreatePayload {
     pub tags: Option<Vec<String>>,
     pub status: i16,
     pub remark: Option<String>,
+    /// 购买后授予的功能开关与限额
+    pub entitlements: Option<Entitlements>,
 }
 
 #[derive(Deserialize, Debug, Validate)]
@@ -28,6 +31,7 @@ pub struct ProductUpdatePayload {
     pub tags: Option<Vec<String>>,
     pub status: Option<i16>,
     pub remark: Option<String>,
+    pub entitlements: Option<Entitlements>,
 }
 
 #[derive(Serialize, Debug)]
@@ -0,0 +1,86 @@
+use crate::types::common::ListParamsReq;
+use crate::utils::convert::from_str_optional;
+use crate::utils::export::ExportFormat;
+use salvo_oapi::ToSchema;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Serialize, Deserialize, Debug, Default, Validate, ToSchema)]
+pub struct CreateRegCodeBatchReq {
+    pub app_id: i32,
+    #[validate(length(min = 1, max = 255))]
+    pub name: String,
+    /// 发放渠道，代理商或活动名称
+    pub channel: Option<String>,
+    pub notes: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Validate, ToSchema)]
+pub struct UpdateRegCodeBatchReq {
+    #[validate(length(min = 1, max = 255))]
+    pub name: Option<String>,
+    pub channel: Option<String>,
+    pub notes: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct SearchRegCodeBatchesParams {
+    #[serde(flatten)]
+    pub pagination: ListParamsReq,
+    #[serde(deserialize_with = "from_str_optional", default)]
+    pub app_id: Option<i32>,
+    #[serde(default)]
+    pub channel: Option<String>,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(deserialize_with = "from_str_optional", default)]
+    pub reseller_id: Option<i32>,
+    /// 是否已作废
+    #[serde(deserialize_with = "from_str_optional", default)]
+    pub voided: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct VoidRegCodeBatchReq {
+    /// 作废原因，同时作为批次内注册码的吊销原因
+    pub reason: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct ExtendRegCodeBatchReq {
+    /// 延长的天数
+    pub days: i32,
+}
+
+/// 批次操作影响的注册码和设备数
+#[derive(Serialize, Debug)]
+pub struct RegCodeBatchActionResp {
+    pub batch: entity::reg_code_batches::Model,
+    pub codes: u64,
+    pub devices: u64,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ExportRegCodeBatchParams {
+    /// 导出格式（csv / json）
+    pub format: ExportFormat,
+}
+
+/// 批次内注册码的兑换统计
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct RegCodeBatchStats {
+    pub batch_id: i32,
+    pub total: i64,
+    pub unused: i64,
+    pub used: i64,
+    pub expired: i64,
+    pub revoked: i64,
+    /// 已兑换（绑定过设备）的注册码数，含之后过期或吊销的
+    pub redeemed: i64,
+    /// 兑换率，百分比
+    pub redeem_rate: f64,
+    /// 计次码累计消耗次数
+    pub use_count: i64,
+    /// 已绑定的设备数
+    pub devices: i64,
+}
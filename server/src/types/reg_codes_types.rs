@@ -1,7 +1,10 @@
 use serde::{Deserialize, Serialize};
 use validator::Validate;
 use chrono::{DateTime, Utc};
+use crate::types::app_configs_types::{AppConfigResp, ConfigQuery};
+use crate::types::app_features_types::Entitlements;
 use crate::types::common::ListParamsReq;
+use crate::utils::export::ExportFormat;
 // use utoipa::ToSchema;
 use salvo_oapi::ToSchema;
 
@@ -11,6 +14,7 @@ use salvo_oapi::ToSchema;
 pub enum CodeType {
     Time = 0,   // 时间类型
     Count = 1,  // 次数类型
+    Floating = 2, // 浮动授权，max_devices 为并发席位数
 }
 
 impl Default for CodeType {
@@ -22,6 +26,7 @@ impl From<i16> for CodeType {
         match value {
             0 => CodeType::Time,
             1 => CodeType::Count,
+            2 => CodeType::Floating,
             _ => CodeType::Time,
         }
     }
@@ -40,6 +45,7 @@ pub enum RegCodeStatus {
     Unused = 0,
     Used = 1,
     Expired = 2,
+    Revoked = 3,
 }
 
 impl Default for RegCodeStatus {
@@ -52,6 +58,7 @@ impl From<i16> for RegCodeStatus {
             0 => RegCodeStatus::Unused,
             1 => RegCodeStatus::Used,
             2 => RegCodeStatus::Expired,
+            3 => RegCodeStatus::Revoked,
             _ => RegCodeStatus::Unused,
         }
     }
@@ -67,12 +74,20 @@ impl From<RegCodeStatus> for i16 {
 pub struct CreateRegCodeReq {
     pub code: String,
     pub app_id: i32,
+    #[validate(range(min = 0))]
     pub valid_days: i32,
+    #[validate(range(min = 1))]
     pub max_devices: i32,
     pub status: RegCodeStatus,
     pub code_type: CodeType,
     pub expire_time: Option<DateTime<Utc>>,
+    /// 计次码必填
+    #[validate(range(min = 1))]
     pub total_count: Option<i32>,
+    /// 额外授予的功能开关与限额
+    pub entitlements: Option<Entitlements>,
+    /// 所属批次
+    pub batch_id: Option<i32>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Validate,ToSchema)]
@@ -80,6 +95,12 @@ pub struct RegCodeValidateReq {
     pub code: Option<String>,
     pub app_key: String,
     pub device_id: String,
+    /// 客户端采集的设备信息（硬件、系统等），首次激活时保存并用于试用指纹比对
+    #[serde(default)]
+    pub device_info: Option<serde_json::Value>,
+    /// 传入时在响应中一并返回远程配置
+    #[serde(default)]
+    pub config: Option<ConfigQuery>,
 }
 
 #[derive(Serialize, Deserialize, Debug,ToSchema)]
@@ -87,17 +108,138 @@ pub struct RegCodeValidateResp {
     pub code_type: CodeType,
     pub expire_time: Option<DateTime<Utc>>,
     pub remaining_count: Option<i32>,
+    /// 签名许可证，客户端可用 /api/reg/public_key 返回的公钥离线校验
+    pub license_token: Option<String>,
+    /// 合并后的功能开关与限额
+    #[serde(default)]
+    pub entitlements: Entitlements,
+    /// 请求中带 config 时返回的远程配置
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config: Option<AppConfigResp>,
+}
+
+/// v2 校验请求，不再明文传输 app_key
+/// sign = hex(HMAC-SHA256(app_valid_key, sign_content()))
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct RegCodeValidateV2Req {
+    /// 应用的 app_id
+    pub app_id: String,
+    pub device_id: String,
+    pub code: Option<String>,
+    /// 客户端时间（unix 秒）
+    pub timestamp: i64,
+    /// 一次性随机串，同一应用内不可重复使用
+    pub nonce: String,
+    pub sign: String,
+    /// 客户端采集的设备信息，不参与签名
+    #[serde(default)]
+    pub device_info: Option<serde_json::Value>,
+    /// 传入时在响应中一并返回远程配置，不参与签名
+    #[serde(default)]
+    pub config: Option<ConfigQuery>,
+}
+
+impl RegCodeValidateV2Req {
+    /// 待签名内容：app_id、device_id、code、timestamp、nonce 以换行拼接，code 为空时用空串
+    pub fn sign_content(&self) -> String {
+        format!(
+            "{}\n{}\n{}\n{}\n{}",
+            self.app_id,
+            self.device_id,
+            self.code.as_deref().unwrap_or_default(),
+            self.timestamp,
+            self.nonce
+        )
+    }
+}
+
+/// 按 v2 协议签名的客户端请求，由 reg_codes_handler::verify_signed_request 统一校验
+pub trait SignedRequest {
+    fn app_id(&self) -> &str;
+    fn timestamp(&self) -> i64;
+    fn nonce(&self) -> &str;
+    fn sign(&self) -> &str;
+    fn sign_content(&self) -> String;
+}
+
+impl SignedRequest for RegCodeValidateV2Req {
+    fn app_id(&self) -> &str {
+        &self.app_id
+    }
+    fn timestamp(&self) -> i64 {
+        self.timestamp
+    }
+    fn nonce(&self) -> &str {
+        &self.nonce
+    }
+    fn sign(&self) -> &str {
+        &self.sign
+    }
+    fn sign_content(&self) -> String {
+        RegCodeValidateV2Req::sign_content(self)
+    }
+}
+
+/// v2 校验响应，payload 为 RegCodeValidateResp 的 JSON 字符串
+/// sign = hex(HMAC-SHA256(app_valid_key, sign_content()))，客户端验签后再解析 payload
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct RegCodeValidateV2Resp {
+    pub payload: String,
+    /// 服务器时间（unix 秒）
+    pub timestamp: i64,
+    /// 原样返回请求的 nonce，防止响应被重放
+    pub nonce: String,
+    pub sign: String,
+}
+
+impl RegCodeValidateV2Resp {
+    /// 待签名内容：nonce、timestamp、payload 以换行拼接
+    pub fn sign_content(&self) -> String {
+        format!("{}\n{}\n{}", self.nonce, self.timestamp, self.payload)
+    }
+}
+
+/// 许可证令牌载荷
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct LicenseClaims {
+    pub app_id: String,
+    pub device_id: String,
+    pub code_type: CodeType,
+    /// 过期时间（unix 秒），None 表示不限时
+    pub expire_at: Option<i64>,
+    pub remaining_count: Option<i32>,
+    /// 服务器签发时间（unix 秒）
+    pub issued_at: i64,
+    /// 离线宽限天数，客户端超过 issued_at + 宽限期后需重新联网校验
+    pub offline_grace_days: i32,
+    #[serde(default)]
+    pub entitlements: Entitlements,
+    /// 离线激活签发，设备不会再联网，有效期内不受离线宽限期限制
+    #[serde(default)]
+    pub offline_activation: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct AppPublicKeyResp {
+    pub app_id: String,
+    pub algorithm: String,
+    pub public_key: String,
+    pub offline_grace_days: i32,
 }
 
 #[derive(Serialize, Deserialize, Debug, Validate,ToSchema)]
 pub struct UpdateRegCodeReq {
     pub code: Option<String>,
     pub app_id: Option<i32>,
+    #[validate(range(min = 0))]
     pub valid_days: Option<i32>,
+    #[validate(range(min = 1))]
     pub max_devices: Option<i32>,
     pub status: Option<i16>,
     pub code_type: Option<CodeType>,
+    #[validate(range(min = 1))]
     pub total_count: Option<i32>,
+    pub entitlements: Option<Entitlements>,
 }
 
 #[derive(Deserialize, Debug, Default)]
@@ -114,6 +256,11 @@ pub struct SearchRegCodesParams {
     pub status: Option<i16>,
     #[serde(default)]
     pub code_type: Option<CodeType>,
+    #[serde(default)]
+    pub batch_id: Option<i32>,
+    /// 代理商生成的注册码
+    #[serde(default)]
+    pub reseller_id: Option<i32>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Validate,ToSchema)]
@@ -129,23 +276,34 @@ pub struct RegCodeInfo {
     pub expire_time: Option<DateTime<Utc>>,
     pub total_count: Option<i32>,
     pub use_count: i32,
-    pub device_id: Option<i32>,
-    pub device_id_str: Option<String>,
+    pub revoked_reason: Option<String>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub entitlements: Entitlements,
+    pub batch_id: Option<i32>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub app_name: Option<String>,
-    pub device_info: Option<serde_json::Value>,
+    /// 已绑定的设备
+    pub devices: Vec<RegCodeDeviceInfo>,
 }
 
-impl TryFrom<(entity::reg_codes::Model, Option<entity::apps::Model>, Option<entity::app_devices::Model>)> for RegCodeInfo {
+impl TryFrom<(entity::reg_codes::Model, Option<entity::apps::Model>)> for RegCodeInfo {
     type Error = crate::types::error::AppError;
 
     fn try_from(
-        value: (entity::reg_codes::Model, Option<entity::apps::Model>, Option<entity::app_devices::Model>)
+        value: (entity::reg_codes::Model, Option<entity::apps::Model>)
     ) -> Result<Self, Self::Error> {
-        let (reg_code, app, device) = value;
-        let device_id_str = device.as_ref().map(|d| d.device_id.clone());
-        let device_info = device.as_ref().and_then(|d| d.device_info.clone());
+        let (reg_code, app) = value;
+        let mut info = RegCodeInfo::try_from(reg_code)?;
+        info.app_name = app.map(|a| a.name);
+        Ok(info)
+    }
+}
+
+impl TryFrom<entity::reg_codes::Model> for RegCodeInfo {
+    type Error = crate::types::error::AppError;
+
+    fn try_from(reg_code: entity::reg_codes::Model) -> Result<Self, Self::Error> {
         Ok(Self {
             id: reg_code.id,
             code: reg_code.code,
@@ -158,38 +316,331 @@ impl TryFrom<(entity::reg_codes::Model, Option<entity::apps::Model>, Option<enti
             expire_time: reg_code.expire_time,
             total_count: reg_code.total_count,
             use_count: reg_code.use_count,
-            device_id: reg_code.device_id,
-            device_id_str,
-            device_info,
+            revoked_reason: reg_code.revoked_reason,
+            revoked_at: reg_code.revoked_at,
+            entitlements: Entitlements::from_json(reg_code.entitlements.as_ref())?,
+            batch_id: reg_code.batch_id,
             created_at: reg_code.created_at,
             updated_at: reg_code.updated_at,
-            app_name: app.map(|a| a.name),
+            app_name: None,
+            devices: Vec::new(),
         })
     }
 }
 
-impl TryFrom<entity::reg_codes::Model> for RegCodeInfo {
-    type Error = crate::types::error::AppError;
+/// 注册码绑定的设备
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct RegCodeDeviceInfo {
+    /// app_devices.id
+    pub id: i32,
+    pub device_id: String,
+    pub device_info: Option<serde_json::Value>,
+    pub bind_time: DateTime<Utc>,
+}
 
-    fn try_from(reg_code: entity::reg_codes::Model) -> Result<Self, Self::Error> {
-        Ok(Self {
+impl From<(entity::reg_code_devices::Model, entity::app_devices::Model)> for RegCodeDeviceInfo {
+    fn from(value: (entity::reg_code_devices::Model, entity::app_devices::Model)) -> Self {
+        let (binding, device) = value;
+        Self {
+            id: device.id,
+            device_id: device.device_id,
+            device_info: device.device_info,
+            bind_time: binding.bind_time,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct TransferRegCodeDeviceReq {
+    /// 目标设备ID（客户端上报的 device_id）
+    pub to_device_id: String,
+}
+
+/// 设备解绑/转移的发起方，见 reg_code_transfers.source
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[repr(i16)]
+#[serde(from = "i16", into = "i16")]
+pub enum TransferSource {
+    Client = 0,     // 客户端自助解绑
+    Portal = 1,     // 买家门户解绑
+    AdminUnbind = 2,
+    AdminTransfer = 3,
+}
+
+impl TransferSource {
+    /// 用户自助发起，计入解绑次数和冷却时间
+    pub fn is_self_service(self) -> bool {
+        matches!(self, TransferSource::Client | TransferSource::Portal)
+    }
+}
+
+impl From<i16> for TransferSource {
+    fn from(value: i16) -> Self {
+        match value {
+            1 => TransferSource::Portal,
+            2 => TransferSource::AdminUnbind,
+            3 => TransferSource::AdminTransfer,
+            _ => TransferSource::Client,
+        }
+    }
+}
+
+impl From<TransferSource> for i16 {
+    fn from(value: TransferSource) -> Self {
+        value as i16
+    }
+}
+
+/// 自助解绑结果，客户端解绑时签名后放在 RegCodeValidateV2Resp.payload 中
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct RegCodeDeactivateResp {
+    /// 已使用的自助解绑次数（含本次）
+    pub transfers_used: i32,
+    pub transfers_left: i32,
+    /// 下一次允许自助解绑的时间
+    pub next_transfer_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct RegCodeTransferInfo {
+    pub id: i32,
+    pub from_device: String,
+    pub to_device: Option<String>,
+    pub source: TransferSource,
+    pub ip: Option<String>,
+    pub operator_id: Option<i32>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<entity::reg_code_transfers::Model> for RegCodeTransferInfo {
+    fn from(value: entity::reg_code_transfers::Model) -> Self {
+        Self {
+            id: value.id,
+            from_device: value.from_device,
+            to_device: value.to_device,
+            source: value.source.into(),
+            ip: value.ip,
+            operator_id: value.operator_id,
+            created_at: value.created_at,
+        }
+    }
+}
+
+/// 批量生成注册码
+#[derive(Serialize, Deserialize, Debug, Validate, ToSchema)]
+pub struct BatchCreateRegCodesReq {
+    pub app_id: i32,
+    /// 生成数量
+    #[validate(range(min = 1, max = 10000))]
+    pub count: u32,
+    #[validate(range(min = 0))]
+    pub valid_days: i32,
+    #[validate(range(min = 1))]
+    pub max_devices: i32,
+    pub code_type: CodeType,
+    pub expire_time: Option<DateTime<Utc>>,
+    /// 计次码必填
+    #[validate(range(min = 1))]
+    pub total_count: Option<i32>,
+    pub entitlements: Option<Entitlements>,
+    /// 注册码格式模板，生成与校验逻辑在 reg-code 库中
+    #[serde(default)]
+    #[salvo(schema(value_type = Object))]
+    pub format: reg_code::CodeFormat,
+    /// 加入已有批次，不传时按 batch_name / channel / notes 新建批次
+    #[serde(default)]
+    pub batch_id: Option<i32>,
+    /// 新建批次的名称，默认按生成时间命名
+    #[serde(default)]
+    pub batch_name: Option<String>,
+    #[serde(default)]
+    pub channel: Option<String>,
+    #[serde(default)]
+    pub notes: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct BatchCreateRegCodesParams {
+    /// 指定后直接下载生成结果（csv / json）
+    #[serde(default)]
+    pub export: Option<ExportFormat>,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct BatchCreateRegCodesResp {
+    pub count: usize,
+    pub codes: Vec<RegCodeExportRow>,
+}
+
+/// 导出的注册码行
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct RegCodeExportRow {
+    pub id: i32,
+    pub code: String,
+    pub app_id: i32,
+    pub code_type: i16,
+    pub status: i16,
+    pub valid_days: i32,
+    pub max_devices: i32,
+    pub total_count: Option<i32>,
+    pub use_count: i32,
+    pub expire_time: Option<DateTime<Utc>>,
+    pub batch_id: Option<i32>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<entity::reg_codes::Model> for RegCodeExportRow {
+    fn from(reg_code: entity::reg_codes::Model) -> Self {
+        Self {
             id: reg_code.id,
             code: reg_code.code,
             app_id: reg_code.app_id,
+            code_type: reg_code.code_type,
+            status: reg_code.status,
             valid_days: reg_code.valid_days,
             max_devices: reg_code.max_devices,
-            status: reg_code.status,
-            binding_time: reg_code.binding_time,
-            code_type: CodeType::from(reg_code.code_type),
-            expire_time: reg_code.expire_time,
             total_count: reg_code.total_count,
             use_count: reg_code.use_count,
-            device_id: reg_code.device_id,
-            device_id_str: None,
-            device_info: None,
+            expire_time: reg_code.expire_time,
+            batch_id: reg_code.batch_id,
             created_at: reg_code.created_at,
-            updated_at: reg_code.updated_at,
-            app_name: None,
-        })
+        }
     }
-} 
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct RevokeRegCodeReq {
+    /// 吊销原因
+    pub reason: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum RevocationKind {
+    /// 已吊销的注册码
+    #[default]
+    Codes,
+    /// 被封禁的设备
+    Devices,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct RevocationListParams {
+    #[serde(flatten)]
+    pub pagination: ListParamsReq,
+    #[serde(default)]
+    pub kind: RevocationKind,
+    /// 只返回该时间（unix 秒）之后变更的记录，用于增量拉取，包括这之后解除的吊销和封禁
+    #[serde(default, deserialize_with = "crate::utils::convert::from_str_optional")]
+    pub since: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct RevocationItem {
+    /// 注册码为 hex(sha256(code))，设备为 device_id
+    pub value: String,
+    pub reason: String,
+    /// 吊销时间；restored 为 true 时为解除时间
+    pub revoked_at: DateTime<Utc>,
+    /// 该记录在 since 之后已解除（注册码恢复或设备解封），客户端应从本地列表移除，只在增量拉取时出现
+    #[serde(default)]
+    pub restored: bool,
+}
+
+/// 吊销列表，签名后放在 RevocationListResp.token 中
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct RevocationList {
+    pub app_id: String,
+    pub kind: RevocationKind,
+    pub page: u64,
+    pub total: u64,
+    pub items: Vec<RevocationItem>,
+    /// 服务器签发时间（unix 秒）
+    pub issued_at: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct RevocationListResp {
+    /// 与许可证令牌相同的签名格式，用 /api/reg/public_key 返回的公钥校验后得到 RevocationList
+    pub token: String,
+}
+
+/// 导入时注册码与已有注册码重复的处理方式
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum DuplicateMode {
+    /// 跳过重复的行
+    Skip,
+    /// 用导入的数据覆盖已有注册码，已绑定的设备保留
+    Overwrite,
+    /// 存在重复时整个导入失败
+    #[default]
+    Fail,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ImportRegCodesParams {
+    #[serde(deserialize_with = "crate::utils::convert::from_str")]
+    pub app_id: i32,
+    /// 请求体格式（csv / json）
+    pub format: ExportFormat,
+    /// 只校验不写入
+    #[serde(default, deserialize_with = "crate::utils::convert::from_str_optional")]
+    pub dry_run: Option<bool>,
+    #[serde(default)]
+    pub on_duplicate: DuplicateMode,
+    /// 写入的注册码归入该批次，不传时新建一个导入批次
+    #[serde(default, deserialize_with = "crate::utils::convert::from_str_optional")]
+    pub batch_id: Option<i32>,
+}
+
+/// 导入的注册码行，CSV 表头与字段名一致
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct ImportRegCodeRow {
+    pub code: String,
+    /// 不传时新注册码为时长码，覆盖时保留原类型
+    #[serde(default)]
+    pub code_type: Option<CodeType>,
+    #[serde(default)]
+    pub valid_days: Option<i32>,
+    #[serde(default)]
+    pub max_devices: Option<i32>,
+    /// 时长码的过期时间，同时作为导入设备的过期时间
+    #[serde(default)]
+    pub expire_time: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub binding_time: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub total_count: Option<i32>,
+    /// 计次码的剩余次数，不传时新注册码视为未使用，覆盖时保留已用次数
+    #[serde(default)]
+    pub remaining_count: Option<i32>,
+    /// 已绑定的设备 device_id，CSV 中以 ; 分隔
+    #[serde(default, deserialize_with = "crate::utils::convert::string_list")]
+    pub devices: Vec<String>,
+    /// 不传时新注册码不吊销，覆盖时保留原吊销状态
+    #[serde(default)]
+    pub revoked: Option<bool>,
+}
+
+/// 导入失败的行，row 从 1 开始且不含 CSV 表头
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct ImportRowError {
+    pub row: usize,
+    pub code: Option<String>,
+    pub message: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, ToSchema)]
+pub struct ImportRegCodesResp {
+    pub dry_run: bool,
+    /// 是否已写入；有任一行出错时不写入
+    pub applied: bool,
+    pub total: usize,
+    pub created: usize,
+    pub updated: usize,
+    pub skipped: usize,
+    /// 新绑定的设备数
+    pub devices_bound: usize,
+    pub errors: Vec<ImportRowError>,
+}
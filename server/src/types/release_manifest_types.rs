@@ -0,0 +1,63 @@
+use crate::types::app_releases_types::ReleaseChannel;
+use crate::utils::convert::from_str_optional;
+use salvo_oapi::ToSchema;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+/// 清单中的一个文件
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+pub struct ManifestFile {
+    /// 相对 base_path 的路径，以 / 分隔
+    pub path: String,
+    pub size: i64,
+    /// hex 编码的 SHA-256
+    pub sha256: String,
+    pub object_key: String,
+    pub url: String,
+}
+
+/// 由 base_path 目录下的 OSS 资源生成版本清单，已有清单时覆盖
+#[derive(Serialize, Deserialize, Debug, Validate, ToSchema)]
+pub struct BuildManifestReq {
+    /// resources.path 目录，包含子目录
+    #[validate(length(min = 1))]
+    pub base_path: String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct ManifestParams {
+    #[serde(deserialize_with = "from_str_optional", default)]
+    pub vercode: Option<i32>,
+    #[serde(default)]
+    pub channel: ReleaseChannel,
+    /// 客户端当前版本号，传入时返回相对该版本需要下载和删除的文件
+    #[serde(deserialize_with = "from_str_optional", default)]
+    pub from_vercode: Option<i32>,
+}
+
+/// 签名清单的载荷
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
+pub struct ReleaseManifest {
+    pub app_id: String,
+    pub channel: String,
+    pub vername: String,
+    pub vercode: i32,
+    /// 该版本的完整文件列表
+    pub files: Vec<ManifestFile>,
+    pub total_size: i64,
+    pub from_vercode: Option<i32>,
+    /// 相对 from_vercode 新增或内容变化的文件路径，未传 from_vercode 时为全部文件
+    pub changed: Vec<String>,
+    /// 相对 from_vercode 需要删除的文件路径
+    pub removed: Vec<String>,
+    /// changed 中文件的总大小
+    pub download_size: i64,
+    /// 服务器签发时间（unix 秒）
+    pub issued_at: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct ReleaseManifestResp {
+    /// 与许可证令牌相同的签名格式，用 /api/reg/public_key 返回的公钥校验后得到 ReleaseManifest
+    pub token: String,
+}
@@ -0,0 +1,174 @@
+use crate::types::common::ListParamsReq;
+use crate::utils::convert::from_str_optional;
+use salvo_oapi::ToSchema;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+/// 代理商状态
+pub const RESELLER_ACTIVE: i16 = 0;
+pub const RESELLER_DISABLED: i16 = 1;
+
+/// 额度流水类型
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum QuotaKind {
+    /// 购买额度
+    #[default]
+    Purchase,
+    /// 管理员调整，可为负
+    Adjust,
+    /// 生成注册码扣减
+    Generate,
+}
+
+impl QuotaKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            QuotaKind::Purchase => "purchase",
+            QuotaKind::Adjust => "adjust",
+            QuotaKind::Generate => "generate",
+        }
+    }
+}
+
+/// 一次额度变动
+pub struct QuotaChange {
+    pub change: i32,
+    pub kind: QuotaKind,
+    pub batch_id: Option<i32>,
+    pub remark: Option<String>,
+    pub created_by: Option<i32>,
+}
+
+/// 把已有用户设为代理商，用户角色改为 reseller
+#[derive(Serialize, Deserialize, Debug, Validate, ToSchema)]
+pub struct CreateResellerReq {
+    pub user_id: i32,
+    #[validate(length(min = 1, max = 255))]
+    pub name: String,
+    pub contact: Option<String>,
+    /// 可生成注册码的应用
+    #[serde(default)]
+    pub app_ids: Vec<i32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Validate, ToSchema)]
+pub struct UpdateResellerReq {
+    #[validate(length(min = 1, max = 255))]
+    pub name: Option<String>,
+    pub contact: Option<String>,
+    /// 0: 正常 1: 停用
+    pub status: Option<i16>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct SearchResellersParams {
+    #[serde(flatten)]
+    pub pagination: ListParamsReq,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(deserialize_with = "from_str_optional", default)]
+    pub status: Option<i16>,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct AdjustQuotaReq {
+    /// 正数增加，负数扣减
+    pub change: i32,
+    /// purchase / adjust
+    #[serde(default)]
+    pub kind: QuotaKind,
+    pub remark: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct AssignResellerAppReq {
+    pub app_id: i32,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct SearchQuotaLedgerParams {
+    #[serde(flatten)]
+    pub pagination: ListParamsReq,
+    #[serde(default)]
+    pub kind: Option<QuotaKind>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ResellerInfo {
+    #[serde(flatten)]
+    pub reseller: entity::resellers::Model,
+    /// 已授权的应用
+    pub app_ids: Vec<i32>,
+}
+
+/// 代理商可见的应用信息，不含密钥
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct ResellerAppInfo {
+    pub id: i32,
+    pub name: String,
+    pub app_id: String,
+    /// 可生成注册码的商品
+    pub products: Vec<ResellerProductInfo>,
+}
+
+impl From<entity::apps::Model> for ResellerAppInfo {
+    fn from(app: entity::apps::Model) -> Self {
+        Self {
+            id: app.id,
+            name: app.name,
+            app_id: app.app_id,
+            products: vec![],
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct ResellerProductInfo {
+    pub id: i32,
+    pub name: String,
+    pub valid_days: i32,
+}
+
+impl From<entity::products::Model> for ResellerProductInfo {
+    fn from(product: entity::products::Model) -> Self {
+        Self {
+            id: product.id,
+            name: product.name,
+            valid_days: product.add_valid_days,
+        }
+    }
+}
+
+/// 代理商生成注册码，时长和功能授权取自商品，代理商只能决定数量和批次信息
+#[derive(Serialize, Deserialize, Debug, Validate, ToSchema)]
+pub struct ResellerBatchCreateReq {
+    pub product_id: i32,
+    #[validate(range(min = 1, max = 10000))]
+    pub count: u32,
+    /// 加入自己已有的批次，不传时新建批次
+    #[serde(default)]
+    pub batch_id: Option<i32>,
+    #[serde(default)]
+    pub batch_name: Option<String>,
+    /// 默认为代理商名称
+    #[serde(default)]
+    pub channel: Option<String>,
+    #[serde(default)]
+    pub notes: Option<String>,
+}
+
+/// 代理商的额度与注册码汇总
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct ResellerStats {
+    pub reseller_id: i32,
+    pub quota_balance: i32,
+    /// 累计生成扣减的额度
+    pub generated: i64,
+    pub batches: i64,
+    pub codes: i64,
+    pub redeemed: i64,
+    pub revoked: i64,
+    /// 兑换率，百分比
+    pub redeem_rate: f64,
+}
@@ -11,6 +11,9 @@ pub struct ResourceCreatePayload {
     pub url: String,
     pub path: String,
     pub file_type: String,
+    /// 0: 本地 1: OSS
+    #[serde(default)]
+    pub res_type: i16,
     pub tags: Option<Vec<String>>,
     pub status: i16,
     pub remark: Option<String>,
@@ -23,6 +26,7 @@ pub struct ResourceUpdatePayload {
     pub url: Option<String>,
     pub path: Option<String>,
     pub file_type: Option<String>,
+    pub res_type: Option<i16>,
     pub tags: Option<Vec<String>>,
     pub status: Option<i16>,
     pub remark: Option<String>,
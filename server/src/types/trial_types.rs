@@ -0,0 +1,23 @@
+use salvo_oapi::ToSchema;
+use serde::{Deserialize, Serialize};
+
+/// 设置应用试用策略，未传的字段保持原值（新建时取默认值）
+#[derive(Serialize, Deserialize, Debug, Default, ToSchema)]
+pub struct UpsertTrialPolicyReq {
+    /// IP 限制的统计窗口（小时）
+    pub window_hours: Option<i32>,
+    /// 窗口内同一 IP 最多试用设备数，0 不限制
+    pub max_trials_per_ip: Option<i32>,
+    /// 窗口内同一网段最多试用设备数，0 不限制
+    pub max_trials_per_subnet: Option<i32>,
+    /// IPv4 网段前缀长度（8-32），IPv6 固定按 /64 计算
+    pub subnet_prefix: Option<i16>,
+    /// 指纹相似度阈值（百分比），0 关闭
+    pub fingerprint_threshold: Option<i16>,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct ExtendTrialReq {
+    /// 在当前有效期（已过期时从现在）基础上增加的天数
+    pub days: i32,
+}
@@ -0,0 +1,70 @@
+use crate::types::common::ListParamsReq;
+use crate::types::reg_codes_types::SignedRequest;
+use chrono::{DateTime, Utc};
+use salvo_oapi::ToSchema;
+use serde::{Deserialize, Serialize};
+
+/// 计次注册码消耗请求，签名方式与 v2 校验相同
+/// sign = hex(HMAC-SHA256(app_valid_key, sign_content()))
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct UsageConsumeReq {
+    /// 应用的 app_id
+    pub app_id: String,
+    pub device_id: String,
+    pub code: String,
+    /// 本次消耗的次数
+    pub amount: i32,
+    /// 客户端生成的幂等键（8-64 字符），重试时保持不变，同一注册码内不会重复扣减
+    pub idempotency_key: String,
+    /// 客户端时间（unix 秒）
+    pub timestamp: i64,
+    /// 一次性随机串，重试时需重新生成
+    pub nonce: String,
+    pub sign: String,
+}
+
+impl SignedRequest for UsageConsumeReq {
+    fn app_id(&self) -> &str {
+        &self.app_id
+    }
+    fn timestamp(&self) -> i64 {
+        self.timestamp
+    }
+    fn nonce(&self) -> &str {
+        &self.nonce
+    }
+    fn sign(&self) -> &str {
+        &self.sign
+    }
+    /// 待签名内容：app_id、device_id、code、amount、idempotency_key、timestamp、nonce 以换行拼接
+    fn sign_content(&self) -> String {
+        format!(
+            "{}\n{}\n{}\n{}\n{}\n{}\n{}",
+            self.app_id,
+            self.device_id,
+            self.code,
+            self.amount,
+            self.idempotency_key,
+            self.timestamp,
+            self.nonce
+        )
+    }
+}
+
+/// 消耗结果，签名后放在 RegCodeValidateV2Resp.payload 中
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct UsageConsumeResp {
+    pub usage_id: i64,
+    pub amount: i32,
+    pub remaining: i32,
+    pub total: i32,
+    /// 幂等键已使用过时为 true，本次没有重复扣减
+    pub replayed: bool,
+    pub consumed_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct SearchCodeUsagesParams {
+    #[serde(flatten)]
+    pub pagination: ListParamsReq,
+}
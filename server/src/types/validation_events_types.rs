@@ -0,0 +1,69 @@
+use crate::types::common::ListParamsReq;
+use crate::utils::convert::from_str_optional;
+use chrono::{DateTime, NaiveDate, Utc};
+use salvo_oapi::ToSchema;
+use sea_orm::FromQueryResult;
+use serde::{Deserialize, Serialize};
+
+/// 请求中标识应用的方式，写库时再解析为 apps.id
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum AppRef {
+    /// v1 请求的 app_valid_key
+    ValidKey(String),
+    /// v2 请求的 apps.app_id
+    AppId(String),
+}
+
+/// 一次校验调用，由 ValidationLogger 异步写入 validation_events
+#[derive(Debug, Clone)]
+pub struct ValidationEvent {
+    pub app: AppRef,
+    pub device_id: String,
+    /// 注册码的 SHA-256 哈希
+    pub code: Option<String>,
+    pub api_version: i16,
+    pub ip: String,
+    pub user_agent: Option<String>,
+    /// 失败时的错误码和原因
+    pub error: Option<(i32, String)>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct SearchValidationEventsParams {
+    #[serde(flatten)]
+    pub pagination: ListParamsReq,
+    #[serde(deserialize_with = "from_str_optional", default)]
+    pub app_id: Option<i32>,
+    pub device_id: Option<String>,
+    /// 注册码原文，查询时转换为哈希
+    pub code: Option<String>,
+    pub ip: Option<String>,
+    #[serde(deserialize_with = "from_str_optional", default)]
+    pub success: Option<bool>,
+    #[serde(deserialize_with = "from_str_optional", default)]
+    pub start_time: Option<DateTime<Utc>>,
+    #[serde(deserialize_with = "from_str_optional", default)]
+    pub end_time: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct ValidationStatsParams {
+    #[serde(deserialize_with = "from_str_optional", default)]
+    pub app_id: Option<i32>,
+    /// 默认最近 30 天
+    #[serde(deserialize_with = "from_str_optional", default)]
+    pub start_time: Option<DateTime<Utc>>,
+    #[serde(deserialize_with = "from_str_optional", default)]
+    pub end_time: Option<DateTime<Utc>>,
+}
+
+/// 按应用和日期（数据库时区）汇总的校验次数
+#[derive(Serialize, Deserialize, Debug, FromQueryResult, ToSchema)]
+pub struct ValidationDailyStats {
+    pub app_id: i32,
+    pub day: NaiveDate,
+    pub total: i64,
+    pub success: i64,
+    pub failed: i64,
+}
@@ -0,0 +1,57 @@
+use ipnet::IpNet;
+use salvo::Request;
+use std::net::IpAddr;
+
+/// 客户端 IP。只有直连地址是受信任的反向代理时才采用 X-Forwarded-For / X-Real-IP，
+/// 否则这些头由客户端任意填写；X-Forwarded-For 从右往左跳过受信任代理取第一个地址
+pub fn client_ip(req: &Request, trusted_proxies: &[IpNet]) -> Option<IpAddr> {
+    let remote = req
+        .remote_addr()
+        .as_ipv4()
+        .map(|addr| IpAddr::V4(*addr.ip()))
+        .or_else(|| req.remote_addr().as_ipv6().map(|addr| IpAddr::V6(*addr.ip())));
+    let trusted = |ip: &IpAddr| trusted_proxies.iter().any(|net| net.contains(ip));
+    if !remote.as_ref().is_some_and(trusted) {
+        return remote;
+    }
+    if let Some(forwarded) = req.header::<String>("x-forwarded-for") {
+        for hop in forwarded.rsplit(',').map(str::trim) {
+            // 无法解析的地址不可信，按取不到 IP 处理
+            let ip = hop.parse::<IpAddr>().ok()?;
+            if !trusted(&ip) {
+                return Some(ip);
+            }
+        }
+    }
+    if let Some(real_ip) = req.header::<String>("x-real-ip") {
+        return real_ip.trim().parse().ok();
+    }
+    remote
+}
+
+pub fn user_agent(req: &Request) -> Option<String> {
+    req.header::<String>("user-agent")
+}
+
+/// 校验请求的客户端信息，用于记录日志和试用策略
+#[derive(Debug, Clone, Default)]
+pub struct ClientInfo {
+    pub ip: String,
+    pub user_agent: Option<String>,
+}
+
+impl ClientInfo {
+    pub fn from_request(req: &Request, trusted_proxies: &[IpNet]) -> Self {
+        Self {
+            ip: client_ip(req, trusted_proxies)
+                .map(|ip| ip.to_string())
+                .unwrap_or_default(),
+            user_agent: user_agent(req),
+        }
+    }
+
+    /// 客户端 IP 地址，取不到时为 None
+    pub fn ip_addr(&self) -> Option<IpAddr> {
+        self.ip.parse().ok()
+    }
+}
@@ -0,0 +1,101 @@
+use crate::types::error::AppError;
+use salvo::http::header::{CONTENT_DISPOSITION, HeaderValue};
+use salvo::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+/// 导出文件格式
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Json,
+    Csv,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Csv => "csv",
+        }
+    }
+}
+
+/// 将数据行写成附件下载，file_name 不含扩展名
+pub fn render_attachment<T: Serialize>(
+    res: &mut Response,
+    format: ExportFormat,
+    file_name: &str,
+    rows: &[T],
+) -> Result<(), AppError> {
+    let body = match format {
+        ExportFormat::Json => serde_json::to_string(rows)?,
+        ExportFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(vec![]);
+            for row in rows {
+                writer
+                    .serialize(row)
+                    .map_err(|e| AppError::Message(format!("csv export error: {}", e)))?;
+            }
+            let bytes = writer
+                .into_inner()
+                .map_err(|e| AppError::Message(format!("csv export error: {}", e)))?;
+            String::from_utf8(bytes)
+                .map_err(|e| AppError::Message(format!("csv export error: {}", e)))?
+        }
+    };
+    set_attachment(res, &format!("{}.{}", file_name, format.extension()))?;
+    match format {
+        ExportFormat::Json => res.render(Text::Json(body)),
+        ExportFormat::Csv => res.render(Text::Csv(body)),
+    }
+    Ok(())
+}
+
+/// 将单个对象写成 JSON 附件下载，file_name 不含扩展名
+pub fn render_json_attachment<T: Serialize>(
+    res: &mut Response,
+    file_name: &str,
+    value: &T,
+) -> Result<(), AppError> {
+    let body = serde_json::to_string_pretty(value)?;
+    set_attachment(res, &format!("{}.json", file_name))?;
+    res.render(Text::Json(body));
+    Ok(())
+}
+
+fn set_attachment(res: &mut Response, file_name: &str) -> Result<(), AppError> {
+    let disposition = format!("attachment; filename=\"{}\"", file_name);
+    res.headers_mut().insert(
+        CONTENT_DISPOSITION,
+        HeaderValue::from_str(&disposition)
+            .map_err(|e| AppError::Message(format!("invalid file name: {}", e)))?,
+    );
+    Ok(())
+}
+
+/// 解析导入的数据行，每行单独返回解析结果以便逐行报错
+pub fn parse_rows<T: DeserializeOwned>(
+    format: ExportFormat,
+    body: &[u8],
+) -> Result<Vec<Result<T, String>>, AppError> {
+    match format {
+        ExportFormat::Json => {
+            let rows: Vec<serde_json::Value> = serde_json::from_slice(body)
+                .map_err(|e| AppError::validation(format!("invalid json: {}", e)))?;
+            Ok(rows
+                .into_iter()
+                .map(|row| serde_json::from_value(row).map_err(|e| e.to_string()))
+                .collect())
+        }
+        ExportFormat::Csv => {
+            let mut reader = csv::ReaderBuilder::new()
+                .trim(csv::Trim::All)
+                .from_reader(body);
+            Ok(reader
+                .deserialize()
+                .map(|row| row.map_err(|e| e.to_string()))
+                .collect())
+        }
+    }
+}
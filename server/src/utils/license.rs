@@ -0,0 +1,81 @@
+use crate::types::error::AppError;
+use base64::{
+    Engine,
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Serialize, de::DeserializeOwned};
+
+pub const SIGN_ALGORITHM: &str = "Ed25519";
+
+/// 应用的签名密钥对（base64 编码）
+pub struct AppKeyPair {
+    pub public_key: String,
+    pub private_key: String,
+}
+
+/// 生成新的 Ed25519 密钥对
+pub fn generate_keypair() -> AppKeyPair {
+    let signing_key = SigningKey::from_bytes(&rand::random::<[u8; 32]>());
+    AppKeyPair {
+        public_key: STANDARD.encode(signing_key.verifying_key().to_bytes()),
+        private_key: STANDARD.encode(signing_key.to_bytes()),
+    }
+}
+
+fn signing_key(private_key: &str) -> Result<SigningKey, AppError> {
+    let bytes: [u8; 32] = STANDARD
+        .decode(private_key)
+        .ok()
+        .and_then(|b| b.try_into().ok())
+        .ok_or_else(|| AppError::InternalError {
+            message: "invalid app signing key".to_string(),
+        })?;
+    Ok(SigningKey::from_bytes(&bytes))
+}
+
+fn verifying_key(public_key: &str) -> Result<VerifyingKey, AppError> {
+    let bytes: [u8; 32] = STANDARD
+        .decode(public_key)
+        .ok()
+        .and_then(|b| b.try_into().ok())
+        .ok_or_else(|| AppError::validation("invalid public key"))?;
+    VerifyingKey::from_bytes(&bytes).map_err(|_| AppError::validation("invalid public key"))
+}
+
+/// 对原始数据签名，返回 base64url 编码的签名
+pub fn sign_bytes(private_key: &str, data: &[u8]) -> Result<String, AppError> {
+    let signature = signing_key(private_key)?.sign(data);
+    Ok(URL_SAFE_NO_PAD.encode(signature.to_bytes()))
+}
+
+/// 校验 sign_bytes 生成的签名
+pub fn verify_bytes(public_key: &str, data: &[u8], signature: &str) -> Result<(), AppError> {
+    let signature: [u8; 64] = URL_SAFE_NO_PAD
+        .decode(signature)
+        .ok()
+        .and_then(|b| b.try_into().ok())
+        .ok_or_else(|| AppError::validation("invalid signature"))?;
+    verifying_key(public_key)?
+        .verify(data, &Signature::from_bytes(&signature))
+        .map_err(|_| AppError::validation("signature mismatch"))
+}
+
+/// 生成签名令牌，格式为 `base64url(payload_json).base64url(signature)`
+pub fn sign_token<T: Serialize>(private_key: &str, claims: &T) -> Result<String, AppError> {
+    let payload = URL_SAFE_NO_PAD.encode(serde_json::to_vec(claims)?);
+    let signature = sign_bytes(private_key, payload.as_bytes())?;
+    Ok(format!("{}.{}", payload, signature))
+}
+
+/// 校验令牌签名并解析载荷
+pub fn verify_token<T: DeserializeOwned>(public_key: &str, token: &str) -> Result<T, AppError> {
+    let (payload, signature) = token
+        .split_once('.')
+        .ok_or_else(|| AppError::validation("malformed token"))?;
+    verify_bytes(public_key, payload.as_bytes(), signature)?;
+    let payload = URL_SAFE_NO_PAD
+        .decode(payload)
+        .map_err(|_| AppError::validation("malformed token"))?;
+    Ok(serde_json::from_slice(&payload)?)
+}
@@ -1,6 +1,11 @@
-// pub mod cache;
-pub mod convert;
-pub mod jwt;
-// pub mod performance;
-pub mod casbin_adapter;
-pub mod redis_cache;
+// pub mod cache;
+pub mod convert;
+pub mod export;
+pub mod jwt;
+pub mod license;
+pub mod oss;
+pub mod signature;
+// pub mod performance;
+pub mod casbin_adapter;
+pub mod client;
+pub mod redis_cache;
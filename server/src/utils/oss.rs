@@ -0,0 +1,113 @@
+use crate::types::config::OssConfig;
+use crate::types::error::AppError;
+use sha2::{Digest, Sha256};
+use xt_oss::prelude::*;
+
+/// 上传时写入的自定义元信息，存在时不需要下载文件计算哈希
+const SHA256_META: &str = "x-oss-meta-sha256";
+/// 计算 sha256 时每次下载的字节数
+const SHA256_CHUNK_SIZE: u64 = 8 * 1024 * 1024;
+
+/// HeadObject 读取的文件信息
+#[derive(Debug, Clone)]
+pub struct ObjectStat {
+    pub size: i64,
+    pub etag: String,
+    pub sha256: Option<String>,
+}
+
+fn client<'a>(config: &'a OssConfig, region: &'a str) -> oss::Client<'a> {
+    let mut options = oss::Options::new()
+        .with_access_key_id(&config.access_key_id)
+        .with_access_key_secret(&config.access_key_secret)
+        .with_region(region)
+        .with_bucket(&config.bucket)
+        .with_secret(!config.endpoint.starts_with("http://"));
+    if !config.endpoint.is_empty() {
+        options = options.with_endpoint(&config.endpoint).with_cname(true);
+    }
+    oss::Client::new(options)
+}
+
+fn region(config: &OssConfig) -> String {
+    if config.region.starts_with("oss-") {
+        config.region.clone()
+    } else {
+        format!("oss-{}", config.region)
+    }
+}
+
+fn oss_error<T>(key: &str, data: oss::api::ApiData<T>) -> AppError {
+    AppError::business_logic(
+        "OSS_ERROR",
+        format!("oss object {} returned {}", key, data.status()),
+    )
+}
+
+fn request_error(e: impl std::fmt::Display) -> AppError {
+    AppError::Message(format!("oss request failed: {}", e))
+}
+
+/// 读取对象大小、ETag 和上传时写入的 sha256 元信息
+pub async fn head_object(config: &OssConfig, key: &str) -> Result<ObjectStat, AppError> {
+    let region = region(config);
+    let client = client(config, &region);
+    let data = client
+        .HeadObject(key)
+        .execute()
+        .await
+        .map_err(request_error)?
+        .map_err(|data| oss_error(key, data))?;
+    let header = |name: &str| {
+        data.headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.trim_matches('"').to_string())
+    };
+    Ok(ObjectStat {
+        size: data.content_length().unwrap_or_default() as i64,
+        etag: header("etag").unwrap_or_default(),
+        sha256: header(SHA256_META)
+            .map(|v| v.to_ascii_lowercase())
+            .filter(|v| v.len() == 64 && v.chars().all(|c| c.is_ascii_hexdigit())),
+    })
+}
+
+/// 按块下载对象计算 sha256，只在对象没有 sha256 元信息时使用
+/// 每块都带 If-Match，下载途中对象被替换时直接失败
+pub async fn object_sha256(
+    config: &OssConfig,
+    key: &str,
+    stat: &ObjectStat,
+) -> Result<String, AppError> {
+    let region = region(config);
+    let client = client(config, &region);
+    let size = stat.size.max(0) as u64;
+    let if_match = format!("\"{}\"", stat.etag);
+    let mut hasher = Sha256::new();
+    let mut start = 0;
+    while start < size {
+        let amount = SHA256_CHUNK_SIZE.min(size - start);
+        let mut builder = client
+            .GetObject(key)
+            .with_range(ByteRange::from((start, amount as i64)));
+        if !stat.etag.is_empty() {
+            builder = builder.with_match(&if_match);
+        }
+        let data = builder
+            .execute()
+            .await
+            .map_err(request_error)?
+            .map_err(|data| oss_error(key, data))?;
+        let chunk = data.content();
+        if chunk.len() as u64 != amount {
+            return Err(AppError::business_logic(
+                "OSS_ERROR",
+                format!("oss object {} changed while computing sha256", key),
+            ));
+        }
+        hasher.update(&chunk);
+        start += amount;
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
@@ -50,6 +50,66 @@ impl RedisCache {
         Ok(())
     }
 
+    /// 仅在 key 不存在时写入，返回是否写入成功
+    pub async fn set_nx<T: Serialize>(
+        &self,
+        key: &str,
+        value: &T,
+        ttl: Duration,
+    ) -> Result<bool, AppError> {
+        let mut conn = self.get_conn().await?;
+        let val_str = serde_json::to_string(value)?;
+        let options = redis::SetOptions::default()
+            .conditional_set(redis::ExistenceCheck::NX)
+            .with_expiration(redis::SetExpiry::EX(ttl.as_secs()));
+        let result: Option<String> = conn.set_options(key, val_str, options).await?;
+        Ok(result.is_some())
+    }
+
+    /// 读取并删除，用于一次性令牌
+    pub async fn get_del<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, AppError> {
+        let mut conn = self.get_conn().await?;
+        let result: Option<String> = conn.get_del(key).await?;
+        match result {
+            Some(val_str) => Ok(Some(serde_json::from_str(&val_str)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// 写入有序集合成员，score 已存在时覆盖
+    pub async fn zadd(&self, key: &str, member: &str, score: i64) -> Result<(), AppError> {
+        let mut conn = self.get_conn().await?;
+        let _: () = conn.zadd(key, member, score).await?;
+        Ok(())
+    }
+
+    /// 删除有序集合成员，返回成员是否存在
+    pub async fn zrem(&self, key: &str, member: &str) -> Result<bool, AppError> {
+        let mut conn = self.get_conn().await?;
+        let removed: i64 = conn.zrem(key, member).await?;
+        Ok(removed > 0)
+    }
+
+    /// 删除 score 不大于 max 的成员
+    pub async fn zrem_by_score_max(&self, key: &str, max: i64) -> Result<(), AppError> {
+        let mut conn = self.get_conn().await?;
+        let _: i64 = conn.zrembyscore(key, "-inf", max).await?;
+        Ok(())
+    }
+
+    /// 按 score 升序返回全部成员及 score
+    pub async fn zrange_withscores(&self, key: &str) -> Result<Vec<(String, i64)>, AppError> {
+        let mut conn = self.get_conn().await?;
+        let members: Vec<(String, i64)> = conn.zrange_withscores(key, 0, -1).await?;
+        Ok(members)
+    }
+
+    pub async fn expire(&self, key: &str, ttl: Duration) -> Result<(), AppError> {
+        let mut conn = self.get_conn().await?;
+        let _: () = conn.expire(key, ttl.as_secs() as i64).await?;
+        Ok(())
+    }
+
     #[allow(dead_code)]
     pub async fn del(&self, key: &str) -> Result<(), AppError> {
         let mut conn = self.get_conn().await?;
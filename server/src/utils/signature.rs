@@ -0,0 +1,21 @@
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// 使用应用密钥计算 HMAC-SHA256，返回小写 hex
+pub fn hmac_sha256_hex(secret: &str, content: &str) -> String {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).expect("hmac accepts any key size");
+    mac.update(content.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// 常量时间比较签名
+pub fn verify_hmac_sha256_hex(secret: &str, content: &str, sign: &str) -> bool {
+    let Ok(sign) = hex::decode(sign) else {
+        return false;
+    };
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).expect("hmac accepts any key size");
+    mac.update(content.as_bytes());
+    mac.verify_slice(&sign).is_ok()
+}
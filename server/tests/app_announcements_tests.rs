@@ -0,0 +1,156 @@
+use chrono::{Duration, Utc};
+use salvo::test::TestClient;
+use serde_json::json;
+use crate::helpers::print_response_body_get_json;
+mod helpers;
+
+#[tokio::test]
+async fn test_app_announcements() {
+    let app = helpers::create_test_app().await;
+    let token = helpers::login_admin(&app).await;
+    let app_data = helpers::create_app(&app, &token, json!({})).await;
+    let id = app_data["id"].as_i64().unwrap();
+    let app_key = app_data["app_valid_key"].as_str().unwrap().to_string();
+    let other_app = helpers::create_app(&app, &token, json!({})).await;
+    let other_key = other_app["app_valid_key"].as_str().unwrap().to_string();
+
+    let now = Utc::now();
+    let common = json!({"app_id": id, "content": "content"});
+    let ids = helpers::admin_create_all(&app, &token, "/api/admin/app_announcements", &common, [
+        json!({"title": "welcome", "priority": 1}),
+        json!({
+            "title": "maintenance", "kind": 1, "priority": 10, "dismissible": false,
+            "start_at": now - Duration::hours(1), "end_at": now + Duration::hours(1)
+        }),
+        json!({"title": "trial promotion", "kind": 2, "audience": 2, "priority": 5}),
+        json!({"title": "licensed notice", "audience": 1, "priority": 5}),
+        json!({"title": "scheduled", "start_at": now + Duration::days(1)}),
+        json!({"title": "expired", "start_at": now - Duration::days(2), "end_at": now - Duration::days(1)}),
+        json!({"title": "new version", "min_vercode": 200}),
+        json!({"title": "disabled", "enabled": false}),
+    ])
+    .await;
+    let resp = helpers::admin_post(
+        "/api/admin/app_announcements",
+        &token,
+        &common,
+        json!({"title": "bad window", "start_at": now, "end_at": now - Duration::hours(1)}),
+    )
+    .send(&app)
+    .await;
+    let json = print_response_body_get_json(resp, "add_invalid").await;
+    assert_eq!(json["success"], false);
+
+    let fetch = |query: &str| {
+        TestClient::get(helpers::get_url(&format!("/api/app/announcements?{}", query)))
+            .add_header("x-app-key", &app_key, true)
+    };
+    let titles = |json: &serde_json::Value| -> Vec<String> {
+        json["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|a| a["title"].as_str().unwrap().to_string())
+            .collect()
+    };
+    // 未知设备只返回面向全部设备的公告
+    let resp = fetch("vercode=100").send(&app).await;
+    let json = print_response_body_get_json(resp, "active_anonymous").await;
+    assert_eq!(titles(&json), ["maintenance", "welcome"]);
+    assert_eq!(json["data"][0]["kind"], 1);
+    assert_eq!(json["data"][0]["dismissible"], false);
+
+    let resp = fetch("vercode=100&device_id=ann-trial").send(&app).await;
+    let json = print_response_body_get_json(resp, "active_trial").await;
+    assert_eq!(titles(&json), ["maintenance", "trial promotion", "welcome"]);
+
+    let dismiss = |key: &str, device_id: &str, announcement_id: i64| {
+        TestClient::post(helpers::get_url("/api/app/announcements/dismiss")).json(&json!({
+            "app_key": key,
+            "device_id": device_id,
+            "announcement_id": announcement_id
+        }))
+    };
+    for _ in 0..2 {
+        let resp = dismiss(&app_key, "ann-trial", ids[2]).send(&app).await;
+        let json = print_response_body_get_json(resp, "dismiss").await;
+        assert_eq!(json["success"], true);
+    }
+    // 并发关闭同一公告都成功
+    let results = futures::future::join_all(
+        (0..4).map(|_| dismiss(&app_key, "ann-trial-3", ids[2]).send(&app)),
+    )
+    .await;
+    for resp in results {
+        let json = print_response_body_get_json(resp, "dismiss_concurrent").await;
+        assert_eq!(json["success"], true);
+    }
+    let resp = dismiss(&app_key, "ann-trial", ids[1]).send(&app).await;
+    let json = print_response_body_get_json(resp, "dismiss_not_dismissible").await;
+    assert_eq!(json["success"], false);
+    let resp = dismiss(&other_key, "ann-trial", ids[0]).send(&app).await;
+    let json = print_response_body_get_json(resp, "dismiss_other_app").await;
+    assert_eq!(json["success"], false);
+
+    let resp = fetch("vercode=100&device_id=ann-trial").send(&app).await;
+    let json = print_response_body_get_json(resp, "active_after_dismiss").await;
+    assert_eq!(titles(&json), ["maintenance", "welcome"]);
+    let resp = fetch("vercode=100&device_id=ann-trial-2").send(&app).await;
+    let json = print_response_body_get_json(resp, "active_other_device").await;
+    assert_eq!(titles(&json), ["maintenance", "trial promotion", "welcome"]);
+
+    // 绑定注册码后按已授权设备匹配
+    let resp = TestClient::post(helpers::get_url("/api/admin/reg_codes/batch"))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&json!({"app_id": id, "count": 1, "valid_days": 30, "max_devices": 1, "code_type": 0}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "batch_add").await;
+    let code = json["data"]["codes"][0]["code"].as_str().unwrap().to_string();
+    let resp = TestClient::post(helpers::get_url("/api/reg/validate"))
+        .json(&json!({"code": code, "app_key": app_key, "device_id": "ann-licensed"}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "validate").await;
+    assert_eq!(json["success"], true);
+    let resp = fetch("vercode=200&device_id=ann-licensed").send(&app).await;
+    let json = print_response_body_get_json(resp, "active_licensed").await;
+    assert_eq!(
+        titles(&json),
+        ["maintenance", "licensed notice", "welcome", "new version"]
+    );
+
+    let resp = TestClient::put(helpers::get_url(&format!("/api/admin/app_announcements/{}", ids[4])))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&json!({"start_at": now - Duration::minutes(1), "priority": 20}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "update").await;
+    assert_eq!(json["data"]["priority"], 20);
+    let resp = fetch("vercode=100").send(&app).await;
+    let json = print_response_body_get_json(resp, "active_after_update").await;
+    assert_eq!(titles(&json), ["scheduled", "maintenance", "welcome"]);
+
+    let resp = TestClient::get(helpers::get_url(&format!(
+        "/api/admin/app_announcements/list?app_id={}",
+        id
+    )))
+    .add_header("authorization", format!("Bearer {}", token), true)
+    .send(&app)
+    .await;
+    let json = print_response_body_get_json(resp, "list").await;
+    assert_eq!(json["data"]["total"], 8);
+
+    let resp = TestClient::get(helpers::get_url("/api/app/announcements"))
+        .add_header("x-app-key", "NO_SUCH_KEY", true)
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "active_unknown_app").await;
+    assert_eq!(json["success"], false);
+    // 应用校验Key不接受查询参数
+    let resp = TestClient::get(helpers::get_url(&format!("/api/app/announcements?app_key={}", app_key)))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "active_key_in_query").await;
+    assert_eq!(json["success"], false);
+}
@@ -0,0 +1,194 @@
+use salvo::test::TestClient;
+use serde_json::json;
+use crate::helpers::print_response_body_get_json;
+mod helpers;
+
+#[tokio::test]
+async fn test_app_configs() {
+    let app = helpers::create_test_app().await;
+    let token = helpers::login_admin(&app).await;
+    let app_data = helpers::create_app(&app, &token, json!({})).await;
+    let id = app_data["id"].as_i64().unwrap();
+    let app_id = app_data["app_id"].as_str().unwrap().to_string();
+    let app_key = app_data["app_valid_key"].as_str().unwrap().to_string();
+
+    let common = json!({"app_id": id});
+    let rule_ids = helpers::admin_create_all(&app, &token, "/api/admin/app_configs", &common, [
+        json!({"config_key": "welcome", "value": "hello"}),
+        json!({"config_key": "welcome", "value": "hello beta", "channel": "beta", "priority": 10}),
+        json!({"config_key": "max_threads", "value_type": 1, "value": 4}),
+        json!({"config_key": "max_threads", "value_type": 1, "value": 16, "audience": 1, "priority": 5}),
+        json!({"config_key": "new_ui", "value_type": 3, "value": true, "min_vercode": 200}),
+        json!({"config_key": "trial_banner", "value_type": 3, "value": true, "audience": 2}),
+        json!({"config_key": "hidden", "value_type": 4, "value": {"a": 1}, "enabled": false}),
+    ])
+    .await;
+    for invalid in [
+        json!({"config_key": "bad_int", "value_type": 1, "value": "abc"}),
+        json!({"config_key": "bad range", "value": "x"}),
+        json!({"config_key": "bad_range", "value": "x", "min_vercode": 300, "max_vercode": 200}),
+    ] {
+        let resp = helpers::admin_post("/api/admin/app_configs", &token, &common, invalid)
+            .send(&app)
+            .await;
+        let json = print_response_body_get_json(resp, "add_invalid").await;
+        assert_eq!(json["success"], false);
+    }
+    let resp = TestClient::put(helpers::get_url(&format!("/api/admin/app_configs/{}", rule_ids[2])))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&json!({"value_type": 3}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "update_invalid").await;
+    assert_eq!(json["success"], false);
+
+    let fetch = |query: &str| {
+        TestClient::get(helpers::get_url(&format!("/api/app/config?{}", query)))
+            .add_header("x-app-key", &app_key, true)
+    };
+    // 未知设备只命中面向全部设备的规则
+    let resp = fetch("vercode=100").send(&app).await;
+    let header = resp.headers().get("etag").unwrap().to_str().unwrap().to_string();
+    let json = print_response_body_get_json(resp, "config_stable").await;
+    assert_eq!(json["data"]["values"], json!({"welcome": "hello", "max_threads": 4}));
+    let etag = json["data"]["etag"].as_str().unwrap().to_string();
+    assert_eq!(header, format!("\"{}\"", etag));
+
+    let resp = fetch(&format!("vercode=100&etag={}", etag)).send(&app).await;
+    let json = print_response_body_get_json(resp, "config_etag").await;
+    assert_eq!(json["data"]["not_modified"], true);
+    assert!(json["data"]["values"].is_null());
+    let resp = fetch("vercode=100")
+        .add_header("if-none-match", format!("W/\"{}\"", etag), true)
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "config_if_none_match").await;
+    assert_eq!(json["data"]["not_modified"], true);
+
+    let resp = fetch("vercode=200&channel=beta").send(&app).await;
+    let json = print_response_body_get_json(resp, "config_beta").await;
+    assert_eq!(
+        json["data"]["values"],
+        json!({"welcome": "hello beta", "max_threads": 4, "new_ui": true})
+    );
+    assert_ne!(json["data"]["etag"], etag.as_str());
+
+    // 校验时一并返回配置，试用设备和授权设备命中不同的规则
+    let resp = TestClient::post(helpers::get_url("/api/reg/validate"))
+        .json(&json!({"app_key": app_key, "device_id": "config-trial", "config": {"vercode": 100}}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "validate_trial").await;
+    assert_eq!(
+        json["data"]["config"]["values"],
+        json!({"welcome": "hello", "max_threads": 4, "trial_banner": true})
+    );
+    let resp = TestClient::post(helpers::get_url("/api/reg/validate"))
+        .json(&json!({"app_key": app_key, "device_id": "config-trial"}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "validate_without_config").await;
+    assert!(json["data"].get("config").is_none());
+
+    let resp = TestClient::post(helpers::get_url("/api/admin/reg_codes/batch"))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&json!({"app_id": id, "count": 1, "valid_days": 30, "max_devices": 2, "code_type": 0}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "batch_add").await;
+    let code = json["data"]["codes"][0]["code"].as_str().unwrap().to_string();
+    let resp = TestClient::post(helpers::get_url("/api/reg/validate"))
+        .json(&json!({
+            "code": code,
+            "app_key": app_key,
+            "device_id": "config-licensed",
+            "config": {"vercode": 100}
+        }))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "validate_licensed").await;
+    assert_eq!(
+        json["data"]["config"]["values"],
+        json!({"welcome": "hello", "max_threads": 16})
+    );
+    let licensed_etag = json["data"]["config"]["etag"].as_str().unwrap().to_string();
+
+    let resp = fetch("vercode=100&device_id=config-licensed").send(&app).await;
+    let json = print_response_body_get_json(resp, "config_licensed").await;
+    assert_eq!(json["data"]["etag"], licensed_etag.as_str());
+
+    let resp = TestClient::put(helpers::get_url(&format!("/api/admin/app_configs/{}", rule_ids[3])))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&json!({"value": 32}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "update_config").await;
+    assert_eq!(json["data"]["value"], 32);
+
+    // v2 校验的配置在签名 payload 中返回
+    let mut req = helpers::signed_request(&app_id, &app_key, "config-licensed", Some(&code));
+    req.config = Some(app_server::types::app_configs_types::ConfigQuery {
+        vercode: Some(100),
+        etag: Some(licensed_etag.clone()),
+        ..Default::default()
+    });
+    let resp = TestClient::post(helpers::get_url("/api/reg/v2/validate"))
+        .json(&req)
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "validate_v2").await;
+    let payload: serde_json::Value =
+        serde_json::from_str(json["data"]["payload"].as_str().unwrap()).unwrap();
+    assert_eq!(payload["config"]["not_modified"], false);
+    assert_eq!(payload["config"]["values"]["max_threads"], 32);
+
+    let resp = TestClient::get(helpers::get_url(&format!("/api/admin/app_configs/list?app_id={}", id)))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "list").await;
+    assert_eq!(json["data"]["total"], 7);
+
+    let resp = TestClient::delete(helpers::get_url(&format!("/api/admin/app_configs/{}", rule_ids[1])))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .send(&app)
+        .await;
+    print_response_body_get_json(resp, "delete").await;
+    let resp = fetch("vercode=200&channel=beta").send(&app).await;
+    let json = print_response_body_get_json(resp, "config_after_delete").await;
+    assert_eq!(json["data"]["values"]["welcome"], "hello");
+
+    // 次数已用完的注册码不算授权设备
+    let resp = TestClient::post(helpers::get_url("/api/admin/reg_codes/batch"))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&json!({"app_id": id, "count": 1, "valid_days": 30, "total_count": 1, "max_devices": 1, "code_type": 1}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "batch_add_count").await;
+    let count_code = json["data"]["codes"][0]["code"].as_str().unwrap().to_string();
+    let resp = TestClient::post(helpers::get_url("/api/reg/validate"))
+        .json(&json!({"code": count_code, "app_key": app_key, "device_id": "config-used-up"}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "validate_count").await;
+    assert_eq!(json["data"]["remaining_count"], 0);
+    let resp = fetch("vercode=100&device_id=config-used-up").send(&app).await;
+    let json = print_response_body_get_json(resp, "config_used_up").await;
+    assert_eq!(
+        json["data"]["values"],
+        json!({"welcome": "hello", "max_threads": 4, "trial_banner": true})
+    );
+
+    let resp = TestClient::get(helpers::get_url("/api/app/config"))
+        .add_header("x-app-key", "NO_SUCH_KEY", true)
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "config_unknown_app").await;
+    assert_eq!(json["success"], false);
+    // 应用校验Key不接受查询参数
+    let resp = TestClient::get(helpers::get_url(&format!("/api/app/config?app_key={}", app_key)))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "config_key_in_query").await;
+    assert_eq!(json["success"], false);
+}
@@ -0,0 +1,264 @@
+use salvo::test::{ResponseExt, TestClient};
+use serde_json::json;
+use crate::helpers::print_response_body_get_json;
+mod helpers;
+
+#[tokio::test]
+async fn test_app_releases_check_update() {
+    let app = helpers::create_test_app().await;
+    let token = helpers::login_admin(&app).await;
+    let app_data = helpers::create_app(&app, &token, json!({})).await;
+    let id = app_data["id"].as_i64().unwrap();
+    let app_id = app_data["app_id"].as_str().unwrap().to_string();
+
+    let common = json!({"app_id": id});
+    let ids = helpers::admin_create_all(&app, &token, "/api/admin/app_releases", &common, [
+        json!({"vername": "1.1.0", "vercode": 110, "status": 1,
+            "downloads": {"windows": "https://example.com/1.1.0.exe", "macos": "https://example.com/1.1.0.dmg"}}),
+        json!({"vername": "1.2.0", "vercode": 120, "status": 1, "force_update": true,
+            "downloads": {"windows": "https://example.com/1.2.0.exe"}}),
+        json!({"vername": "1.3.0", "vercode": 130, "status": 1, "min_vercode": 100,
+            "release_notes": "bug fixes", "downloads": {"windows": "https://example.com/1.3.0.exe"}}),
+        json!({"channel": "beta", "vername": "1.4.0-beta", "vercode": 140, "status": 1,
+            "downloads": {"windows": "https://example.com/1.4.0-beta.exe"}}),
+        json!({"vername": "1.5.0", "vercode": 150,
+            "downloads": {"windows": "https://example.com/1.5.0.exe"}}),
+    ])
+    .await;
+
+    let resp = helpers::admin_post(
+        "/api/admin/app_releases",
+        &token,
+        &common,
+        json!({"vername": "1.3.0", "vercode": 130}),
+    )
+    .send(&app)
+    .await;
+    let json = print_response_body_get_json(resp, "add_duplicate").await;
+    assert_eq!(json["success"], false);
+
+    let check = |query: &str| {
+        TestClient::get(helpers::get_url(&format!("/api/app/check_update/{}?{}", app_id, query)))
+    };
+
+    // 草稿和测试版对正式版客户端不可见，跳过的版本中有强制更新
+    let resp = check("vercode=110").send(&app).await;
+    let json = print_response_body_get_json(resp, "check_stable").await;
+    assert_eq!(json["data"]["has_update"], true);
+    assert_eq!(json["data"]["force_update"], true);
+    assert_eq!(json["data"]["release"]["vercode"], 130);
+    assert_eq!(json["data"]["release"]["release_notes"], "bug fixes");
+
+    let resp = check("vercode=120").send(&app).await;
+    let json = print_response_body_get_json(resp, "check_optional").await;
+    assert_eq!(json["data"]["force_update"], false);
+
+    // 低于 min_vercode 必须更新
+    let resp = check("vercode=90&platform=windows").send(&app).await;
+    let json = print_response_body_get_json(resp, "check_min_vercode").await;
+    assert_eq!(json["data"]["force_update"], true);
+    assert_eq!(json["data"]["release"]["download_url"], "https://example.com/1.3.0.exe");
+
+    // 没有该平台下载地址的版本被跳过
+    let resp = check("vercode=100&platform=macos").send(&app).await;
+    let json = print_response_body_get_json(resp, "check_macos").await;
+    assert_eq!(json["data"]["release"]["vercode"], 110);
+    assert_eq!(json["data"]["release"]["download_url"], "https://example.com/1.1.0.dmg");
+
+    let resp = check("vercode=130&channel=beta").send(&app).await;
+    let json = print_response_body_get_json(resp, "check_beta").await;
+    assert_eq!(json["data"]["release"]["vercode"], 140);
+
+    let resp = check("vercode=130").send(&app).await;
+    let json = print_response_body_get_json(resp, "check_latest").await;
+    assert_eq!(json["data"]["has_update"], false);
+    assert!(json["data"]["release"].is_null());
+
+    // 发布草稿后正式版客户端可以收到，并同步到应用的版本字段
+    let resp = TestClient::put(helpers::get_url(&format!("/api/admin/app_releases/{}", ids[4])))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&json!({"status": 1, "release_notes": "new features"}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "publish").await;
+    assert!(json["data"]["published_at"].as_str().is_some());
+    let resp = check("vercode=130").send(&app).await;
+    let json = print_response_body_get_json(resp, "check_published").await;
+    assert_eq!(json["data"]["release"]["vercode"], 150);
+    let resp = TestClient::get(helpers::get_url(&format!("/api/admin/apps/{}", id)))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "get_app").await;
+    assert_eq!(json["data"]["app_vercode"], 150);
+    assert_eq!(json["data"]["app_vername"], "1.5.0");
+    assert_eq!(json["data"]["app_download_url"], "https://example.com/1.5.0.exe");
+    assert_eq!(json["data"]["app_update_info"], "new features");
+
+    let resp = TestClient::get(helpers::get_url(&format!(
+        "/api/admin/app_releases/list?app_id={}&channel=stable",
+        id
+    )))
+    .add_header("authorization", format!("Bearer {}", token), true)
+    .send(&app)
+    .await;
+    let json = print_response_body_get_json(resp, "list").await;
+    assert_eq!(json["data"]["total"], 4);
+    assert_eq!(json["data"]["list"][0]["vercode"], 150);
+
+    let resp = check("channel=stable").send(&app).await;
+    let json = print_response_body_get_json(resp, "check_missing_vercode").await;
+    assert_eq!(json["success"], false);
+}
+
+#[tokio::test]
+async fn test_app_release_rollout_and_rollback() {
+    let app = helpers::create_test_app().await;
+    let token = helpers::login_admin(&app).await;
+    let app_data = helpers::create_app(&app, &token, json!({})).await;
+    let id = app_data["id"].as_i64().unwrap();
+    let app_id = app_data["app_id"].as_str().unwrap().to_string();
+
+    let common = json!({"app_id": id});
+    let ids = helpers::admin_create_all(&app, &token, "/api/admin/app_releases", &common, [
+        json!({"vername": "2.0.0", "vercode": 200, "status": 1}),
+        json!({"vername": "2.1.0", "vercode": 210, "status": 1, "rollout_percent": 30}),
+    ])
+    .await;
+    let (stable_id, release_id) = (ids[0], ids[1]);
+    let resp = helpers::admin_post(
+        "/api/admin/app_releases",
+        &token,
+        &common,
+        json!({"vername": "2.2.0", "vercode": 220, "status": 2}),
+    )
+    .send(&app)
+    .await;
+    let json = print_response_body_get_json(resp, "add_paused").await;
+    assert_eq!(json["success"], false);
+
+    let check = |query: String| {
+        TestClient::get(helpers::get_url(&format!("/api/app/check_update/{}?{}", app_id, query)))
+    };
+    let mut results = Vec::new();
+    for i in 0..200 {
+        let mut resp = check(format!("vercode=100&device_id=rollout-dev-{}", i)).send(&app).await;
+        results.push(serde_json::from_str::<serde_json::Value>(&resp.take_string().await.unwrap()).unwrap());
+    }
+    // 约 30% 的设备收到灰度版本，其余设备仍收到上一个正式版
+    let selected = results
+        .iter()
+        .filter(|json| json["data"]["release"]["vercode"] == 210)
+        .count();
+    assert!((30..=90).contains(&selected), "selected {}", selected);
+    assert!(results.iter().all(|json| json["data"]["has_update"] == true));
+
+    // 同一设备的结果固定，调大比例后已命中的设备仍然命中
+    let hit = (0..200)
+        .find(|i| results[*i]["data"]["release"]["vercode"] == 210)
+        .unwrap();
+    let miss = (0..200)
+        .find(|i| results[*i]["data"]["release"]["vercode"] == 200)
+        .unwrap();
+    let resp = check(format!("vercode=100&device_id=rollout-dev-{}", hit)).send(&app).await;
+    let json = print_response_body_get_json(resp, "check_hit").await;
+    assert_eq!(json["data"]["release"]["vercode"], 210);
+    let resp = check("vercode=100".to_string()).send(&app).await;
+    let json = print_response_body_get_json(resp, "check_no_device").await;
+    assert_eq!(json["data"]["release"]["vercode"], 200);
+
+    let resp = TestClient::put(helpers::get_url(&format!("/api/admin/app_releases/{}", release_id)))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&json!({"rollout_percent": 100}))
+        .send(&app)
+        .await;
+    print_response_body_get_json(resp, "rollout_full").await;
+    let resp = check(format!("vercode=100&device_id=rollout-dev-{}", miss)).send(&app).await;
+    let json = print_response_body_get_json(resp, "check_full").await;
+    assert_eq!(json["data"]["release"]["vercode"], 210);
+
+    // 暂停后客户端回到上一个版本，恢复后重新可见
+    let post = |action: &str| {
+        TestClient::post(helpers::get_url(&format!(
+            "/api/admin/app_releases/{}/{}",
+            release_id, action
+        )))
+        .add_header("authorization", format!("Bearer {}", token), true)
+    };
+    let resp = post("pause").send(&app).await;
+    let json = print_response_body_get_json(resp, "pause").await;
+    assert_eq!(json["data"]["status"], 2);
+    let resp = check("vercode=100".to_string()).send(&app).await;
+    let json = print_response_body_get_json(resp, "check_paused").await;
+    assert_eq!(json["data"]["release"]["vercode"], 200);
+    let resp = TestClient::get(helpers::get_url(&format!("/api/admin/apps/{}", id)))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "get_app_paused").await;
+    assert_eq!(json["data"]["app_vercode"], 200);
+    let resp = post("pause").send(&app).await;
+    let json = print_response_body_get_json(resp, "pause_again").await;
+    assert_eq!(json["success"], false);
+    let resp = post("resume").send(&app).await;
+    let json = print_response_body_get_json(resp, "resume").await;
+    assert_eq!(json["data"]["status"], 1);
+
+    // 回滚后已安装该版本的客户端收到降级提示
+    let resp = post("rollback").send(&app).await;
+    let json = print_response_body_get_json(resp, "rollback").await;
+    assert_eq!(json["data"]["status"], 3);
+    let resp = check("vercode=210".to_string()).send(&app).await;
+    let json = print_response_body_get_json(resp, "check_rolled_back").await;
+    assert_eq!(json["data"]["has_update"], true);
+    assert_eq!(json["data"]["rollback"], true);
+    assert_eq!(json["data"]["release"]["vercode"], 200);
+    let resp = check("vercode=200".to_string()).send(&app).await;
+    let json = print_response_body_get_json(resp, "check_after_rollback").await;
+    assert_eq!(json["data"]["has_update"], false);
+    let resp = TestClient::put(helpers::get_url(&format!("/api/admin/app_releases/{}", release_id)))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&json!({"status": 1}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "republish").await;
+    assert_eq!(json["success"], false);
+
+    let resp = TestClient::delete(helpers::get_url(&format!("/api/admin/app_releases/{}", release_id)))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .send(&app)
+        .await;
+    print_response_body_get_json(resp, "delete").await;
+    let resp = TestClient::get(helpers::get_url(&format!("/api/admin/app_release_events/list?app_id={}", id)))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "events").await;
+    let events = json["data"]["list"].as_array().unwrap();
+    let actions: Vec<&str> = events.iter().map(|e| e["action"].as_str().unwrap()).collect();
+    assert_eq!(
+        actions,
+        ["delete", "rollback", "resume", "pause", "update", "create", "create"]
+    );
+    assert!(events[0]["release_id"].is_null());
+    assert_eq!(events[0]["vercode"], 210);
+    assert_eq!(events[4]["changes"]["rollout_percent"], json!([30, 100]));
+    assert_eq!(events[1]["changes"]["status"], json!([1, 3]));
+    assert!(events[1]["operator_id"].as_i64().is_some());
+
+    // 没有可用的正式版时，应用的版本字段恢复为首次发布前的值
+    let resp = TestClient::post(helpers::get_url(&format!("/api/admin/app_releases/{}/pause", stable_id)))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "pause_last_stable").await;
+    assert_eq!(json["data"]["status"], 2);
+    let resp = TestClient::get(helpers::get_url(&format!("/api/admin/apps/{}", id)))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "get_app_restored").await;
+    assert_eq!(json["data"]["app_vercode"], 1);
+    assert_eq!(json["data"]["app_vername"], "1.0.0");
+    assert_eq!(json["data"]["app_download_url"], "https://example.com/dl");
+}
@@ -1,7 +1,8 @@
+use app_server::types::config::Config;
 use app_server::{app, constants, router};
 use http_body_util::BodyExt;
 use salvo::prelude::*;
-use salvo::test::TestClient;
+use salvo::test::{RequestBuilder, TestClient};
 use serde_json::json;
 use std::env;
 use std::process::Command;
@@ -94,13 +95,42 @@ pub async fn create_test_app() -> Service {
     // 确保测试数据库存在（若不存在则创建），再初始化应用
     ensure_test_database_exists().await;
     run_init_sql_with_psql();
-    let app_state = app::init_app()
+    let mut config = Config::from_env().unwrap_or_else(|e| panic!("failed to load config:{}", e));
+    // 定时任务只由测试手动触发，避免后台调度影响断言
+    config.scheduler_enabled = false;
+    // 测试中经 send_from(TEST_PROXY_IP, ..) 发出的请求才采用 X-Forwarded-For
+    config.trusted_proxies = vec![
+        format!("{}/32", TEST_PROXY_IP).parse().unwrap(),
+        "172.16.0.0/12".parse().unwrap(),
+    ];
+    let app_state = app::init_app_with_config(config)
         .await
         .unwrap_or_else(|e| panic!("failed to initialize app:{}", e.to_string()));
     let app = router::create_router(app_state);
     app
 }
 
+/// 直接连接测试数据库，用于准备没有管理接口的数据
+#[allow(dead_code)]
+pub async fn connect_db() -> sea_orm::DatabaseConnection {
+    let config = Config::from_env().unwrap_or_else(|e| panic!("failed to load config:{}", e));
+    app_server::database::init_db(&config.database)
+        .await
+        .unwrap_or_else(|e| panic!("failed to connect database:{}", e))
+}
+
+/// 测试配置中受信任的反向代理地址
+pub const TEST_PROXY_IP: &str = "127.0.0.1";
+
+/// 以 ip 作为 TCP 对端地址发送请求
+#[allow(dead_code)]
+pub async fn send_from(app: &Service, ip: &str, builder: RequestBuilder) -> Response {
+    let mut req = builder.build();
+    let addr = std::net::SocketAddr::new(ip.parse().unwrap(), 40000);
+    *req.remote_addr_mut() = addr.into();
+    app.handle(req).await
+}
+
 pub async fn print_response_body_get_json(response: Response, label: &str) -> serde_json::Value {
     let status = response.status_code;
     let body = response.body.collect().await.unwrap().to_bytes();
@@ -145,6 +175,110 @@ pub async fn create_test_user_and_login(app: &Service) -> String {
     json["data"]["token"].as_str().unwrap().to_string()
 }
 
+#[allow(dead_code)]
+pub async fn login_admin(app: &Service) -> String {
+    // 使用 init.sql 中初始化的 admin 账号登录
+    let login_body = json!({
+        "username": "admin",
+        "password": "admin"
+    });
+    let url = get_url("/api/login");
+    let response = TestClient::post(url)
+        .add_header("content-type", "application/json", true)
+        .json(&login_body)
+        .send(app)
+        .await;
+    assert_eq!(response.status_code, Some(StatusCode::OK));
+    let json = print_response_body_get_json(response, "admin_login_response").await;
+    json["data"]["token"].as_str().unwrap().to_string()
+}
+
+#[allow(dead_code)]
+pub async fn create_app(app: &Service, token: &str, extra: serde_json::Value) -> serde_json::Value {
+    // 创建测试应用，extra 中的字段覆盖默认值，返回应用数据
+    let ts = chrono::Utc::now().timestamp_micros();
+    let mut body = json!({
+        "name": format!("Test-App-{}", ts),
+        "app_id": format!("com.test.{}", ts),
+        "app_vername": "1.0.0",
+        "app_vercode": 1,
+        "app_download_url": "https://example.com/dl",
+        "app_res_url": "https://example.com/res",
+        "app_valid_key": format!("KEY_TEST_{}", ts),
+        "trial_days": 3,
+        "sort_order": 0,
+        "status": 1
+    });
+    for (k, v) in extra.as_object().unwrap() {
+        body[k] = v.clone();
+    }
+    let resp = TestClient::post(get_url("/api/admin/apps"))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&body)
+        .send(app)
+        .await;
+    let json = print_response_body_get_json(resp, "create_app").await;
+    json["data"].clone()
+}
+
+#[allow(dead_code)]
+pub fn admin_post(
+    path: &str,
+    token: &str,
+    common: &serde_json::Value,
+    body: serde_json::Value,
+) -> RequestBuilder {
+    // 合并 common 中的字段（如 app_id）后以管理员身份提交
+    let mut body = body;
+    for (k, v) in common.as_object().unwrap() {
+        body[k] = v.clone();
+    }
+    TestClient::post(get_url(path))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&body)
+}
+
+#[allow(dead_code)]
+pub async fn admin_create_all(
+    app: &Service,
+    token: &str,
+    path: &str,
+    common: &serde_json::Value,
+    bodies: impl IntoIterator<Item = serde_json::Value>,
+) -> Vec<i64> {
+    // 逐条创建记录，全部成功后按顺序返回 id
+    let mut ids = Vec::new();
+    for body in bodies {
+        let resp = admin_post(path, token, common, body).send(app).await;
+        let json = print_response_body_get_json(resp, &format!("create {}", path)).await;
+        assert_eq!(json["success"], true);
+        ids.push(json["data"]["id"].as_i64().unwrap());
+    }
+    ids
+}
+
+#[allow(dead_code)]
+pub fn signed_request(
+    app_id: &str,
+    secret: &str,
+    device_id: &str,
+    code: Option<&str>,
+) -> app_server::types::reg_codes_types::RegCodeValidateV2Req {
+    // 按 v2 协议构造签名请求
+    let mut req = app_server::types::reg_codes_types::RegCodeValidateV2Req {
+        app_id: app_id.to_string(),
+        device_id: device_id.to_string(),
+        code: code.map(|c| c.to_string()),
+        timestamp: chrono::Utc::now().timestamp(),
+        nonce: uuid::Uuid::new_v4().to_string(),
+        sign: String::new(),
+        device_info: None,
+        config: None,
+    };
+    req.sign = app_server::utils::signature::hmac_sha256_hex(secret, &req.sign_content());
+    req
+}
+
 pub fn get_url(path: &str) -> String {
     let host = env::var("LISTEN_HOST").expect("LISTEN_HOST not set");
     let port = env::var("LISTEN_PORT").expect("LISTEN_PORT not set");
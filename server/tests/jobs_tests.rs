@@ -0,0 +1,87 @@
+use salvo::test::TestClient;
+use serde_json::json;
+use crate::helpers::print_response_body_get_json;
+mod helpers;
+
+#[tokio::test]
+async fn test_expiry_jobs_and_run_history() {
+    let app = helpers::create_test_app().await;
+    let token = helpers::login_admin(&app).await;
+    let app_data = helpers::create_app(&app, &token, json!({"trial_days": 0})).await;
+    let app_id = app_data["id"].as_i64().unwrap();
+    let app_key = app_data["app_valid_key"].as_str().unwrap().to_string();
+    let code = format!("SWEEP_{}", chrono::Utc::now().timestamp_micros());
+    let resp = TestClient::post(helpers::get_url("/api/admin/reg_codes"))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&json!({
+            "code": code,
+            "app_id": app_id,
+            "valid_days": 30,
+            "max_devices": 1,
+            "status": 0,
+            "code_type": 0,
+            "expire_time": chrono::Utc::now() - chrono::Duration::days(1)
+        }))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "create_expired_code").await;
+    let reg_code_id = json["data"]["id"].as_i64().unwrap();
+    // 试用期为 0，设备立即过期
+    let resp = TestClient::post(helpers::get_url("/api/reg/validate"))
+        .json(&json!({"app_key": app_key, "device_id": "sweep-dev-1"}))
+        .send(&app)
+        .await;
+    print_response_body_get_json(resp, "validate_trial_device").await;
+
+    for job in ["expire_reg_codes", "expire_devices"] {
+        let resp = TestClient::post(helpers::get_url(&format!("/api/admin/jobs/{}/run", job)))
+            .add_header("authorization", format!("Bearer {}", token), true)
+            .send(&app)
+            .await;
+        let json = print_response_body_get_json(resp, job).await;
+        assert!(json["data"]["success"].as_bool().unwrap());
+        assert_eq!(json["data"]["trigger"].as_str().unwrap(), "manual");
+    }
+
+    let resp = TestClient::get(helpers::get_url(&format!("/api/admin/reg_codes/{}", reg_code_id)))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "get_swept_code").await;
+    assert_eq!(json["data"]["status"].as_i64().unwrap(), 2);
+    let resp = TestClient::get(helpers::get_url(&format!(
+        "/api/admin/devices/list?app_id={}&expired=true",
+        app_id
+    )))
+    .add_header("authorization", format!("Bearer {}", token), true)
+    .send(&app)
+    .await;
+    let json = print_response_body_get_json(resp, "list_expired_devices").await;
+    assert_eq!(json["data"]["total"].as_i64().unwrap(), 1);
+    assert_eq!(json["data"]["list"][0]["device_id"].as_str().unwrap(), "sweep-dev-1");
+
+    let resp = TestClient::get(helpers::get_url("/api/admin/jobs/runs?job_name=expire_devices"))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "job_runs").await;
+    let runs = json["data"]["list"].as_array().unwrap();
+    assert!(runs.iter().all(|r| r["trigger"] == "manual"));
+    let resp = TestClient::get(helpers::get_url("/api/admin/jobs/list"))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "job_list").await;
+    let jobs = json["data"].as_array().unwrap();
+    let orders = jobs.iter().find(|j| j["name"] == "close_stale_orders").unwrap();
+    assert!(orders["last_run"].is_null());
+    let devices = jobs.iter().find(|j| j["name"] == "expire_devices").unwrap();
+    assert!(devices["last_run"]["success"].as_bool().unwrap());
+
+    let resp = TestClient::post(helpers::get_url("/api/admin/jobs/not_a_job/run"))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "run_unknown_job").await;
+    assert!(!json["success"].as_bool().unwrap());
+}
@@ -0,0 +1,148 @@
+use salvo::prelude::*;
+use salvo::test::TestClient;
+use serde_json::json;
+use crate::helpers::print_response_body_get_json;
+mod helpers;
+
+#[tokio::test]
+async fn test_license_portal() {
+    let app = helpers::create_test_app().await;
+    let token = helpers::login_admin(&app).await;
+    let app_data = helpers::create_app(&app, &token, json!({})).await;
+    let app_id = app_data["id"].as_i64().unwrap();
+    let app_key = app_data["app_valid_key"].as_str().unwrap().to_string();
+
+    let resp = TestClient::post(helpers::get_url("/api/admin/reg_codes/batch"))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&json!({"app_id": app_id, "count": 2, "valid_days": 30, "max_devices": 2, "code_type": 0}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "batch_add").await;
+    let codes = json["data"]["codes"].as_array().unwrap().clone();
+    let code_id = codes[0]["id"].as_i64().unwrap();
+    let other_code_id = codes[1]["id"].as_i64().unwrap();
+
+    let resp = TestClient::post(helpers::get_url("/api/admin/pay_methods"))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&json!({"name": "portal pay", "description": "portal", "is_active": true}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "create_pay_method").await;
+    let pay_method_id = json["data"]["id"].as_i64().unwrap();
+    let resp = TestClient::post(helpers::get_url("/api/admin/orders"))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&json!({
+            "order_id": "PORTAL-1001",
+            "status": 1,
+            "pay_method_id": pay_method_id,
+            "original_price": 100,
+            "final_price": 100,
+            "contact_email": "Buyer@Example.com",
+            "created_by": 1,
+            "updated_by": 1
+        }))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "create_order").await;
+    let order_id = json["data"]["id"].as_i64().unwrap();
+    let resp = TestClient::post(helpers::get_url(&format!("/api/admin/orders/{}/reg_codes", order_id)))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&json!({"reg_code_ids": [code_id]}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "link_codes").await;
+    assert_eq!(json["data"], json!([code_id]));
+
+    let resp = TestClient::post(helpers::get_url("/api/reg/validate"))
+        .json(&json!({"code": codes[0]["code"], "app_key": app_key, "device_id": "portal-dev-1"}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "redeem").await;
+    assert_eq!(json["success"], true);
+
+    // 邮箱不匹配
+    let resp = TestClient::post(helpers::get_url("/api/portal/login"))
+        .json(&json!({"order_no": "PORTAL-1001", "email": "other@example.com"}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "login_mismatch").await;
+    assert_eq!(json["success"], false);
+    let resp = TestClient::post(helpers::get_url("/api/portal/login"))
+        .json(&json!({"order_no": "PORTAL-1001", "email": "buyer@example.com"}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "login").await;
+    let session = json["data"]["token"].as_str().unwrap().to_string();
+
+    let resp = TestClient::get(helpers::get_url("/api/portal/codes"))
+        .add_header("authorization", format!("Bearer {}", session), true)
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "portal_codes").await;
+    let list = json["data"].as_array().unwrap();
+    assert_eq!(list.len(), 1);
+    assert_eq!(list[0]["id"].as_i64().unwrap(), code_id);
+    let device_id = list[0]["devices"][0]["id"].as_i64().unwrap();
+
+    let resp = TestClient::get(helpers::get_url(&format!(
+        "/api/portal/codes/{}/devices/{}/license",
+        code_id, device_id
+    )))
+    .add_header("authorization", format!("Bearer {}", session), true)
+    .send(&app)
+    .await;
+    let json = print_response_body_get_json(resp, "license").await;
+    assert!(json["data"]["license_token"].as_str().is_some());
+    // 未关联到订单的注册码不可访问
+    let resp = TestClient::get(helpers::get_url(&format!(
+        "/api/portal/codes/{}/devices/{}/license",
+        other_code_id, device_id
+    )))
+    .add_header("authorization", format!("Bearer {}", session), true)
+    .send(&app)
+    .await;
+    let json = print_response_body_get_json(resp, "license_other_code").await;
+    assert_eq!(json["success"], false);
+
+    // 一次性登录链接
+    let resp = TestClient::post(helpers::get_url(&format!("/api/admin/orders/{}/portal_link", order_id)))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "portal_link").await;
+    assert_eq!(json["data"]["email"].as_str().unwrap(), "buyer@example.com");
+    let link = json["data"]["token"].as_str().unwrap().to_string();
+    let resp = TestClient::post(helpers::get_url("/api/portal/magic_login"))
+        .json(&json!({"token": link}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "magic_login").await;
+    let magic_session = json["data"]["token"].as_str().unwrap().to_string();
+    let resp = TestClient::post(helpers::get_url("/api/portal/magic_login"))
+        .json(&json!({"token": link}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "magic_login_reuse").await;
+    assert_eq!(json["success"], false);
+
+    let resp = TestClient::post(helpers::get_url(&format!(
+        "/api/portal/codes/{}/devices/{}/deactivate",
+        code_id, device_id
+    )))
+    .add_header("authorization", format!("Bearer {}", magic_session), true)
+    .send(&app)
+    .await;
+    let json = print_response_body_get_json(resp, "deactivate").await;
+    assert_eq!(json["success"], true);
+    let resp = TestClient::get(helpers::get_url("/api/portal/codes"))
+        .add_header("authorization", format!("Bearer {}", magic_session), true)
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "codes_after_deactivate").await;
+    assert!(json["data"][0]["devices"].as_array().unwrap().is_empty());
+
+    let resp = TestClient::get(helpers::get_url("/api/portal/codes"))
+        .send(&app)
+        .await;
+    assert_eq!(resp.status_code, Some(StatusCode::UNAUTHORIZED));
+}
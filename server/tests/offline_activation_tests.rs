@@ -0,0 +1,179 @@
+use app_server::types::offline_activation_types::OfflineActivationReq;
+use app_server::types::reg_codes_types::LicenseClaims;
+use app_server::utils::{license, signature};
+use salvo::test::TestClient;
+use serde_json::json;
+use crate::helpers::print_response_body_get_json;
+mod helpers;
+
+fn offline_request(app_id: &str, secret: &str, device_id: &str, code: &str) -> OfflineActivationReq {
+    let mut req = OfflineActivationReq {
+        app_id: app_id.to_string(),
+        code: code.to_string(),
+        device_id: device_id.to_string(),
+        device_info: Some(json!({"os": "windows"})),
+        created_at: chrono::Utc::now().timestamp(),
+        nonce: uuid::Uuid::new_v4().simple().to_string(),
+        sign: String::new(),
+    };
+    req.sign = signature::hmac_sha256_hex(secret, &req.sign_content());
+    req
+}
+
+#[tokio::test]
+async fn test_offline_activation() {
+    let app = helpers::create_test_app().await;
+    let token = helpers::login_admin(&app).await;
+    let app_data = helpers::create_app(&app, &token, json!({})).await;
+    let id = app_data["id"].as_i64().unwrap();
+    let app_id = app_data["app_id"].as_str().unwrap().to_string();
+    let app_key = app_data["app_valid_key"].as_str().unwrap().to_string();
+
+    let resp = TestClient::post(helpers::get_url("/api/admin/reg_codes/batch"))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&json!({"app_id": id, "count": 2, "valid_days": 30, "max_devices": 1, "code_type": 0}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "batch_add").await;
+    let codes = json["data"]["codes"].as_array().unwrap().clone();
+    let code = codes[0]["code"].as_str().unwrap().to_string();
+    let code_id = codes[0]["id"].as_i64().unwrap();
+    let portal_code = codes[1]["code"].as_str().unwrap().to_string();
+    let portal_code_id = codes[1]["id"].as_i64().unwrap();
+
+    // 请求文件中的注册码带首尾空白时与在线校验一样去掉
+    let req = offline_request(&app_id, &app_key, "air-gapped-1", &format!(" {}\n", code));
+    let resp = TestClient::post(helpers::get_url("/api/admin/offline_activations"))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&req)
+        .send(&app)
+        .await;
+    let disposition = resp
+        .headers()
+        .get("content-disposition")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    assert_eq!(disposition, format!("attachment; filename=\"activation_{}.json\"", req.nonce));
+    let json = print_response_body_get_json(resp, "offline_activate").await;
+    assert_eq!(json["nonce"].as_str().unwrap(), req.nonce);
+    assert_eq!(json["code"].as_str().unwrap(), code);
+    assert!(json["expire_time"].as_str().is_some());
+
+    // 许可证可用应用公钥校验，并标记为离线激活
+    let resp = TestClient::get(helpers::get_url(&format!("/api/reg/public_key/{}", app_id)))
+        .send(&app)
+        .await;
+    let key = print_response_body_get_json(resp, "public_key").await;
+    let claims: LicenseClaims = license::verify_token(
+        key["data"]["public_key"].as_str().unwrap(),
+        json["license_token"].as_str().unwrap(),
+    )
+    .unwrap();
+    assert!(claims.offline_activation);
+    assert_eq!(claims.device_id, "air-gapped-1");
+
+    // 激活记录与在线校验相同
+    let resp = TestClient::get(helpers::get_url(&format!("/api/admin/reg_codes/{}/devices", code_id)))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "devices").await;
+    let devices = json["data"].as_array().unwrap();
+    assert_eq!(devices.len(), 1);
+    assert_eq!(devices[0]["device_id"].as_str().unwrap(), "air-gapped-1");
+
+    // 设备数上限同样生效
+    let req = offline_request(&app_id, &app_key, "air-gapped-2", &code);
+    let resp = TestClient::post(helpers::get_url("/api/admin/offline_activations"))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&req)
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "device_limit").await;
+    assert_eq!(json["success"], false);
+
+    let mut req = offline_request(&app_id, &app_key, "air-gapped-2", &portal_code);
+    req.sign = signature::hmac_sha256_hex("wrong key", &req.sign_content());
+    let resp = TestClient::post(helpers::get_url("/api/admin/offline_activations"))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&req)
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "bad_sign").await;
+    assert_eq!(json["success"], false);
+    assert!(json["message"].as_str().unwrap().contains("signature"));
+
+    // 次数类注册码需要联网计数
+    let resp = TestClient::post(helpers::get_url("/api/admin/reg_codes/batch"))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&json!({"app_id": id, "count": 1, "valid_days": 30, "total_count": 10, "max_devices": 1, "code_type": 1}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "batch_add_count").await;
+    let count_code = json["data"]["codes"][0]["code"].as_str().unwrap().to_string();
+    let req = offline_request(&app_id, &app_key, "air-gapped-2", &count_code);
+    let resp = TestClient::post(helpers::get_url("/api/admin/offline_activations"))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&req)
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "count_code").await;
+    assert_eq!(json["success"], false);
+    assert!(json["message"].as_str().unwrap().contains("offline"));
+
+    // 门户只能激活自己订单下的注册码
+    let resp = TestClient::post(helpers::get_url("/api/admin/pay_methods"))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&json!({"name": "offline pay", "description": "offline", "is_active": true}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "create_pay_method").await;
+    let pay_method_id = json["data"]["id"].as_i64().unwrap();
+    let resp = TestClient::post(helpers::get_url("/api/admin/orders"))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&json!({
+            "order_id": "OFFLINE-1001",
+            "status": 1,
+            "pay_method_id": pay_method_id,
+            "original_price": 100,
+            "final_price": 100,
+            "contact_email": "buyer@example.com",
+            "created_by": 1,
+            "updated_by": 1
+        }))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "create_order").await;
+    let order_id = json["data"]["id"].as_i64().unwrap();
+    let resp = TestClient::post(helpers::get_url(&format!("/api/admin/orders/{}/reg_codes", order_id)))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&json!({"reg_code_ids": [portal_code_id]}))
+        .send(&app)
+        .await;
+    print_response_body_get_json(resp, "link_codes").await;
+    let resp = TestClient::post(helpers::get_url("/api/portal/login"))
+        .json(&json!({"order_no": "OFFLINE-1001", "email": "buyer@example.com"}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "login").await;
+    let session = json["data"]["token"].as_str().unwrap().to_string();
+
+    let req = offline_request(&app_id, &app_key, "air-gapped-3", &count_code);
+    let resp = TestClient::post(helpers::get_url("/api/portal/offline_activations"))
+        .add_header("authorization", format!("Bearer {}", session), true)
+        .json(&req)
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "portal_other_code").await;
+    assert_eq!(json["success"], false);
+    let req = offline_request(&app_id, &app_key, "air-gapped-3", &portal_code);
+    let resp = TestClient::post(helpers::get_url("/api/portal/offline_activations"))
+        .add_header("authorization", format!("Bearer {}", session), true)
+        .json(&req)
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "portal_activate").await;
+    assert_eq!(json["device_id"].as_str().unwrap(), "air-gapped-3");
+    assert!(json["license_token"].as_str().is_some());
+}
@@ -0,0 +1,160 @@
+use salvo::test::{ResponseExt, TestClient};
+use serde_json::json;
+use crate::helpers::print_response_body_get_json;
+mod helpers;
+
+#[tokio::test]
+async fn test_reg_code_batch_lifecycle() {
+    let app = helpers::create_test_app().await;
+    let token = helpers::login_admin(&app).await;
+    let app_data = helpers::create_app(&app, &token, json!({})).await;
+    let app_id = app_data["id"].as_i64().unwrap();
+    let app_key = app_data["app_valid_key"].as_str().unwrap().to_string();
+
+    let resp = TestClient::post(helpers::get_url("/api/admin/reg_codes/batch"))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&json!({
+            "app_id": app_id,
+            "count": 3,
+            "valid_days": 30,
+            "max_devices": 1,
+            "code_type": 0,
+            "batch_name": "spring promo",
+            "channel": "reseller-a"
+        }))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "batch_add").await;
+    let codes = json["data"]["codes"].as_array().unwrap().clone();
+    let batch_id = codes[0]["batch_id"].as_i64().unwrap();
+    assert!(codes.iter().all(|c| c["batch_id"].as_i64() == Some(batch_id)));
+
+    let resp = TestClient::get(helpers::get_url(&format!(
+        "/api/admin/reg_code_batches/list?app_id={}&channel=reseller-a",
+        app_id
+    )))
+    .add_header("authorization", format!("Bearer {}", token), true)
+    .send(&app)
+    .await;
+    let json = print_response_body_get_json(resp, "batch_list").await;
+    assert_eq!(json["data"]["total"].as_i64().unwrap(), 1);
+    assert_eq!(json["data"]["list"][0]["name"].as_str().unwrap(), "spring promo");
+    assert!(json["data"]["list"][0]["created_by"].as_i64().is_some());
+
+    let resp = TestClient::get(helpers::get_url(&format!(
+        "/api/admin/reg_codes/list?batch_id={}",
+        batch_id
+    )))
+    .add_header("authorization", format!("Bearer {}", token), true)
+    .send(&app)
+    .await;
+    let json = print_response_body_get_json(resp, "codes_by_batch").await;
+    assert_eq!(json["data"]["total"].as_i64().unwrap(), 3);
+
+    let code = codes[0]["code"].as_str().unwrap().to_string();
+    let resp = TestClient::post(helpers::get_url("/api/reg/validate"))
+        .json(&json!({"code": code, "app_key": app_key, "device_id": "batch-dev-1"}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "redeem").await;
+    let expire: chrono::DateTime<chrono::Utc> =
+        serde_json::from_value(json["data"]["expire_time"].clone()).unwrap();
+
+    let resp = TestClient::get(helpers::get_url(&format!(
+        "/api/admin/reg_code_batches/{}/stats",
+        batch_id
+    )))
+    .add_header("authorization", format!("Bearer {}", token), true)
+    .send(&app)
+    .await;
+    let json = print_response_body_get_json(resp, "batch_stats").await;
+    assert_eq!(json["data"]["total"].as_i64().unwrap(), 3);
+    assert_eq!(json["data"]["unused"].as_i64().unwrap(), 2);
+    assert_eq!(json["data"]["redeemed"].as_i64().unwrap(), 1);
+    assert_eq!(json["data"]["devices"].as_i64().unwrap(), 1);
+
+    // 已兑换的设备有效期和未兑换注册码的天数一起顺延
+    let resp = TestClient::post(helpers::get_url(&format!(
+        "/api/admin/reg_code_batches/{}/extend",
+        batch_id
+    )))
+    .add_header("authorization", format!("Bearer {}", token), true)
+    .json(&json!({"days": 10}))
+    .send(&app)
+    .await;
+    let json = print_response_body_get_json(resp, "batch_extend").await;
+    assert_eq!(json["data"]["codes"].as_i64().unwrap(), 3);
+    assert_eq!(json["data"]["devices"].as_i64().unwrap(), 1);
+    let resp = TestClient::post(helpers::get_url("/api/reg/validate"))
+        .json(&json!({"code": code, "app_key": app_key, "device_id": "batch-dev-1"}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "validate_extended").await;
+    let extended: chrono::DateTime<chrono::Utc> =
+        serde_json::from_value(json["data"]["expire_time"].clone()).unwrap();
+    assert_eq!(extended - expire, chrono::Duration::days(10));
+    let resp = TestClient::get(helpers::get_url(&format!(
+        "/api/admin/reg_codes/{}",
+        codes[1]["id"].as_i64().unwrap()
+    )))
+    .add_header("authorization", format!("Bearer {}", token), true)
+    .send(&app)
+    .await;
+    let json = print_response_body_get_json(resp, "unused_code").await;
+    assert_eq!(json["data"]["valid_days"].as_i64().unwrap(), 40);
+
+    let mut resp = TestClient::get(helpers::get_url(&format!(
+        "/api/admin/reg_code_batches/{}/export?format=csv",
+        batch_id
+    )))
+    .add_header("authorization", format!("Bearer {}", token), true)
+    .send(&app)
+    .await;
+    let body = resp.take_string().await.unwrap();
+    assert_eq!(body.lines().count(), 4);
+    assert!(body.contains(&code));
+
+    // 并发作废只有一个请求成功
+    let void_url = helpers::get_url(&format!("/api/admin/reg_code_batches/{}/void", batch_id));
+    let results = futures::future::join_all((0..3).map(|_| {
+        TestClient::post(&void_url)
+            .add_header("authorization", format!("Bearer {}", token), true)
+            .json(&json!({"reason": "reseller contract ended"}))
+            .send(&app)
+    }))
+    .await;
+    let mut voided = Vec::new();
+    for resp in results {
+        let json = print_response_body_get_json(resp, "batch_void").await;
+        if json["success"].as_bool().unwrap() {
+            voided.push(json);
+        } else {
+            assert!(json["message"].as_str().unwrap().contains("BATCH_VOIDED"));
+        }
+    }
+    assert_eq!(voided.len(), 1);
+    assert_eq!(voided[0]["data"]["codes"].as_i64().unwrap(), 3);
+    assert!(voided[0]["data"]["batch"]["voided_at"].is_string());
+    let resp = TestClient::post(helpers::get_url("/api/reg/validate"))
+        .json(&json!({"code": codes[1]["code"], "app_key": app_key, "device_id": "batch-dev-2"}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "validate_voided").await;
+    assert!(!json["success"].as_bool().unwrap());
+
+    // 作废的批次不能再加入注册码
+    let resp = TestClient::post(helpers::get_url("/api/admin/reg_codes/batch"))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&json!({
+            "app_id": app_id,
+            "count": 1,
+            "valid_days": 30,
+            "max_devices": 1,
+            "code_type": 0,
+            "batch_id": batch_id
+        }))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "add_to_voided").await;
+    assert!(json["message"].as_str().unwrap().contains("BATCH_VOIDED"));
+}
@@ -229,6 +229,32 @@ async fn test_update_reg_code() {
     assert_eq!(json["data"]["valid_days"], 60);
     assert_eq!(json["data"]["max_devices"], 10);
     assert_eq!(json["data"]["status"], 1);
+
+    // 更新接口不能吊销，吊销后可以通过更新接口恢复并清空吊销信息
+    let url = helpers::get_url(&format!("/api/admin/reg_codes/{}", reg_code_id));
+    let response = TestClient::put(&url)
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&json!({"status": 3}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(response, "update_reg_code_revoke").await;
+    assert!(!json["success"].as_bool().unwrap());
+    let response = TestClient::post(format!("{}/revoke", url))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&json!({"reason": "refund"}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(response, "revoke_reg_code").await;
+    assert_eq!(json["data"]["status"], 3);
+    assert!(json["data"]["revoked_at"].is_string());
+    let response = TestClient::put(&url)
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&json!({"status": 1}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(response, "update_reg_code_unrevoke").await;
+    assert_eq!(json["data"]["status"], 1);
+    assert!(json["data"]["revoked_at"].is_null());
 }
 
 #[tokio::test]
@@ -403,3 +429,1239 @@ async fn test_reg_code_validation_errors() {
     let json = print_response_body_get_json(response, "create_reg_code_response").await;
     assert_eq!(json["success"].as_bool().unwrap(), false);
 }
+
+#[tokio::test]
+async fn test_validate_returns_signed_license() {
+    let app = helpers::create_test_app().await;
+    let token = helpers::login_admin(&app).await;
+    let ts = chrono::Utc::now().timestamp_micros();
+    let app_key = format!("KEY_LIC_{}", ts);
+    let app_id_str = format!("com.lic.{}", ts);
+    let create_app_body = json!({
+        "name": format!("LIC-App-{}", ts),
+        "app_id": app_id_str,
+        "app_vername": "1.0.0",
+        "app_vercode": 1,
+        "app_download_url": "https://example.com/dl",
+        "app_res_url": "https://example.com/res",
+        "app_valid_key": app_key,
+        "trial_days": 3,
+        "offline_grace_days": 5,
+        "sort_order": 0,
+        "status": 1
+    });
+    let resp = TestClient::post(helpers::get_url("/api/admin/apps"))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .add_header("content-type", "application/json", true)
+        .json(&create_app_body)
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "create_app_for_license").await;
+    assert!(json["data"]["sign_public_key"].is_string());
+    assert!(json["data"].get("sign_private_key").is_none());
+
+    let resp = TestClient::get(helpers::get_url(&format!("/api/reg/public_key/{}", app_id_str)))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "get_public_key").await;
+    assert_eq!(json["data"]["algorithm"], "Ed25519");
+    let public_key = json["data"]["public_key"].as_str().unwrap().to_string();
+
+    let resp = TestClient::post(helpers::get_url("/api/reg/validate"))
+        .add_header("content-type", "application/json", true)
+        .json(&json!({"app_key":app_key, "device_id":"lic-dev-1"}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "validate_with_license").await;
+    let license_token = json["data"]["license_token"].as_str().unwrap();
+    let claims: app_server::types::reg_codes_types::LicenseClaims =
+        app_server::utils::license::verify_token(&public_key, license_token).unwrap();
+    assert_eq!(claims.app_id, app_id_str);
+    assert_eq!(claims.device_id, "lic-dev-1");
+    assert_eq!(claims.offline_grace_days, 5);
+    assert!(claims.expire_at.is_some());
+
+    // 篡改后的令牌无法通过校验
+    let tampered = format!("x{}", license_token);
+    assert!(app_server::utils::license::verify_token::<app_server::types::reg_codes_types::LicenseClaims>(&public_key, &tampered).is_err());
+}
+
+#[tokio::test]
+async fn test_reg_code_multi_device_binding() {
+    let app = helpers::create_test_app().await;
+    let token = helpers::login_admin(&app).await;
+    let ts = chrono::Utc::now().timestamp_micros();
+    let app_key = format!("KEY_MD_{}", ts);
+    let create_app_body = json!({
+        "name": format!("MD-App-{}", ts),
+        "app_id": format!("com.md.{}", ts),
+        "app_vername": "1.0.0",
+        "app_vercode": 1,
+        "app_download_url": "https://example.com/dl",
+        "app_res_url": "https://example.com/res",
+        "app_valid_key": app_key,
+        "trial_days": 0,
+        "sort_order": 0,
+        "status": 1
+    });
+    let resp = TestClient::post(helpers::get_url("/api/admin/apps"))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .add_header("content-type", "application/json", true)
+        .json(&create_app_body)
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "create_app_for_multi_device").await;
+    let app_id = json["data"]["id"].as_i64().unwrap() as i32;
+    let code = format!("MD_{}", ts);
+    let resp = TestClient::post(helpers::get_url("/api/admin/reg_codes"))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .add_header("content-type", "application/json", true)
+        .json(&json!({
+            "code": code,
+            "app_id": app_id,
+            "valid_days": 30,
+            "max_devices": 2,
+            "status": 0,
+            "code_type": 0
+        }))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "create_reg_code_for_multi_device").await;
+    let reg_code_id = json["data"]["id"].as_i64().unwrap();
+
+    for device in ["md-dev-1", "md-dev-2", "md-dev-1"] {
+        let resp = TestClient::post(helpers::get_url("/api/reg/validate"))
+            .add_header("content-type", "application/json", true)
+            .json(&json!({"code":code, "app_key":app_key, "device_id":device}))
+            .send(&app)
+            .await;
+        let json = print_response_body_get_json(resp, "validate_multi_device").await;
+        assert!(json["success"].as_bool().unwrap());
+    }
+    let resp = TestClient::post(helpers::get_url("/api/reg/validate"))
+        .add_header("content-type", "application/json", true)
+        .json(&json!({"code":code, "app_key":app_key, "device_id":"md-dev-3"}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "validate_device_limit").await;
+    assert!(!json["success"].as_bool().unwrap());
+    assert!(json["message"].as_str().unwrap().contains("device limit reached"));
+
+    let resp = TestClient::get(helpers::get_url(&format!("/api/admin/reg_codes/{}", reg_code_id)))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "reg_code_with_devices").await;
+    let devices = json["data"]["devices"].as_array().unwrap();
+    assert_eq!(devices.len(), 2);
+    let first_device = devices[0]["id"].as_i64().unwrap();
+    let second_device = devices[1]["id"].as_i64().unwrap();
+
+    // 转移第一个设备到新设备
+    let resp = TestClient::post(helpers::get_url(&format!(
+        "/api/admin/reg_codes/{}/devices/{}/transfer",
+        reg_code_id, first_device
+    )))
+    .add_header("authorization", format!("Bearer {}", token), true)
+    .add_header("content-type", "application/json", true)
+    .json(&json!({"to_device_id": "md-dev-3"}))
+    .send(&app)
+    .await;
+    let json = print_response_body_get_json(resp, "transfer_device").await;
+    let device_ids: Vec<&str> = json["data"]["devices"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|d| d["device_id"].as_str().unwrap())
+        .collect();
+    assert!(device_ids.contains(&"md-dev-3"));
+    assert!(!device_ids.contains(&"md-dev-1"));
+
+    // 解绑后释放名额
+    let resp = TestClient::delete(helpers::get_url(&format!(
+        "/api/admin/reg_codes/{}/devices/{}",
+        reg_code_id, second_device
+    )))
+    .add_header("authorization", format!("Bearer {}", token), true)
+    .send(&app)
+    .await;
+    let json = print_response_body_get_json(resp, "unbind_device").await;
+    assert!(json["success"].as_bool().unwrap());
+    let resp = TestClient::get(helpers::get_url(&format!("/api/admin/reg_codes/{}/devices", reg_code_id)))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "list_devices").await;
+    assert_eq!(json["data"].as_array().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_batch_create_reg_codes() {
+    use salvo::test::ResponseExt;
+    let app = helpers::create_test_app().await;
+    let token = helpers::login_admin(&app).await;
+    let ts = chrono::Utc::now().timestamp_micros();
+    let create_app_body = json!({
+        "name": format!("Batch-App-{}", ts),
+        "app_id": format!("com.batch.{}", ts),
+        "app_vername": "1.0.0",
+        "app_vercode": 1,
+        "app_download_url": "https://example.com/dl",
+        "app_res_url": "https://example.com/res",
+        "app_valid_key": format!("KEY_BATCH_{}", ts),
+        "trial_days": 0,
+        "sort_order": 0,
+        "status": 1
+    });
+    let resp = TestClient::post(helpers::get_url("/api/admin/apps"))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .add_header("content-type", "application/json", true)
+        .json(&create_app_body)
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "create_app_for_batch").await;
+    let app_id = json["data"]["id"].as_i64().unwrap() as i32;
+    let batch_body = json!({
+        "app_id": app_id,
+        "count": 20,
+        "valid_days": 30,
+        "max_devices": 1,
+        "code_type": 0,
+        "format": {"prefix": "VIP", "segments": 3, "segment_len": 4, "checksum": true}
+    });
+
+    let resp = TestClient::post(helpers::get_url("/api/admin/reg_codes/batch"))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .add_header("content-type", "application/json", true)
+        .json(&batch_body)
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "batch_create_reg_codes").await;
+    assert!(json["success"].as_bool().unwrap());
+    assert_eq!(json["data"]["count"].as_u64().unwrap(), 20);
+    let code_id = json["data"]["codes"][0]["id"].as_i64().unwrap();
+    let codes: std::collections::HashSet<&str> = json["data"]["codes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| c["code"].as_str().unwrap())
+        .collect();
+    assert_eq!(codes.len(), 20);
+    for code in &codes {
+        let parts: Vec<&str> = code.split('-').collect();
+        assert_eq!(parts.len(), 5);
+        assert_eq!(parts[0], "VIP");
+        assert!(parts[1..].iter().all(|p| p.len() == 4));
+    }
+
+    // 导出为 csv 附件
+    let mut resp = TestClient::post(helpers::get_url("/api/admin/reg_codes/batch?export=csv"))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .add_header("content-type", "application/json", true)
+        .json(&batch_body)
+        .send(&app)
+        .await;
+    let disposition = resp
+        .headers()
+        .get("content-disposition")
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_string();
+    assert!(disposition.contains("attachment") && disposition.ends_with(".csv\""));
+    let body = resp.take_string().await.unwrap();
+    let mut lines = body.lines();
+    assert!(lines.next().unwrap().starts_with("id,code,app_id"));
+    assert_eq!(lines.count(), 20);
+
+    // 非法的格式模板
+    let resp = TestClient::post(helpers::get_url("/api/admin/reg_codes/batch"))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .add_header("content-type", "application/json", true)
+        .json(&json!({
+            "app_id": app_id,
+            "count": 1,
+            "valid_days": 30,
+            "max_devices": 1,
+            "code_type": 0,
+            "format": {"segment_len": 1}
+        }))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "batch_create_invalid_format").await;
+    assert!(!json["success"].as_bool().unwrap());
+
+    // 格式可组合的注册码数量不足
+    let resp = TestClient::post(helpers::get_url("/api/admin/reg_codes/batch"))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .add_header("content-type", "application/json", true)
+        .json(&json!({
+            "app_id": app_id,
+            "count": 200,
+            "valid_days": 30,
+            "max_devices": 1,
+            "code_type": 0,
+            "format": {"segments": 1, "segment_len": 3, "charset": "0123456789"}
+        }))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "batch_create_code_space_exhausted").await;
+    assert!(!json["success"].as_bool().unwrap());
+    assert!(json["message"].as_str().unwrap().contains("CODE_SPACE_EXHAUSTED"));
+
+    // 计次码未指定总次数，设备数为 0
+    for body in [
+        json!({"app_id": app_id, "count": 1, "valid_days": 30, "max_devices": 1, "code_type": 1}),
+        json!({"app_id": app_id, "count": 1, "valid_days": 30, "max_devices": 0, "code_type": 0}),
+    ] {
+        let resp = TestClient::post(helpers::get_url("/api/admin/reg_codes/batch"))
+            .add_header("authorization", format!("Bearer {}", token), true)
+            .add_header("content-type", "application/json", true)
+            .json(&body)
+            .send(&app)
+            .await;
+        let json = print_response_body_get_json(resp, "batch_create_invalid_terms").await;
+        assert!(!json["success"].as_bool().unwrap());
+    }
+
+    // 更新时同样校验，计时码改成计次码必须带总次数
+    for body in [
+        json!({"max_devices": 0}),
+        json!({"valid_days": -1}),
+        json!({"total_count": 0}),
+        json!({"code_type": 1}),
+    ] {
+        let resp = TestClient::put(helpers::get_url(&format!("/api/admin/reg_codes/{}", code_id)))
+            .add_header("authorization", format!("Bearer {}", token), true)
+            .json(&body)
+            .send(&app)
+            .await;
+        let json = print_response_body_get_json(resp, "update_invalid_terms").await;
+        assert!(!json["success"].as_bool().unwrap());
+    }
+}
+
+#[tokio::test]
+async fn test_validate_rejects_malformed_code() {
+    let app = helpers::create_test_app().await;
+    let token = helpers::login_admin(&app).await;
+    let ts = chrono::Utc::now().timestamp_micros();
+    let app_id_str = format!("com.strict.{}", ts);
+    let app_key = format!("KEY_STRICT_{}", ts);
+    let resp = TestClient::post(helpers::get_url("/api/admin/apps"))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .add_header("content-type", "application/json", true)
+        .json(&json!({
+            "name": format!("Strict-App-{}", ts),
+            "app_id": app_id_str,
+            "app_vername": "1.0.0",
+            "app_vercode": 1,
+            "app_download_url": "https://example.com/dl",
+            "app_res_url": "https://example.com/res",
+            "app_valid_key": app_key,
+            "trial_days": 0,
+            "strict_code_format": true,
+            "sort_order": 0,
+            "status": 1
+        }))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "create_strict_app").await;
+    let app_id = json["data"]["id"].as_i64().unwrap() as i32;
+    assert!(json["data"]["strict_code_format"].as_bool().unwrap());
+
+    let resp = TestClient::post(helpers::get_url("/api/admin/reg_codes/batch"))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .add_header("content-type", "application/json", true)
+        .json(&json!({
+            "app_id": app_id,
+            "count": 1,
+            "valid_days": 30,
+            "max_devices": 1,
+            "code_type": 0
+        }))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "batch_create_strict_code").await;
+    let code = json["data"]["codes"][0]["code"].as_str().unwrap().to_string();
+    let code_id = json["data"]["codes"][0]["id"].as_i64().unwrap();
+    assert!(reg_code::verify(&code, Some(&app_id_str)).is_ok());
+
+    let resp = TestClient::post(helpers::get_url("/api/reg/validate"))
+        .add_header("content-type", "application/json", true)
+        .json(&json!({"code": code, "app_key": app_key, "device_id": "strict-dev-1"}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "validate_strict_code").await;
+    assert!(json["success"].as_bool().unwrap());
+
+    // 改错一位，校验段不匹配
+    let mut typo: Vec<char> = code.chars().collect();
+    typo[0] = if typo[0] == 'A' { 'B' } else { 'A' };
+    let typo: String = typo.into_iter().collect();
+    for bad in [typo.as_str(), "not-a-valid code"] {
+        let resp = TestClient::post(helpers::get_url("/api/reg/validate"))
+            .add_header("content-type", "application/json", true)
+            .json(&json!({"code": bad, "app_key": app_key, "device_id": "strict-dev-2"}))
+            .send(&app)
+            .await;
+        let json = print_response_body_get_json(resp, "validate_malformed_code").await;
+        assert!(!json["success"].as_bool().unwrap());
+        assert!(json["message"].as_str().unwrap().contains("INVALID_CODE_FORMAT"));
+    }
+
+    // 首尾空白在校验和查库前统一去掉
+    let resp = TestClient::post(helpers::get_url("/api/reg/validate"))
+        .add_header("content-type", "application/json", true)
+        .json(&json!({"code": format!("  {}\n", code), "app_key": app_key, "device_id": "strict-dev-1"}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "validate_padded_strict_code").await;
+    assert!(json["success"].as_bool().unwrap());
+
+    // 严格格式的应用不能手动添加或导入没有校验段的注册码
+    let resp = TestClient::post(helpers::get_url("/api/admin/reg_codes"))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .add_header("content-type", "application/json", true)
+        .json(&json!({
+            "code": format!("PLAIN_{}", ts),
+            "app_id": app_id,
+            "code_type": 0,
+            "valid_days": 30,
+            "max_devices": 1,
+            "status": 0
+        }))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "add_plain_code_to_strict_app").await;
+    assert!(!json["success"].as_bool().unwrap());
+    assert!(json["message"].as_str().unwrap().contains("INVALID_CODE_FORMAT"));
+
+    let rows = json!([{"code": format!("PLAIN_{}", ts)}]).to_string();
+    let resp = TestClient::post(helpers::get_url(&format!(
+        "/api/admin/reg_codes/import?app_id={}&format=json",
+        app_id
+    )))
+    .add_header("authorization", format!("Bearer {}", token), true)
+    .text(rows)
+    .send(&app)
+    .await;
+    let json = print_response_body_get_json(resp, "import_plain_code_to_strict_app").await;
+    assert!(!json["data"]["applied"].as_bool().unwrap());
+    assert_eq!(json["data"]["errors"].as_array().unwrap().len(), 1);
+
+    let resp = TestClient::put(helpers::get_url(&format!("/api/admin/reg_codes/{}", code_id)))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&json!({"code": format!("PLAIN_{}", ts)}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "rename_strict_code_to_plain").await;
+    assert!(!json["success"].as_bool().unwrap());
+    assert!(json["message"].as_str().unwrap().contains("INVALID_CODE_FORMAT"));
+}
+
+#[tokio::test]
+async fn test_validate_v2_signed_request() {
+    use app_server::types::reg_codes_types::{RegCodeValidateV2Req, RegCodeValidateV2Resp};
+    use app_server::utils::signature;
+    let app = helpers::create_test_app().await;
+    let token = helpers::login_admin(&app).await;
+    let ts = chrono::Utc::now().timestamp_micros();
+    let app_id_str = format!("com.v2.{}", ts);
+    let secret = format!("KEY_V2_{}", ts);
+    let resp = TestClient::post(helpers::get_url("/api/admin/apps"))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .add_header("content-type", "application/json", true)
+        .json(&json!({
+            "name": format!("V2-App-{}", ts),
+            "app_id": app_id_str,
+            "app_vername": "1.0.0",
+            "app_vercode": 1,
+            "app_download_url": "https://example.com/dl",
+            "app_res_url": "https://example.com/res",
+            "app_valid_key": secret,
+            "trial_days": 3,
+            "sort_order": 0,
+            "status": 1
+        }))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "create_app_for_v2").await;
+    let app_id = json["data"]["id"].as_i64().unwrap();
+
+    let signed_req = |nonce: &str, timestamp: i64, key: &str| {
+        let mut req = RegCodeValidateV2Req {
+            app_id: app_id_str.clone(),
+            device_id: "v2-dev-1".to_string(),
+            code: None,
+            timestamp,
+            nonce: nonce.to_string(),
+            sign: String::new(),
+            device_info: None,
+            config: None,
+        };
+        req.sign = signature::hmac_sha256_hex(key, &req.sign_content());
+        req
+    };
+    let now = chrono::Utc::now().timestamp();
+    let nonce = format!("nonce-{}", ts);
+
+    let resp = TestClient::post(helpers::get_url("/api/reg/v2/validate"))
+        .json(&signed_req(&nonce, now, &secret))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "validate_v2").await;
+    assert!(json["success"].as_bool().unwrap());
+    let v2_resp: RegCodeValidateV2Resp = serde_json::from_value(json["data"].clone()).unwrap();
+    assert_eq!(v2_resp.nonce, nonce);
+    assert!(signature::verify_hmac_sha256_hex(&secret, &v2_resp.sign_content(), &v2_resp.sign));
+    let payload: serde_json::Value = serde_json::from_str(&v2_resp.payload).unwrap();
+    assert!(payload["license_token"].is_string());
+
+    // 重放同一个 nonce
+    let resp = TestClient::post(helpers::get_url("/api/reg/v2/validate"))
+        .json(&signed_req(&nonce, now, &secret))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "validate_v2_replay").await;
+    assert!(json["message"].as_str().unwrap().contains("NONCE_REPLAYED"));
+
+    // 签名错误、时间戳超出范围
+    let resp = TestClient::post(helpers::get_url("/api/reg/v2/validate"))
+        .json(&signed_req(&format!("bad-sign-{}", ts), now, "wrong-secret"))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "validate_v2_bad_sign").await;
+    assert!(json["message"].as_str().unwrap().contains("SIGN_MISMATCH"));
+    let resp = TestClient::post(helpers::get_url("/api/reg/v2/validate"))
+        .json(&signed_req(&format!("stale-{}", ts), now - 3600, &secret))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "validate_v2_stale").await;
+    assert!(json["message"].as_str().unwrap().contains("TIMESTAMP_EXPIRED"));
+
+    // 关闭旧版接口后，明文 app_key 的校验被拒绝
+    let resp = TestClient::put(helpers::get_url(&format!("/api/admin/apps/{}", app_id)))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&json!({"allow_legacy_validate": false}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "disable_legacy_validate").await;
+    assert!(!json["data"]["allow_legacy_validate"].as_bool().unwrap());
+    let resp = TestClient::post(helpers::get_url("/api/reg/validate"))
+        .json(&json!({"app_key": secret, "device_id": "v2-dev-1"}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "legacy_validate_disabled").await;
+    assert!(json["message"].as_str().unwrap().contains("LEGACY_VALIDATE_DISABLED"));
+
+    // 没有设置密钥的应用不接受签名请求，否则用空密钥即可伪造签名
+    let keyless = helpers::create_app(&app, &token, json!({"app_valid_key": ""})).await;
+    let keyless_id = keyless["app_id"].as_str().unwrap();
+    let resp = TestClient::post(helpers::get_url("/api/reg/v2/validate"))
+        .json(&helpers::signed_request(keyless_id, "", "v2-dev-1", None))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "validate_v2_empty_key").await;
+    assert!(json["message"].as_str().unwrap().contains("SIGN_KEY_MISSING"));
+}
+
+#[tokio::test]
+async fn test_revoke_code_and_ban_device() {
+    use app_server::types::reg_codes_types::RevocationList;
+    use sha2::{Digest, Sha256};
+    let app = helpers::create_test_app().await;
+    let token = helpers::login_admin(&app).await;
+    let ts = chrono::Utc::now().timestamp_micros();
+    let app_id_str = format!("com.revoke.{}", ts);
+    let app_key = format!("KEY_REVOKE_{}", ts);
+    let resp = TestClient::post(helpers::get_url("/api/admin/apps"))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&json!({
+            "name": format!("Revoke-App-{}", ts),
+            "app_id": app_id_str,
+            "app_vername": "1.0.0",
+            "app_vercode": 1,
+            "app_download_url": "https://example.com/dl",
+            "app_res_url": "https://example.com/res",
+            "app_valid_key": app_key,
+            "trial_days": 3,
+            "sort_order": 0,
+            "status": 1
+        }))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "create_app_for_revoke").await;
+    let app_id = json["data"]["id"].as_i64().unwrap();
+    let public_key = json["data"]["sign_public_key"].as_str().unwrap().to_string();
+    let code = format!("REVOKE_{}", ts);
+    let resp = TestClient::post(helpers::get_url("/api/admin/reg_codes"))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&json!({
+            "code": code,
+            "app_id": app_id,
+            "valid_days": 30,
+            "max_devices": 1,
+            "status": 0,
+            "code_type": 0
+        }))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "create_reg_code_for_revoke").await;
+    let reg_code_id = json["data"]["id"].as_i64().unwrap();
+
+    let resp = TestClient::post(helpers::get_url(&format!("/api/admin/reg_codes/{}/revoke", reg_code_id)))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&json!({"reason": "leaked"}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "revoke_reg_code").await;
+    assert_eq!(json["data"]["status"].as_i64().unwrap(), 3);
+    assert_eq!(json["data"]["revoked_reason"].as_str().unwrap(), "leaked");
+    let resp = TestClient::post(helpers::get_url("/api/reg/validate"))
+        .json(&json!({"code": code, "app_key": app_key, "device_id": "revoke-dev-1"}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "validate_revoked_code").await;
+    assert!(json["message"].as_str().unwrap().contains("CODE_REVOKED"));
+    assert_eq!(json["code"].as_u64().unwrap(), app_server::constants::APP_LICENSE_REVOKED as u64);
+
+    let resp = TestClient::get(helpers::get_url(&format!("/api/reg/revocations/{}?kind=codes", app_id_str)))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "revocation_list_codes").await;
+    let list: RevocationList =
+        app_server::utils::license::verify_token(&public_key, json["data"]["token"].as_str().unwrap()).unwrap();
+    assert_eq!(list.total, 1);
+    assert_eq!(list.items[0].value, hex::encode(Sha256::digest(code.as_bytes())));
+
+    // 封禁设备后，试用校验也被拒绝
+    let resp = TestClient::post(helpers::get_url("/api/admin/devices/bans"))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&json!({"app_id": app_id, "device_id": "revoke-dev-2", "reason": "cracked"}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "ban_device").await;
+    let ban_id = json["data"]["id"].as_i64().unwrap();
+    let resp = TestClient::post(helpers::get_url("/api/reg/validate"))
+        .json(&json!({"app_key": app_key, "device_id": "revoke-dev-2"}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "validate_banned_device").await;
+    assert!(json["message"].as_str().unwrap().contains("DEVICE_BANNED"));
+    let resp = TestClient::get(helpers::get_url(&format!("/api/reg/revocations/{}?kind=devices", app_id_str)))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "revocation_list_devices").await;
+    let list: RevocationList =
+        app_server::utils::license::verify_token(&public_key, json["data"]["token"].as_str().unwrap()).unwrap();
+    assert_eq!(list.items[0].value, "revoke-dev-2");
+
+    let resp = TestClient::delete(helpers::get_url(&format!("/api/admin/devices/bans/{}", ban_id)))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .send(&app)
+        .await;
+    print_response_body_get_json(resp, "unban_device").await;
+    let resp = TestClient::post(helpers::get_url("/api/reg/validate"))
+        .json(&json!({"app_key": app_key, "device_id": "revoke-dev-2"}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "validate_unbanned_device").await;
+    assert!(json["success"].as_bool().unwrap());
+
+    // 恢复注册码后，全量列表不再包含，增量列表标记为已解除
+    let resp = TestClient::put(helpers::get_url(&format!("/api/admin/reg_codes/{}", reg_code_id)))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&json!({"status": 1}))
+        .send(&app)
+        .await;
+    print_response_body_get_json(resp, "restore_reg_code").await;
+    let since = chrono::Utc::now().timestamp() - 600;
+    let revocations = |kind: &str, since: Option<i64>| {
+        let query = since.map(|s| format!("&since={}", s)).unwrap_or_default();
+        TestClient::get(helpers::get_url(&format!(
+            "/api/reg/revocations/{}?kind={}{}",
+            app_id_str, kind, query
+        )))
+        .send(&app)
+    };
+    let list_of = |json: serde_json::Value| -> RevocationList {
+        app_server::utils::license::verify_token(&public_key, json["data"]["token"].as_str().unwrap()).unwrap()
+    };
+    let json = print_response_body_get_json(revocations("codes", None).await, "revocation_codes_full").await;
+    assert_eq!(list_of(json).total, 0);
+    let json = print_response_body_get_json(revocations("codes", Some(since)).await, "revocation_codes_since").await;
+    let list = list_of(json);
+    assert_eq!(list.total, 1);
+    assert!(list.items[0].restored);
+    let json = print_response_body_get_json(revocations("devices", None).await, "revocation_devices_full").await;
+    assert_eq!(list_of(json).total, 0);
+    let json = print_response_body_get_json(revocations("devices", Some(since)).await, "revocation_devices_since").await;
+    let list = list_of(json);
+    assert_eq!(list.items[0].value, "revoke-dev-2");
+    assert!(list.items[0].restored);
+
+    // 再次封禁复用原记录
+    let resp = TestClient::post(helpers::get_url("/api/admin/devices/bans"))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&json!({"app_id": app_id, "device_id": "revoke-dev-2", "reason": "again"}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "ban_device_again").await;
+    assert_eq!(json["data"]["id"].as_i64().unwrap(), ban_id);
+    let json = print_response_body_get_json(revocations("devices", Some(since)).await, "revocation_devices_rebanned").await;
+    let list = list_of(json);
+    assert!(!list.items[0].restored);
+    assert_eq!(list.items[0].reason, "again");
+}
+
+#[tokio::test]
+async fn test_floating_license_leases() {
+    let app = helpers::create_test_app().await;
+    let token = helpers::login_admin(&app).await;
+    let app_data = helpers::create_app(&app, &token, json!({})).await;
+    let app_id = app_data["id"].as_i64().unwrap();
+    let app_id_str = app_data["app_id"].as_str().unwrap().to_string();
+    let secret = app_data["app_valid_key"].as_str().unwrap().to_string();
+    let code = format!("FLOAT_{}", chrono::Utc::now().timestamp_micros());
+    let resp = TestClient::post(helpers::get_url("/api/admin/reg_codes"))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&json!({
+            "code": code,
+            "app_id": app_id,
+            "valid_days": 30,
+            "max_devices": 2,
+            "status": 0,
+            "code_type": 2
+        }))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "create_floating_code").await;
+    let reg_code_id = json["data"]["id"].as_i64().unwrap();
+
+    let lease = |path: &'static str, device: &'static str| {
+        let req = helpers::signed_request(&app_id_str, &secret, device, Some(&code));
+        let app = &app;
+        async move {
+            let resp = TestClient::post(helpers::get_url(path)).json(&req).send(app).await;
+            print_response_body_get_json(resp, path).await
+        }
+    };
+    for device in ["float-dev-1", "float-dev-2"] {
+        let json = lease("/api/reg/lease/checkout", device).await;
+        assert!(json["success"].as_bool().unwrap());
+        let payload: serde_json::Value =
+            serde_json::from_str(json["data"]["payload"].as_str().unwrap()).unwrap();
+        assert_eq!(payload["seats_total"].as_i64().unwrap(), 2);
+    }
+    let json = lease("/api/reg/lease/checkout", "float-dev-3").await;
+    assert!(json["message"].as_str().unwrap().contains("SEATS_EXHAUSTED"));
+
+    let resp = TestClient::get(helpers::get_url(&format!("/api/admin/reg_codes/{}/leases", reg_code_id)))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "seat_usage").await;
+    assert_eq!(json["data"]["seats_used"].as_i64().unwrap(), 2);
+
+    // 释放一个席位后其它设备可以签出
+    let json = lease("/api/reg/lease/checkin", "float-dev-1").await;
+    assert!(json["success"].as_bool().unwrap());
+    let json = lease("/api/reg/lease/checkout", "float-dev-3").await;
+    assert!(json["success"].as_bool().unwrap());
+    let json = lease("/api/reg/lease/heartbeat", "float-dev-3").await;
+    assert!(json["success"].as_bool().unwrap());
+    let json = lease("/api/reg/lease/heartbeat", "float-dev-1").await;
+    assert!(json["message"].as_str().unwrap().contains("LEASE_EXPIRED"));
+
+    // 浮动授权不能走普通校验
+    let resp = TestClient::post(helpers::get_url("/api/reg/validate"))
+        .json(&json!({"code": code, "app_key": secret, "device_id": "float-dev-4"}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "validate_floating_code").await;
+    assert!(json["message"].as_str().unwrap().contains("FLOATING_CODE"));
+}
+
+#[tokio::test]
+async fn test_feature_entitlements() {
+    let app = helpers::create_test_app().await;
+    let token = helpers::login_admin(&app).await;
+    let app_data = helpers::create_app(&app, &token, json!({})).await;
+    let app_id = app_data["id"].as_i64().unwrap();
+    let app_key = app_data["app_valid_key"].as_str().unwrap().to_string();
+    let public_key = app_data["sign_public_key"].as_str().unwrap().to_string();
+    for (key, feature_type, default_value) in [("export", 0, 0), ("basic_ui", 0, 1), ("max_projects", 1, 3)] {
+        let resp = TestClient::post(helpers::get_url("/api/admin/app_features"))
+            .add_header("authorization", format!("Bearer {}", token), true)
+            .json(&json!({
+                "app_id": app_id,
+                "feature_key": key,
+                "name": key,
+                "feature_type": feature_type,
+                "default_value": default_value
+            }))
+            .send(&app)
+            .await;
+        let json = print_response_body_get_json(resp, "create_app_feature").await;
+        assert_eq!(json["data"]["feature_key"].as_str().unwrap(), key);
+    }
+    let resp = TestClient::get(helpers::get_url(&format!("/api/admin/app_features/list?app_id={}", app_id)))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "list_app_features").await;
+    assert_eq!(json["data"]["total"].as_i64().unwrap(), 3);
+
+    // 未定义的功能不能授予
+    let code = format!("FEAT_{}", chrono::Utc::now().timestamp_micros());
+    let mut body = json!({
+        "code": code,
+        "app_id": app_id,
+        "valid_days": 30,
+        "max_devices": 1,
+        "status": 0,
+        "code_type": 0,
+        "entitlements": {"features": ["unknown"]}
+    });
+    let resp = TestClient::post(helpers::get_url("/api/admin/reg_codes"))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&body)
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "create_code_undefined_feature").await;
+    assert!(!json["success"].as_bool().unwrap());
+
+    body["entitlements"] = json!({"features": ["export"], "limits": {"max_projects": 10}});
+    let resp = TestClient::post(helpers::get_url("/api/admin/reg_codes"))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&body)
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "create_code_with_entitlements").await;
+    assert_eq!(json["data"]["entitlements"]["features"], json!(["export"]));
+
+    let resp = TestClient::post(helpers::get_url("/api/reg/validate"))
+        .json(&json!({"code": code, "app_key": app_key, "device_id": "feat-dev-1"}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "validate_with_entitlements").await;
+    let entitlements = &json["data"]["entitlements"];
+    assert_eq!(entitlements["features"], json!(["basic_ui", "export"]));
+    assert_eq!(entitlements["limits"]["max_projects"].as_i64().unwrap(), 10);
+    let claims: app_server::types::reg_codes_types::LicenseClaims = app_server::utils::license::verify_token(
+        &public_key,
+        json["data"]["license_token"].as_str().unwrap(),
+    )
+    .unwrap();
+    assert!(claims.entitlements.features.contains("export"));
+
+    // 试用只有默认值
+    let resp = TestClient::post(helpers::get_url("/api/reg/validate"))
+        .json(&json!({"app_key": app_key, "device_id": "feat-dev-2"}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "validate_trial_entitlements").await;
+    let entitlements = &json["data"]["entitlements"];
+    assert_eq!(entitlements["features"], json!(["basic_ui"]));
+    assert_eq!(entitlements["limits"]["max_projects"].as_i64().unwrap(), 3);
+}
+
+#[tokio::test]
+async fn test_time_code_renewal_stacking() {
+    let app = helpers::create_test_app().await;
+    let token = helpers::login_admin(&app).await;
+    let app_data = helpers::create_app(&app, &token, json!({"trial_days": 3})).await;
+    let app_id = app_data["id"].as_i64().unwrap();
+    let app_key = app_data["app_valid_key"].as_str().unwrap().to_string();
+    let ts = chrono::Utc::now().timestamp_micros();
+    let mut codes = vec![];
+    for (i, days) in [30, 10].iter().enumerate() {
+        let code = format!("RENEW_{}_{}", ts, i);
+        let resp = TestClient::post(helpers::get_url("/api/admin/reg_codes"))
+            .add_header("authorization", format!("Bearer {}", token), true)
+            .json(&json!({
+                "code": code,
+                "app_id": app_id,
+                "valid_days": days,
+                "max_devices": 1,
+                "status": 0,
+                "code_type": 0
+            }))
+            .send(&app)
+            .await;
+        let json = print_response_body_get_json(resp, "create_renewal_code").await;
+        assert!(json["success"].as_bool().unwrap());
+        codes.push(code);
+    }
+    let validate = |code: Option<String>| {
+        TestClient::post(helpers::get_url("/api/reg/validate"))
+            .json(&json!({"code": code, "app_key": app_key, "device_id": "renew-dev-1"}))
+            .send(&app)
+    };
+    let expire_of = |json: &serde_json::Value| {
+        chrono::DateTime::parse_from_rfc3339(json["data"]["expire_time"].as_str().unwrap())
+            .unwrap()
+            .with_timezone(&chrono::Utc)
+    };
+    let now = chrono::Utc::now();
+
+    // 首次兑换替换试用期
+    let json = print_response_body_get_json(validate(Some(codes[0].clone())).await, "redeem_first").await;
+    let first = expire_of(&json);
+    assert!((first - now - chrono::Duration::days(30)).num_minutes().abs() < 5);
+
+    // 第二个注册码在剩余时长上叠加
+    let json = print_response_body_get_json(validate(Some(codes[1].clone())).await, "redeem_second").await;
+    let stacked = expire_of(&json);
+    assert_eq!((stacked - first).num_days(), 10);
+
+    // 再次校验任一注册码或不带注册码都返回叠加后的有效期
+    let json = print_response_body_get_json(validate(Some(codes[0].clone())).await, "revalidate_first").await;
+    assert_eq!(expire_of(&json), stacked);
+    let json = print_response_body_get_json(validate(None).await, "validate_without_code").await;
+    assert_eq!(expire_of(&json), stacked);
+
+    let resp = TestClient::get(helpers::get_url(&format!(
+        "/api/admin/devices/list?app_id={}&device_id=renew-dev-1",
+        app_id
+    )))
+    .add_header("authorization", format!("Bearer {}", token), true)
+    .send(&app)
+    .await;
+    let json = print_response_body_get_json(resp, "list_renewal_device").await;
+    let device_id = json["data"]["list"][0]["id"].as_i64().unwrap();
+    let resp = TestClient::get(helpers::get_url(&format!("/api/admin/devices/{}/renewals", device_id)))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "device_renewals").await;
+    let renewals = json["data"].as_array().unwrap();
+    assert_eq!(renewals.len(), 2);
+    assert_eq!(renewals[0]["added_days"].as_i64().unwrap(), 30);
+    assert_eq!(renewals[1]["added_days"].as_i64().unwrap(), 10);
+    assert_eq!(renewals[1]["previous_expire"], renewals[0]["new_expire"]);
+
+    // 只设置绝对过期时间的注册码，续期后不早于注册码的过期时间
+    let code_expire = stacked + chrono::Duration::days(20);
+    let code = format!("RENEW_{}_abs", ts);
+    let resp = TestClient::post(helpers::get_url("/api/admin/reg_codes"))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&json!({
+            "code": code,
+            "app_id": app_id,
+            "valid_days": 0,
+            "max_devices": 1,
+            "status": 0,
+            "code_type": 0,
+            "expire_time": code_expire
+        }))
+        .send(&app)
+        .await;
+    print_response_body_get_json(resp, "create_absolute_code").await;
+    let json = print_response_body_get_json(validate(Some(code)).await, "redeem_absolute").await;
+    assert_eq!(expire_of(&json).timestamp(), code_expire.timestamp());
+}
+
+#[tokio::test]
+async fn test_time_code_from_multi_quantity_order() {
+    use sea_orm::ActiveModelTrait;
+    let app = helpers::create_test_app().await;
+    let token = helpers::login_admin(&app).await;
+    let app_data = helpers::create_app(&app, &token, json!({})).await;
+    let app_id = app_data["id"].as_i64().unwrap();
+    let app_key = app_data["app_valid_key"].as_str().unwrap().to_string();
+    let ts = chrono::Utc::now().timestamp_micros();
+    let resp = TestClient::post(helpers::get_url("/api/admin/products"))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&json!({
+            "name": format!("Multi-Product-{}", ts),
+            "price": 100,
+            "app_id": app_id,
+            "product_id": format!("multi.{}", ts),
+            "add_valid_days": 30,
+            "status": 1
+        }))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "create_product").await;
+    let product_id = json["data"]["id"].as_i64().unwrap();
+    let resp = TestClient::post(helpers::get_url("/api/admin/pay_methods"))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&json!({"name": "multi pay", "description": "multi", "is_active": true}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "create_pay_method").await;
+    let pay_method_id = json["data"]["id"].as_i64().unwrap();
+    let resp = TestClient::post(helpers::get_url("/api/admin/orders"))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&json!({
+            "order_id": format!("MULTI-{}", ts),
+            "status": 1,
+            "pay_method_id": pay_method_id,
+            "original_price": 300,
+            "final_price": 300,
+            "created_by": 1,
+            "updated_by": 1
+        }))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "create_order").await;
+    let order_id = json["data"]["id"].as_i64().unwrap();
+    // 一个订单买了 3 份同一商品，生成 3 个注册码
+    let db = helpers::connect_db().await;
+    entity::order_products::ActiveModel {
+        order_id: sea_orm::Set(order_id as i32),
+        product_id: sea_orm::Set(product_id as i32),
+        num: sea_orm::Set(3),
+        ..Default::default()
+    }
+    .insert(&db)
+    .await
+    .unwrap();
+    let resp = TestClient::post(helpers::get_url("/api/admin/reg_codes/batch"))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&json!({"app_id": app_id, "count": 3, "valid_days": 1, "max_devices": 1, "code_type": 0}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "batch_add").await;
+    let codes = json["data"]["codes"].as_array().unwrap().clone();
+    let code_ids: Vec<i64> = codes.iter().map(|c| c["id"].as_i64().unwrap()).collect();
+    let resp = TestClient::post(helpers::get_url(&format!("/api/admin/orders/{}/reg_codes", order_id)))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&json!({"reg_code_ids": code_ids}))
+        .send(&app)
+        .await;
+    print_response_body_get_json(resp, "link_codes").await;
+
+    // 每个注册码只叠加一份商品时长
+    let now = chrono::Utc::now();
+    let resp = TestClient::post(helpers::get_url("/api/reg/validate"))
+        .json(&json!({"code": codes[0]["code"], "app_key": app_key, "device_id": "multi-dev-1"}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "redeem_multi").await;
+    let expire: chrono::DateTime<chrono::Utc> =
+        json["data"]["expire_time"].as_str().unwrap().parse().unwrap();
+    assert!((expire - now - chrono::Duration::days(30)).num_minutes().abs() < 5);
+}
+
+#[tokio::test]
+async fn test_count_code_usage_consume() {
+    use app_server::types::reg_codes_types::SignedRequest;
+    use app_server::types::usage_types::UsageConsumeReq;
+    let app = helpers::create_test_app().await;
+    let token = helpers::login_admin(&app).await;
+    let app_data = helpers::create_app(&app, &token, json!({})).await;
+    let app_id = app_data["id"].as_i64().unwrap();
+    let app_id_str = app_data["app_id"].as_str().unwrap().to_string();
+    let secret = app_data["app_valid_key"].as_str().unwrap().to_string();
+    let code = format!("METER_{}", chrono::Utc::now().timestamp_micros());
+    let resp = TestClient::post(helpers::get_url("/api/admin/reg_codes"))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&json!({
+            "code": code,
+            "app_id": app_id,
+            "valid_days": 30,
+            "max_devices": 1,
+            "total_count": 10,
+            "status": 0,
+            "code_type": 1
+        }))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "create_count_code").await;
+    let reg_code_id = json["data"]["id"].as_i64().unwrap();
+
+    let consume = |device: &str, amount: i32, key: String| {
+        let mut req = UsageConsumeReq {
+            app_id: app_id_str.clone(),
+            device_id: device.to_string(),
+            code: code.clone(),
+            amount,
+            idempotency_key: key,
+            timestamp: chrono::Utc::now().timestamp(),
+            nonce: uuid::Uuid::new_v4().to_string(),
+            sign: String::new(),
+        };
+        req.sign = app_server::utils::signature::hmac_sha256_hex(&secret, &req.sign_content());
+        let app = &app;
+        async move {
+            let resp = TestClient::post(helpers::get_url("/api/reg/usage/consume"))
+                .json(&req)
+                .send(app)
+                .await;
+            print_response_body_get_json(resp, "consume_usage").await
+        }
+    };
+    let payload = |json: &serde_json::Value| -> serde_json::Value {
+        serde_json::from_str(json["data"]["payload"].as_str().unwrap()).unwrap()
+    };
+
+    // 被拒绝的请求不占用设备名额（max_devices 为 1）
+    let json = consume("meter-dev-2", 11, "usage-key-0".to_string()).await;
+    assert!(json["message"].as_str().unwrap().contains("INSUFFICIENT_BALANCE"));
+    // 经扣减接口创建的设备没有试用期
+    let resp = TestClient::post(helpers::get_url("/api/reg/validate"))
+        .json(&json!({"app_key": secret, "device_id": "meter-dev-2"}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "validate_consume_device_without_code").await;
+    assert!(!json["success"].as_bool().unwrap());
+    let json = consume("meter-dev-1", 3, "usage-key-1".to_string()).await;
+    assert_eq!(payload(&json)["remaining"].as_i64().unwrap(), 7);
+    // 重试同一幂等键不重复扣减
+    let json = consume("meter-dev-1", 3, "usage-key-1".to_string()).await;
+    let replay = payload(&json);
+    assert!(replay["replayed"].as_bool().unwrap());
+    assert_eq!(replay["remaining"].as_i64().unwrap(), 7);
+    let json = consume("meter-dev-1", 2, "usage-key-1".to_string()).await;
+    assert!(json["message"].as_str().unwrap().contains("IDEMPOTENCY_KEY_REUSED"));
+    let json = consume("meter-dev-1", 8, "usage-key-2".to_string()).await;
+    assert!(json["message"].as_str().unwrap().contains("INSUFFICIENT_BALANCE"));
+
+    // 并发扣减不会超出总次数
+    let results = futures::future::join_all(
+        (0..9).map(|i| consume("meter-dev-1", 1, format!("usage-key-c{}", i))),
+    )
+    .await;
+    let ok = results.iter().filter(|j| j["success"].as_bool().unwrap()).count();
+    assert_eq!(ok, 7);
+    let resp = TestClient::post(helpers::get_url("/api/reg/validate"))
+        .json(&json!({"code": code, "app_key": secret, "device_id": "meter-dev-1"}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "validate_used_up_code").await;
+    assert!(json["message"].as_str().unwrap().contains("used up"));
+    assert_eq!(json["code"].as_u64().unwrap(), app_server::constants::APP_LICENSE_EXPIRED as u64);
+
+    let resp = TestClient::get(helpers::get_url(&format!("/api/admin/reg_codes/{}/usages", reg_code_id)))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "list_usages").await;
+    assert_eq!(json["data"]["total"].as_i64().unwrap(), 8);
+    assert_eq!(json["data"]["list"][0]["remaining"].as_i64().unwrap(), 0);
+}
+
+#[tokio::test]
+async fn test_import_reg_codes() {
+    let app = helpers::create_test_app().await;
+    let token = helpers::login_admin(&app).await;
+    let app_data = helpers::create_app(&app, &token, json!({})).await;
+    let app_id = app_data["id"].as_i64().unwrap();
+    let app_key = app_data["app_valid_key"].as_str().unwrap().to_string();
+    let ts = chrono::Utc::now().timestamp_micros();
+    let (time_code, count_code) = (format!("IMP_T_{}", ts), format!("IMP_C_{}", ts));
+    let import = |query: &'static str, body: String| {
+        let token = token.clone();
+        let app = &app;
+        async move {
+            let resp = TestClient::post(helpers::get_url(&format!(
+                "/api/admin/reg_codes/import?app_id={}&{}",
+                app_id, query
+            )))
+            .add_header("authorization", format!("Bearer {}", token), true)
+            .text(body)
+            .send(app)
+            .await;
+            print_response_body_get_json(resp, "import_reg_codes").await
+        }
+    };
+
+    let header = "code,code_type,valid_days,max_devices,expire_time,total_count,remaining_count,devices\n";
+    let valid_rows = format!(
+        "{},0,30,2,2030-01-01T00:00:00Z,,,imp-dev-a;imp-dev-b\n{},1,,,,10,4,imp-dev-c\n",
+        time_code, count_code
+    );
+    let bad_rows = format!("IMP_BAD_{},1,,,,,,\n{},0,30,1,,,,\n", ts, time_code);
+    let json = import("format=csv", format!("{}{}{}", header, valid_rows, bad_rows)).await;
+    let data = &json["data"];
+    assert!(!data["applied"].as_bool().unwrap());
+    assert_eq!(data["total"].as_i64().unwrap(), 4);
+    let errors = data["errors"].as_array().unwrap();
+    assert_eq!(errors.len(), 2);
+    assert_eq!(errors[0]["row"].as_i64().unwrap(), 3);
+    assert!(errors[1]["message"].as_str().unwrap().contains("duplicate"));
+
+    let json = import("format=csv&dry_run=true", format!("{}{}", header, valid_rows)).await;
+    assert!(!json["data"]["applied"].as_bool().unwrap());
+    assert_eq!(json["data"]["created"].as_i64().unwrap(), 2);
+    let json = import("format=csv", format!("{}{}", header, valid_rows)).await;
+    let data = &json["data"];
+    assert!(data["applied"].as_bool().unwrap());
+    assert_eq!(data["devices_bound"].as_i64().unwrap(), 3);
+
+    // 计次码保留剩余次数
+    let resp = TestClient::post(helpers::get_url("/api/reg/validate"))
+        .json(&json!({"code": count_code, "app_key": app_key, "device_id": "imp-dev-c"}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "validate_imported_code").await;
+    assert_eq!(json["data"]["remaining_count"].as_i64().unwrap(), 3);
+
+    let again = json!([{"code": time_code, "max_devices": 3, "devices": ["imp-dev-d"]}]).to_string();
+    let json = import("format=json", again.clone()).await;
+    assert!(json["data"]["errors"][0]["message"].as_str().unwrap().contains("already exists"));
+    let json = import("format=json&on_duplicate=skip", again.clone()).await;
+    assert_eq!(json["data"]["skipped"].as_i64().unwrap(), 1);
+    let json = import("format=json&on_duplicate=overwrite", again).await;
+    assert_eq!(json["data"]["updated"].as_i64().unwrap(), 1);
+    assert_eq!(json["data"]["devices_bound"].as_i64().unwrap(), 1);
+
+    let resp = TestClient::get(helpers::get_url(&format!(
+        "/api/admin/reg_codes/list?app_id={}&code={}",
+        app_id, time_code
+    )))
+    .add_header("authorization", format!("Bearer {}", token), true)
+    .send(&app)
+    .await;
+    let json = print_response_body_get_json(resp, "list_imported_code").await;
+    let code = &json["data"]["list"][0];
+    assert_eq!(code["max_devices"].as_i64().unwrap(), 3);
+    assert_eq!(code["valid_days"].as_i64().unwrap(), 30);
+    assert_eq!(code["devices"].as_array().unwrap().len(), 3);
+    let time_code_id = code["id"].as_i64().unwrap();
+
+    // 覆盖时只提供部分字段，计次码保留类型、次数和状态
+    let partial = json!([{"code": count_code, "max_devices": 2}]).to_string();
+    let json = import("format=json&on_duplicate=overwrite", partial).await;
+    assert_eq!(json["data"]["updated"].as_i64().unwrap(), 1);
+    let get_code = |code: String| {
+        let app = &app;
+        let token = token.clone();
+        async move {
+            let resp = TestClient::get(helpers::get_url(&format!(
+                "/api/admin/reg_codes/list?app_id={}&code={}",
+                app_id, code
+            )))
+            .add_header("authorization", format!("Bearer {}", token), true)
+            .send(app)
+            .await;
+            let json = print_response_body_get_json(resp, "get_imported_code").await;
+            json["data"]["list"][0].clone()
+        }
+    };
+    let code = get_code(count_code.clone()).await;
+    assert_eq!(code["code_type"].as_i64().unwrap(), 1);
+    assert_eq!(code["total_count"].as_i64().unwrap(), 10);
+    assert_eq!(code["use_count"].as_i64().unwrap(), 7);
+    assert_eq!(code["status"].as_i64().unwrap(), 1);
+    assert_eq!(code["max_devices"].as_i64().unwrap(), 2);
+
+    // 覆盖已吊销的注册码且未提供 revoked 时保持吊销
+    let resp = TestClient::post(helpers::get_url(&format!("/api/admin/reg_codes/{}/revoke", time_code_id)))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&json!({"reason": "refund"}))
+        .send(&app)
+        .await;
+    print_response_body_get_json(resp, "revoke_imported_code").await;
+    let partial = json!([{"code": time_code, "valid_days": 60}]).to_string();
+    let json = import("format=json&on_duplicate=overwrite", partial).await;
+    assert_eq!(json["data"]["updated"].as_i64().unwrap(), 1);
+    let code = get_code(time_code.clone()).await;
+    assert_eq!(code["status"].as_i64().unwrap(), 3);
+    assert_eq!(code["revoked_reason"].as_str().unwrap(), "refund");
+    assert_eq!(code["valid_days"].as_i64().unwrap(), 60);
+    assert_eq!(code["devices"].as_array().unwrap().len(), 3);
+}
@@ -0,0 +1,267 @@
+use crate::helpers::print_response_body_get_json;
+use app_server::types::release_manifest_types::ReleaseManifest;
+use app_server::utils::license;
+use salvo::test::TestClient;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+mod helpers;
+
+/// 模拟 OSS：HEAD 返回大小、ETag 和 sha256 元信息，GET 返回文件内容并计数
+async fn start_mock_oss(objects: HashMap<String, (Vec<u8>, bool)>) -> (String, Arc<AtomicUsize>) {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let gets = Arc::new(AtomicUsize::new(0));
+    let counter = gets.clone();
+    let objects = Arc::new(objects);
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let objects = objects.clone();
+            let counter = counter.clone();
+            tokio::spawn(async move {
+                let mut buf = Vec::new();
+                let mut chunk = [0u8; 1024];
+                while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+                    let n = socket.read(&mut chunk).await.unwrap();
+                    if n == 0 {
+                        return;
+                    }
+                    buf.extend_from_slice(&chunk[..n]);
+                }
+                let request = String::from_utf8_lossy(&buf).to_string();
+                let mut parts = request.split_whitespace();
+                let method = parts.next().unwrap_or_default().to_string();
+                let key = parts
+                    .next()
+                    .unwrap_or_default()
+                    .trim_start_matches('/')
+                    .to_string();
+                let response = match objects.get(&key) {
+                    Some((body, with_meta)) => {
+                        let sha256 = hex::encode(Sha256::digest(body));
+                        let mut head = format!(
+                            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nETag: \"{}\"\r\nx-oss-request-id: mock\r\nConnection: close\r\n",
+                            body.len(),
+                            &sha256[..16]
+                        );
+                        if *with_meta {
+                            head.push_str(&format!("x-oss-meta-sha256: {}\r\n", sha256));
+                        }
+                        head.push_str("\r\n");
+                        let mut response = head.into_bytes();
+                        if method == "GET" {
+                            counter.fetch_add(1, Ordering::SeqCst);
+                            response.extend_from_slice(body);
+                        }
+                        response
+                    }
+                    None => b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nx-oss-request-id: mock\r\nConnection: close\r\n\r\n".to_vec(),
+                };
+                socket.write_all(&response).await.unwrap();
+                socket.shutdown().await.ok();
+            });
+        }
+    });
+    (format!("http://{}", addr), gets)
+}
+
+#[tokio::test]
+async fn test_release_manifest() {
+    let objects: HashMap<String, (Vec<u8>, bool)> = [
+        ("demo/1.0/app.exe", b"exe-v1".to_vec(), true),
+        ("demo/1.0/data/config.json", b"{\"a\":1}".to_vec(), false),
+        ("demo/1.0/old.dll", b"old".to_vec(), true),
+        ("demo/1.1/app.exe", b"exe-v2-bigger".to_vec(), true),
+        ("demo/1.1/data/config.json", b"{\"a\":1}".to_vec(), false),
+        ("demo/1.1/new.dll", b"new".to_vec(), true),
+    ]
+    .into_iter()
+    .map(|(k, v, m)| (k.to_string(), (v, m)))
+    .collect();
+    let (endpoint, gets) = start_mock_oss(objects).await;
+    // dotenvy 不覆盖已存在的环境变量，测试进程内 OSS 请求都发到模拟服务
+    unsafe { std::env::set_var("OSS_ENDPOINT", &endpoint) };
+
+    let app = helpers::create_test_app().await;
+    let token = helpers::login_admin(&app).await;
+    let app_data = helpers::create_app(&app, &token, json!({})).await;
+    let id = app_data["id"].as_i64().unwrap();
+    let app_id = app_data["app_id"].as_str().unwrap().to_string();
+
+    let mut release_ids = Vec::new();
+    for (vername, vercode) in [("1.0.0", 100), ("1.1.0", 110)] {
+        let resp = TestClient::post(helpers::get_url("/api/admin/app_releases"))
+            .add_header("authorization", format!("Bearer {}", token), true)
+            .json(&json!({"app_id": id, "vername": vername, "vercode": vercode, "status": 1}))
+            .send(&app)
+            .await;
+        let json = print_response_body_get_json(resp, "add_release").await;
+        release_ids.push(json["data"]["id"].as_i64().unwrap());
+    }
+    let mut resource_ids = Vec::new();
+    for (name, path) in [
+        ("app.exe", "/demo/1.0"),
+        ("config.json", "/demo/1.0/data/"),
+        ("old.dll", "demo/1.0"),
+        ("app.exe", "/demo/1.1"),
+        ("config.json", "/demo/1.1/data"),
+        ("new.dll", "/demo/1.1"),
+    ] {
+        let object_key = format!("{}/{}", path.trim_matches('/'), name);
+        let resp = TestClient::post(helpers::get_url("/api/admin/resources"))
+            .add_header("authorization", format!("Bearer {}", token), true)
+            .json(&json!({
+                "name": name,
+                "object_key": object_key,
+                "url": format!("https://cdn.example.com/{}", object_key),
+                "path": path,
+                "file_type": "file",
+                "res_type": 1,
+                "status": 1
+            }))
+            .send(&app)
+            .await;
+        let json = print_response_body_get_json(resp, "add_resource").await;
+        resource_ids.push(json["data"]["id"].as_i64().unwrap());
+    }
+
+    let build = |release_id: i64, base_path: &str| {
+        TestClient::post(helpers::get_url(&format!(
+            "/api/admin/app_releases/{}/manifest",
+            release_id
+        )))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&json!({"base_path": base_path}))
+    };
+    let resp = build(release_ids[0], "/demo/1.0").send(&app).await;
+    let json = print_response_body_get_json(resp, "build_v1").await;
+    assert_eq!(json["data"]["files"].as_array().unwrap().len(), 3);
+    assert_eq!(json["data"]["total_size"], 6 + 7 + 3);
+    assert_eq!(json["data"]["files"][1]["path"], "data/config.json");
+    // 没有 sha256 元信息的文件下载后计算
+    assert_eq!(gets.load(Ordering::SeqCst), 1);
+    let resp = build(release_ids[1], "demo/1.1/").send(&app).await;
+    print_response_body_get_json(resp, "build_v2").await;
+    assert_eq!(gets.load(Ordering::SeqCst), 2);
+
+    // ETag 未变化时沿用 resources 中记录的哈希
+    let resp = build(release_ids[0], "/demo/1.0").send(&app).await;
+    print_response_body_get_json(resp, "rebuild_v1").await;
+    assert_eq!(gets.load(Ordering::SeqCst), 2);
+    let resp = TestClient::get(helpers::get_url(&format!(
+        "/api/admin/resources/{}",
+        resource_ids[1]
+    )))
+    .add_header("authorization", format!("Bearer {}", token), true)
+    .send(&app)
+    .await;
+    let json = print_response_body_get_json(resp, "resource").await;
+    let config_sha = hex::encode(Sha256::digest(b"{\"a\":1}"));
+    assert_eq!(json["data"]["sha256"], config_sha.as_str());
+    assert_eq!(json["data"]["size"], 7);
+
+    let resp = build(release_ids[1], "/demo/2.0").send(&app).await;
+    let json = print_response_body_get_json(resp, "build_empty").await;
+    assert_eq!(json["success"], false);
+
+    let resp = TestClient::get(helpers::get_url(&format!("/api/reg/public_key/{}", app_id)))
+        .send(&app)
+        .await;
+    let key = print_response_body_get_json(resp, "public_key").await;
+    let public_key = key["data"]["public_key"].as_str().unwrap().to_string();
+    let fetch = |query: &str| {
+        TestClient::get(helpers::get_url(&format!(
+            "/api/app/manifest/{}?{}",
+            app_id, query
+        )))
+    };
+
+    // 增量清单只包含内容变化的文件
+    let resp = fetch("vercode=110&from_vercode=100").send(&app).await;
+    let json = print_response_body_get_json(resp, "manifest_diff").await;
+    let manifest: ReleaseManifest =
+        license::verify_token(&public_key, json["data"]["token"].as_str().unwrap()).unwrap();
+    assert_eq!(manifest.vercode, 110);
+    assert_eq!(manifest.files.len(), 3);
+    assert_eq!(manifest.changed, ["app.exe", "new.dll"]);
+    assert_eq!(manifest.removed, ["old.dll"]);
+    assert_eq!(manifest.download_size, 13 + 3);
+    let config = manifest
+        .files
+        .iter()
+        .find(|f| f.path == "data/config.json")
+        .unwrap();
+    assert_eq!(config.sha256, config_sha);
+    assert_eq!(config.object_key, "demo/1.1/data/config.json");
+
+    let resp = fetch("vercode=110").send(&app).await;
+    let json = print_response_body_get_json(resp, "manifest_full").await;
+    let manifest: ReleaseManifest =
+        license::verify_token(&public_key, json["data"]["token"].as_str().unwrap()).unwrap();
+    assert_eq!(manifest.changed.len(), 3);
+    assert_eq!(manifest.download_size, manifest.total_size);
+
+    let mut forged = manifest.clone();
+    forged.files[0].sha256 = "0".repeat(64);
+    let (_, signature) = json["data"]["token"]
+        .as_str()
+        .unwrap()
+        .split_once('.')
+        .unwrap();
+    let forged_token = format!(
+        "{}.{}",
+        base64::Engine::encode(
+            &base64::engine::general_purpose::URL_SAFE_NO_PAD,
+            serde_json::to_vec(&forged).unwrap()
+        ),
+        signature
+    );
+    assert!(license::verify_token::<ReleaseManifest>(&public_key, &forged_token).is_err());
+
+    // 暂停的版本不提供清单
+    let resp = TestClient::post(helpers::get_url(&format!(
+        "/api/admin/app_releases/{}/pause",
+        release_ids[1]
+    )))
+    .add_header("authorization", format!("Bearer {}", token), true)
+    .send(&app)
+    .await;
+    print_response_body_get_json(resp, "pause").await;
+    let resp = fetch("vercode=110").send(&app).await;
+    let json = print_response_body_get_json(resp, "manifest_paused").await;
+    assert_eq!(json["success"], false);
+
+    let resp = TestClient::get(helpers::get_url(&format!(
+        "/api/admin/app_release_events/list?release_id={}",
+        release_ids[0]
+    )))
+    .add_header("authorization", format!("Bearer {}", token), true)
+    .send(&app)
+    .await;
+    let json = print_response_body_get_json(resp, "events").await;
+    assert_eq!(json["data"]["list"][0]["action"], "manifest");
+    assert_eq!(json["data"]["list"][0]["changes"]["files"], json!([3, 3]));
+
+    // 客户端版本已暂停时不用它的清单计算增量，按全量下载
+    let post = |release_id: i64, action: &str| {
+        TestClient::post(helpers::get_url(&format!(
+            "/api/admin/app_releases/{}/{}",
+            release_id, action
+        )))
+        .add_header("authorization", format!("Bearer {}", token), true)
+    };
+    let resp = post(release_ids[1], "resume").send(&app).await;
+    print_response_body_get_json(resp, "resume").await;
+    let resp = post(release_ids[0], "pause").send(&app).await;
+    print_response_body_get_json(resp, "pause_from").await;
+    let resp = fetch("vercode=110&from_vercode=100").send(&app).await;
+    let json = print_response_body_get_json(resp, "manifest_from_paused").await;
+    let manifest: ReleaseManifest =
+        license::verify_token(&public_key, json["data"]["token"].as_str().unwrap()).unwrap();
+    assert_eq!(manifest.changed.len(), 3);
+    assert!(manifest.removed.is_empty());
+}
@@ -0,0 +1,147 @@
+use salvo::prelude::*;
+use salvo::test::TestClient;
+use serde_json::json;
+use app_server::constants;
+use crate::helpers::print_response_body_get_json;
+mod helpers;
+
+#[tokio::test]
+async fn test_reseller_quota_and_scope() {
+    let app = helpers::create_test_app().await;
+    let token = helpers::login_admin(&app).await;
+    let app_a = helpers::create_app(&app, &token, json!({})).await["id"].as_i64().unwrap();
+    let app_b = helpers::create_app(&app, &token, json!({})).await["id"].as_i64().unwrap();
+    let ts = chrono::Utc::now().timestamp_micros();
+    let mut products = vec![];
+    for app_id in [app_a, app_b] {
+        let resp = TestClient::post(helpers::get_url("/api/admin/products"))
+            .add_header("authorization", format!("Bearer {}", token), true)
+            .json(&json!({
+                "name": format!("Reseller-Product-{}-{}", app_id, ts),
+                "price": 100,
+                "app_id": app_id,
+                "product_id": format!("reseller.{}.{}", app_id, ts),
+                "add_valid_days": 30,
+                "status": 1
+            }))
+            .send(&app)
+            .await;
+        let json = print_response_body_get_json(resp, "create_product").await;
+        products.push(json["data"]["id"].as_i64().unwrap());
+    }
+    let (product_a, product_b) = (products[0], products[1]);
+
+    let resp = TestClient::post(helpers::get_url("/api/admin/users"))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&json!({"username": "reseller1", "password": "reseller123", "role_id": 2}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "create_user").await;
+    let user_id = json["data"]["id"].as_i64().unwrap();
+    let resp = TestClient::post(helpers::get_url("/api/admin/resellers"))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&json!({"user_id": user_id, "name": "Distributor A", "app_ids": [app_a]}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "create_reseller").await;
+    let reseller_id = json["data"]["id"].as_i64().unwrap();
+    assert_eq!(json["data"]["app_ids"], json!([app_a]));
+
+    let resp = TestClient::post(helpers::get_url("/api/login"))
+        .json(&json!({"username": "reseller1", "password": "reseller123"}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "reseller_login").await;
+    let reseller_token = json["data"]["token"].as_str().unwrap().to_string();
+    // 时长、设备数等由商品决定，请求中多传的字段被忽略
+    let generate = |product_id: i64, count: i64| {
+        TestClient::post(helpers::get_url("/api/reseller/reg_codes/batch"))
+            .add_header("authorization", format!("Bearer {}", reseller_token), true)
+            .json(&json!({
+                "product_id": product_id,
+                "count": count,
+                "valid_days": 3650,
+                "max_devices": 100,
+                "code_type": 1,
+                "total_count": 100000
+            }))
+    };
+
+    // 没有额度时不能生成
+    let json = print_response_body_get_json(generate(product_a, 1).send(&app).await, "no_quota").await;
+    assert!(json["message"].as_str().unwrap().contains("QUOTA_EXCEEDED"));
+    let resp = TestClient::post(helpers::get_url(&format!(
+        "/api/admin/resellers/{}/quota",
+        reseller_id
+    )))
+    .add_header("authorization", format!("Bearer {}", token), true)
+    .json(&json!({"change": 10, "kind": "purchase", "remark": "order 1001"}))
+    .send(&app)
+    .await;
+    let json = print_response_body_get_json(resp, "purchase_quota").await;
+    assert_eq!(json["data"]["balance_after"].as_i64().unwrap(), 10);
+
+    let json = print_response_body_get_json(generate(product_a, 4).send(&app).await, "generate").await;
+    assert_eq!(json["data"]["count"].as_i64().unwrap(), 4);
+    let generated = &json["data"]["codes"][0];
+    assert_eq!(generated["valid_days"].as_i64().unwrap(), 30);
+    assert_eq!(generated["max_devices"].as_i64().unwrap(), 1);
+    assert_eq!(generated["code_type"].as_i64().unwrap(), 0);
+    let batch_id = generated["batch_id"].as_i64().unwrap();
+    // 未授权应用的商品
+    let json = print_response_body_get_json(generate(product_b, 1).send(&app).await, "other_app").await;
+    assert_eq!(json["code"].as_u64().unwrap(), constants::APP_FORBIDDEN as u64);
+    let json = print_response_body_get_json(generate(product_a, 7).send(&app).await, "over_quota").await;
+    assert!(json["message"].as_str().unwrap().contains("only 6 codes left"));
+
+    // 管理员生成的批次对代理商不可见
+    let resp = TestClient::post(helpers::get_url("/api/admin/reg_codes/batch"))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&json!({"app_id": app_a, "count": 2, "valid_days": 30, "max_devices": 1, "code_type": 0}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "admin_generate").await;
+    let admin_batch = json["data"]["codes"][0]["batch_id"].as_i64().unwrap();
+
+    let get = |path: &str| {
+        TestClient::get(helpers::get_url(path))
+            .add_header("authorization", format!("Bearer {}", reseller_token), true)
+    };
+    let json = print_response_body_get_json(get("/api/reseller/apps").send(&app).await, "apps").await;
+    assert_eq!(json["data"][0]["products"][0]["id"].as_i64().unwrap(), product_a);
+    let json = print_response_body_get_json(get("/api/reseller/me").send(&app).await, "me").await;
+    assert_eq!(json["data"]["quota_balance"].as_i64().unwrap(), 6);
+    let json = print_response_body_get_json(get("/api/reseller/reg_codes/list").send(&app).await, "codes").await;
+    assert_eq!(json["data"]["total"].as_i64().unwrap(), 4);
+    let json = print_response_body_get_json(get("/api/reseller/batches/list").send(&app).await, "batches").await;
+    assert_eq!(json["data"]["total"].as_i64().unwrap(), 1);
+    assert_eq!(json["data"]["list"][0]["channel"].as_str().unwrap(), "Distributor A");
+    let path = format!("/api/reseller/batches/{}/stats", batch_id);
+    let json = print_response_body_get_json(get(&path).send(&app).await, "batch_stats").await;
+    assert_eq!(json["data"]["total"].as_i64().unwrap(), 4);
+    let path = format!("/api/reseller/batches/{}/stats", admin_batch);
+    let json = print_response_body_get_json(get(&path).send(&app).await, "admin_batch_stats").await;
+    assert_eq!(json["code"].as_u64().unwrap(), constants::APP_NOT_FOUND as u64);
+    let json = print_response_body_get_json(get("/api/reseller/stats").send(&app).await, "stats").await;
+    assert_eq!(json["data"]["generated"].as_i64().unwrap(), 4);
+    assert_eq!(json["data"]["codes"].as_i64().unwrap(), 4);
+    let json = print_response_body_get_json(get("/api/reseller/ledger").send(&app).await, "ledger").await;
+    assert_eq!(json["data"]["total"].as_i64().unwrap(), 2);
+    assert_eq!(json["data"]["list"][0]["change"].as_i64().unwrap(), -4);
+
+    // 代理商不能访问管理接口
+    let resp = get("/api/admin/apps/list").send(&app).await;
+    assert_eq!(resp.status_code, Some(StatusCode::FORBIDDEN));
+
+    let resp = TestClient::delete(helpers::get_url(&format!(
+        "/api/admin/resellers/{}/apps/{}",
+        reseller_id, app_a
+    )))
+    .add_header("authorization", format!("Bearer {}", token), true)
+    .send(&app)
+    .await;
+    let json = print_response_body_get_json(resp, "unassign").await;
+    assert_eq!(json["data"]["app_ids"], json!([]));
+    let json = print_response_body_get_json(generate(product_a, 1).send(&app).await, "unassigned").await;
+    assert_eq!(json["code"].as_u64().unwrap(), constants::APP_FORBIDDEN as u64);
+}
@@ -0,0 +1,131 @@
+use app_server::types::reg_codes_types::{RegCodeDeactivateResp, RegCodeValidateV2Resp};
+use app_server::utils::signature;
+use salvo::test::TestClient;
+use serde_json::json;
+use crate::helpers::print_response_body_get_json;
+mod helpers;
+
+#[tokio::test]
+async fn test_device_self_deactivation() {
+    let app = helpers::create_test_app().await;
+    let token = helpers::login_admin(&app).await;
+    let app_data = helpers::create_app(
+        &app,
+        &token,
+        json!({"trial_days": 0, "max_transfers": 2, "transfer_cooldown_hours": 24}),
+    )
+    .await;
+    let id = app_data["id"].as_i64().unwrap();
+    let app_id = app_data["app_id"].as_str().unwrap().to_string();
+    let secret = app_data["app_valid_key"].as_str().unwrap().to_string();
+    assert_eq!(app_data["max_transfers"].as_i64().unwrap(), 2);
+
+    let resp = TestClient::post(helpers::get_url("/api/admin/reg_codes/batch"))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&json!({"app_id": id, "count": 1, "valid_days": 30, "max_devices": 1, "code_type": 0}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "batch_add").await;
+    let code = json["data"]["codes"][0]["code"].as_str().unwrap().to_string();
+    let code_id = json["data"]["codes"][0]["id"].as_i64().unwrap();
+
+    let validate = |device: &str, code: Option<&str>| {
+        let req = helpers::signed_request(&app_id, &secret, device, code);
+        TestClient::post(helpers::get_url("/api/reg/v2/validate")).json(&req)
+    };
+    let deactivate = |device: &str| {
+        let req = helpers::signed_request(&app_id, &secret, device, Some(&code));
+        TestClient::post(helpers::get_url("/api/reg/deactivate")).json(&req)
+    };
+
+    let resp = validate("move-dev-1", Some(&code)).send(&app).await;
+    let json = print_response_body_get_json(resp, "validate_dev1").await;
+    assert_eq!(json["success"], true);
+    let resp = validate("move-dev-2", Some(&code)).send(&app).await;
+    let json = print_response_body_get_json(resp, "validate_dev2_full").await;
+    assert_eq!(json["success"], false);
+
+    // 旧设备自助解绑，响应带签名
+    let resp = deactivate("move-dev-1").send(&app).await;
+    let json = print_response_body_get_json(resp, "deactivate_dev1").await;
+    let signed: RegCodeValidateV2Resp = serde_json::from_value(json["data"].clone()).unwrap();
+    assert!(signature::verify_hmac_sha256_hex(&secret, &signed.sign_content(), &signed.sign));
+    let result: RegCodeDeactivateResp = serde_json::from_str(&signed.payload).unwrap();
+    assert_eq!(result.transfers_used, 1);
+    assert_eq!(result.transfers_left, 1);
+
+    // 解绑后旧设备不再保留注册码的时长
+    let resp = validate("move-dev-1", None).send(&app).await;
+    let json = print_response_body_get_json(resp, "validate_dev1_after").await;
+    assert_eq!(json["success"], false);
+    let resp = validate("move-dev-2", Some(&code)).send(&app).await;
+    let json = print_response_body_get_json(resp, "validate_dev2").await;
+    assert_eq!(json["success"], true);
+
+    // 冷却时间内不能再次解绑
+    let resp = deactivate("move-dev-2").send(&app).await;
+    let json = print_response_body_get_json(resp, "deactivate_cooldown").await;
+    assert_eq!(json["success"], false);
+    assert!(json["message"].as_str().unwrap().contains("not available until"));
+
+    let resp = TestClient::put(helpers::get_url(&format!("/api/admin/apps/{}", id)))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&json!({"transfer_cooldown_hours": 0}))
+        .send(&app)
+        .await;
+    print_response_body_get_json(resp, "update_app").await;
+    let resp = deactivate("move-dev-2").send(&app).await;
+    let json = print_response_body_get_json(resp, "deactivate_dev2").await;
+    assert_eq!(json["success"], true);
+    let resp = validate("move-dev-3", Some(&code)).send(&app).await;
+    let json = print_response_body_get_json(resp, "validate_dev3").await;
+    assert_eq!(json["success"], true);
+
+    // 次数用完后只能由管理员解绑
+    let resp = deactivate("move-dev-3").send(&app).await;
+    let json = print_response_body_get_json(resp, "deactivate_limit").await;
+    assert_eq!(json["success"], false);
+    assert!(json["message"].as_str().unwrap().contains("limit"));
+    let mut req = helpers::signed_request(&app_id, &secret, "move-dev-3", Some(&code));
+    req.sign = signature::hmac_sha256_hex("wrong key", &req.sign_content());
+    let resp = TestClient::post(helpers::get_url("/api/reg/deactivate"))
+        .json(&req)
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "deactivate_bad_sign").await;
+    assert_eq!(json["success"], false);
+
+    let resp = TestClient::get(helpers::get_url(&format!("/api/admin/reg_codes/{}/devices", code_id)))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "devices").await;
+    let device_id = json["data"][0]["id"].as_i64().unwrap();
+    let resp = TestClient::delete(helpers::get_url(&format!(
+        "/api/admin/reg_codes/{}/devices/{}",
+        code_id, device_id
+    )))
+    .add_header("authorization", format!("Bearer {}", token), true)
+    .send(&app)
+    .await;
+    let json = print_response_body_get_json(resp, "admin_unbind").await;
+    assert_eq!(json["success"], true);
+    let resp = validate("move-dev-3", None).send(&app).await;
+    let json = print_response_body_get_json(resp, "validate_dev3_after_unbind").await;
+    assert_eq!(json["success"], false);
+
+    let resp = TestClient::get(helpers::get_url(&format!("/api/admin/reg_codes/{}/transfers", code_id)))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "transfers").await;
+    let list = json["data"].as_array().unwrap();
+    assert_eq!(list.len(), 3);
+    assert_eq!(list[0]["source"], 2);
+    assert_eq!(list[0]["from_device"], "move-dev-3");
+    assert!(list[0]["operator_id"].as_i64().is_some());
+    assert_eq!(list[1]["source"], 0);
+    assert_eq!(list[1]["from_device"], "move-dev-2");
+    assert_eq!(list[2]["from_device"], "move-dev-1");
+    assert_eq!(list[2]["source"], 0);
+}
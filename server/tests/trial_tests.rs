@@ -0,0 +1,117 @@
+use salvo::test::TestClient;
+use serde_json::json;
+use crate::helpers::print_response_body_get_json;
+mod helpers;
+
+#[tokio::test]
+async fn test_trial_policy_refuse_reset_extend() {
+    let app = helpers::create_test_app().await;
+    let token = helpers::login_admin(&app).await;
+    let app_data = helpers::create_app(&app, &token, json!({})).await;
+    let app_id = app_data["id"].as_i64().unwrap();
+    let app_key = app_data["app_valid_key"].as_str().unwrap().to_string();
+
+    let resp = TestClient::put(helpers::get_url(&format!("/api/admin/apps/{}/trial_policy", app_id)))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&json!({"max_trials_per_ip": 1, "max_trials_per_subnet": 3, "fingerprint_threshold": 80}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "upsert_trial_policy").await;
+    assert!(json["success"].as_bool().unwrap());
+    assert_eq!(json["data"]["window_hours"].as_i64().unwrap(), 24);
+    assert_eq!(json["data"]["subnet_prefix"].as_i64().unwrap(), 24);
+
+    let info = json!({"cpu": "i7-9700", "board": "B1", "disk": "D1", "mac": "00:11", "os": "win11"});
+    let validate = |device: &'static str, ip: &'static str, info: serde_json::Value| {
+        let app_key = app_key.clone();
+        let app = &app;
+        async move {
+            let req = TestClient::post(helpers::get_url("/api/reg/validate"))
+                .json(&json!({"app_key": app_key, "device_id": device, "device_info": info}));
+            let resp = helpers::send_from(app, ip, req).await;
+            print_response_body_get_json(resp, "validate_trial").await
+        }
+    };
+
+    let json = validate("trial-dev-1", "10.9.0.1", info.clone()).await;
+    assert!(json["success"].as_bool().unwrap());
+    // 同一 IP 超过次数
+    let json = validate("trial-dev-2", "10.9.0.1", json!({"cpu": "other"})).await;
+    assert!(!json["success"].as_bool().unwrap());
+    let message = json["message"].as_str().unwrap();
+    assert!(message.contains("TRIAL_REFUSED") && message.contains("ip 10.9.0.1"));
+    // 不是受信任代理发来的 X-Forwarded-For 不能绕过 IP 限制
+    let req = TestClient::post(helpers::get_url("/api/reg/validate"))
+        .add_header("x-forwarded-for", "10.7.0.1", true)
+        .json(&json!({"app_key": app_key, "device_id": "trial-dev-spoof", "device_info": {"cpu": "spoof"}}));
+    let resp = helpers::send_from(&app, "10.9.0.1", req).await;
+    let json = print_response_body_get_json(resp, "validate_spoofed_ip").await;
+    assert!(json["message"].as_str().unwrap().contains("ip 10.9.0.1"));
+    // 取不到 IP 的请求归为同一个 IP 统计
+    for (device, allowed) in [("trial-dev-noip-1", true), ("trial-dev-noip-2", false)] {
+        let resp = TestClient::post(helpers::get_url("/api/reg/validate"))
+            .add_header("x-forwarded-for", "not-an-ip", true)
+            .json(&json!({"app_key": app_key, "device_id": device, "device_info": {"cpu": device}}))
+            .send(&app)
+            .await;
+        let json = print_response_body_get_json(resp, "validate_unknown_ip").await;
+        assert_eq!(json["success"].as_bool().unwrap(), allowed);
+    }
+    // 同网段的其他 IP 仍在网段限额内
+    let json = validate("trial-dev-3", "10.9.0.2", json!({"cpu": "third"})).await;
+    assert!(json["success"].as_bool().unwrap());
+    // 换 IP 后指纹与 trial-dev-1 有 4/5 相同
+    let mut similar = info.clone();
+    similar["mac"] = json!("00:22");
+    let json = validate("trial-dev-4", "10.8.0.1", similar.clone()).await;
+    let message = json["message"].as_str().unwrap();
+    assert!(message.contains("TRIAL_REFUSED") && message.contains("80%"));
+    // 再次校验仍返回拒绝原因
+    let json = validate("trial-dev-4", "10.8.0.1", similar.clone()).await;
+    assert!(json["message"].as_str().unwrap().contains("fingerprint"));
+
+    let resp = TestClient::get(helpers::get_url(&format!(
+        "/api/admin/devices/list?app_id={}&device_id=trial-dev-4",
+        app_id
+    )))
+    .add_header("authorization", format!("Bearer {}", token), true)
+    .send(&app)
+    .await;
+    let json = print_response_body_get_json(resp, "list_refused_device").await;
+    let device = &json["data"]["list"][0];
+    assert_eq!(device["bind_ip"].as_str().unwrap(), "10.8.0.1");
+    assert!(device["trial_refused_reason"].as_str().unwrap().contains("fingerprint"));
+    let device_id = device["id"].as_i64().unwrap();
+
+    let resp = TestClient::post(helpers::get_url(&format!("/api/admin/devices/{}/trial/reset", device_id)))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "reset_trial").await;
+    assert!(json["data"]["trial_refused_reason"].is_null());
+    let json = validate("trial-dev-4", "10.8.0.1", similar.clone()).await;
+    assert!(json["success"].as_bool().unwrap());
+    let expire: chrono::DateTime<chrono::Utc> =
+        json["data"]["expire_time"].as_str().unwrap().parse().unwrap();
+    assert!(expire > chrono::Utc::now() + chrono::Duration::days(2));
+
+    let resp = TestClient::post(helpers::get_url(&format!("/api/admin/devices/{}/trial/extend", device_id)))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&json!({"days": 5}))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "extend_trial").await;
+    let extended: chrono::DateTime<chrono::Utc> =
+        json["data"]["expire_time"].as_str().unwrap().parse().unwrap();
+    assert_eq!((extended - expire).num_days(), 5);
+
+    // 带无效注册码首次校验不能绕过试用策略，之后不带注册码校验也拿不到试用期
+    let req = TestClient::post(helpers::get_url("/api/reg/validate"))
+        .json(&json!({"code": "BOGUS-CODE", "app_key": app_key, "device_id": "trial-dev-bogus"}));
+    let resp = helpers::send_from(&app, "10.6.0.1", req).await;
+    let json = print_response_body_get_json(resp, "validate_bogus_code").await;
+    assert!(!json["success"].as_bool().unwrap());
+    let json = validate("trial-dev-bogus", "10.6.0.1", json!({"cpu": "bogus"})).await;
+    assert!(!json["success"].as_bool().unwrap());
+    assert!(json["message"].as_str().unwrap().contains("device expired"));
+}
@@ -0,0 +1,94 @@
+use salvo::test::TestClient;
+use serde_json::json;
+use crate::helpers::print_response_body_get_json;
+mod helpers;
+
+#[tokio::test]
+async fn test_validation_events_log_and_stats() {
+    let app = helpers::create_test_app().await;
+    let token = helpers::login_admin(&app).await;
+    let app_data = helpers::create_app(&app, &token, json!({})).await;
+    let app_id = app_data["id"].as_i64().unwrap();
+    let app_key = app_data["app_valid_key"].as_str().unwrap().to_string();
+    let code = format!("AUDIT_{}", chrono::Utc::now().timestamp_micros());
+    let resp = TestClient::post(helpers::get_url("/api/admin/reg_codes"))
+        .add_header("authorization", format!("Bearer {}", token), true)
+        .json(&json!({
+            "code": code,
+            "app_id": app_id,
+            "valid_days": 30,
+            "max_devices": 1,
+            "status": 0,
+            "code_type": 0
+        }))
+        .send(&app)
+        .await;
+    let json = print_response_body_get_json(resp, "create_audit_code").await;
+    assert!(json["success"].as_bool().unwrap());
+
+    // 带首尾空白的注册码按去掉空白后的哈希记录
+    let padded = format!(" {} ", code);
+    for (code, device) in [(padded.as_str(), "audit-dev-1"), ("NOT_EXIST", "audit-dev-2")] {
+        // 经两层受信任代理转发
+        let req = TestClient::post(helpers::get_url("/api/reg/validate"))
+            .add_header("x-forwarded-for", "10.1.2.3, 172.16.0.1", true)
+            .add_header("user-agent", "audit-test/1.0", true)
+            .json(&json!({"code": code, "app_key": app_key, "device_id": device}));
+        let resp = helpers::send_from(&app, helpers::TEST_PROXY_IP, req).await;
+        print_response_body_get_json(resp, "validate_for_audit").await;
+    }
+    // 日志由后台任务批量写入
+    tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+
+    let resp = TestClient::get(helpers::get_url(&format!(
+        "/api/admin/validation_events/list?app_id={}",
+        app_id
+    )))
+    .add_header("authorization", format!("Bearer {}", token), true)
+    .send(&app)
+    .await;
+    let json = print_response_body_get_json(resp, "list_validation_events").await;
+    assert_eq!(json["data"]["total"].as_i64().unwrap(), 2);
+    let list = json["data"]["list"].as_array().unwrap();
+    let failed = list.iter().find(|e| e["device_id"] == "audit-dev-2").unwrap();
+    assert!(!failed["success"].as_bool().unwrap());
+    assert!(failed["reason"].as_str().unwrap().contains("reg_code"));
+    assert_eq!(failed["ip"].as_str().unwrap(), "10.1.2.3");
+    assert_eq!(failed["user_agent"].as_str().unwrap(), "audit-test/1.0");
+
+    let resp = TestClient::get(helpers::get_url(&format!(
+        "/api/admin/validation_events/list?app_id={}&success=true",
+        app_id
+    )))
+    .add_header("authorization", format!("Bearer {}", token), true)
+    .send(&app)
+    .await;
+    let json = print_response_body_get_json(resp, "list_success_events").await;
+    assert_eq!(json["data"]["total"].as_i64().unwrap(), 1);
+    // 日志只保存注册码哈希，按原文查询仍能命中
+    let code_hash = app_server::services::validation_log::code_hash(&code);
+    assert_eq!(json["data"]["list"][0]["code"].as_str().unwrap(), code_hash);
+    let resp = TestClient::get(helpers::get_url(&format!(
+        "/api/admin/validation_events/list?app_id={}&code={}",
+        app_id, code
+    )))
+    .add_header("authorization", format!("Bearer {}", token), true)
+    .send(&app)
+    .await;
+    let json = print_response_body_get_json(resp, "list_events_by_code").await;
+    assert_eq!(json["data"]["total"].as_i64().unwrap(), 1);
+
+    let resp = TestClient::get(helpers::get_url(&format!(
+        "/api/admin/validation_events/stats?app_id={}",
+        app_id
+    )))
+    .add_header("authorization", format!("Bearer {}", token), true)
+    .send(&app)
+    .await;
+    let json = print_response_body_get_json(resp, "validation_stats").await;
+    let stats = json["data"].as_array().unwrap();
+    assert_eq!(stats.len(), 1);
+    assert_eq!(stats[0]["total"].as_i64().unwrap(), 2);
+    assert_eq!(stats[0]["success"].as_i64().unwrap(), 1);
+    assert_eq!(stats[0]["failed"].as_i64().unwrap(), 1);
+}